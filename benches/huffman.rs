@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use huff_tree_tap::HuffmanData;
+use huff_tree_tap::{BlockOptions, HuffmanBlocks, HuffmanData};
 use std::hint::black_box;
 
 fn huffman_encode_bench(input_data: &[u8]) -> HuffmanData {
@@ -10,6 +10,10 @@ fn huffman_decode_bench(input_data: &HuffmanData) -> Vec<u8> {
     input_data.decode().unwrap()
 }
 
+fn blocks_decode_bench(input_data: &HuffmanBlocks) -> Vec<u8> {
+    input_data.decode().unwrap()
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let mut unencoded_data = Vec::<u8>::new();
     for _ in 0..1000 {
@@ -17,6 +21,11 @@ fn criterion_benchmark(c: &mut Criterion) {
     }
 
     let encoded_data = HuffmanData::new(&unencoded_data).unwrap();
+    let block_options = BlockOptions {
+        block_size: 1000,
+        ..Default::default()
+    };
+    let encoded_blocks = HuffmanBlocks::new(&unencoded_data, &block_options).unwrap();
 
     let mut group = c.benchmark_group("Huffman");
     group.bench_function("huffman_encode", |b| {
@@ -25,6 +34,9 @@ fn criterion_benchmark(c: &mut Criterion) {
     group.bench_function("huffman_decode", |b| {
         b.iter(|| black_box(huffman_decode_bench(black_box(&encoded_data))))
     });
+    group.bench_function("blocks_decode", |b| {
+        b.iter(|| black_box(blocks_decode_bench(black_box(&encoded_blocks))))
+    });
     group.finish();
 }
 
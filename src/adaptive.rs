@@ -0,0 +1,298 @@
+use crate::container::payload_size;
+use crate::crc32::crc32;
+use crate::data::{BitVec, UnPaddedBits};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::check_checksum;
+use crate::packing::Packing;
+
+/// Identifies an adaptive Huffman encoding, ahead of everything else
+pub(crate) const MAGIC: [u8; 4] = *b"HTTA";
+pub(crate) const VERSION: u8 = 1;
+/// Number of the root, the highest node number. 256 leaves, the NYT leaf and 256 branches take every number below
+const ROOT_NUMBER: usize = 2 * 256;
+
+/// Huffman encodes `data` in one pass with adaptive (FGK) Huffman coding: encoder and decoder both start from a
+/// tree holding nothing but the "not yet transmitted" (NYT) leaf and update it the same way after every u8, so no
+/// encoding map is ever stored. A u8 seen for the first time is sent as the code of the NYT leaf followed by its 8 raw
+/// bits. Payloads of a few hundred u8s, where the map of `encode` takes more room than the codes, come out smaller.
+///
+/// The layout is:
+/// * `[u8; 4]` magic `HTTA`
+/// * `u8` version, 1
+/// * `u32` little endian number of u8s the data decodes to
+/// * `u32` little endian CRC-32 of the data
+/// * the codes, packed as `Packing::Dense` packs them
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let encoded_data = adaptive_encode(&data).unwrap();
+/// assert!(encoded_data.len() < encode(&data).unwrap().len());
+/// assert_eq!(adaptive_decode(&encoded_data).unwrap(), data);
+/// ```
+pub fn adaptive_encode(data: &[u8]) -> Result<Vec<u8>> {
+    let data_len = payload_size(data.len() as u64)?;
+    let mut tree = AdaptiveTree::new();
+    let mut bits = UnPaddedBits::new();
+    for &byte in data {
+        match tree.leaves[byte as usize] {
+            Some(leaf) => bits.extend(tree.code(leaf)),
+            None => {
+                bits.extend(tree.code(tree.nyt));
+                bits.extend((0..8).rev().map(|shift| (byte >> shift) & 1));
+            }
+        }
+        tree.update(byte);
+    }
+
+    let mut encoded_data = MAGIC.to_vec();
+    encoded_data.push(VERSION);
+    encoded_data.extend_from_slice(&data_len.to_le_bytes());
+    encoded_data.extend_from_slice(&crc32(data).to_le_bytes());
+    encoded_data.extend(Packing::Dense.pack(bits)?);
+    Ok(encoded_data)
+}
+
+/// Decodes an encoding produced by `adaptive_encode`, rebuilding the tree as the encoder did
+///
+/// # Arguments
+///
+/// * `encoded_data` - The adaptive encoding
+pub fn adaptive_decode(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    if encoded_data.len() < 13 || encoded_data[..4] != MAGIC {
+        return Err(HuffmanError::DecodeError("Not an adaptive encoding"));
+    }
+    if encoded_data[4] != VERSION {
+        return Err(HuffmanError::DecodeError("Unsupported encoding version"));
+    }
+    let data_len = u32::from_le_bytes(encoded_data[5..9].try_into().unwrap_or_default()) as usize;
+    let checksum = u32::from_le_bytes(encoded_data[9..13].try_into().unwrap_or_default());
+    let bits = Packing::Dense.unpack(&encoded_data[13..]);
+
+    let mut tree = AdaptiveTree::new();
+    let mut bits = bits.iter();
+    let mut next_bit = || {
+        bits.next()
+            .copied()
+            .ok_or(HuffmanError::DecodeError("Adaptive encoding ends early"))
+    };
+    // Every u8 takes at least one bit, so a length the payload cannot hold is not allocated up front
+    let mut data = Vec::with_capacity(data_len.min(encoded_data.len() * 8));
+    while data.len() < data_len {
+        let mut node = tree.order[ROOT_NUMBER];
+        while let Some((left, right)) = tree.nodes[node].children {
+            node = match next_bit()? {
+                0 => left,
+                _ => right,
+            };
+        }
+        let byte = match tree.nodes[node].symbol {
+            Some(byte) => byte,
+            None => {
+                let mut byte = 0u8;
+                for _ in 0..8 {
+                    byte = (byte << 1) | next_bit()?;
+                }
+                byte
+            }
+        };
+        data.push(byte);
+        tree.update(byte);
+    }
+    check_checksum(checksum, crc32(&data))?;
+    Ok(data)
+}
+
+#[derive(Debug, Clone)]
+struct AdaptiveNode {
+    weight: u64,
+    /// Position in the numbering, weights never decrease as numbers grow (the sibling property)
+    number: usize,
+    parent: Option<usize>,
+    /// Left and right child of a branch
+    children: Option<(usize, usize)>,
+    /// The u8 of a leaf, `None` for the NYT leaf and branches
+    symbol: Option<u8>,
+}
+
+/// The FGK tree shared by the encoder and decoder, nodes indexed by creation order
+struct AdaptiveTree {
+    nodes: Vec<AdaptiveNode>,
+    /// Node index of every node number
+    order: Vec<usize>,
+    /// Leaf of every u8 seen so far
+    leaves: [Option<usize>; 256],
+    nyt: usize,
+}
+
+impl AdaptiveTree {
+    fn new() -> Self {
+        let mut order = vec![usize::MAX; ROOT_NUMBER + 1];
+        order[ROOT_NUMBER] = 0;
+        AdaptiveTree {
+            nodes: vec![AdaptiveNode {
+                weight: 0,
+                number: ROOT_NUMBER,
+                parent: None,
+                children: None,
+                symbol: None,
+            }],
+            order,
+            leaves: [None; 256],
+            nyt: 0,
+        }
+    }
+
+    /// The code of `node`, the path from the root to it
+    fn code(&self, mut node: usize) -> BitVec {
+        let mut code = BitVec::new();
+        while let Some(parent) = self.nodes[node].parent {
+            let (left, _) = self.nodes[parent].children.unwrap_or_default();
+            code.push((left != node) as u8);
+            node = parent;
+        }
+        code.reverse();
+        code
+    }
+
+    /// Counts one more `byte`, splitting the NYT leaf for a u8 seen for the first time, then walks up to the root
+    /// swapping every node with the highest numbered node of its weight before incrementing it
+    fn update(&mut self, byte: u8) {
+        let mut node = match self.leaves[byte as usize] {
+            Some(leaf) => leaf,
+            None => {
+                let nyt = self.nyt;
+                let number = self.nodes[nyt].number;
+                let new_nyt = self.push_node(number - 2, nyt, None);
+                let leaf = self.push_node(number - 1, nyt, Some(byte));
+                self.nodes[nyt].children = Some((new_nyt, leaf));
+                self.nyt = new_nyt;
+                self.leaves[byte as usize] = Some(leaf);
+                leaf
+            }
+        };
+        loop {
+            let leader = self.leader(node);
+            if leader != node && Some(leader) != self.nodes[node].parent {
+                self.swap(node, leader);
+            }
+            self.nodes[node].weight += 1;
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => break,
+            }
+        }
+    }
+
+    fn push_node(&mut self, number: usize, parent: usize, symbol: Option<u8>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(AdaptiveNode {
+            weight: 0,
+            number,
+            parent: Some(parent),
+            children: None,
+            symbol,
+        });
+        self.order[number] = index;
+        index
+    }
+
+    /// The highest numbered node weighing as much as `node`
+    fn leader(&self, node: usize) -> usize {
+        let weight = self.nodes[node].weight;
+        let mut leader = node;
+        for &index in &self.order[self.nodes[node].number + 1..] {
+            if self.nodes[index].weight != weight {
+                break;
+            }
+            leader = index;
+        }
+        leader
+    }
+
+    /// Swaps the positions of two nodes, neither an ancestor of the other, their subtrees moving with them
+    fn swap(&mut self, a: usize, b: usize) {
+        // Only the root has no parent, and it is never swapped
+        let (Some(parent_a), Some(parent_b)) = (self.nodes[a].parent, self.nodes[b].parent) else {
+            return;
+        };
+        if parent_a == parent_b {
+            if let Some((left, right)) = self.nodes[parent_a].children.as_mut() {
+                std::mem::swap(left, right);
+            }
+        } else {
+            self.replace_child(parent_a, a, b);
+            self.replace_child(parent_b, b, a);
+            self.nodes[a].parent = Some(parent_b);
+            self.nodes[b].parent = Some(parent_a);
+        }
+        let (number_a, number_b) = (self.nodes[a].number, self.nodes[b].number);
+        self.nodes[a].number = number_b;
+        self.nodes[b].number = number_a;
+        self.order[number_a] = b;
+        self.order[number_b] = a;
+    }
+
+    fn replace_child(&mut self, parent: usize, from: usize, to: usize) {
+        if let Some((left, right)) = self.nodes[parent].children.as_mut() {
+            match *left == from {
+                true => *left = to,
+                false => *right = to,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode;
+
+    #[test]
+    fn test_adaptive_encode_decode() {
+        let mut state = 5u32;
+        let noise: Vec<u8> = (0..5000)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect();
+        let input_data = [
+            Vec::new(),
+            b"a".to_vec(),
+            b"this is a test string!".to_vec(),
+            b"aaaaaaaabbbbbbbbccccdde".repeat(50),
+            (0..=255).cycle().take(2000).collect(),
+            noise,
+        ];
+
+        for input_data in input_data {
+            let test_output = adaptive_encode(&input_data).unwrap();
+
+            assert_eq!(input_data, adaptive_decode(&test_output).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_adaptive_small_payloads() {
+        let input_data = b"this is a test string!";
+        let expected_data = encode(input_data).unwrap();
+
+        let test_output = adaptive_encode(input_data).unwrap();
+
+        assert!(test_output.len() < expected_data.len());
+        let mut corrupted = test_output.clone();
+        corrupted[13] ^= 0x10;
+        assert!(adaptive_decode(&corrupted).is_err());
+        assert!(adaptive_decode(&expected_data).is_err());
+        assert!(adaptive_decode(&test_output[..12]).is_err());
+    }
+}
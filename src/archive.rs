@@ -0,0 +1,252 @@
+use crate::block_encoder::encode_each_block;
+use crate::blocks::{
+    read_seek_index, read_version, write_seek_index, write_start, IndexEntry, VERSION,
+};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::options::BlockOptions;
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Random access into a block encoding written by `HuffmanBlocks::to_bytes` or `HuffmanArchive::append`, decoding
+/// only the blocks a read touches by way of the encoding's seek index
+#[derive(Debug)]
+pub struct HuffmanArchive<R> {
+    reader: R,
+    /// Position of the encoding's magic in `reader`, which index offsets count from
+    base: u64,
+    index: Vec<IndexEntry>,
+}
+
+impl<R: Read + Seek> HuffmanArchive<R> {
+    /// Reads the seek index of the block encoding starting at the current position of `reader`
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The block encoding, e.g. an open file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = (0..10_000u32).map(|n| (n % 251) as u8).collect();
+    /// let options = BlockOptions { block_size: 1024, ..Default::default() };
+    /// let bytes = HuffmanBlocks::new(&data, &options).unwrap().to_bytes().unwrap();
+    ///
+    /// let mut archive = HuffmanArchive::open(std::io::Cursor::new(bytes)).unwrap();
+    /// assert_eq!(archive.len(), 10_000);
+    /// assert_eq!(archive.read_at(5000, 100).unwrap(), &data[5000..5100]);
+    /// ```
+    pub fn open(mut reader: R) -> Result<HuffmanArchive<R>> {
+        let base = reader.stream_position()?;
+        let version = read_version(&mut reader)?;
+        let index = read_seek_index(&mut reader, base, version)?;
+        Ok(HuffmanArchive {
+            reader,
+            base,
+            index,
+        })
+    }
+
+    /// Number of u8s the encoding decodes to
+    pub fn len(&self) -> u64 {
+        self.index.last().map_or(0, |end| end.data_offset)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Decodes `len` u8s starting `data_offset` u8s into the decoded data, fewer when the data ends first
+    ///
+    /// # Arguments
+    ///
+    /// * `data_offset` - Where to start reading in the decoded data
+    /// * `len` - The most u8s to return
+    pub fn read_at(&mut self, data_offset: u64, len: usize) -> Result<Vec<u8>> {
+        let end = data_offset.saturating_add(len as u64).min(self.len());
+        let mut data = Vec::new();
+        // The last block starting at or before `data_offset`
+        let mut block = self
+            .index
+            .partition_point(|entry| entry.data_offset <= data_offset)
+            .saturating_sub(1);
+        while block + 1 < self.index.len() && self.index[block].data_offset < end {
+            let (start, next) = (self.index[block], self.index[block + 1]);
+            let mut encoded = vec![0u8; (next.offset - start.offset) as usize];
+            self.reader
+                .seek(SeekFrom::Start(self.base + start.offset))?;
+            self.reader.read_exact(&mut encoded)?;
+            let decoded = HuffmanData::from_bytes(&encoded)?.decode()?;
+            if decoded.len() as u64 != next.data_offset - start.data_offset {
+                return Err(HuffmanError::DecodeError(
+                    "Block does not decode to the size its index entry gives",
+                ));
+            }
+
+            let from = data_offset.saturating_sub(start.data_offset) as usize;
+            let to = (end - start.data_offset).min(decoded.len() as u64) as usize;
+            data.extend_from_slice(&decoded[from..to]);
+            block += 1;
+        }
+        Ok(data)
+    }
+}
+
+impl<R: Read + Write + Seek> HuffmanArchive<R> {
+    /// Encodes `data` into new blocks and appends them to the block encoding starting at the current position of
+    /// `writer`, starting a new encoding there when nothing follows it. Earlier blocks are left untouched, only the
+    /// seek index and footer after them are rewritten, so a log can be compressed as it grows.
+    ///
+    /// Encodings written before the index moved to the end, by version 2 and older, cannot be appended to.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - The block encoding, e.g. a file opened for reading and writing
+    /// * `data` - The data to append
+    /// * `options` - A reference to `BlockOptions` setting how the new blocks are encoded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let options = BlockOptions::default();
+    /// let mut log = std::io::Cursor::new(Vec::new());
+    /// HuffmanArchive::append(&mut log, b"first entry\n", &options).unwrap();
+    /// log.set_position(0);
+    /// HuffmanArchive::append(&mut log, b"second entry\n", &options).unwrap();
+    ///
+    /// let bytes = log.into_inner();
+    /// let huffman_blocks = HuffmanBlocks::from_bytes(&bytes).unwrap();
+    /// assert_eq!(huffman_blocks.decode().unwrap(), b"first entry\nsecond entry\n");
+    /// ```
+    pub fn append(writer: &mut R, data: &[u8], options: &BlockOptions) -> Result<()> {
+        let base = writer.stream_position()?;
+        let mut bytes = Vec::new();
+        let mut index = match writer.seek(SeekFrom::End(0))? > base {
+            true => {
+                writer.seek(SeekFrom::Start(base))?;
+                let version = read_version(writer)?;
+                if version != VERSION {
+                    return Err(HuffmanError::DecodeError(
+                        "Block encoding has its index up front, so it cannot be appended to",
+                    ));
+                }
+                read_seek_index(writer, base, version)?
+            }
+            false => {
+                write_start(&mut bytes);
+                vec![IndexEntry {
+                    offset: bytes.len() as u64,
+                    data_offset: 0,
+                }]
+            }
+        };
+
+        // The entry for the end of the blocks becomes the first new block's, and the new blocks go over the index
+        let mut entry = index.pop().unwrap();
+        let start = entry.offset - bytes.len() as u64;
+        for block in encode_each_block(data, options)? {
+            index.push(entry);
+            let encoded = block.to_bytes()?;
+            entry.offset += encoded.len() as u64;
+            entry.data_offset += block.data_len as u64;
+            bytes.extend(encoded);
+        }
+        index.push(entry);
+        write_seek_index(&mut bytes, &index)?;
+
+        writer.seek(SeekFrom::Start(base + start))?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::HuffmanBlocks;
+    use crate::options::BlockOptions;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_at() {
+        let input_data = b"this is a test string!".repeat(200);
+        let options = BlockOptions {
+            block_size: 500,
+            ..Default::default()
+        };
+        // Ahead of the encoding, to check offsets count from where it starts
+        let mut bytes = vec![0xff; 7];
+        bytes.extend(
+            HuffmanBlocks::new(&input_data, &options)
+                .unwrap()
+                .to_bytes()
+                .unwrap(),
+        );
+        let mut reader = Cursor::new(bytes);
+        reader.set_position(7);
+        let mut archive = HuffmanArchive::open(reader).unwrap();
+
+        for (data_offset, len) in [(0, 10), (495, 10), (499, 1002), (4390, 100), (5000, 1)] {
+            let expected_data = &input_data[data_offset.min(4400)..(data_offset + len).min(4400)];
+
+            let test_output = archive.read_at(data_offset as u64, len).unwrap();
+
+            assert_eq!(expected_data, test_output);
+        }
+        assert_eq!(input_data, archive.read_at(0, usize::MAX).unwrap());
+    }
+
+    #[test]
+    fn test_append() {
+        let entries = [
+            b"first entry\n".repeat(50),
+            b"second entry\n".repeat(90),
+            Vec::new(),
+        ];
+        let options = BlockOptions {
+            block_size: 500,
+            ..Default::default()
+        };
+        let mut writer = Cursor::new(vec![0xff; 7]);
+        writer.set_position(7);
+
+        let mut expected_data = Vec::new();
+        for entry in &entries {
+            let previous = writer.get_ref().clone();
+            writer.set_position(7);
+            HuffmanArchive::append(&mut writer, entry, &options).unwrap();
+            expected_data.extend_from_slice(entry);
+
+            // Earlier blocks are kept as they were, only the index and footer after them change
+            if previous.len() > 7 {
+                let count =
+                    u32::from_le_bytes(previous[previous.len() - 12..][..4].try_into().unwrap());
+                let blocks_end = previous.len() - 12 - 16 * (count as usize + 1);
+                assert_eq!(previous[..blocks_end], writer.get_ref()[..blocks_end]);
+            }
+        }
+        let bytes = writer.into_inner();
+
+        assert_eq!(
+            expected_data,
+            HuffmanBlocks::from_bytes(&bytes[7..])
+                .unwrap()
+                .decode()
+                .unwrap()
+        );
+        let mut reader = Cursor::new(bytes);
+        reader.set_position(7);
+        let mut archive = HuffmanArchive::open(reader).unwrap();
+        assert_eq!(expected_data.len() as u64, archive.len());
+        assert_eq!(expected_data[590..610], archive.read_at(590, 20).unwrap());
+
+        let version_2 = [b"HTTB".as_slice(), &[2], &[0; 20]].concat();
+        assert!(HuffmanArchive::append(&mut Cursor::new(version_2), b"data", &options).is_err());
+    }
+}
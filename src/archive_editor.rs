@@ -0,0 +1,255 @@
+use crate::block_encoder::encode_each_block;
+use crate::blocks::{block_slices, write_blocks};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::options::BlockOptions;
+
+/// A byte-range edit of the decoded data: `removed` u8s starting `offset` u8s in are replaced by `inserted`.
+/// An insertion removes nothing and a deletion inserts nothing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    /// Where the edit starts in the decoded data
+    pub offset: u64,
+    /// Number of u8s removed from `offset` on
+    pub removed: u64,
+    /// The u8s put in their place
+    pub inserted: Vec<u8>,
+}
+
+/// Edits the data of a block encoding written by `HuffmanBlocks::to_bytes` or `HuffmanArchive::append` in place,
+/// re-encoding only the blocks an edit touches and copying every other block as it is encoded, so a small change to
+/// a large archive costs a few blocks rather than a full recompression. `to_bytes` writes the blocks back out with a
+/// fresh seek index.
+#[derive(Debug)]
+pub struct ArchiveEditor {
+    /// Every block's self-contained encoding and the number of u8s it decodes to, in data order
+    blocks: Vec<(Vec<u8>, u64)>,
+    options: BlockOptions,
+}
+
+impl ArchiveEditor {
+    /// Splits the block encoding `bytes` into its blocks, without decoding any of them
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The block encoding, any version
+    /// * `options` - How edited blocks are re-encoded, `block_size` capping the size of the blocks an edit produces
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data = b"this is a test string!".repeat(1000);
+    /// let options = BlockOptions { block_size: 4096, ..Default::default() };
+    /// let bytes = HuffmanBlocks::new(&data, &options).unwrap().to_bytes().unwrap();
+    ///
+    /// let mut editor = ArchiveEditor::open(&bytes, options).unwrap();
+    /// let patch = Patch { offset: 10, removed: 4, inserted: b"TEST".to_vec() };
+    /// assert_eq!(editor.apply_patch(&patch).unwrap(), 1);
+    ///
+    /// let edited = HuffmanBlocks::from_bytes(&editor.to_bytes().unwrap()).unwrap().decode().unwrap();
+    /// assert_eq!(&edited[..22], b"this is a TEST string!");
+    /// ```
+    pub fn open(bytes: &[u8], options: BlockOptions) -> Result<ArchiveEditor> {
+        options.validate()?;
+        let blocks = block_slices(bytes)?
+            .into_iter()
+            .map(|(block, data_len)| (block.to_vec(), data_len))
+            .collect();
+        Ok(ArchiveEditor { blocks, options })
+    }
+
+    /// Number of u8s the edited data decodes to
+    pub fn len(&self) -> u64 {
+        self.blocks.iter().map(|(_, data_len)| data_len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Applies `patch`, decoding the blocks it touches, splicing the edit into their data and encoding the result
+    /// into new blocks of at most `block_size` u8s in their place. Returns the number of blocks decoded.
+    ///
+    /// The touched blocks are those overlapping the removed range, or holding `offset` for an insertion, with an
+    /// insertion at the very end of the data going into the last block. Fails, leaving the blocks as they were, when
+    /// the removed range runs past the end of the data.
+    ///
+    /// # Arguments
+    ///
+    /// * `patch` - The edit to apply
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<usize> {
+        let end = patch
+            .offset
+            .checked_add(patch.removed)
+            .filter(|&end| end <= self.len())
+            .ok_or(HuffmanError::OptionsError(
+                "Patch range runs past the end of the data",
+            ))?;
+
+        // The first and one past the last block touched, and where the first one starts in the data
+        let (mut first, mut last, mut first_start) = (None, 0, 0);
+        let mut block_start = 0;
+        for (block, (_, data_len)) in self.blocks.iter().enumerate() {
+            let block_end = block_start + data_len;
+            let touched = match patch.removed {
+                0 => block_start <= patch.offset && patch.offset < block_end,
+                _ => block_start < end && patch.offset < block_end,
+            };
+            if touched {
+                if first.is_none() {
+                    first = Some(block);
+                    first_start = block_start;
+                }
+                last = block + 1;
+            }
+            block_start = block_end;
+        }
+        let first = match first {
+            Some(first) => first,
+            // Only an insertion at the end of the data touches no block, it goes into the last one
+            None => {
+                last = self.blocks.len();
+                first_start = self.len() - self.blocks.last().map_or(0, |&(_, data_len)| data_len);
+                last.saturating_sub(1)
+            }
+        };
+
+        let mut data = Vec::new();
+        for (block, data_len) in &self.blocks[first..last] {
+            let decoded = HuffmanData::from_bytes(block)?.decode_checked()?;
+            if decoded.len() as u64 != *data_len {
+                return Err(HuffmanError::DecodeError(
+                    "Block does not decode to the size its index entry gives",
+                ));
+            }
+            data.extend(decoded);
+        }
+        let from = (patch.offset - first_start) as usize;
+        data.splice(
+            from..from + patch.removed as usize,
+            patch.inserted.iter().copied(),
+        );
+
+        // Data edited away entirely leaves no block, unless it was the only data there was
+        let mut encoded = Vec::new();
+        if !data.is_empty() || last - first == self.blocks.len() {
+            for block in encode_each_block(&data, &self.options)? {
+                let data_len = block.data_len as u64;
+                encoded.push((block.to_bytes()?, data_len));
+            }
+        }
+        self.blocks.splice(first..last, encoded);
+        Ok(last - first)
+    }
+
+    /// Lays the blocks out as a block encoding, with a seek index covering the edited blocks
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        write_blocks(
+            self.blocks
+                .iter()
+                .map(|(block, data_len)| (block.as_slice(), *data_len)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{HuffmanBlocks, FOOTER_LEN};
+
+    #[test]
+    fn test_apply_patch() {
+        let mut input_data = b"this is a test string!".repeat(100);
+        let options = BlockOptions {
+            block_size: 500,
+            ..Default::default()
+        };
+        let bytes = HuffmanBlocks::new(&input_data, &options)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        let mut editor = ArchiveEditor::open(&bytes, options).unwrap();
+
+        // Replace within a block, across a boundary, insert at a boundary and at the end, then delete a block's worth
+        for (patch, expected_data) in [
+            ((10, 4, b"TEST".as_slice()), 1),
+            ((490, 20, b"".as_slice()), 2),
+            ((1000, 0, b"inserted".as_slice()), 1),
+            ((2188, 0, b"appended".as_slice()), 1),
+            ((0, 480, b"".as_slice()), 1),
+        ] {
+            let (offset, removed, inserted) = patch;
+            let patch = Patch {
+                offset,
+                removed,
+                inserted: inserted.to_vec(),
+            };
+            let untouched = editor.blocks.last().unwrap().clone();
+
+            let test_output = editor.apply_patch(&patch).unwrap();
+
+            let (offset, removed) = (offset as usize, removed as usize);
+            input_data.splice(offset..offset + removed, inserted.iter().copied());
+            assert_eq!(expected_data, test_output);
+            assert_eq!(input_data.len() as u64, editor.len());
+            if offset + removed < 1500 {
+                assert_eq!(&untouched, editor.blocks.last().unwrap());
+            }
+        }
+
+        let bytes = editor.to_bytes().unwrap();
+        assert_eq!(
+            input_data,
+            HuffmanBlocks::from_bytes(&bytes).unwrap().decode().unwrap()
+        );
+        let patch = Patch {
+            offset: input_data.len() as u64,
+            removed: 1,
+            inserted: Vec::new(),
+        };
+        assert!(editor.apply_patch(&patch).is_err());
+        let everything = Patch {
+            offset: 0,
+            removed: input_data.len() as u64,
+            inserted: Vec::new(),
+        };
+        editor.apply_patch(&everything).unwrap();
+        assert!(editor.is_empty());
+        assert!(HuffmanBlocks::from_bytes(&editor.to_bytes().unwrap())
+            .unwrap()
+            .decode()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_apply_patch_forged_index() {
+        let options = BlockOptions {
+            block_size: 100,
+            ..Default::default()
+        };
+        let mut bytes = HuffmanBlocks::new(&b"this is a test string!".repeat(10), &options)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        // Claim the last block decodes to 100 u8s more than it does
+        let data_offset = bytes.len() - FOOTER_LEN as usize - 8;
+        let forged = u64::from_le_bytes(bytes[data_offset..data_offset + 8].try_into().unwrap()) + 100;
+        bytes[data_offset..data_offset + 8].copy_from_slice(&forged.to_le_bytes());
+
+        let mut editor = ArchiveEditor::open(&bytes, options).unwrap();
+        assert_eq!(320, editor.len());
+        let patch = Patch {
+            offset: 300,
+            removed: 10,
+            inserted: Vec::new(),
+        };
+        assert!(matches!(
+            editor.apply_patch(&patch),
+            Err(HuffmanError::DecodeError(_))
+        ));
+    }
+}
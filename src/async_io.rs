@@ -0,0 +1,273 @@
+use crate::container::Header;
+use crate::encoder::HuffmanEncoder;
+use crate::error::{HuffmanError, Result};
+use crate::options::HuffmanOptions;
+use crate::stream::{DecodedCheck, PayloadDecoder, CHUNK_SIZE};
+
+use std::io::{self, ErrorKind};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Decodes a self-contained encoding as it is read from an `AsyncRead`, the async counterpart of `HuffmanReader`.
+///
+/// Each poll decodes at most one chunk of the input, so a large payload never holds the runtime thread for longer
+/// than a chunk takes to decode.
+pub struct AsyncHuffmanReader<R: AsyncRead + Unpin> {
+    reader: R,
+    /// Input read before the header is complete
+    header_bytes: Vec<u8>,
+    /// `None` until the header has been read and again once the payload is exhausted
+    decoder: Option<PayloadDecoder>,
+    done: bool,
+    chunk: Vec<u8>,
+    decoded: Vec<u8>,
+    position: usize,
+    check: DecodedCheck,
+}
+
+impl<R: AsyncRead + Unpin> AsyncHuffmanReader<R> {
+    /// Creates an `AsyncHuffmanReader` decoding the encoding `reader` holds
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The encoding, as written by `encode_to_writer` or `encode`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use tokio::io::AsyncReadExt;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let encoded_data = encode(&data).unwrap();
+    ///
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let mut decoded_data = Vec::new();
+    /// runtime
+    ///     .block_on(AsyncHuffmanReader::new(encoded_data.as_slice()).read_to_end(&mut decoded_data))
+    ///     .unwrap();
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn new(reader: R) -> Self {
+        AsyncHuffmanReader {
+            reader,
+            header_bytes: Vec::new(),
+            decoder: None,
+            done: false,
+            chunk: vec![0u8; CHUNK_SIZE],
+            decoded: Vec::new(),
+            position: 0,
+            check: DecodedCheck::default(),
+        }
+    }
+
+    /// Returns the underlying reader, positioned wherever decoding left it
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decodes the next `read` u8s of `chunk` into `decoded`, an empty read marking the end of the input
+    fn process(&mut self, read: usize) -> Result<()> {
+        let chunk = &self.chunk[..read];
+        let Some(decoder) = self.decoder.as_mut() else {
+            // The header has no fixed size, so it is parsed again from everything read so far until it is complete
+            self.header_bytes.extend_from_slice(chunk);
+            let mut rest = self.header_bytes.as_slice();
+            let header = match Header::read_from(&mut rest) {
+                Err(HuffmanError::IoError(e))
+                    if e.kind() == ErrorKind::UnexpectedEof && read > 0 =>
+                {
+                    return Ok(())
+                }
+                header => header?,
+            };
+            self.check = DecodedCheck::new(&header);
+            let mut decoder = PayloadDecoder::new(header);
+            decoder.feed(rest, &mut self.decoded)?;
+            self.header_bytes = Vec::new();
+            self.decoder = Some(decoder);
+            return self.finish_if_empty(read);
+        };
+
+        if read > 0 {
+            decoder.feed(chunk, &mut self.decoded)?;
+        }
+        self.finish_if_empty(read)
+    }
+
+    fn finish_if_empty(&mut self, read: usize) -> Result<()> {
+        if read == 0 {
+            if let Some(decoder) = self.decoder.take() {
+                decoder.finish(&mut self.decoded)?;
+            }
+            self.done = true;
+        }
+        self.check.update(&self.decoded);
+        if self.done {
+            self.check.finish()?;
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncHuffmanReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        while this.position == this.decoded.len() {
+            if this.done || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            let mut chunk = ReadBuf::new(&mut this.chunk);
+            ready!(Pin::new(&mut this.reader).poll_read(cx, &mut chunk))?;
+            let read = chunk.filled().len();
+
+            this.decoded.clear();
+            this.position = 0;
+            this.process(read)?;
+        }
+        let read = buf.remaining().min(this.decoded.len() - this.position);
+        buf.put_slice(&this.decoded[this.position..this.position + read]);
+        this.position += read;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Compresses everything written to it into a self-contained encoding on an `AsyncWrite`, the async counterpart of
+/// `HuffmanWriter`.
+///
+/// Written u8s are kept until `shutdown`, which encodes them and writes the encoding to the underlying writer,
+/// then shuts it down.
+pub struct AsyncHuffmanWriter<W: AsyncWrite + Unpin> {
+    writer: W,
+    /// `None` once `shutdown` has started encoding
+    encoder: Option<HuffmanEncoder>,
+    encoded: Vec<u8>,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncHuffmanWriter<W> {
+    /// Creates an `AsyncHuffmanWriter` encoding into `writer`
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the encoding is written on `shutdown`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let mut writer = AsyncHuffmanWriter::new(Vec::new());
+    /// runtime.block_on(async {
+    ///     writer.write_all(b"this is a test string!").await.unwrap();
+    ///     writer.shutdown().await.unwrap();
+    /// });
+    /// assert_eq!(decode(&writer.into_inner()).unwrap(), b"this is a test string!");
+    /// ```
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, HuffmanOptions::default())
+    }
+
+    /// Creates an `AsyncHuffmanWriter` that encodes with the given `HuffmanOptions`
+    pub fn with_options(writer: W, options: HuffmanOptions) -> Self {
+        AsyncHuffmanWriter {
+            writer,
+            encoder: Some(HuffmanEncoder::with_options(options)),
+            encoded: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Returns the underlying writer, which only holds the encoding once `shutdown` has completed
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncHuffmanWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(encoder) = this.encoder.as_mut() else {
+            return Poll::Ready(Err(io::Error::new(
+                ErrorKind::BrokenPipe,
+                "Write after shutdown",
+            )));
+        };
+        encoder.update(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    /// Only flushes the underlying writer, the encoding can only be written once all the data is known
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(encoder) = this.encoder.take() {
+            let huffman_data = encoder.finish()?;
+            this.encoded = huffman_data.to_bytes()?;
+        }
+        while this.written < this.encoded.len() {
+            let written =
+                ready!(Pin::new(&mut this.writer).poll_write(cx, &this.encoded[this.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            this.written += written;
+        }
+        ready!(Pin::new(&mut this.writer).poll_flush(cx))?;
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_async_round_trip() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let mut writer = AsyncHuffmanWriter::new(Vec::new());
+        runtime.block_on(async {
+            for chunk in input_data.chunks(1000) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+        });
+        let test_output = writer.into_inner();
+
+        assert_eq!(encode(&input_data).unwrap(), test_output);
+
+        // A reader handing out a few u8s at a time splits the header across reads
+        let (mut client, server) = tokio::io::duplex(7);
+        runtime.spawn(async move {
+            client.write_all(&test_output).await.unwrap();
+            client.shutdown().await.unwrap();
+        });
+        let mut decoded_data = Vec::new();
+        runtime
+            .block_on(AsyncHuffmanReader::new(server).read_to_end(&mut decoded_data))
+            .unwrap();
+        assert_eq!(input_data, decoded_data);
+    }
+}
@@ -0,0 +1,32 @@
+use huff_tree_tap::{capabilities, gen_vectors};
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: huff gen-vectors <dir> | huff capabilities";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["gen-vectors", dir] => match gen_vectors(Path::new(dir)) {
+            Ok(names) => {
+                println!("Wrote {} test vectors to {}", names.len(), dir);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        },
+        ["capabilities"] => {
+            println!("{}", capabilities());
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
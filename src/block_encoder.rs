@@ -0,0 +1,169 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::options::BlockOptions;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Splits `data` into blocks, encodes each into a self-contained encoding with a code of its own and returns them
+/// appended back to back in block order, ready for `decode_all_members`.
+///
+/// Blocks are encoded on `options.threads` threads. With `options.deterministic` set the block boundaries only
+/// depend on `block_size` and the blocks are assembled in order whichever thread finishes first, so the output is
+/// byte-identical to a serial encode on any machine, as reproducible builds need. Without it the data is shared
+/// evenly between the threads instead, though no block is ever larger than `block_size` in either mode.
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+/// * `options` - A reference to `BlockOptions` setting the block size, thread count and how blocks are encoded
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = b"this is a test string!".repeat(100);
+/// let serial = BlockOptions { block_size: 512, ..Default::default() };
+/// let parallel = BlockOptions { threads: 4, ..serial.clone() };
+///
+/// let encoded_data = encode_blocks(&data, &parallel).unwrap();
+/// assert_eq!(encoded_data, encode_blocks(&data, &serial).unwrap());
+/// assert_eq!(decode_all_members(&mut encoded_data.as_slice()).unwrap(), data);
+/// ```
+pub fn encode_blocks(data: &[u8], options: &BlockOptions) -> Result<Vec<u8>> {
+    let mut encoded_data = Vec::new();
+    for block in encode_each_block(data, options)? {
+        encoded_data.extend(block.to_bytes()?);
+    }
+    Ok(encoded_data)
+}
+
+/// Splits `data` into blocks as `encode_blocks` does and encodes each on its own, returning them in block order
+pub(crate) fn encode_each_block(data: &[u8], options: &BlockOptions) -> Result<Vec<HuffmanData>> {
+    options.validate()?;
+    let block_size = match options.deterministic {
+        true => options.block_size,
+        false => data
+            .len()
+            .div_ceil(options.threads)
+            .clamp(1, options.block_size),
+    };
+    // Empty data still gets one block, so the output always decodes
+    let blocks: Vec<&[u8]> = match data.is_empty() {
+        true => vec![data],
+        false => data.chunks(block_size).collect(),
+    };
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let encoded: Vec<Mutex<Option<HuffmanData>>> =
+        blocks.iter().map(|_| Mutex::new(None)).collect();
+    let encode_blocks = || -> Result<()> {
+        while !failed.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(block) = blocks.get(index) else {
+                break;
+            };
+            match HuffmanData::with_block_selection(block, &options.huffman) {
+                Ok(block) => *encoded[index].lock().unwrap() = Some(block),
+                Err(e) => {
+                    failed.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    };
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.threads.min(blocks.len()))
+            .map(|_| scope.spawn(encode_blocks))
+            .collect();
+        for worker in workers {
+            worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+        }
+        Ok::<(), HuffmanError<'static>>(())
+    })?;
+
+    // Every block was encoded once the workers all returned without an error
+    Ok(encoded
+        .into_iter()
+        .flat_map(|block| block.into_inner().unwrap())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::members::decode_all_members;
+
+    #[test]
+    fn test_encode_blocks() {
+        let mut input_data = b"this is a test string!".repeat(300);
+        input_data.extend((0..=255).cycle().take(5000));
+        let options = BlockOptions {
+            block_size: 1000,
+            ..Default::default()
+        };
+        let expected_data = encode_blocks(&input_data, &options).unwrap();
+
+        for threads in [2, 3, 8, 64] {
+            let test_output = encode_blocks(
+                &input_data,
+                &BlockOptions {
+                    threads,
+                    ..options.clone()
+                },
+            )
+            .unwrap();
+            assert_eq!(expected_data, test_output);
+        }
+        assert_eq!(
+            input_data,
+            decode_all_members(&mut expected_data.as_slice()).unwrap()
+        );
+
+        let large_blocks = BlockOptions {
+            block_size: 100_000,
+            ..options.clone()
+        };
+        let test_output = encode_blocks(
+            &input_data,
+            &BlockOptions {
+                threads: 4,
+                deterministic: false,
+                ..large_blocks.clone()
+            },
+        )
+        .unwrap();
+        assert_ne!(encode_blocks(&input_data, &large_blocks).unwrap(), test_output);
+        assert_eq!(
+            input_data,
+            decode_all_members(&mut test_output.as_slice()).unwrap()
+        );
+
+        // Sharing the data between threads still keeps every block within block_size
+        let test_output = encode_each_block(
+            &input_data,
+            &BlockOptions {
+                threads: 4,
+                deterministic: false,
+                ..options.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(input_data.len().div_ceil(1000), test_output.len());
+        for block in test_output {
+            assert!(block.decode().unwrap().len() <= 1000);
+        }
+        let test_output = encode_blocks(&[], &options).unwrap();
+        assert!(decode_all_members(&mut test_output.as_slice())
+            .unwrap()
+            .is_empty());
+    }
+}
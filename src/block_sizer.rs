@@ -0,0 +1,109 @@
+use crate::encoding_map::EncodingMap;
+use crate::error::Result;
+use crate::frequency_map;
+use crate::huffman_tree;
+use crate::options::BlockSizerOptions;
+
+/// Estimated header bits per code stored alongside a block
+const CODE_HEADER_BITS: u64 = 24;
+
+/// Estimated fixed header bits of a block
+const BLOCK_HEADER_BITS: u64 = 64;
+
+/// Splits `data` into blocks sized to what compresses best, returning the size of every block in order.
+///
+/// Each block starts at `min_block_size` and keeps doubling while encoding the doubled block with one code
+/// saves more than `min_gain` of the bits needed to encode its two halves separately. Homogeneous regions
+/// therefore grow into large blocks that amortize their headers, while a boundary between differing regions
+/// stops the growth so each region gets a code of its own.
+///
+/// # Arguments
+///
+/// * `data` - The data to split
+/// * `options` - A reference to `BlockSizerOptions` bounding the block sizes and setting the gain threshold
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let mut data = b"abab".repeat(4096);
+/// data.extend(b"xyzw".repeat(4096));
+/// let options = BlockSizerOptions { min_block_size: 1024, min_gain: 0.001, ..Default::default() };
+/// let block_sizes = adaptive_block_sizes(&data, &options).unwrap();
+/// // Each block grows up to the boundary between the two regions but never across it
+/// assert_eq!(block_sizes, vec![16384, 16384]);
+/// ```
+pub fn adaptive_block_sizes(data: &[u8], options: &BlockSizerOptions) -> Result<Vec<usize>> {
+    options.validate()?;
+    let mut block_sizes = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let mut size = options.min_block_size.min(data.len() - start);
+        loop {
+            let doubled = (size * 2)
+                .min(options.max_block_size)
+                .min(data.len() - start);
+            if doubled == size {
+                break;
+            }
+            let split_bits = estimate_bits(&data[start..start + size])?
+                + estimate_bits(&data[start + size..start + doubled])?;
+            let merged_bits = estimate_bits(&data[start..start + doubled])?;
+            if (split_bits as f64 - merged_bits as f64) < options.min_gain * split_bits as f64 {
+                break;
+            }
+            size = doubled;
+        }
+        block_sizes.push(size);
+        start += size;
+    }
+    Ok(block_sizes)
+}
+
+/// Estimates the bits a block takes when Huffman encoded on its own, header included
+fn estimate_bits(block: &[u8]) -> Result<u64> {
+    let frequency_map = frequency_map::count(block);
+    let encoding_map = EncodingMap::new(&huffman_tree::build(&frequency_map)?)?;
+    let payload_bits: u64 = frequency_map
+        .iter()
+        .map(|(byte, &freq)| freq as u64 * encoding_map.get(byte).map_or(0, Vec::len) as u64)
+        .sum();
+    Ok(BLOCK_HEADER_BITS + CODE_HEADER_BITS * frequency_map.len() as u64 + payload_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_block_sizes() {
+        let options = BlockSizerOptions {
+            min_block_size: 256,
+            max_block_size: 4096,
+            min_gain: 0.01,
+        };
+        let input_data: Vec<u8> = b"abcd".repeat(4096);
+
+        let test_output = adaptive_block_sizes(&input_data, &options).unwrap();
+
+        // A uniform input grows every block to the maximum
+        assert_eq!(vec![4096; 4], test_output);
+        assert_eq!(vec![3], adaptive_block_sizes(b"abc", &options).unwrap());
+        assert!(adaptive_block_sizes(
+            b"abc",
+            &BlockSizerOptions {
+                min_block_size: 0,
+                ..options
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_estimate_bits() {
+        // 4 u8s of 2 bit codes plus the header of 2 codes
+        assert_eq!(64 + 2 * 24 + 4, estimate_bits(b"abab").unwrap());
+    }
+}
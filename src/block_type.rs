@@ -0,0 +1,45 @@
+use crate::error::{HuffmanError, Result};
+
+/// How the payload of a block is encoded, stored in 2 bits like the DEFLATE block types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockType {
+    /// The data is stored as is
+    Stored = 0,
+    /// The data is Huffman encoded with the block's encoding map
+    Huffman = 1,
+    /// The data is run-length encoded as `(run length, byte)` pairs
+    Rle = 2,
+}
+
+impl BlockType {
+    /// Returns the 2-bit value identifying the block type
+    pub fn to_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the block type identified by the low 2 bits of `bits`
+    pub fn from_bits(bits: u8) -> Result<BlockType> {
+        match bits & 0b11 {
+            0 => Ok(BlockType::Stored),
+            1 => Ok(BlockType::Huffman),
+            2 => Ok(BlockType::Rle),
+            _ => Err(HuffmanError::DecodeError("Reserved block type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_type_bits() {
+        for block_type in [BlockType::Stored, BlockType::Huffman, BlockType::Rle] {
+            assert_eq!(
+                block_type,
+                BlockType::from_bits(block_type.to_bits()).unwrap()
+            );
+        }
+        assert!(BlockType::from_bits(0b11).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use crate::block_encoder::encode_each_block;
+use crate::container::payload_size;
+use crate::encoding_stats::EncodingStats;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::options::BlockOptions;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// Identifies a block mode encoding, ahead of everything else
+pub(crate) const MAGIC: [u8; 4] = *b"HTTB";
+/// Version of the layout following the magic, bumped whenever the layout changes.
+/// Version 1 lacks the seek index and prefixes every block with its size instead. Version 2 has the block count and
+/// seek index ahead of the blocks, so blocks cannot be appended without moving every one of them. Both are still read
+const SIZED_VERSION: u8 = 1;
+const INDEXED_VERSION: u8 = 2;
+pub(crate) const VERSION: u8 = 3;
+/// Number of u8s in the footer closing a version 3 encoding
+pub(crate) const FOOTER_LEN: u64 = 12;
+
+/// Data split into blocks, each Huffman encoded with a frequency map and tree of its own.
+///
+/// Data mixing differing sections, such as text followed by binary, compresses better block by block than with one
+/// tree fitted to all of it.
+///
+/// `to_bytes` lays the blocks out sequentially as:
+/// * `[u8; 4]` magic `HTTB`
+/// * `u8` version, 3
+/// * every block's self-contained encoding as `HuffmanData::to_bytes` writes it, back to back
+/// * the seek index, one `IndexEntry` per block then one giving the end of the blocks and the decoded length,
+///   each as a `u64` little endian offset in the encoding followed by a `u64` little endian offset in the decoded data
+/// * a `u32` little endian number of blocks and the `u64` little endian offset of the seek index
+///
+/// The index lets `HuffmanArchive` decode just the blocks a read needs, and being last lets `HuffmanArchive::append`
+/// add blocks by overwriting it alone.
+#[derive(Debug)]
+pub struct HuffmanBlocks {
+    /// The blocks in data order
+    pub blocks: Vec<HuffmanData>,
+}
+
+impl HuffmanBlocks {
+    /// Splits `data` into blocks as `options` says and Huffman encodes each on its own, see `encode_blocks`
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    /// * `options` - A reference to `BlockOptions` setting the block size, thread count and how blocks are encoded
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut data = b"this is a test string!".repeat(100);
+    /// data.extend((0..=255u8).cycle().take(2200));
+    /// let options = BlockOptions { block_size: 2200, ..Default::default() };
+    ///
+    /// let huffman_blocks = HuffmanBlocks::new(&data, &options).unwrap();
+    /// assert_eq!(huffman_blocks.blocks.len(), 2);
+    /// let bytes = huffman_blocks.to_bytes().unwrap();
+    /// assert_eq!(HuffmanBlocks::from_bytes(&bytes).unwrap().decode().unwrap(), data);
+    /// ```
+    pub fn new(data: &[u8], options: &BlockOptions) -> Result<HuffmanBlocks> {
+        Ok(HuffmanBlocks {
+            blocks: encode_each_block(data, options)?,
+        })
+    }
+
+    /// Decodes every block and returns their data concatenated in order, failing for a block that does not decode
+    /// to as many u8s as its header says.
+    /// With the `rayon` feature the blocks are decoded in parallel on the rayon thread pool
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        #[cfg(feature = "rayon")]
+        let decoded: Vec<Vec<u8>> = self
+            .blocks
+            .par_iter()
+            .map(HuffmanData::decode_checked)
+            .collect::<Result<_>>()?;
+        #[cfg(not(feature = "rayon"))]
+        let decoded: Vec<Vec<u8>> = self
+            .blocks
+            .iter()
+            .map(HuffmanData::decode_checked)
+            .collect::<Result<_>>()?;
+
+        // Sized by what the blocks decoded to, not by what their headers claim
+        let mut data = Vec::with_capacity(decoded.iter().map(Vec::len).sum());
+        for block in decoded {
+            data.extend(block);
+        }
+        Ok(data)
+    }
+
+    /// Decodes `len` u8s starting `start` u8s into the data like `HuffmanData::decode_range`, skipping the blocks
+    /// the range does not touch. Fails for a block holding fewer u8s than its header says, rather than shifting the
+    /// rest of the range
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Where to start in the decoded data
+    /// * `len` - The most u8s to return
+    pub fn decode_range(&self, start: usize, len: usize) -> Result<Vec<u8>> {
+        let end = start.saturating_add(len);
+        let mut data = Vec::new();
+        let mut block_start = 0usize;
+        for block in &self.blocks {
+            let block_end = block_start
+                .checked_add(block.data_len)
+                .ok_or(HuffmanError::DecodeError(
+                    "Blocks decode to more u8s than a usize holds",
+                ))?;
+            if block_end > start && block_start < end {
+                let from = start.saturating_sub(block_start);
+                let len = end.min(block_end) - block_start - from;
+                let decoded = block.decode_range(from, len)?;
+                if decoded.len() != len {
+                    return Err(HuffmanError::DecodeError(
+                        "Decoded size does not match the header",
+                    ));
+                }
+                data.extend(decoded);
+            }
+            block_start = block_end;
+        }
+        Ok(data)
+    }
+
+    /// Number of u8s the blocks decode to, failing with `PayloadTooLarge` when their sizes add up past `usize::MAX`
+    pub fn data_len(&self) -> Result<usize> {
+        self.blocks.iter().try_fold(0usize, |data_len, block| {
+            data_len
+                .checked_add(block.data_len)
+                .ok_or(HuffmanError::PayloadTooLarge(u64::MAX, usize::MAX as u64))
+        })
+    }
+
+    /// Overall stats of the blocks, their stats summed with `EncodingStats::accumulate`. The sizes count the blocks'
+    /// encodings only, not the seek index `to_bytes` adds
+    pub fn stats(&self) -> EncodingStats {
+        let mut stats = EncodingStats::default();
+        for block in &self.blocks {
+            stats.accumulate(&block.stats);
+        }
+        stats
+    }
+
+    /// Stats of every block in order, to see which parts of the data compress and which do not
+    pub fn block_stats(&self) -> Vec<&EncodingStats> {
+        self.blocks.iter().map(|block| &block.stats).collect()
+    }
+
+    /// Serializes the blocks into the layout described on `HuffmanBlocks`
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut encoded = Vec::with_capacity(self.blocks.len());
+        for block in &self.blocks {
+            encoded.push((block.to_bytes()?, block.data_len as u64));
+        }
+        write_blocks(
+            encoded
+                .iter()
+                .map(|(bytes, data_len)| (bytes.as_slice(), *data_len)),
+        )
+    }
+
+    /// Parses bytes written by `to_bytes` back into `HuffmanBlocks`
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<HuffmanBlocks> {
+        let mut blocks = Vec::new();
+        for (block, data_len) in block_slices(bytes)? {
+            let block = HuffmanData::from_bytes(block)?;
+            if block.data_len as u64 != data_len {
+                return Err(HuffmanError::DecodeError(
+                    "Block encoding index does not match a block's size",
+                ));
+            }
+            blocks.push(block);
+        }
+        Ok(HuffmanBlocks { blocks })
+    }
+}
+
+/// Lays out blocks already in their self-contained encoding, each with the number of u8s it decodes to, as
+/// `HuffmanBlocks::to_bytes` does, failing with `PayloadTooLarge` when the offsets add up past `u64::MAX`
+pub(crate) fn write_blocks<'a>(blocks: impl Iterator<Item = (&'a [u8], u64)>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    write_start(&mut bytes);
+    let mut index = Vec::new();
+    let mut entry = IndexEntry {
+        offset: bytes.len() as u64,
+        data_offset: 0,
+    };
+    for (block, data_len) in blocks {
+        index.push(entry);
+        entry.offset = entry
+            .offset
+            .checked_add(block.len() as u64)
+            .ok_or(HuffmanError::PayloadTooLarge(u64::MAX, u64::MAX))?;
+        entry.data_offset = entry
+            .data_offset
+            .checked_add(data_len)
+            .ok_or(HuffmanError::PayloadTooLarge(u64::MAX, u64::MAX))?;
+        bytes.extend_from_slice(block);
+    }
+    index.push(entry);
+    write_seek_index(&mut bytes, &index)?;
+    Ok(bytes)
+}
+
+/// Slices out the self-contained encoding of every block in `bytes`, any version, and the number of u8s each
+/// decodes to, without decoding them
+pub(crate) fn block_slices(bytes: &[u8]) -> Result<Vec<(&[u8], u64)>> {
+    let mut reader = Cursor::new(bytes);
+    let version = read_version(&mut reader)?;
+    let mut blocks = Vec::new();
+    let end = match version {
+        SIZED_VERSION => {
+            let count = u32::from_le_bytes(read_array(&mut reader)?);
+            let mut rest = &bytes[reader.position() as usize..];
+            for _ in 0..count {
+                let size = u32::from_le_bytes(read_array(&mut rest)?) as usize;
+                let block = rest
+                    .get(..size)
+                    .ok_or(HuffmanError::DecodeError("Block encoding is truncated"))?;
+                // Version 1 has no index, so the length comes from the block's own header
+                blocks.push((block, HuffmanData::from_bytes(block)?.data_len as u64));
+                rest = &rest[size..];
+            }
+            bytes.len() - rest.len()
+        }
+        _ => {
+            let index = read_seek_index(&mut reader, 0, version)?;
+            for pair in index.windows(2) {
+                let block = bytes
+                    .get(pair[0].offset as usize..pair[1].offset as usize)
+                    .ok_or(HuffmanError::DecodeError("Block encoding is truncated"))?;
+                blocks.push((block, pair[1].data_offset - pair[0].data_offset));
+            }
+            match version {
+                INDEXED_VERSION => index[index.len() - 1].offset as usize,
+                _ => reader.position() as usize + FOOTER_LEN as usize,
+            }
+        }
+    };
+    match end == bytes.len() {
+        true => Ok(blocks),
+        false => Err(HuffmanError::DecodeError(
+            "Block encoding has trailing data",
+        )),
+    }
+}
+
+/// Where a block starts, in the encoding and in the decoded data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IndexEntry {
+    pub offset: u64,
+    pub data_offset: u64,
+}
+
+impl IndexEntry {
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.offset.to_le_bytes());
+        bytes.extend_from_slice(&self.data_offset.to_le_bytes());
+    }
+}
+
+/// Reads the magic and version of a block encoding
+pub(crate) fn read_version(reader: &mut impl Read) -> Result<u8> {
+    if read_array::<4>(reader)? != MAGIC {
+        return Err(HuffmanError::DecodeError(
+            "Not a huff-tree-tap block encoding",
+        ));
+    }
+    match read_array::<1>(reader)?[0] {
+        version @ (SIZED_VERSION | INDEXED_VERSION | VERSION) => Ok(version),
+        _ => Err(HuffmanError::DecodeError(
+            "Unsupported block encoding version",
+        )),
+    }
+}
+
+/// Reads the seek index of a block encoding whose magic lies at `base` in `reader`, following `read_version`.
+/// A version 3 index is read from the footer, leaving `reader` at the start of the footer
+pub(crate) fn read_seek_index<R: Read + Seek>(
+    reader: &mut R,
+    base: u64,
+    version: u8,
+) -> Result<Vec<IndexEntry>> {
+    match version {
+        SIZED_VERSION => Err(HuffmanError::DecodeError(
+            "Block encoding predates the seek index",
+        )),
+        INDEXED_VERSION => {
+            let count = u32::from_le_bytes(read_array(reader)?);
+            read_index(reader, count)
+        }
+        _ => {
+            let footer = match reader.seek(SeekFrom::End(0))?.checked_sub(FOOTER_LEN) {
+                Some(footer) if footer >= base => reader.seek(SeekFrom::Start(footer))?,
+                _ => return Err(HuffmanError::DecodeError("Block encoding is truncated")),
+            };
+            let count = u32::from_le_bytes(read_array(reader)?);
+            let index_offset = u64::from_le_bytes(read_array(reader)?);
+            reader.seek(SeekFrom::Start(base.saturating_add(index_offset)))?;
+            let index = read_index(reader, count)?;
+            // The blocks end where the index starts, and the index where the footer does
+            match index[index.len() - 1].offset == index_offset
+                && reader.stream_position()? == footer
+            {
+                true => Ok(index),
+                false => Err(HuffmanError::DecodeError(
+                    "Block encoding index does not line up with its blocks",
+                )),
+            }
+        }
+    }
+}
+
+/// Reads `count` blocks' worth of seek index, checking the offsets only grow
+fn read_index(reader: &mut impl Read, count: u32) -> Result<Vec<IndexEntry>> {
+    let mut index: Vec<IndexEntry> = Vec::new();
+    for _ in 0..=count {
+        let entry = IndexEntry {
+            offset: u64::from_le_bytes(read_array(reader)?),
+            data_offset: u64::from_le_bytes(read_array(reader)?),
+        };
+        if let Some(previous) = index.last() {
+            if entry.offset < previous.offset || entry.data_offset < previous.data_offset {
+                return Err(HuffmanError::DecodeError(
+                    "Block encoding index is out of order",
+                ));
+            }
+        }
+        index.push(entry);
+    }
+    Ok(index)
+}
+
+/// Writes the magic and version of a version 3 encoding
+pub(crate) fn write_start(bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+}
+
+/// Writes the seek index and footer of a version 3 encoding, `index` ending in the entry for the end of the blocks
+pub(crate) fn write_seek_index(bytes: &mut Vec<u8>, index: &[IndexEntry]) -> Result<()> {
+    for entry in index {
+        entry.write_to(bytes);
+    }
+    bytes.extend_from_slice(&payload_size(index.len() as u64 - 1)?.to_le_bytes());
+    bytes.extend_from_slice(&index[index.len() - 1].offset.to_le_bytes());
+    Ok(())
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_huffman_blocks() {
+        // Text then noise-like binary, which one tree fitted to both codes poorly
+        let mut input_data = b"this is a test string!".repeat(500);
+        let mut state = 1u32;
+        input_data.extend((0..11000).map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        }));
+        let options = BlockOptions {
+            block_size: 11000,
+            threads: 2,
+            ..Default::default()
+        };
+
+        let test_output = HuffmanBlocks::new(&input_data, &options).unwrap();
+        let bytes = test_output.to_bytes().unwrap();
+
+        assert_eq!(2, test_output.blocks.len());
+        assert_eq!(input_data.len(), test_output.data_len().unwrap());
+        assert_eq!((input_data.len() * 8) as f32, test_output.stats().data_size);
+        // The text block compresses, the noise block is stored
+        let block_stats = test_output.block_stats();
+        assert!(block_stats[0].ratio > test_output.stats().ratio);
+        assert!(block_stats[1].ratio <= 0.0);
+        assert!(
+            bytes.len()
+                < HuffmanData::new(&input_data)
+                    .unwrap()
+                    .to_bytes()
+                    .unwrap()
+                    .len()
+        );
+        assert_eq!(
+            input_data,
+            HuffmanBlocks::from_bytes(&bytes).unwrap().decode().unwrap()
+        );
+        assert!(HuffmanBlocks::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(
+            input_data[10990..11010],
+            test_output.decode_range(10990, 20).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_huffman_blocks_decode_checks_block_sizes() {
+        let options = BlockOptions {
+            block_size: 64,
+            ..Default::default()
+        };
+        let mut test_output =
+            HuffmanBlocks::new(&b"this is a test string!".repeat(8), &options).unwrap();
+
+        // A header claiming far more than the block holds fails rather than allocating or decoding short
+        for data_len in [usize::MAX / 2 + 1, u32::MAX as usize] {
+            test_output.blocks[0].data_len = data_len;
+            assert!(matches!(
+                test_output.decode(),
+                Err(HuffmanError::DecodeError(_))
+            ));
+        }
+
+        // A block decoding short fails rather than shifting the blocks after it
+        assert!(matches!(
+            test_output.decode_range(0, 128),
+            Err(HuffmanError::DecodeError(_))
+        ));
+
+        // Sizes adding up past what the index stores fail rather than overflowing
+        test_output.blocks[1].data_len = usize::MAX;
+        assert!(matches!(
+            test_output.decode_range(usize::MAX, 1),
+            Err(HuffmanError::DecodeError(_))
+        ));
+        assert!(matches!(
+            test_output.data_len(),
+            Err(HuffmanError::PayloadTooLarge(..))
+        ));
+        assert!(matches!(
+            write_blocks([(&[][..], u64::MAX), (&[][..], 1)].into_iter()),
+            Err(HuffmanError::PayloadTooLarge(..))
+        ));
+
+        // An index disagreeing with a block's header is rejected
+        test_output.blocks[0].data_len = 64;
+        test_output.blocks[1].data_len = 64;
+        let mut bytes = test_output.to_bytes().unwrap();
+        let index_offset = bytes.len() - FOOTER_LEN as usize + 4;
+        let index_offset =
+            u64::from_le_bytes(bytes[index_offset..index_offset + 8].try_into().unwrap()) as usize;
+        // The data offset of the second block's entry
+        bytes[index_offset + 24] += 1;
+        assert!(matches!(
+            HuffmanBlocks::from_bytes(&bytes),
+            Err(HuffmanError::DecodeError(_))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::block_type::BlockType;
+
+use core::fmt;
+
+/// What the built crate supports, for plugins and CLIs that adapt to it or report it in diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Version of the crate
+    pub version: &'static str,
+    /// Codes are packed a word at a time (the `simd` feature), using BMI2 where the CPU has it
+    pub simd: bool,
+    /// Blocks can be encoded in parallel, see `encode_blocks`
+    pub parallel: bool,
+    /// `HuffmanBlocks::decode` decodes blocks in parallel (the `rayon` feature)
+    pub parallel_decode: bool,
+    /// `encode_parallel` encodes a single payload in parallel (the `rayon` feature)
+    pub parallel_encode: bool,
+    /// `AsyncHuffmanReader` and `AsyncHuffmanWriter` are available (the `tokio` feature)
+    pub async_io: bool,
+    /// `EncodingMap` implements `Serialize` and `Deserialize` (the `serde` feature)
+    pub serde: bool,
+    /// `EncodingMapCache` is available (the `cache` feature)
+    pub cache: bool,
+    /// `content_digest` and `HuffmanData::verify_digest` are available (the `digest` feature)
+    pub digest: bool,
+    /// The `legacy` module is available (the `legacy` feature)
+    pub legacy: bool,
+    /// Transforms can be loaded from a C ABI function table (the `plugin` feature)
+    pub plugin: bool,
+    /// `encode_encrypted` and `decode_encrypted` are available (the `crypto` feature)
+    pub crypto: bool,
+    /// `EncodingMap` hashes its codes with FNV-1a (the `fast-hash` feature)
+    pub fast_hash: bool,
+    /// Every block type a payload can be encoded with
+    pub coders: &'static [BlockType],
+    /// Names of the transforms built in, none yet as `TransformPipeline` runs whichever are registered with it
+    pub transforms: &'static [&'static str],
+}
+
+/// Returns what this build of the crate supports
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let capabilities = capabilities();
+/// assert!(capabilities.coders.contains(&BlockType::Huffman));
+/// println!("{}", capabilities);
+/// ```
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        simd: cfg!(feature = "simd"),
+        parallel: true,
+        parallel_decode: cfg!(feature = "rayon"),
+        parallel_encode: cfg!(feature = "rayon"),
+        async_io: cfg!(feature = "tokio"),
+        serde: cfg!(feature = "serde"),
+        cache: cfg!(feature = "cache"),
+        digest: cfg!(feature = "digest"),
+        legacy: cfg!(feature = "legacy"),
+        plugin: cfg!(feature = "plugin"),
+        crypto: cfg!(feature = "crypto"),
+        fast_hash: cfg!(feature = "fast-hash"),
+        coders: &[BlockType::Stored, BlockType::Huffman, BlockType::Rle],
+        transforms: &[],
+    }
+}
+
+/// One `name: value` line per capability
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "simd: {}", self.simd)?;
+        writeln!(f, "parallel: {}", self.parallel)?;
+        writeln!(f, "parallel_decode: {}", self.parallel_decode)?;
+        writeln!(f, "parallel_encode: {}", self.parallel_encode)?;
+        writeln!(f, "async_io: {}", self.async_io)?;
+        writeln!(f, "serde: {}", self.serde)?;
+        writeln!(f, "cache: {}", self.cache)?;
+        writeln!(f, "digest: {}", self.digest)?;
+        writeln!(f, "legacy: {}", self.legacy)?;
+        writeln!(f, "plugin: {}", self.plugin)?;
+        writeln!(f, "crypto: {}", self.crypto)?;
+        writeln!(f, "fast_hash: {}", self.fast_hash)?;
+        writeln!(f, "coders: {:?}", self.coders)?;
+        write!(f, "transforms: {:?}", self.transforms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let test_output = capabilities();
+
+        assert_eq!(cfg!(feature = "simd"), test_output.simd);
+        assert!(test_output
+            .to_string()
+            .starts_with(&format!("version: {}\n", env!("CARGO_PKG_VERSION"))));
+    }
+}
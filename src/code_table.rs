@@ -0,0 +1,269 @@
+//! Human-editable text forms of an `EncodingMap`, for hand tuning a code table and loading it back.
+//!
+//! Both forms give one symbol per line, as its decimal value (or `0x` hex when read) or `escape` for the escape code,
+//! with its code as a string of 0s and 1s:
+//!
+//! ```toml
+//! [codes]
+//! 32 = "00" # ' '
+//! 97 = "010" # 'a'
+//! escape = "11"
+//! ```
+//!
+//! ```csv
+//! symbol,code
+//! 32,00
+//! 97,010
+//! escape,11
+//! ```
+//!
+//! Importing checks the table as strictly as `EncodingMap::from` does, so a hand edited table that is no longer a
+//! prefix code is rejected rather than decoding wrongly.
+
+use crate::data::{BitVec, BitVector};
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+const ESCAPE_KEY: &str = "escape";
+const CSV_HEADER: &str = "symbol,code";
+const TOML_TABLE: &str = "[codes]";
+
+impl EncodingMap {
+    /// Writes the codes as a TOML `[codes]` table, see the `code_table` module docs for the layout
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let huffman_data = HuffmanData::new(&b"abracadabra".repeat(8)).unwrap();
+    /// let toml = huffman_data.encoding_map.to_toml();
+    /// assert!(toml.contains("97 = \"0\" # 'a'"));
+    /// assert_eq!(EncodingMap::from_toml(&toml).unwrap(), huffman_data.encoding_map);
+    /// ```
+    pub fn to_toml(&self) -> String {
+        let mut toml = String::from("# Code table, one symbol = \"code\" per line\n");
+        toml.push_str(TOML_TABLE);
+        toml.push('\n');
+        for (symbol, code) in self.sorted_codes() {
+            let _ = match symbol {
+                Some(symbol) if symbol.is_ascii_graphic() || symbol == b' ' => {
+                    writeln!(toml, "{} = \"{}\" # '{}'", symbol, code, symbol as char)
+                }
+                Some(symbol) => writeln!(toml, "{} = \"{}\"", symbol, code),
+                None => writeln!(toml, "{} = \"{}\"", ESCAPE_KEY, code),
+            };
+        }
+        toml
+    }
+
+    /// Reads a table written by `to_toml`, or edited by hand, back into an `EncodingMap`
+    ///
+    /// # Arguments
+    ///
+    /// * `toml` - A `[codes]` table of `symbol = "code"` lines, blank lines and `#` comments being skipped
+    pub fn from_toml(toml: &str) -> Result<EncodingMap> {
+        let mut in_table = false;
+        let mut entries = Vec::new();
+        for (line_number, line) in toml
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+        {
+            // No key or code can hold a #, so everything after one is a comment
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                match line == TOML_TABLE {
+                    true => in_table = true,
+                    false => {
+                        return Err(HuffmanError::TableParseError(line_number, "Unknown table"))
+                    }
+                }
+                continue;
+            }
+            if !in_table {
+                return Err(HuffmanError::TableParseError(
+                    line_number,
+                    "Codes have to be under [codes]",
+                ));
+            }
+            let Some((key, code)) = line.split_once('=') else {
+                return Err(HuffmanError::TableParseError(
+                    line_number,
+                    "Expected symbol = \"code\"",
+                ));
+            };
+            let code = code
+                .trim()
+                .strip_prefix('"')
+                .and_then(|code| code.strip_suffix('"'))
+                .ok_or(HuffmanError::TableParseError(
+                    line_number,
+                    "Code has to be a quoted string",
+                ))?;
+            entries.push((line_number, key.trim(), code));
+        }
+        from_entries(&entries)
+    }
+
+    /// Writes the codes as CSV with a `symbol,code` header, see the `code_table` module docs for the layout
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let huffman_data = HuffmanData::new(&b"abracadabra".repeat(8)).unwrap();
+    /// let csv = huffman_data.encoding_map.to_csv();
+    /// assert!(csv.starts_with("symbol,code\n97,0\n"));
+    /// assert_eq!(EncodingMap::from_csv(&csv).unwrap(), huffman_data.encoding_map);
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv.push('\n');
+        for (symbol, code) in self.sorted_codes() {
+            let _ = match symbol {
+                Some(symbol) => writeln!(csv, "{},{}", symbol, code),
+                None => writeln!(csv, "{},{}", ESCAPE_KEY, code),
+            };
+        }
+        csv
+    }
+
+    /// Reads a table written by `to_csv`, or edited by hand, back into an `EncodingMap`
+    ///
+    /// # Arguments
+    ///
+    /// * `csv` - `symbol,code` rows, the header being optional and blank lines skipped
+    pub fn from_csv(csv: &str) -> Result<EncodingMap> {
+        let mut entries = Vec::new();
+        for (line_number, line) in csv
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line))
+        {
+            let line = line.trim();
+            if line.is_empty() || (line_number == 1 && line == CSV_HEADER) {
+                continue;
+            }
+            let Some((symbol, code)) = line.split_once(',') else {
+                return Err(HuffmanError::TableParseError(
+                    line_number,
+                    "Expected symbol,code",
+                ));
+            };
+            entries.push((line_number, symbol.trim(), code.trim()));
+        }
+        from_entries(&entries)
+    }
+
+    /// Every code ordered by symbol, `None` standing for the escape and coming last
+    fn sorted_codes(&self) -> Vec<(Option<u8>, String)> {
+        let mut codes: Vec<(Option<u8>, String)> = self
+            .iter()
+            .map(|(&symbol, code)| (Some(symbol), code.to_string()))
+            .collect();
+        codes.sort();
+        if let Some(escape) = self.get_escape() {
+            codes.push((None, escape.to_string()));
+        }
+        codes
+    }
+}
+
+/// Builds the map from `(line number, symbol, code)` entries, rejecting unknown or repeated symbols
+fn from_entries(entries: &[(usize, &str, &str)]) -> Result<EncodingMap> {
+    let mut map: HashMap<u8, String> = HashMap::new();
+    let mut escape = None;
+    for &(line_number, symbol, code) in entries {
+        let repeated = match symbol {
+            ESCAPE_KEY => escape.replace(code).is_some(),
+            symbol => {
+                let symbol = parse_symbol(symbol).ok_or(HuffmanError::TableParseError(
+                    line_number,
+                    "Symbol has to be 0 to 255, 0x00 to 0xff or escape",
+                ))?;
+                map.insert(symbol, code.to_string()).is_some()
+            }
+        };
+        if repeated {
+            return Err(HuffmanError::TableParseError(
+                line_number,
+                "Symbol is given a code twice",
+            ));
+        }
+    }
+
+    let encoding_map = EncodingMap::from(map)?;
+    match escape {
+        Some(escape) if escape.is_empty() || escape.chars().any(|c| c != '0' && c != '1') => Err(
+            HuffmanError::DecodeError("Escape code has to be a non-empty string of 0s and 1s"),
+        ),
+        Some(escape) => encoding_map.with_escape(BitVec::from_string(escape)),
+        None => Ok(encoding_map),
+    }
+}
+
+fn parse_symbol(symbol: &str) -> Option<u8> {
+    match symbol.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => symbol.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_table_round_trip() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10"), (0xff, "110")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_data = EncodingMap::from(map)
+            .unwrap()
+            .with_escape(BitVec::from_string("111"))
+            .unwrap();
+
+        let toml = input_data.to_toml();
+        let csv = input_data.to_csv();
+
+        assert_eq!("symbol,code\n97,0\n98,10\n255,110\nescape,111\n", csv);
+        assert_eq!(input_data, EncodingMap::from_toml(&toml).unwrap());
+        assert_eq!(input_data, EncodingMap::from_csv(&csv).unwrap());
+        assert_eq!(
+            input_data,
+            EncodingMap::from_csv("0x61,0\n0x62 , 10\n0xff,110\nescape,111").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_code_table_validation() {
+        assert!(matches!(
+            EncodingMap::from_toml("97 = \"0\""),
+            Err(HuffmanError::TableParseError(1, _))
+        ));
+        assert!(matches!(
+            EncodingMap::from_toml("[codes]\n97 = \"0\"\n97 = \"10\""),
+            Err(HuffmanError::TableParseError(3, _))
+        ));
+        assert!(matches!(
+            EncodingMap::from_csv("symbol,code\n256,0"),
+            Err(HuffmanError::TableParseError(2, _))
+        ));
+        assert!(matches!(
+            EncodingMap::from_csv("97,0\n98,01"),
+            Err(HuffmanError::PrefixCollision(97, 98))
+        ));
+        assert!(EncodingMap::from_csv("97,0\nescape,01").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::data::BitVec;
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::options::DecodeOptions;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Decodes a payload produced by the `huffman-compress` crate, easing migration of stored data into this crate.
+///
+/// `huffman-compress` writes codes into a `bit_vec::BitVec`, whose `to_bytes()` packs the first bit into the
+/// high-order bit of the first byte and zero fills the last byte. The padding can look like symbols, so the
+/// number of encoded symbols has to be supplied alongside the bytes.
+///
+/// A book with a single symbol codes it in no bits at all, leaving nothing to bound the count by, so such books are
+/// only decoded by `decode_huffman_compress_with` under a `max_output` and without a `max_symbols_per_bit`.
+///
+/// # Arguments
+///
+/// * `book` - The `huffman-compress` book as symbol to code bits, e.g. collected from `Book::into_inner()`
+/// * `encoded_data` - The bytes of the encoded `BitVec`
+/// * `num_symbols` - How many symbols were encoded
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use std::collections::BTreeMap;
+///
+/// let book: BTreeMap<u8, Vec<bool>> = [
+///     (b'a', vec![false]),
+///     (b'b', vec![true, false]),
+///     (b'c', vec![true, true]),
+/// ]
+/// .into_iter()
+/// .collect();
+/// let decoded_data = decode_huffman_compress(&book, &[0x58], 4).unwrap();
+/// assert_eq!(decoded_data, b"abca");
+///
+/// // Re-encode with this crate to migrate the data
+/// let huffman_data = HuffmanData::new(&decoded_data).unwrap();
+/// assert_eq!(huffman_data.decode().unwrap(), decoded_data);
+/// ```
+pub fn decode_huffman_compress(
+    book: &BTreeMap<u8, Vec<bool>>,
+    encoded_data: &[u8],
+    num_symbols: usize,
+) -> Result<Vec<u8>> {
+    decode_huffman_compress_with(book, encoded_data, num_symbols, &DecodeOptions::default())
+}
+
+/// Decodes a payload produced by the `huffman-compress` crate under the given `DecodeOptions`, whose `max_output`
+/// and `max_growth` bound `num_symbols` before anything is allocated. A book with a single symbol codes it in no
+/// bits at all, so such books fail with `OptionsError` unless `max_output` is set to stop a forged count, and are
+/// only decoded past `max_symbols_per_bit` symbols per bit of `encoded_data` once it is `None`
+///
+/// # Arguments
+///
+/// * `book` - The `huffman-compress` book as symbol to code bits, e.g. collected from `Book::into_inner()`
+/// * `encoded_data` - The bytes of the encoded `BitVec`
+/// * `num_symbols` - How many symbols were encoded
+/// * `options` - A reference to `DecodeOptions` controlling output limits
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use std::collections::BTreeMap;
+///
+/// let book: BTreeMap<u8, Vec<bool>> = [(b'z', vec![])].into_iter().collect();
+/// let options = DecodeOptions {
+///     max_output: Some(1024),
+///     max_symbols_per_bit: None,
+///     ..Default::default()
+/// };
+/// assert_eq!(decode_huffman_compress_with(&book, &[], 3, &options).unwrap(), b"zzz");
+/// assert!(decode_huffman_compress_with(&book, &[], usize::MAX, &options).is_err());
+/// ```
+pub fn decode_huffman_compress_with(
+    book: &BTreeMap<u8, Vec<bool>>,
+    encoded_data: &[u8],
+    num_symbols: usize,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>> {
+    options.check_output(num_symbols, encoded_data.len())?;
+    let input_bits = encoded_data.len().saturating_mul(8);
+    // A book with a single symbol gives it an empty code, every symbol is then that one
+    if let [(&symbol, code)] = book.iter().collect::<Vec<_>>().as_slice() {
+        if code.is_empty() {
+            if options.max_output.is_none() {
+                return Err(HuffmanError::OptionsError(
+                    "A book coding its only symbol in no bits needs max_output to bound the symbol count",
+                ));
+            }
+            options.check_run(num_symbols, input_bits)?;
+            return Ok(vec![symbol; num_symbols]);
+        }
+    }
+
+    let encoding_map = encoding_map_from_book(book)?;
+    // Every other code is at least a bit long, so the data can't hold more symbols than it has bits
+    if num_symbols > input_bits {
+        return Err(HuffmanError::DecodeError(
+            "Encoded data ends before the expected number of symbols",
+        ));
+    }
+    // Grown as symbols decode rather than sized by the count the caller passed
+    let mut data = Vec::new();
+    let max_len = encoding_map.get_longest_code();
+    let mut code = BitVec::with_capacity(max_len);
+    let bits = encoded_data
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |shift| (byte >> shift) & 1));
+
+    for bit in bits {
+        if data.len() == num_symbols {
+            break;
+        }
+        code.push(bit);
+        if let Some(&byte) = encoding_map.get_inverse(&code) {
+            options.check_run(data.len() + 1, input_bits)?;
+            data.push(byte);
+            code.clear();
+        } else if code.len() >= max_len {
+            return Err(HuffmanError::DecodeError(
+                "Encoded data holds a code longer than any in the book",
+            ));
+        }
+    }
+
+    if data.len() < num_symbols {
+        return Err(HuffmanError::DecodeError(
+            "Encoded data ends before the expected number of symbols",
+        ));
+    }
+    Ok(data)
+}
+
+/// Converts a `huffman-compress` book into an `EncodingMap`
+pub fn encoding_map_from_book(book: &BTreeMap<u8, Vec<bool>>) -> Result<EncodingMap> {
+    let map: HashMap<u8, String> = book
+        .iter()
+        .map(|(&symbol, code)| {
+            let code = code
+                .iter()
+                .map(|&bit| if bit { '1' } else { '0' })
+                .collect();
+            (symbol, code)
+        })
+        .collect();
+    EncodingMap::from(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(codes: &[(u8, &str)]) -> BTreeMap<u8, Vec<bool>> {
+        codes
+            .iter()
+            .map(|(symbol, code)| (*symbol, code.chars().map(|c| c == '1').collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_huffman_compress() {
+        // Code "0" is all padding, so the symbol count has to stop the decode
+        let input_book = book(&[(b'a', "0"), (b'b', "10"), (b'c', "11")]);
+        let input_data: Vec<u8> = vec![0b0101_1010, 0b1110_0000];
+
+        let expected_data: Vec<u8> = Vec::from("abcabcb");
+
+        let test_output = decode_huffman_compress(&input_book, &input_data, 7).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert!(decode_huffman_compress(&input_book, &input_data, 17).is_err());
+        assert!(decode_huffman_compress(&input_book, &input_data, usize::MAX).is_err());
+        // An incomplete book, where no code starts with 11
+        let input_book = book(&[(b'a', "0"), (b'b', "10")]);
+        assert!(decode_huffman_compress(&input_book, &[0b0111_1111], 2).is_err());
+    }
+
+    #[test]
+    fn test_decode_huffman_compress_single_symbol() {
+        let input_book = book(&[(b'z', "")]);
+        let options = DecodeOptions {
+            max_output: Some(16),
+            max_symbols_per_bit: None,
+            ..Default::default()
+        };
+
+        let test_output = decode_huffman_compress_with(&input_book, &[], 3, &options).unwrap();
+
+        assert_eq!(vec![b'z'; 3], test_output);
+        // The empty code decodes symbols without reading a bit, which max_symbols_per_bit caps
+        let options = DecodeOptions {
+            max_symbols_per_bit: Some(1),
+            ..options
+        };
+        assert!(decode_huffman_compress_with(&input_book, &[0], 8, &options).is_ok());
+        assert!(matches!(
+            decode_huffman_compress_with(&input_book, &[0], 9, &options),
+            Err(HuffmanError::DecodeError(_))
+        ));
+        assert!(matches!(
+            decode_huffman_compress_with(&input_book, &[], 1, &options),
+            Err(HuffmanError::DecodeError(_))
+        ));
+        // Nothing bounds the count without a max_output
+        assert!(matches!(
+            decode_huffman_compress(&input_book, &[], 1 << 40),
+            Err(HuffmanError::OptionsError(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_huffman_compress_limits() {
+        let input_book = book(&[(b'z', "")]);
+        let options = DecodeOptions {
+            max_output: Some(16),
+            max_symbols_per_bit: None,
+            ..Default::default()
+        };
+
+        assert!(decode_huffman_compress_with(&input_book, &[], 16, &options).is_ok());
+        assert!(decode_huffman_compress_with(&input_book, &[], 17, &options).is_err());
+        assert!(decode_huffman_compress_with(&input_book, &[], usize::MAX, &options).is_err());
+
+        let input_book = book(&[(b'a', "0"), (b'b', "1")]);
+        let options = DecodeOptions {
+            max_growth: Some(4),
+            ..Default::default()
+        };
+
+        assert!(decode_huffman_compress_with(&input_book, &[0x55], 4, &options).is_ok());
+        assert!(decode_huffman_compress_with(&input_book, &[0x55], 5, &options).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::{HuffmanError, Result};
+use crate::fixed_buffer::FixedBuffer;
+
+/// Length of the longest code a `ConstDecodeTable` accepts
+pub const MAX_CONST_CODE_LEN: usize = 32;
+
+/// A canonical Huffman decode table built entirely by const evaluation, for targets that cannot build tables at runtime.
+///
+/// The table holds only the number of codes of each length and the symbols ordered by (code length, symbol),
+/// matching the canonical codes this crate assigns. Invalid length arrays fail to compile when the table is built
+/// in a const context, see [`const_decode_table!`](crate::const_decode_table).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDecodeTable<const N: usize> {
+    counts: [u16; MAX_CONST_CODE_LEN + 1],
+    symbols: [u8; N],
+}
+
+impl<const N: usize> ConstDecodeTable<N> {
+    /// Builds the table from the code length of every symbol, panicking (a compile error in const context) if the
+    /// lengths cannot form a prefix code.
+    ///
+    /// # Arguments
+    ///
+    /// * `lengths` - The code length of each u8, indexed by symbol. A length of 0 means the symbol has no code
+    pub const fn from_lengths(lengths: &[u8; N]) -> Self {
+        assert!(N <= 256, "A decode table covers at most 256 symbols");
+
+        let mut counts = [0u16; MAX_CONST_CODE_LEN + 1];
+        let mut symbol = 0;
+        while symbol < N {
+            let length = lengths[symbol] as usize;
+            assert!(length <= MAX_CONST_CODE_LEN, "Code length too long");
+            if length > 0 {
+                counts[length] += 1;
+            }
+            symbol += 1;
+        }
+
+        // Kraft inequality: every length may use at most the codes left over by shorter lengths
+        let mut left: i64 = 1;
+        let mut length = 1;
+        while length <= MAX_CONST_CODE_LEN {
+            left = (left << 1) - counts[length] as i64;
+            assert!(left >= 0, "Code lengths are over-subscribed");
+            length += 1;
+        }
+        assert!(left < 1 << MAX_CONST_CODE_LEN, "No symbol has a code");
+
+        let mut offsets = [0usize; MAX_CONST_CODE_LEN + 2];
+        let mut length = 1;
+        while length <= MAX_CONST_CODE_LEN {
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+            length += 1;
+        }
+        let mut symbols = [0u8; N];
+        let mut symbol = 0;
+        while symbol < N {
+            let length = lengths[symbol] as usize;
+            if length > 0 {
+                symbols[offsets[length]] = symbol as u8;
+                offsets[length] += 1;
+            }
+            symbol += 1;
+        }
+
+        Self { counts, symbols }
+    }
+
+    /// Decodes MSB-first packed codes until `output` is full, without allocating
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded_data` - The codes packed from the high-order bit of the first byte, the last byte may be padded
+    /// * `output` - Buffer receiving the decoded u8s, its length is the number of symbols to decode
+    pub fn decode(&self, encoded_data: &[u8], output: &mut [u8]) -> Result<()> {
+        let mut written = 0;
+        self.decode_symbols(encoded_data, output.len(), |byte| {
+            output[written] = byte;
+            written += 1;
+            Ok(())
+        })
+    }
+
+    /// Decodes `num_symbols` u8s into a `FixedBuffer` whose capacity is fixed at compile time,
+    /// failing instead of growing when the data needs more than `CAP` u8s
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded_data` - The codes packed from the high-order bit of the first byte, the last byte may be padded
+    /// * `num_symbols` - How many symbols were encoded
+    pub fn decode_fixed<const CAP: usize>(
+        &self,
+        encoded_data: &[u8],
+        num_symbols: usize,
+    ) -> Result<FixedBuffer<CAP>> {
+        let mut output = FixedBuffer::new();
+        self.decode_symbols(encoded_data, num_symbols, |byte| output.push(byte))?;
+        Ok(output)
+    }
+
+    /// Walks the canonical code bit by bit, handing each of the `num_symbols` decoded u8s to `emit`
+    fn decode_symbols(
+        &self,
+        encoded_data: &[u8],
+        num_symbols: usize,
+        mut emit: impl FnMut(u8) -> Result<()>,
+    ) -> Result<()> {
+        let mut decoded = 0;
+        // `first` is the first code of the current length and `index` the position of its symbol
+        let (mut code, mut first, mut index, mut code_len) = (0u64, 0u64, 0usize, 0usize);
+
+        'bytes: for &byte in encoded_data {
+            for shift in (0..8).rev() {
+                if decoded == num_symbols {
+                    break 'bytes;
+                }
+                code |= ((byte >> shift) & 1) as u64;
+                code_len += 1;
+                let count = self.counts[code_len] as u64;
+                if code < first + count {
+                    emit(self.symbols[index + (code - first) as usize])?;
+                    decoded += 1;
+                    (code, first, index, code_len) = (0, 0, 0, 0);
+                } else if code_len == MAX_CONST_CODE_LEN {
+                    return Err(HuffmanError::DecodeError("Invalid code in encoded data"));
+                } else {
+                    index += count as usize;
+                    first = (first + count) << 1;
+                    code <<= 1;
+                }
+            }
+        }
+
+        if decoded < num_symbols {
+            return Err(HuffmanError::DecodeError(
+                "Encoded data ends before the expected number of symbols",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`ConstDecodeTable`] from a code length array at compile time.
+/// Lengths that cannot form a prefix code are a compile error rather than a runtime failure.
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// // b = 0, a = 10, c = 110, d = 111
+/// const LENGTHS: [u8; 256] = {
+///     let mut lengths = [0u8; 256];
+///     lengths[b'a' as usize] = 2;
+///     lengths[b'b' as usize] = 1;
+///     lengths[b'c' as usize] = 3;
+///     lengths[b'd' as usize] = 3;
+///     lengths
+/// };
+/// static TABLE: ConstDecodeTable<256> = const_decode_table!(LENGTHS);
+///
+/// let mut output = [0u8; 4];
+/// TABLE.decode(&[0b1001_1011, 0b1000_0000], &mut output).unwrap();
+/// assert_eq!(&output, b"abcd");
+/// ```
+#[macro_export]
+macro_rules! const_decode_table {
+    ($lengths:expr) => {{
+        const TABLE: $crate::ConstDecodeTable<{ $lengths.len() }> =
+            $crate::ConstDecodeTable::from_lengths(&$lengths);
+        TABLE
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTHS: [u8; 4] = [2, 1, 3, 3];
+
+    #[test]
+    fn test_const_decode_table() {
+        const TABLE: ConstDecodeTable<4> = ConstDecodeTable::from_lengths(&LENGTHS);
+        // 0 = 10, 1 = 0, 2 = 110, 3 = 111
+        let input_data = [0b1001_1011, 0b1000_0000];
+        let expected_data = [0, 1, 2, 3, 1];
+
+        let mut test_output = [0u8; 5];
+        TABLE.decode(&input_data, &mut test_output).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        // The 7 padding bits decode as 1s, a 13th symbol runs past the data
+        assert!(TABLE.decode(&input_data, &mut [0u8; 13]).is_err());
+    }
+
+    #[test]
+    fn test_const_decode_table_macro() {
+        let table = const_decode_table!(LENGTHS);
+
+        assert_eq!(ConstDecodeTable::from_lengths(&LENGTHS), table);
+    }
+
+    #[test]
+    fn test_decode_fixed() {
+        const TABLE: ConstDecodeTable<4> = ConstDecodeTable::from_lengths(&LENGTHS);
+        let input_data = [0b1001_1011, 0b1000_0000];
+
+        let test_output = TABLE.decode_fixed::<4>(&input_data, 4).unwrap();
+
+        assert_eq!([0, 1, 2, 3], *test_output);
+        assert!(TABLE.decode_fixed::<4>(&input_data, 5).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_const_decode_table_over_subscribed() {
+        ConstDecodeTable::from_lengths(&[1, 1, 1]);
+    }
+}
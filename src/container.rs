@@ -0,0 +1,1302 @@
+use crate::block_encoder::encode_blocks;
+use crate::block_type::BlockType;
+use crate::data::{BitVec, BitVector};
+use crate::encoding_map::{EncodingMap, ESCAPE_SYMBOL};
+use crate::encoding_stats::EncodingStats;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::metadata::Metadata;
+use crate::options::{BlockOptions, ContainerOptions, HuffmanOptions, MapForm, MAX_STREAMS};
+use crate::packing::Packing;
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// Identifies a self-contained encoding, ahead of everything else
+pub(crate) const MAGIC: [u8; 4] = *b"HTTC";
+/// Version of the layout following the magic, bumped whenever the layout changes.
+/// Versions 1 and 2 store every code in full and tell the packing of Huffman payloads apart by their version alone
+const PADDED_VERSION: u8 = 1;
+const DENSE_VERSION: u8 = 2;
+/// Version 3 stores the packing in a u8 of its own and canonical maps as code lengths
+const UNCHECKED_VERSION: u8 = 3;
+/// Version 4 adds the CRC-32 of the decoded data, only data without a checksum is still written as version 3
+const CHECKED_VERSION: u8 = 4;
+/// Version 5 adds the metadata, only data with metadata is written as version 5
+const METADATA_VERSION: u8 = 5;
+/// Version 6 widens the decoded size and stream sizes to `u64`, only data with a size past `MAX_PAYLOAD_SIZE`, or
+/// whose size is only known once its payload is written, is written as version 6
+pub(crate) const VERSION: u8 = 6;
+/// Offsets of the decoded size and checksum in a version 6 header
+pub(crate) const WIDE_DATA_LEN_OFFSET: u64 = 7;
+pub(crate) const WIDE_CHECKSUM_OFFSET: u64 = 15;
+
+/// How the payload of a Huffman block is packed, in the packing u8 of a version 3 to 6 header
+pub(crate) const PADDED_PACKING: u8 = 0;
+pub(crate) const DENSE_PACKING: u8 = 1;
+
+/// How the encoding map of a version 3 to 6 header is stored
+pub(crate) const CODES_FORM: u8 = 0;
+pub(crate) const SPARSE_LENGTHS_FORM: u8 = 1;
+pub(crate) const LENGTH_TABLE_FORM: u8 = 2;
+pub(crate) const TREE_FORM: u8 = 3;
+
+/// Leaf number standing for no escape leaf in the tree form
+const NO_ESCAPE_LEAF: u16 = u16::MAX;
+
+/// Number of u8s in the code length table, one per u8 and one for the escape
+const LENGTH_TABLE_LEN: usize = ESCAPE_SYMBOL as usize + 1;
+
+/// Longest code the header can store, as code lengths are stored in a `u8`.
+/// Only a hand built map or one with the escape and all 256 u8s on a single deepest branch can exceed it
+pub(crate) const MAX_HEADER_CODE_LEN: usize = u8::MAX as usize;
+
+/// Largest size a `u32` size field stores: the largest number of u8s a version 1 to 5 header, a `FormatV0` payload
+/// or an adaptive encoding may describe, and the largest stream a version 1 to 5 header may hold.
+/// Self-contained encodings of larger data are written as version 6, with `u64` sizes
+pub const MAX_PAYLOAD_SIZE: u64 = u32::MAX as u64;
+
+/// Everything a decoder needs ahead of the payload of a self-contained encoding.
+///
+/// Laid out as:
+/// * `[u8; 4]` magic `HTTC`
+/// * `u8` version, 6
+/// * `u8` packing of a Huffman payload, 0 for `Packing::Padded` and 1 for `Packing::Dense`
+/// * `u8` block type
+/// * `u64` little endian number of decoded u8s
+/// * `u32` little endian CRC-32 of the decoded u8s
+/// * `u8` map form, then the encoding map in that form, every symbol being a `u8` or 256 for the escape:
+///   * 0: `u16` little endian number of codes, then per code a `u16` symbol, a `u8` code length and the code bits
+///     packed MSB first into as few u8s as hold them
+///   * 1: `u16` little endian number of codes, then per code a `u16` symbol and a `u8` code length
+///   * 2: 257 `u8` code lengths, one per symbol and 0 for a symbol without a code
+///   * 3: the code tree in pre-order, see `write_tree`
+/// * `u8` number of streams, then a `u64` little endian byte size for every stream but the last
+/// * the metadata, see `Metadata::write_to`
+///
+/// Forms 1 and 2 stand for the canonical codes of those lengths, as DEFLATE describes its trees. They are written
+/// whenever the map is canonical, whichever is smaller, and form 0 only for a map that is not. Form 3 is only written
+/// when `ContainerOptions` asks for `MapForm::Tree`.
+/// Version 6 is only written for data whose size or a stream size is past `MAX_PAYLOAD_SIZE`, and needs a CRC-32.
+/// Version 5 stores both sizes as `u32`, and is written for any other data with metadata. Version 4 also lacks the
+/// metadata, and is written for data without any. Version 3 also lacks the CRC-32. Versions 1 and 2 also lack the
+/// packing and map form u8s, always storing the map in form 0. All five are still read.
+///
+/// The payload follows and runs to the end of the data, so the header can be written before the payload size is known.
+///
+/// Only Huffman blocks carry codes and more than one stream, and a Huffman block always carries at least one code.
+/// Empty data is a stored block with no codes, a single stream and an empty payload, 19 u8s in all.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Header {
+    pub block_type: BlockType,
+    pub data_len: u64,
+    pub encoding_map: EncodingMap,
+    pub stream_sizes: Vec<usize>,
+    pub packing: Packing,
+    /// `None` for a version 1 to 3 header, and written as one
+    pub checksum: Option<u32>,
+    /// Empty for a version 1 to 4 header, and written as one
+    pub metadata: Metadata,
+}
+
+impl Header {
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        self.write_to_with(writer, &ContainerOptions::default())
+    }
+
+    pub fn write_to_with(&self, writer: &mut impl Write, options: &ContainerOptions) -> Result<()> {
+        let wide = self.data_len > MAX_PAYLOAD_SIZE
+            || self
+                .stream_sizes
+                .iter()
+                .any(|&size| size as u64 > MAX_PAYLOAD_SIZE);
+        self.write_sized(writer, options, wide)
+    }
+
+    /// Writes a version 6 header whatever the sizes, so the size and checksum written can be overwritten in place
+    /// once known, at `WIDE_DATA_LEN_OFFSET` and `WIDE_CHECKSUM_OFFSET`
+    pub fn write_wide_to(&self, writer: &mut impl Write) -> Result<()> {
+        self.write_sized(writer, &ContainerOptions::default(), true)
+    }
+
+    fn write_sized(
+        &self,
+        writer: &mut impl Write,
+        options: &ContainerOptions,
+        wide: bool,
+    ) -> Result<()> {
+        self.check_members().map_err(HuffmanError::TreeError)?;
+        // Only version 6 headers, which always carry a checksum, hold sizes past MAX_PAYLOAD_SIZE
+        if wide && self.checksum.is_none() {
+            let size = self
+                .stream_sizes
+                .iter()
+                .map(|&size| size as u64)
+                .fold(self.data_len, u64::max);
+            return Err(HuffmanError::PayloadTooLarge(size, MAX_PAYLOAD_SIZE));
+        }
+        let mut header = MAGIC.to_vec();
+        header.push(match self.checksum {
+            Some(_) if wide => VERSION,
+            Some(_) if !self.metadata.is_empty() => METADATA_VERSION,
+            Some(_) => CHECKED_VERSION,
+            None => UNCHECKED_VERSION,
+        });
+        header.push(match self.packing {
+            Packing::Padded => PADDED_PACKING,
+            Packing::Dense => DENSE_PACKING,
+        });
+        header.push(self.block_type.to_bits());
+        match wide {
+            true => header.extend_from_slice(&self.data_len.to_le_bytes()),
+            false => header.extend_from_slice(&payload_size(self.data_len)?.to_le_bytes()),
+        }
+        if let Some(checksum) = self.checksum {
+            header.extend_from_slice(&checksum.to_le_bytes());
+        }
+        self.write_map(&mut header, options.map_form)?;
+
+        header.push(self.stream_count()?);
+        for &size in &self.stream_sizes {
+            match wide {
+                true => header.extend_from_slice(&(size as u64).to_le_bytes()),
+                false => header.extend_from_slice(&payload_size(size as u64)?.to_le_bytes()),
+            }
+        }
+        if wide || !self.metadata.is_empty() {
+            self.metadata.write_to(&mut header);
+        }
+        writer.write_all(&header)?;
+        Ok(())
+    }
+
+    /// Appends the map form and the encoding map, as code lengths whenever those are all it takes to rebuild it
+    /// unless `map_form` asks for the tree
+    fn write_map(&self, header: &mut Vec<u8>, map_form: MapForm) -> Result<()> {
+        let lengths = self.code_lengths()?;
+        if map_form == MapForm::Tree {
+            header.push(TREE_FORM);
+            write_tree(&self.encoding_map, header);
+        } else if !self.encoding_map.is_canonical() {
+            header.push(CODES_FORM);
+            self.write_codes(header)?;
+        } else if 2 + lengths.len() * 3 <= LENGTH_TABLE_LEN {
+            header.push(SPARSE_LENGTHS_FORM);
+            header.extend_from_slice(&(lengths.len() as u16).to_le_bytes());
+            for (symbol, length) in lengths {
+                header.extend_from_slice(&symbol.to_le_bytes());
+                header.push(length as u8);
+            }
+        } else {
+            header.push(LENGTH_TABLE_FORM);
+            let mut table = [0u8; LENGTH_TABLE_LEN];
+            for (symbol, length) in lengths {
+                table[symbol as usize] = length as u8;
+            }
+            header.extend_from_slice(&table);
+        }
+        Ok(())
+    }
+
+    /// The code lengths of the encoding map, failing for a code too long for the header to store
+    fn code_lengths(&self) -> Result<Vec<(u16, usize)>> {
+        let lengths = self.encoding_map.code_lengths();
+        if lengths
+            .iter()
+            .any(|&(_, length)| length > MAX_HEADER_CODE_LEN)
+        {
+            return Err(HuffmanError::TreeError(
+                "Code is too long for the header, codes are limited to 255 bits",
+            ));
+        }
+        Ok(lengths)
+    }
+
+    /// Appends every code of the encoding map in full, form 0 without its map form u8
+    fn write_codes(&self, header: &mut Vec<u8>) -> Result<()> {
+        let lengths = self.code_lengths()?;
+        header.extend_from_slice(&(lengths.len() as u16).to_le_bytes());
+        for (symbol, _) in lengths {
+            let code = match symbol {
+                ESCAPE_SYMBOL => self.encoding_map.get_escape(),
+                symbol => self.encoding_map.get(&(symbol as u8)),
+            }
+            .ok_or(HuffmanError::TreeError("Code is missing from the map"))?;
+            header.extend_from_slice(&symbol.to_le_bytes());
+            header.push(code.len() as u8);
+            for chunk in code.chunks(8) {
+                let byte = chunk.iter().fold(0u8, |byte, &bit| (byte << 1) | bit);
+                header.push(byte << (8 - chunk.len()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the header as version 1, the layout `FormatV0` is frozen to. Version 1 has no packing u8, CRC-32 or
+    /// metadata, so only a padded header without a checksum or metadata can be written this way
+    pub fn write_v0_to(&self, writer: &mut impl Write) -> Result<()> {
+        self.check_members().map_err(HuffmanError::TreeError)?;
+        if self.packing != Packing::Padded || self.checksum.is_some() || !self.metadata.is_empty() {
+            return Err(HuffmanError::TreeError(
+                "Version 1 headers only describe padded payloads without a checksum or metadata",
+            ));
+        }
+        let mut header = MAGIC.to_vec();
+        header.push(PADDED_VERSION);
+        header.push(self.block_type.to_bits());
+        header.extend_from_slice(&payload_size(self.data_len)?.to_le_bytes());
+        self.write_codes(&mut header)?;
+
+        header.push(self.stream_count()?);
+        for &size in &self.stream_sizes {
+            let size = payload_size(size as u64)?;
+            header.extend_from_slice(&size.to_le_bytes());
+        }
+        writer.write_all(&header)?;
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> Result<Header> {
+        if read_array::<4>(reader)? != MAGIC {
+            return Err(HuffmanError::DecodeError("Not a huff-tree-tap encoding"));
+        }
+        match read_array::<1>(reader)?[0] {
+            PADDED_VERSION => Self::read_body(reader, PADDED_VERSION, Packing::Padded),
+            DENSE_VERSION => Self::read_body(reader, DENSE_VERSION, Packing::Dense),
+            version @ (UNCHECKED_VERSION | CHECKED_VERSION | METADATA_VERSION | VERSION) => {
+                let packing = match read_array::<1>(reader)?[0] {
+                    PADDED_PACKING => Packing::Padded,
+                    DENSE_PACKING => Packing::Dense,
+                    _ => return Err(HuffmanError::DecodeError("Unknown payload packing")),
+                };
+                Self::read_body(reader, version, packing)
+            }
+            _ => Err(HuffmanError::DecodeError("Unsupported encoding version")),
+        }
+    }
+
+    /// Reads everything following the version, and the packing u8 of versions 3 to 6, up to the payload
+    fn read_body(reader: &mut impl Read, version: u8, packing: Packing) -> Result<Header> {
+        let block_type = BlockType::from_bits(read_array::<1>(reader)?[0])?;
+        let data_len = read_size(reader, version)?;
+        let checksum = match version >= CHECKED_VERSION {
+            true => Some(u32::from_le_bytes(read_array(reader)?)),
+            false => None,
+        };
+        let map_form = match version >= UNCHECKED_VERSION {
+            true => read_array::<1>(reader)?[0],
+            false => CODES_FORM,
+        };
+        let encoding_map = Self::read_map(reader, map_form)?;
+
+        let stream_count = read_array::<1>(reader)?[0] as usize;
+        if stream_count == 0 {
+            return Err(HuffmanError::DecodeError("Header holds no streams"));
+        }
+        if stream_count > MAX_STREAMS {
+            return Err(HuffmanError::DecodeError(
+                "Header holds more than MAX_STREAMS streams",
+            ));
+        }
+        let mut stream_sizes = Vec::with_capacity(stream_count - 1);
+        for _ in 1..stream_count {
+            stream_sizes.push(in_memory_size(read_size(reader, version)?)?);
+        }
+        let metadata = match version >= METADATA_VERSION {
+            true => Metadata::read_from(reader)?,
+            false => Metadata::new(),
+        };
+
+        let header = Header {
+            block_type,
+            data_len,
+            encoding_map,
+            stream_sizes,
+            packing,
+            checksum,
+            metadata,
+        };
+        header.check_members().map_err(HuffmanError::DecodeError)?;
+        Ok(header)
+    }
+
+    /// Reads an encoding map stored in `map_form`
+    fn read_map(reader: &mut impl Read, map_form: u8) -> Result<EncodingMap> {
+        if map_form == LENGTH_TABLE_FORM {
+            let table = read_array::<LENGTH_TABLE_LEN>(reader)?;
+            let lengths: Vec<(u16, usize)> = (0..=ESCAPE_SYMBOL)
+                .zip(table)
+                .filter(|&(_, length)| length > 0)
+                .map(|(symbol, length)| (symbol, length as usize))
+                .collect();
+            return EncodingMap::from_lengths(&lengths);
+        }
+        if map_form == TREE_FORM {
+            return read_tree(reader);
+        }
+        if map_form != CODES_FORM && map_form != SPARSE_LENGTHS_FORM {
+            return Err(HuffmanError::DecodeError("Unknown encoding map form"));
+        }
+
+        let code_count = u16::from_le_bytes(read_array(reader)?);
+        if code_count > ESCAPE_SYMBOL + 1 {
+            return Err(HuffmanError::DecodeError("Header holds too many codes"));
+        }
+        let mut lengths = Vec::with_capacity(code_count as usize);
+        let mut map: HashMap<u8, String> = HashMap::with_capacity(code_count as usize);
+        let mut escape = None;
+        for _ in 0..code_count {
+            let symbol = u16::from_le_bytes(read_array(reader)?);
+            let code_len = read_array::<1>(reader)?[0] as usize;
+            if map_form == SPARSE_LENGTHS_FORM {
+                lengths.push((symbol, code_len));
+                continue;
+            }
+            let mut packed = vec![0u8; code_len.div_ceil(8)];
+            reader.read_exact(&mut packed)?;
+            let code: BitVec = (0..code_len)
+                .map(|bit| (packed[bit / 8] >> (7 - bit % 8)) & 1)
+                .collect();
+            match symbol {
+                ESCAPE_SYMBOL => escape = Some(code),
+                symbol if symbol < ESCAPE_SYMBOL => {
+                    map.insert(symbol as u8, code.to_string());
+                }
+                _ => return Err(HuffmanError::DecodeError("Invalid symbol in header")),
+            }
+        }
+        if map_form == SPARSE_LENGTHS_FORM {
+            return EncodingMap::from_lengths(&lengths);
+        }
+        let encoding_map = EncodingMap::from(map)?;
+        match escape {
+            Some(escape) => encoding_map.with_escape(escape),
+            None => Ok(encoding_map),
+        }
+    }
+
+    /// The stream count u8 of the header, one more than the number of stream sizes it holds
+    fn stream_count(&self) -> Result<u8> {
+        u8::try_from(self.stream_sizes.len() + 1)
+            .ok()
+            .filter(|&stream_count| stream_count as usize <= MAX_STREAMS)
+            .ok_or(HuffmanError::OptionsError(
+                "Headers hold at most MAX_STREAMS streams",
+            ))
+    }
+
+    /// Checks the map and streams match the block type, the same way on both sides of the format
+    fn check_members(&self) -> std::result::Result<(), &'static str> {
+        self.metadata.check()?;
+        if self.checksum.is_none() && !self.metadata.is_empty() {
+            return Err("Metadata needs a checksum, as only version 5 and 6 headers carry it");
+        }
+        match self.block_type {
+            BlockType::Huffman if self.encoding_map.is_empty() => {
+                Err("Huffman blocks need at least one code")
+            }
+            BlockType::Huffman => Ok(()),
+            _ if !self.encoding_map.is_empty() || !self.stream_sizes.is_empty() => {
+                Err("Only Huffman blocks carry an encoding map or streams")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Appends the code tree of `encoding_map` in pre-order: a 0 for a branch, followed by its 0 then its 1 side, and a 1
+/// for a leaf, followed by its u8. Laid out as:
+/// * `u16` little endian number of the escape leaf, counting leaves in pre-order from 0, or `u16::MAX` for none
+/// * `u16` little endian number of empty leaves, where no code lies, then the `u16` number of each
+/// * `u16` little endian number of tree bits, then the bits packed MSB first
+///
+/// The escape and empty leaves are followed by a 0 u8.
+fn write_tree(encoding_map: &EncodingMap, header: &mut Vec<u8>) {
+    let mut codes: HashMap<&[u8], u16> = encoding_map
+        .iter()
+        .map(|(&symbol, code)| (code.as_slice(), symbol as u16))
+        .collect();
+    if let Some(escape) = encoding_map.get_escape() {
+        codes.insert(escape.as_slice(), ESCAPE_SYMBOL);
+    }
+
+    let mut tree = TreeBits::default();
+    tree.push_node(&codes, &mut BitVec::new());
+
+    header.extend_from_slice(&tree.escape_leaf.unwrap_or(NO_ESCAPE_LEAF).to_le_bytes());
+    header.extend_from_slice(&(tree.empty_leaves.len() as u16).to_le_bytes());
+    for leaf in tree.empty_leaves {
+        header.extend_from_slice(&leaf.to_le_bytes());
+    }
+    header.extend_from_slice(&(tree.bits.len() as u16).to_le_bytes());
+    for chunk in tree.bits.chunks(8) {
+        let byte = chunk.iter().fold(0u8, |byte, &bit| (byte << 1) | bit);
+        header.push(byte << (8 - chunk.len()));
+    }
+}
+
+/// Reads a code tree written by `write_tree`
+fn read_tree(reader: &mut impl Read) -> Result<EncodingMap> {
+    let escape_leaf = u16::from_le_bytes(read_array(reader)?);
+    let empty_count = u16::from_le_bytes(read_array(reader)?);
+    let mut empty_leaves = Vec::with_capacity(empty_count.min(ESCAPE_SYMBOL) as usize);
+    for _ in 0..empty_count {
+        empty_leaves.push(u16::from_le_bytes(read_array(reader)?));
+    }
+    let bit_count = u16::from_le_bytes(read_array(reader)?) as usize;
+    let mut packed = vec![0u8; bit_count.div_ceil(8)];
+    reader.read_exact(&mut packed)?;
+    let bits: BitVec = (0..bit_count)
+        .map(|bit| (packed[bit / 8] >> (7 - bit % 8)) & 1)
+        .collect();
+
+    let mut tree = TreeBits {
+        bits,
+        escape_leaf: (escape_leaf != NO_ESCAPE_LEAF).then_some(escape_leaf),
+        empty_leaves,
+        ..Default::default()
+    };
+    let mut map: HashMap<u8, String> = HashMap::new();
+    let mut escape = None;
+    tree.read_node(&mut BitVec::new(), &mut map, &mut escape)?;
+    if tree.cursor != tree.bits.len() {
+        return Err(HuffmanError::DecodeError(
+            "Trailing bits after the code tree",
+        ));
+    }
+
+    let encoding_map = EncodingMap::from(map)?;
+    match escape {
+        Some(escape) => encoding_map.with_escape(escape),
+        None => Ok(encoding_map),
+    }
+}
+
+/// The pre-order bits of a code tree and the leaves that hold no u8, shared by `write_tree` and `read_tree`
+#[derive(Default)]
+struct TreeBits {
+    bits: BitVec,
+    cursor: usize,
+    leaves: u16,
+    escape_leaf: Option<u16>,
+    empty_leaves: Vec<u16>,
+}
+
+impl TreeBits {
+    /// Pushes the node reached by `prefix` and everything below it
+    fn push_node(&mut self, codes: &HashMap<&[u8], u16>, prefix: &mut BitVec) {
+        let leaf = match codes.get(prefix.as_slice()) {
+            Some(&ESCAPE_SYMBOL) => {
+                self.escape_leaf = Some(self.leaves);
+                Some(0)
+            }
+            Some(&symbol) => Some(symbol as u8),
+            None if !codes.keys().any(|code| code.starts_with(prefix)) => {
+                self.empty_leaves.push(self.leaves);
+                Some(0)
+            }
+            None => None,
+        };
+        match leaf {
+            Some(byte) => {
+                self.leaves += 1;
+                self.bits.push(1);
+                self.bits
+                    .extend((0..8).rev().map(|shift| (byte >> shift) & 1));
+            }
+            None => {
+                self.bits.push(0);
+                for bit in [0, 1] {
+                    prefix.push(bit);
+                    self.push_node(codes, prefix);
+                    prefix.pop();
+                }
+            }
+        }
+    }
+
+    /// Reads the node reached by `prefix` and everything below it into `map` and `escape`
+    fn read_node(
+        &mut self,
+        prefix: &mut BitVec,
+        map: &mut HashMap<u8, String>,
+        escape: &mut Option<BitVec>,
+    ) -> Result<()> {
+        let truncated = || HuffmanError::DecodeError("Code tree is truncated");
+        let &bit = self.bits.get(self.cursor).ok_or_else(truncated)?;
+        self.cursor += 1;
+        if bit == 0 {
+            if prefix.len() == MAX_HEADER_CODE_LEN {
+                return Err(HuffmanError::DecodeError("Code tree is too deep"));
+            }
+            for bit in [0, 1] {
+                prefix.push(bit);
+                self.read_node(prefix, map, escape)?;
+                prefix.pop();
+            }
+            return Ok(());
+        }
+
+        let byte = self
+            .bits
+            .get(self.cursor..self.cursor + 8)
+            .ok_or_else(truncated)?
+            .iter()
+            .fold(0u8, |byte, &bit| (byte << 1) | bit);
+        self.cursor += 8;
+        let leaf = self.leaves;
+        self.leaves = self.leaves.saturating_add(1);
+        if self.escape_leaf == Some(leaf) {
+            *escape = Some(prefix.clone());
+        } else if !self.empty_leaves.contains(&leaf)
+            && map.insert(byte, prefix.to_string()).is_some()
+        {
+            return Err(HuffmanError::DecodeError("Code tree holds a u8 twice"));
+        }
+        Ok(())
+    }
+}
+
+/// Narrows a size to a `u32` size field, failing with `PayloadTooLarge` past `MAX_PAYLOAD_SIZE`
+pub(crate) fn payload_size(size: u64) -> Result<u32> {
+    u32::try_from(size).map_err(|_| HuffmanError::PayloadTooLarge(size, MAX_PAYLOAD_SIZE))
+}
+
+/// Widens a size held in memory to the `u64` it is stored as, failing with `PayloadTooLarge` on targets where a `usize`
+/// cannot hold it, rather than truncating it
+pub(crate) fn in_memory_size(size: u64) -> Result<usize> {
+    usize::try_from(size).map_err(|_| HuffmanError::PayloadTooLarge(size, usize::MAX as u64))
+}
+
+/// Reads a size, stored as a `u64` by version 6 headers and as a `u32` by older ones
+fn read_size(reader: &mut impl Read, version: u8) -> Result<u64> {
+    match version >= VERSION {
+        true => Ok(u64::from_le_bytes(read_array(reader)?)),
+        false => Ok(u32::from_le_bytes(read_array(reader)?) as u64),
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl HuffmanData {
+    /// Serializes the `HuffmanData` into a self-contained `Vec<u8>`, the header described on `Header` followed by
+    /// `encoded_data`, so it can be persisted without any external serializer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    /// let bytes = huffman_data.to_bytes().unwrap();
+    /// assert_eq!(HuffmanData::from_bytes(&bytes).unwrap().decode().unwrap(), data);
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.to_bytes_with(&ContainerOptions::default())
+    }
+
+    /// Serializes the `HuffmanData` like `to_bytes`, laying out the header as `options` says
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - How to lay out the header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    /// let options = ContainerOptions {
+    ///     map_form: MapForm::Tree,
+    /// };
+    /// let bytes = huffman_data.to_bytes_with(&options).unwrap();
+    /// let decoded = HuffmanData::from_bytes(&bytes).unwrap();
+    /// assert_eq!(decoded.encoding_map, huffman_data.encoding_map);
+    /// ```
+    pub fn to_bytes_with(&self, options: &ContainerOptions) -> Result<Vec<u8>> {
+        let header = Header {
+            block_type: self.block_type,
+            data_len: self.data_len as u64,
+            encoding_map: self.encoding_map.clone(),
+            stream_sizes: self.stream_sizes.clone(),
+            packing: self.packing,
+            checksum: self.checksum,
+            metadata: self.metadata.clone(),
+        };
+        let mut bytes = Vec::with_capacity(self.encoded_data.len() + 64);
+        header.write_to_with(&mut bytes, options)?;
+        bytes.extend_from_slice(&self.encoded_data);
+        Ok(bytes)
+    }
+
+    /// Number of u8s `to_bytes` writes for the block, its payload plus the parts of the header that depend on it:
+    /// the encoding map in its compact form and the stream sizes. `usize::MAX` for a map the header cannot store
+    pub(crate) fn block_size(&self) -> usize {
+        let header = Header {
+            block_type: self.block_type,
+            data_len: 0,
+            encoding_map: self.encoding_map.clone(),
+            stream_sizes: self.stream_sizes.clone(),
+            packing: self.packing,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+        let mut map = Vec::new();
+        match header.write_map(&mut map, MapForm::Compact) {
+            Ok(()) => self.encoded_data.len() + map.len() + 4 * self.stream_sizes.len(),
+            Err(_) => usize::MAX,
+        }
+    }
+
+    /// Parses bytes written by `to_bytes` back into a `HuffmanData`, stats included
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The bytes written by `to_bytes` or `encode`
+    pub fn from_bytes(bytes: &[u8]) -> Result<HuffmanData> {
+        let mut reader = bytes;
+        let header = Header::read_from(&mut reader)?;
+        Self::from_header(header, reader.to_vec())
+    }
+
+    /// Puts a `HuffmanData` together from a header and the payload that followed it
+    pub(crate) fn from_header(header: Header, encoded_data: Vec<u8>) -> Result<HuffmanData> {
+        let payload_bits = match header.block_type {
+            BlockType::Huffman => {
+                let mut payload_bits = 0;
+                let mut rest = encoded_data.as_slice();
+                for &size in &header.stream_sizes {
+                    let (stream, remainder) = rest.split_at(size.min(rest.len()));
+                    payload_bits += header.packing.payload_len(stream);
+                    rest = remainder;
+                }
+                payload_bits + header.packing.payload_len(rest)
+            }
+            _ => encoded_data.len() as u64 * 8,
+        };
+        let stats =
+            EncodingStats::from_sizes(header.data_len, encoded_data.len() as u64, payload_bits);
+
+        Ok(HuffmanData {
+            encoded_data,
+            encoding_map: header.encoding_map,
+            stats,
+            stream_sizes: header.stream_sizes,
+            block_type: header.block_type,
+            data_len: in_memory_size(header.data_len)?,
+            packing: header.packing,
+            checksum: header.checksum,
+            metadata: header.metadata,
+        })
+    }
+
+    /// Decodes the data like `decode`, also failing unless it decodes to as many u8s as the header said
+    pub(crate) fn decode_checked(&self) -> Result<Vec<u8>> {
+        let data = self.decode()?;
+        if data.len() != self.data_len {
+            return Err(HuffmanError::DecodeError(
+                "Decoded size does not match the header",
+            ));
+        }
+        Ok(data)
+    }
+}
+
+/// Encodes `data` into a single self-contained `Vec<u8>` holding everything needed to decode it
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let encoded_data = encode(&data).unwrap();
+/// assert_eq!(decode(&encoded_data).unwrap(), data);
+/// ```
+pub fn encode(data: &[u8]) -> Result<Vec<u8>> {
+    HuffmanData::new(data)?.to_bytes()
+}
+
+/// Encodes `data` like `encode`, with every knob of the encoder set by `options`.
+///
+/// Without a `block_size` the output is a single self-contained encoding, readable by `decode`. With one the data is
+/// split into blocks of that many u8s, the last one possibly shorter, encoded one after the other as `encode_blocks`
+/// does, and the output is read back by `decode_all_members`, which reads a single encoding as well.
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+/// * `options` - A reference to `HuffmanOptions` controlling the encoding
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = b"this is a test string!".repeat(100);
+/// let options = HuffmanOptions { block_size: Some(512), checksum: false, ..Default::default() };
+/// let encoded_data = encode_with(&data, &options).unwrap();
+/// assert_eq!(decode_all_members(&mut encoded_data.as_slice()).unwrap(), data);
+/// ```
+pub fn encode_with(data: &[u8], options: &HuffmanOptions) -> Result<Vec<u8>> {
+    match options.block_size {
+        Some(block_size) => encode_blocks(
+            data,
+            &BlockOptions {
+                block_size,
+                huffman: options.clone(),
+                ..Default::default()
+            },
+        ),
+        None => HuffmanData::with_block_selection(data, options)?.to_bytes(),
+    }
+}
+
+/// Decodes a `Vec<u8>` produced by `encode`
+///
+/// # Arguments
+///
+/// * `encoded_data` - The self-contained encoding
+pub fn decode(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    HuffmanData::from_bytes(encoded_data)?.decode_checked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{decode_from_reader, encode_to_writer};
+
+    #[test]
+    fn test_header_write_to() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10"), (b'c', "11")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_data = Header {
+            block_type: BlockType::Huffman,
+            data_len: 42,
+            encoding_map: EncodingMap::from(map).unwrap(),
+            stream_sizes: vec![3],
+            packing: Packing::Padded,
+            checksum: Some(0x0403_0201),
+            metadata: Metadata::new(),
+        };
+        let expected_data: Vec<u8> = [
+            &MAGIC[..],
+            &[CHECKED_VERSION, 0, 1, 42, 0, 0, 0, 1, 2, 3, 4],
+            &[
+                SPARSE_LENGTHS_FORM,
+                3,
+                0,
+                b'a',
+                0,
+                1,
+                b'b',
+                0,
+                2,
+                b'c',
+                0,
+                2,
+            ],
+            &[2, 3, 0, 0, 0],
+        ]
+        .concat();
+
+        let mut test_output = Vec::new();
+        input_data.write_to(&mut test_output).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(
+            input_data,
+            Header::read_from(&mut test_output.as_slice()).unwrap()
+        );
+        assert!(Header::read_from(&mut &test_output[..test_output.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_header_long_codes() {
+        // Codes 1, 01, 001, ... up to a 255 bit code and its sibling
+        let map: HashMap<u8, String> = (0..=u8::MAX)
+            .map(|symbol| {
+                let zeros = (symbol as usize).min(MAX_HEADER_CODE_LEN - 1);
+                let last = if symbol == u8::MAX { "0" } else { "1" };
+                (symbol, "0".repeat(zeros) + last)
+            })
+            .collect();
+        let input_data = Header {
+            block_type: BlockType::Huffman,
+            data_len: 0,
+            encoding_map: EncodingMap::from(map).unwrap(),
+            stream_sizes: Vec::new(),
+            packing: Packing::Dense,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+
+        let mut test_output = Vec::new();
+        input_data.write_to(&mut test_output).unwrap();
+
+        assert_eq!(
+            MAX_HEADER_CODE_LEN,
+            input_data.encoding_map.get_longest_code()
+        );
+        assert_eq!(
+            input_data,
+            Header::read_from(&mut test_output.as_slice()).unwrap()
+        );
+
+        let too_long = Header {
+            encoding_map: EncodingMap::default()
+                .with_escape(vec![0; MAX_HEADER_CODE_LEN + 1])
+                .unwrap(),
+            ..input_data
+        };
+        assert!(too_long.write_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_header_stream_count() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_data = Header {
+            block_type: BlockType::Huffman,
+            data_len: 0,
+            encoding_map: EncodingMap::from(map).unwrap(),
+            stream_sizes: vec![0; MAX_STREAMS - 1],
+            packing: Packing::Padded,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+        let mut test_output = Vec::new();
+        input_data.write_to(&mut test_output).unwrap();
+
+        assert_eq!(
+            input_data,
+            Header::read_from(&mut test_output.as_slice()).unwrap()
+        );
+
+        // 255 sizes would wrap the stream count u8 around to 0
+        for stream_count in [MAX_STREAMS, u8::MAX as usize] {
+            let too_many = Header {
+                stream_sizes: vec![0; stream_count],
+                ..input_data.clone()
+            };
+            assert!(matches!(
+                too_many.write_to(&mut Vec::new()),
+                Err(HuffmanError::OptionsError(_))
+            ));
+            assert!(matches!(
+                too_many.write_v0_to(&mut Vec::new()),
+                Err(HuffmanError::OptionsError(_))
+            ));
+        }
+
+        let stream_count_offset = test_output.len() - (MAX_STREAMS - 1) * 4;
+        assert_eq!(MAX_STREAMS as u8, test_output[stream_count_offset - 1]);
+        test_output[stream_count_offset - 1] += 1;
+        test_output.extend_from_slice(&[0; 4]);
+        assert!(Header::read_from(&mut test_output.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_map_forms() {
+        let non_canonical: HashMap<u8, String> = [(b'a', "1"), (b'b', "01"), (b'c', "00")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let all_bytes: Vec<(u16, usize)> = (0..256).map(|symbol| (symbol, 8)).collect();
+        let inputs = [
+            (EncodingMap::from(non_canonical).unwrap(), CODES_FORM, 27),
+            (
+                EncodingMap::from_lengths(&all_bytes).unwrap(),
+                LENGTH_TABLE_FORM,
+                270,
+            ),
+        ];
+
+        for (encoding_map, expected_form, expected_len) in inputs {
+            let input_data = Header {
+                block_type: BlockType::Huffman,
+                data_len: 0,
+                encoding_map,
+                stream_sizes: Vec::new(),
+                packing: Packing::Dense,
+                checksum: None,
+                metadata: Metadata::new(),
+            };
+
+            let mut test_output = Vec::new();
+            input_data.write_to(&mut test_output).unwrap();
+
+            assert_eq!(expected_form, test_output[11]);
+            assert_eq!(expected_len, test_output.len());
+            assert_eq!(
+                input_data,
+                Header::read_from(&mut test_output.as_slice()).unwrap()
+            );
+
+            // Versions 1 and 2 hold the same map in form 0 with no packing or map form u8
+            if expected_form == CODES_FORM {
+                let legacy = [
+                    &MAGIC[..],
+                    &[DENSE_VERSION],
+                    &test_output[6..11],
+                    &test_output[12..],
+                ]
+                .concat();
+                assert_eq!(
+                    input_data,
+                    Header::read_from(&mut legacy.as_slice()).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tree_form() {
+        let to_map = |codes: &[(u8, &str)]| -> EncodingMap {
+            let map: HashMap<u8, String> = codes.iter().map(|(k, v)| (*k, v.to_string())).collect();
+            EncodingMap::from(map).unwrap()
+        };
+        let tree = ContainerOptions {
+            map_form: MapForm::Tree,
+        };
+        let input_data = Header {
+            block_type: BlockType::Huffman,
+            data_len: 0,
+            encoding_map: to_map(&[(b'a', "0"), (b'b', "10"), (b'c', "11")]),
+            stream_sizes: Vec::new(),
+            packing: Packing::Dense,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+        // 0 1'a' 0 1'b' 1'c', with no escape and no empty leaves
+        let expected_data: Vec<u8> = vec![TREE_FORM, 0xff, 0xff, 0, 0, 29, 0, 88, 86, 43, 24, 1];
+
+        let mut test_output = Vec::new();
+        input_data.write_to_with(&mut test_output, &tree).unwrap();
+
+        assert_eq!(expected_data, test_output[11..]);
+        assert_eq!(
+            input_data,
+            Header::read_from(&mut test_output.as_slice()).unwrap()
+        );
+
+        // A lone code leaves an empty leaf beside it, and the escape sits on a leaf of its own
+        for encoding_map in [
+            to_map(&[(b'a', "0")]),
+            to_map(&[(b'a', "1"), (b'b', "000")])
+                .with_escape(BitVec::from_string("01"))
+                .unwrap(),
+        ] {
+            let input_data = Header {
+                encoding_map,
+                ..input_data.clone()
+            };
+            let mut test_output = Vec::new();
+            input_data.write_to_with(&mut test_output, &tree).unwrap();
+
+            assert_eq!(
+                input_data,
+                Header::read_from(&mut test_output.as_slice()).unwrap()
+            );
+        }
+        assert!(Header::read_from(&mut &test_output[..test_output.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for input_data in [&b"a"[..], b"aaaaaaaaaaaa", b"this is a test string!"] {
+            let encoded_data = encode(input_data).unwrap();
+
+            let huffman_data = HuffmanData::from_bytes(&encoded_data).unwrap();
+
+            assert_eq!(input_data, decode(&encoded_data).unwrap());
+            assert_eq!(
+                HuffmanData::new(input_data).unwrap().stats,
+                huffman_data.stats
+            );
+        }
+        assert!(decode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_huffman_data_bytes() {
+        let input_data: Vec<u8> =
+            Vec::from("this is a test string! this is a longer test string!!");
+        for streams in 1..=4 {
+            let expected_data = HuffmanData::with_options(
+                &input_data,
+                &HuffmanOptions {
+                    streams,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            let test_output = HuffmanData::from_bytes(&expected_data.to_bytes().unwrap()).unwrap();
+
+            assert_eq!(expected_data.encoded_data, test_output.encoded_data);
+            assert_eq!(expected_data.encoding_map, test_output.encoding_map);
+            assert_eq!(expected_data.stream_sizes, test_output.stream_sizes);
+            assert_eq!(expected_data.block_type, test_output.block_type);
+            assert_eq!(expected_data.data_len, test_output.data_len);
+            assert_eq!(expected_data.stats, test_output.stats);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let input_data: &[u8] = &[];
+        let expected_data: Vec<u8> = [
+            &MAGIC[..],
+            &[CHECKED_VERSION, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            &[SPARSE_LENGTHS_FORM, 0, 0, 1],
+        ]
+        .concat();
+
+        let test_output = encode(input_data).unwrap();
+        let mut streamed = Vec::new();
+        encode_to_writer(&mut std::io::Cursor::new(input_data), &mut streamed).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(expected_data, streamed);
+        assert_eq!(input_data, decode(&test_output).unwrap());
+        assert_eq!(
+            HuffmanData::new(input_data).unwrap().stats,
+            HuffmanData::from_bytes(&test_output).unwrap().stats
+        );
+        let mut decoded_data = Vec::new();
+        decode_from_reader(&mut test_output.as_slice(), &mut decoded_data).unwrap();
+        assert_eq!(input_data, decoded_data);
+
+        // Huffman blocks need a code and only Huffman blocks carry one, whether writing or reading
+        let no_codes = [1, 0, 0, 0, 0, 0, 0, 1];
+        let stored_with_code = [0, 0, 0, 0, 0, 1, 0, b'a', 0, 1, 0, 1];
+        let stored_with_streams = [0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0];
+        for bytes in [&no_codes[..], &stored_with_code, &stored_with_streams] {
+            let bytes = [&MAGIC[..], &[DENSE_VERSION], bytes].concat();
+            assert!(Header::read_from(&mut bytes.as_slice()).is_err());
+        }
+        let header = Header {
+            block_type: BlockType::Huffman,
+            data_len: 0,
+            encoding_map: EncodingMap::default(),
+            stream_sizes: Vec::new(),
+            packing: Packing::Dense,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+        assert!(header.write_to(&mut Vec::new()).is_err());
+        let header = Header {
+            block_type: BlockType::Stored,
+            stream_sizes: vec![0],
+            ..header
+        };
+        assert!(header.write_to(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_packing_versions() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(100);
+
+        for (packing, expected_packing) in [(Packing::Padded, 0), (Packing::Dense, 1)] {
+            let options = HuffmanOptions {
+                packing,
+                ..Default::default()
+            };
+            let test_output = HuffmanData::with_options(&input_data, &options)
+                .unwrap()
+                .to_bytes()
+                .unwrap();
+
+            assert_eq!([CHECKED_VERSION, expected_packing], test_output[4..6]);
+            assert_eq!(input_data, decode(&test_output).unwrap());
+            let mut decoded_data = Vec::new();
+            decode_from_reader(&mut test_output.as_slice(), &mut decoded_data).unwrap();
+            assert_eq!(input_data, decoded_data);
+        }
+    }
+
+    #[test]
+    fn test_checksum() {
+        let input_data = b"this is a test string!";
+        let encoded_data = encode(input_data).unwrap();
+        let huffman_data = HuffmanData::from_bytes(&encoded_data).unwrap();
+
+        // Swapping two payload u8s keeps the size but changes the decoded data
+        let mut swapped = huffman_data.to_bytes().unwrap();
+        let len = swapped.len();
+        swapped.swap(len - 3, len - 2);
+        let mut unchecked = HuffmanData::from_bytes(&encoded_data).unwrap();
+        unchecked.checksum = None;
+        let unchecked = unchecked.to_bytes().unwrap();
+
+        assert_eq!(Some(crate::crc32::crc32(input_data)), huffman_data.checksum);
+        assert!(matches!(
+            decode(&swapped),
+            Err(HuffmanError::ChecksumMismatch(..))
+        ));
+        let mut decoded_data = Vec::new();
+        assert!(decode_from_reader(&mut swapped.as_slice(), &mut decoded_data).is_err());
+        assert_eq!(UNCHECKED_VERSION, unchecked[4]);
+        assert_eq!(encoded_data.len() - 4, unchecked.len());
+        assert_eq!(input_data.to_vec(), decode(&unchecked).unwrap());
+    }
+
+    #[test]
+    fn test_encode_with() {
+        let input_data = b"this is a test string!".repeat(10);
+        let unchecked = HuffmanOptions {
+            checksum: false,
+            ..Default::default()
+        };
+        let blocks = HuffmanOptions {
+            block_size: Some(100),
+            ..Default::default()
+        };
+
+        let test_output = encode_with(&input_data, &unchecked).unwrap();
+        let block_output = encode_with(&input_data, &blocks).unwrap();
+
+        assert_eq!(UNCHECKED_VERSION, test_output[4]);
+        assert_eq!(input_data, decode(&test_output).unwrap());
+        assert_eq!(
+            encode(&input_data).unwrap(),
+            encode_with(&input_data, &HuffmanOptions::default()).unwrap()
+        );
+        assert_eq!(
+            input_data,
+            crate::members::decode_all_members(&mut block_output.as_slice()).unwrap()
+        );
+        assert_eq!(
+            3,
+            block_output
+                .windows(4)
+                .filter(|window| *window == MAGIC)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let input_data = b"this is a test string!";
+        let encoded_data = encode(input_data).unwrap();
+        let mut huffman_data = HuffmanData::from_bytes(&encoded_data).unwrap();
+        huffman_data.metadata.insert(Metadata::FILENAME, "test.txt");
+
+        let test_output = huffman_data.to_bytes().unwrap();
+
+        assert_eq!(CHECKED_VERSION, encoded_data[4]);
+        assert_eq!(METADATA_VERSION, test_output[4]);
+        assert_eq!(
+            Some("test.txt"),
+            HuffmanData::from_bytes(&test_output)
+                .unwrap()
+                .metadata()
+                .get(Metadata::FILENAME)
+        );
+        assert_eq!(input_data.to_vec(), decode(&test_output).unwrap());
+        let mut decoded_data = Vec::new();
+        decode_from_reader(&mut test_output.as_slice(), &mut decoded_data).unwrap();
+        assert_eq!(input_data.to_vec(), decoded_data);
+
+        huffman_data.checksum = None;
+        assert!(huffman_data.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_magic_version() {
+        let input_data = encode(b"this is a test string!").unwrap();
+
+        let mut other_version = input_data.clone();
+        other_version[4] = VERSION + 1;
+        let mut not_encoded = input_data.clone();
+        not_encoded[..4].copy_from_slice(b"GIF8");
+
+        assert!(matches!(
+            decode(&other_version),
+            Err(HuffmanError::DecodeError("Unsupported encoding version"))
+        ));
+        assert!(matches!(
+            decode(&not_encoded),
+            Err(HuffmanError::DecodeError("Not a huff-tree-tap encoding"))
+        ));
+    }
+
+    #[test]
+    fn test_payload_size() {
+        assert_eq!(u32::MAX, payload_size(MAX_PAYLOAD_SIZE).unwrap());
+        assert!(matches!(
+            payload_size(MAX_PAYLOAD_SIZE + 1),
+            Err(HuffmanError::PayloadTooLarge(size, MAX_PAYLOAD_SIZE)) if size == MAX_PAYLOAD_SIZE + 1
+        ));
+    }
+
+    #[test]
+    fn test_wide_header() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        // Either side of the 4 GiB boundary, for the decoded size and for a stream size
+        let input_data = [
+            (MAX_PAYLOAD_SIZE, 0, CHECKED_VERSION),
+            (MAX_PAYLOAD_SIZE + 1, 0, VERSION),
+            (MAX_PAYLOAD_SIZE, MAX_PAYLOAD_SIZE as usize, CHECKED_VERSION),
+            (MAX_PAYLOAD_SIZE, MAX_PAYLOAD_SIZE as usize + 1, VERSION),
+        ];
+
+        for (data_len, stream_size, expected_data) in input_data {
+            let header = Header {
+                block_type: BlockType::Huffman,
+                data_len,
+                encoding_map: EncodingMap::from(map.clone()).unwrap(),
+                stream_sizes: vec![stream_size],
+                packing: Packing::Dense,
+                checksum: Some(0x0403_0201),
+                metadata: Metadata::new(),
+            };
+            let mut test_output = Vec::new();
+            header.write_to(&mut test_output).unwrap();
+
+            assert_eq!(expected_data, test_output[4]);
+            assert_eq!(
+                header,
+                Header::read_from(&mut test_output.as_slice()).unwrap()
+            );
+            let unchecked = Header {
+                checksum: None,
+                ..header
+            };
+            match unchecked.write_to(&mut Vec::new()) {
+                Err(HuffmanError::PayloadTooLarge(size, limit)) => {
+                    assert_eq!(VERSION, expected_data);
+                    assert_eq!(MAX_PAYLOAD_SIZE + 1, size);
+                    assert_eq!(MAX_PAYLOAD_SIZE, limit);
+                }
+                result => {
+                    assert_ne!(VERSION, expected_data);
+                    assert!(result.is_ok());
+                }
+            }
+        }
+    }
+}
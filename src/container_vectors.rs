@@ -0,0 +1,240 @@
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+use crate::metadata::Metadata;
+use crate::options::{ContainerOptions, HuffmanOptions, MapForm};
+use crate::packing::Packing;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Where one field of a self-contained encoding lies, in u8s from the start of the encoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutField {
+    /// Name of the field, as `Header` documents the layout
+    pub name: &'static str,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// A golden input, its self-contained encoding and where every field of the encoding lies, for checking an
+/// implementation in another language reads and writes the same format
+#[derive(Debug, Clone)]
+pub struct ContainerVector {
+    /// Name used for the files written for this vector
+    pub name: String,
+    /// The unencoded input
+    pub input: Vec<u8>,
+    /// The input encoded as `to_bytes` writes it
+    pub encoded: Vec<u8>,
+    /// Every field of `encoded` in order, up to and including the payload
+    pub layout: Vec<LayoutField>,
+}
+
+/// Returns encodings covering every header version written today, block type, packing, map form and stream count
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// for vector in container_vectors().unwrap() {
+///     assert_eq!(decode(&vector.encoded).unwrap(), vector.input);
+///     let payload = vector.layout.last().unwrap();
+///     assert_eq!(payload.offset + payload.len, vector.encoded.len());
+/// }
+/// ```
+pub fn container_vectors() -> Result<Vec<ContainerVector>> {
+    let text = Vec::from("this is a test string!");
+    let cases: [(&str, Vec<u8>, HuffmanOptions, MapForm); 7] = [
+        (
+            "empty",
+            Vec::new(),
+            HuffmanOptions::default(),
+            MapForm::Compact,
+        ),
+        (
+            "text",
+            text.clone(),
+            HuffmanOptions::default(),
+            MapForm::Compact,
+        ),
+        (
+            "text_padded",
+            text.clone(),
+            HuffmanOptions {
+                packing: Packing::Padded,
+                ..Default::default()
+            },
+            MapForm::Compact,
+        ),
+        (
+            "text_tree",
+            text.clone(),
+            HuffmanOptions::default(),
+            MapForm::Tree,
+        ),
+        (
+            "text_streams_3",
+            text.repeat(10),
+            HuffmanOptions {
+                streams: 3,
+                ..Default::default()
+            },
+            MapForm::Compact,
+        ),
+        (
+            "runs",
+            [vec![b'a'; 300], vec![b'b'; 20]].concat(),
+            HuffmanOptions::default(),
+            MapForm::Compact,
+        ),
+        (
+            "all_bytes",
+            (0..=255).cycle().take(1024).collect(),
+            HuffmanOptions::default(),
+            MapForm::Compact,
+        ),
+    ];
+
+    let mut vectors = Vec::new();
+    for (name, input, options, map_form) in cases {
+        let huffman_data = HuffmanData::with_options(&input, &options)?;
+        vectors.push(vector(name, input, &huffman_data, map_form)?);
+    }
+
+    let mut huffman_data = HuffmanData::new(&text)?;
+    huffman_data.checksum = None;
+    vectors.push(vector(
+        "unchecked",
+        text.clone(),
+        &huffman_data,
+        MapForm::Compact,
+    )?);
+
+    let mut huffman_data = HuffmanData::new(&text)?;
+    huffman_data.metadata.insert(Metadata::FILENAME, "text.txt");
+    vectors.push(vector(
+        "text_metadata",
+        text,
+        &huffman_data,
+        MapForm::Compact,
+    )?);
+    Ok(vectors)
+}
+
+fn vector(
+    name: &str,
+    input: Vec<u8>,
+    huffman_data: &HuffmanData,
+    map_form: MapForm,
+) -> Result<ContainerVector> {
+    let encoded = huffman_data.to_bytes_with(&ContainerOptions { map_form })?;
+    Ok(ContainerVector {
+        name: name.to_string(),
+        input,
+        layout: layout(huffman_data, &encoded),
+        encoded,
+    })
+}
+
+/// Lays out the fields of `encoded`, the encoding of `huffman_data`. Only the map has no size of its own to go by,
+/// it takes up whatever the other fields leave of the header
+fn layout(huffman_data: &HuffmanData, encoded: &[u8]) -> Vec<LayoutField> {
+    let mut metadata = Vec::new();
+    if !huffman_data.metadata.is_empty() {
+        huffman_data.metadata.write_to(&mut metadata);
+    }
+    let header_len = encoded.len() - huffman_data.encoded_data.len();
+    let streams_len = 1 + 4 * huffman_data.stream_sizes.len();
+    let checksum_len = huffman_data.checksum.map_or(0, |_| 4);
+    let map_len = header_len - 12 - checksum_len - streams_len - metadata.len();
+
+    let sizes = [
+        ("magic", 4),
+        ("version", 1),
+        ("packing", 1),
+        ("block_type", 1),
+        ("data_len", 4),
+        ("checksum", checksum_len),
+        ("map_form", 1),
+        ("map", map_len),
+        ("stream_count", 1),
+        ("stream_sizes", streams_len - 1),
+        ("metadata", metadata.len()),
+        ("payload", huffman_data.encoded_data.len()),
+    ];
+    let mut offset = 0;
+    let mut fields = Vec::new();
+    for (name, len) in sizes {
+        // Fields a version leaves out are left out of the layout too, an empty map or payload is still listed
+        if len > 0 || name == "map" || name == "payload" {
+            fields.push(LayoutField { name, offset, len });
+        }
+        offset += len;
+    }
+    fields
+}
+
+/// Writes every container vector into `dir` and returns their names.
+///
+/// Each vector produces three files:
+/// * `<name>.input` - the unencoded bytes
+/// * `<name>.htt` - the self-contained encoding
+/// * `<name>.layout` - one `<field>=<offset>:<length>` line per field of the encoding, in order
+///
+/// # Arguments
+///
+/// * `dir` - The directory to write the vectors into, created if missing
+pub fn gen_container_vectors(dir: &Path) -> Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+
+    let mut names = Vec::new();
+    for vector in container_vectors()? {
+        let mut layout = String::new();
+        for field in &vector.layout {
+            let _ = writeln!(layout, "{}={}:{}", field.name, field.offset, field.len);
+        }
+        fs::write(dir.join(format!("{}.input", vector.name)), &vector.input)?;
+        fs::write(dir.join(format!("{}.htt", vector.name)), &vector.encoded)?;
+        fs::write(dir.join(format!("{}.layout", vector.name)), layout)?;
+        names.push(vector.name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_vectors() {
+        let dir = std::env::temp_dir().join(format!(
+            "huff-tree-tap-container-vectors-{}",
+            std::process::id()
+        ));
+
+        let test_output = gen_container_vectors(&dir).unwrap();
+
+        let vectors = container_vectors().unwrap();
+        assert_eq!(vectors.len(), test_output.len());
+        let layout = fs::read_to_string(dir.join("text.layout")).unwrap();
+        assert!(layout.starts_with(
+            "magic=0:4\nversion=4:1\npacking=5:1\nblock_type=6:1\ndata_len=7:4\nchecksum=11:4\nmap_form=15:1\n"
+        ));
+        let layout = fs::read_to_string(dir.join("empty.layout")).unwrap();
+        assert!(layout.ends_with("map=16:2\nstream_count=18:1\npayload=19:0\n"));
+        for vector in vectors {
+            let fields: Vec<&str> = vector.layout.iter().map(|field| field.name).collect();
+            let version = vector.encoded[4];
+            assert_eq!(version >= 4, fields.contains(&"checksum"));
+            assert_eq!(version >= 5, fields.contains(&"metadata"));
+            for pair in vector.layout.windows(2) {
+                assert_eq!(pair[0].offset + pair[0].len, pair[1].offset);
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::container::decode;
+use crate::error::{HuffmanError, Result};
+
+use std::panic::{self, AssertUnwindSafe};
+
+/// Number of u8s at the start of an encoding that never depend on the data: the magic, version, packing, block type and
+/// the decoded size
+const FIXED_HEADER_LEN: usize = 11;
+
+/// Damage `corrupt` can apply to an encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorruptionKind {
+    /// Flips the bit at the given index, counted from the most significant bit of the first u8 and wrapping around
+    BitFlip(usize),
+    /// Keeps only the given number of u8s
+    Truncate(usize),
+    /// Inverts every bit of the block type and decoded size at the start of the header
+    HeaderMangle,
+}
+
+/// Returns a copy of `data` with the given damage applied, to exercise error handling against realistic corruption
+///
+/// # Arguments
+///
+/// * `data` - An encoding, such as the output of `encode`
+/// * `kind` - The damage to apply
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let encoded_data = encode(b"this is a test string!").unwrap();
+/// let corrupted = corrupt(&encoded_data, CorruptionKind::Truncate(encoded_data.len() - 1));
+/// assert_decode_fails(&corrupted);
+/// ```
+pub fn corrupt(data: &[u8], kind: CorruptionKind) -> Vec<u8> {
+    let mut corrupted = data.to_vec();
+    match kind {
+        CorruptionKind::BitFlip(bit) if !corrupted.is_empty() => {
+            let bit = bit % (corrupted.len() * 8);
+            corrupted[bit / 8] ^= 0x80 >> (bit % 8);
+        }
+        CorruptionKind::BitFlip(_) => {}
+        CorruptionKind::Truncate(len) => corrupted.truncate(len),
+        CorruptionKind::HeaderMangle => {
+            for byte in corrupted.iter_mut().take(FIXED_HEADER_LEN) {
+                *byte = !*byte;
+            }
+        }
+    }
+    corrupted
+}
+
+/// Decodes `corrupted` with `decode`, panicking with a clear message if decoding panics instead of returning.
+/// A bit flip in the payload is caught by the CRC-32 of the decoded data, but an encoding without one, such as a
+/// version 3 encoding, can decode to other data rather than fail.
+pub fn decode_without_panic(corrupted: &[u8]) -> Result<Vec<u8>> {
+    match panic::catch_unwind(AssertUnwindSafe(|| decode(corrupted))) {
+        Ok(result) => result,
+        Err(_) => panic!("decode panicked on corrupted input instead of returning an error"),
+    }
+}
+
+/// Asserts `decode` rejects `corrupted` with an error, without panicking, and returns that error
+pub fn assert_decode_fails(corrupted: &[u8]) -> HuffmanError<'static> {
+    match decode_without_panic(corrupted) {
+        Ok(decoded) => panic!(
+            "decode accepted corrupted input, returning {} u8s",
+            decoded.len()
+        ),
+        Err(e) => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode;
+
+    #[test]
+    fn test_corrupt() {
+        let input_data = encode(b"this is a test string!").unwrap();
+
+        assert_eq!(
+            input_data[0] ^ 0b0100_0000,
+            corrupt(&input_data, CorruptionKind::BitFlip(1))[0]
+        );
+        for len in 0..input_data.len() {
+            assert_decode_fails(&corrupt(&input_data, CorruptionKind::Truncate(len)));
+        }
+        assert_decode_fails(&corrupt(&input_data, CorruptionKind::HeaderMangle));
+        for bit in 0..input_data.len() * 8 {
+            let _ = decode_without_panic(&corrupt(&input_data, CorruptionKind::BitFlip(bit)));
+        }
+    }
+}
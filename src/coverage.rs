@@ -0,0 +1,73 @@
+use crate::encoding_map::EncodingMap;
+
+use std::collections::BTreeMap;
+
+/// How well an external map fit the data it encoded, returned by `HuffmanDictionary::encode_with_coverage`.
+/// A growing share of escapes, or a few u8s missing over and over, shows the dictionary no longer fits the data.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoverageReport {
+    /// u8s encoded with a code of the map
+    pub covered: u64,
+    /// u8s encoded through the escape, as the map has no code for them
+    pub escaped: u64,
+    /// How many times each u8 missing from the map was escaped
+    pub misses: BTreeMap<u8, u64>,
+}
+
+impl CoverageReport {
+    /// Counts which u8s of `data` have a code in `encoding_map` and which have to be escaped
+    pub(crate) fn new(data: &[u8], encoding_map: &EncodingMap) -> Self {
+        let mut counts = [0u64; 256];
+        for &byte in data {
+            counts[byte as usize] += 1;
+        }
+
+        let mut report = CoverageReport::default();
+        for (byte, &count) in counts.iter().enumerate().filter(|(_, &count)| count > 0) {
+            let byte = byte as u8;
+            match encoding_map.get(&byte) {
+                Some(_) => report.covered += count,
+                None => {
+                    report.escaped += count;
+                    report.misses.insert(byte, count);
+                }
+            }
+        }
+        report
+    }
+
+    /// Share of u8s encoded with a code of the map, from 0 to 1 and 1 for empty data
+    pub fn coverage(&self) -> f64 {
+        match self.covered + self.escaped {
+            0 => 1.0,
+            total => self.covered as f64 / total as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_coverage_report() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let encoding_map = EncodingMap::from(map).unwrap();
+        let input_data = b"abacabzz";
+        let expected_data = CoverageReport {
+            covered: 5,
+            escaped: 3,
+            misses: [(b'c', 1), (b'z', 2)].into_iter().collect(),
+        };
+
+        let test_output = CoverageReport::new(input_data, &encoding_map);
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(0.625, test_output.coverage());
+        assert_eq!(1.0, CoverageReport::new(&[], &encoding_map).coverage());
+    }
+}
@@ -0,0 +1,75 @@
+/// Reflected polynomial of the CRC-32 used by gzip, zip and PNG
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+/// Remainder of every u8, so the checksum advances a u8 at a time
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut crc = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ POLYNOMIAL,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[byte] = crc;
+        byte += 1;
+    }
+    table
+}
+
+/// CRC-32 of data arriving in chunks of any size, the same as `crc32` over all of them at once
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32 { state: u32::MAX }
+    }
+}
+
+impl Crc32 {
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// Returns the CRC-32 of `data`
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::default();
+    crc.update(data);
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        let input_data = b"123456789";
+        let expected_data = 0xcbf4_3926;
+
+        let mut chunked = Crc32::default();
+        for chunk in input_data.chunks(4) {
+            chunked.update(chunk);
+        }
+
+        assert_eq!(expected_data, crc32(input_data));
+        assert_eq!(expected_data, chunked.finish());
+        assert_eq!(0, crc32(&[]));
+    }
+}
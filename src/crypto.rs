@@ -0,0 +1,191 @@
+use crate::container::{decode, encode};
+use crate::error::{HuffmanError, Result};
+
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::XChaCha20Poly1305;
+
+/// Identifies an encrypted container, ahead of everything else
+const MAGIC: [u8; 4] = *b"HTTE";
+const VERSION: u8 = 1;
+/// Scheme byte of XChaCha20-Poly1305, the only scheme so far
+const XCHACHA20_POLY1305: u8 = 1;
+/// Number of u8s of the header, which is authenticated along with the ciphertext
+const HEADER_LEN: usize = 6 + NONCE_SIZE;
+const TAG_SIZE: usize = 16;
+
+/// Number of u8s of an encryption key
+pub const KEY_SIZE: usize = 32;
+/// Number of u8s of a nonce, large enough to be picked at random for every encryption
+pub const NONCE_SIZE: usize = 24;
+
+/// Compresses `data` into a self-contained encoding, as `encode` does, then encrypts and authenticates it with
+/// XChaCha20-Poly1305. Compression has to come first as ciphertext does not compress.
+///
+/// The encrypted container is laid out as:
+/// * `[u8; 4]` magic `HTTE`
+/// * `u8` version, 1
+/// * `u8` scheme, 1 for XChaCha20-Poly1305
+/// * `[u8; 24]` nonce
+/// * the encrypted self-contained encoding
+/// * `[u8; 16]` Poly1305 tag, over the header and the ciphertext
+///
+/// # Arguments
+///
+/// * `data` - The data to compress and encrypt
+/// * `key` - The secret key
+/// * `nonce` - A nonce never used with `key` before, such as 24 random u8s
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let key = [7u8; KEY_SIZE];
+/// let nonce = [1u8; NONCE_SIZE];
+/// let data = b"this is a test string!".repeat(8);
+///
+/// let encrypted_data = encode_encrypted(&data, &key, &nonce).unwrap();
+/// assert_eq!(decode_encrypted(&encrypted_data, &key).unwrap(), data);
+/// assert!(decode_encrypted(&encrypted_data, &[8u8; KEY_SIZE]).is_err());
+/// ```
+pub fn encode_encrypted(
+    data: &[u8],
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+) -> Result<Vec<u8>> {
+    let mut encrypted_data = MAGIC.to_vec();
+    encrypted_data.push(VERSION);
+    encrypted_data.push(XCHACHA20_POLY1305);
+    encrypted_data.extend_from_slice(nonce);
+
+    let mut ciphertext = encode(data)?;
+    let tag = xchacha20_poly1305_seal(key, nonce, &encrypted_data, &mut ciphertext)?;
+    encrypted_data.extend(ciphertext);
+    encrypted_data.extend_from_slice(&tag);
+    Ok(encrypted_data)
+}
+
+/// Authenticates and decrypts a container written by `encode_encrypted`, then decodes it. Nothing is decrypted
+/// unless the tag matches, so a wrong key and tampered data fail the same way
+///
+/// # Arguments
+///
+/// * `encrypted_data` - The encrypted container
+/// * `key` - The key it was encrypted with
+pub fn decode_encrypted(encrypted_data: &[u8], key: &[u8; KEY_SIZE]) -> Result<Vec<u8>> {
+    if encrypted_data.len() < HEADER_LEN + TAG_SIZE {
+        return Err(HuffmanError::DecodeError(
+            "Encrypted container is truncated",
+        ));
+    }
+    let (header, rest) = encrypted_data.split_at(HEADER_LEN);
+    if header[..4] != MAGIC {
+        return Err(HuffmanError::DecodeError(
+            "Not a huff-tree-tap encrypted container",
+        ));
+    }
+    if header[4] != VERSION {
+        return Err(HuffmanError::DecodeError(
+            "Unsupported encrypted container version",
+        ));
+    }
+    if header[5] != XCHACHA20_POLY1305 {
+        return Err(HuffmanError::CryptoError("Unknown encryption scheme"));
+    }
+    let nonce: &[u8; NONCE_SIZE] = header[6..]
+        .try_into()
+        .map_err(|_| HuffmanError::DecodeError("Encrypted container is truncated"))?;
+    let (ciphertext, tag) = rest
+        .split_last_chunk::<TAG_SIZE>()
+        .ok_or(HuffmanError::DecodeError("Encrypted container is truncated"))?;
+
+    let mut plaintext = ciphertext.to_vec();
+    xchacha20_poly1305_open(key, nonce, header, &mut plaintext, tag)?;
+    decode(&plaintext)
+}
+
+/// Encrypts `data` in place and returns the tag over `aad` and the ciphertext
+fn xchacha20_poly1305_seal(
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+) -> Result<[u8; TAG_SIZE]> {
+    XChaCha20Poly1305::new(key.into())
+        .encrypt_in_place_detached(nonce.into(), aad, data)
+        .map(Into::into)
+        .map_err(|_| HuffmanError::CryptoError("Data is too large to encrypt under a single nonce"))
+}
+
+/// Checks `tag` and only then decrypts `data` in place
+fn xchacha20_poly1305_open(
+    key: &[u8; KEY_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    data: &mut [u8],
+    tag: &[u8; TAG_SIZE],
+) -> Result<()> {
+    XChaCha20Poly1305::new(key.into())
+        .decrypt_in_place_detached(nonce.into(), aad, data, tag.into())
+        .map_err(|_| {
+            HuffmanError::CryptoError(
+                "Authentication failed, the key is wrong or the data was tampered with",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_xchacha20_poly1305() {
+        // draft-irtf-cfrg-xchacha section A.3.1
+        let input_data =
+            b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for \
+            the future, sunscreen would be it.";
+        let key: [u8; KEY_SIZE] = (0x80..0xa0).collect::<Vec<u8>>().try_into().unwrap();
+        let nonce: [u8; NONCE_SIZE] = (0x40..0x58).collect::<Vec<u8>>().try_into().unwrap();
+        let aad = from_hex("50515253c0c1c2c3c4c5c6c7");
+        let expected_data = from_hex(
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+            731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+            2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+            21f9664c97637da9768812f615c68b13b52e",
+        );
+
+        let mut test_output = input_data.to_vec();
+        let tag = xchacha20_poly1305_seal(&key, &nonce, &aad, &mut test_output).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(from_hex("c0875924c1c7987947deafd8780acf49"), tag);
+        xchacha20_poly1305_open(&key, &nonce, &aad, &mut test_output, &tag).unwrap();
+        assert_eq!(input_data.to_vec(), test_output);
+    }
+
+    #[test]
+    fn test_encode_encrypted() {
+        let input_data = b"this is a test string!".repeat(8);
+        let key = [3u8; KEY_SIZE];
+        let encrypted_data = encode_encrypted(&input_data, &key, &[9u8; NONCE_SIZE]).unwrap();
+
+        assert_eq!(input_data, decode_encrypted(&encrypted_data, &key).unwrap());
+        for position in [5, 6, HEADER_LEN, encrypted_data.len() - 1] {
+            let mut tampered = encrypted_data.clone();
+            tampered[position] ^= 1;
+            assert!(decode_encrypted(&tampered, &key).is_err());
+        }
+        assert!(matches!(
+            decode_encrypted(&encrypted_data, &[4u8; KEY_SIZE]),
+            Err(HuffmanError::CryptoError(_))
+        ));
+    }
+}
@@ -1,9 +1,14 @@
 use crate::error::{HuffmanError, Result};
 
+use std::ops::Deref;
+
 pub type Bit = u8;
 
 pub type BitVec = Vec<Bit>;
 
+/// A borrowed view of a code or any other run of bits, lets lookups work on part of a stream without copying it out
+pub type BitSlice = [Bit];
+
 pub trait ToFromChar {
     fn to_char(&self) -> char;
     fn from_char(c: char) -> Self;
@@ -29,7 +34,7 @@ impl ToFromChar for Bit {
 
 pub trait BitVector {
     fn to_string(&self) -> String;
-    fn from_string(s: &str) -> BitVec;
+    fn from_string(s: &str) -> Self;
 }
 
 impl BitVector for BitVec {
@@ -42,11 +47,89 @@ impl BitVector for BitVec {
     }
 }
 
+impl BitVector for UnPaddedBits {
+    fn to_string(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn from_string(s: &str) -> UnPaddedBits {
+        UnPaddedBits(BitVec::from_string(s))
+    }
+}
+
 pub type Byte = BitVec;
 
-pub type PaddedBits = BitVec;
+/// Bits laid out as padded u8s, a marker bit in front of every 7 payload bits.
+/// Only produced by padding `UnPaddedBits` or reading padded u8s, so payload bits can never be padded twice
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaddedBits(BitVec);
+
+/// Payload bits as the codes emit them, before any padding
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnPaddedBits(BitVec);
+
+impl UnPaddedBits {
+    pub fn new() -> Self {
+        UnPaddedBits(BitVec::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        UnPaddedBits(BitVec::with_capacity(capacity))
+    }
+
+    pub fn extend_from_slice(&mut self, bits: &BitSlice) {
+        self.0.extend_from_slice(bits);
+    }
+}
+
+impl Deref for UnPaddedBits {
+    type Target = BitSlice;
+
+    fn deref(&self) -> &BitSlice {
+        &self.0
+    }
+}
 
-pub type UnPaddedBits = BitVec;
+impl Deref for PaddedBits {
+    type Target = BitSlice;
+
+    fn deref(&self) -> &BitSlice {
+        &self.0
+    }
+}
+
+impl From<BitVec> for UnPaddedBits {
+    fn from(bits: BitVec) -> Self {
+        UnPaddedBits(bits)
+    }
+}
+
+impl From<UnPaddedBits> for BitVec {
+    fn from(bits: UnPaddedBits) -> Self {
+        bits.0
+    }
+}
+
+impl Extend<Bit> for UnPaddedBits {
+    fn extend<I: IntoIterator<Item = Bit>>(&mut self, bits: I) {
+        self.0.extend(bits);
+    }
+}
+
+impl FromIterator<Bit> for UnPaddedBits {
+    fn from_iter<I: IntoIterator<Item = Bit>>(bits: I) -> Self {
+        UnPaddedBits(bits.into_iter().collect())
+    }
+}
+
+impl IntoIterator for UnPaddedBits {
+    type Item = Bit;
+    type IntoIter = std::vec::IntoIter<Bit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
 
 pub trait UnPadded {
     fn pad(&self) -> PaddedBits;
@@ -96,8 +179,8 @@ pub trait Padded {
 impl Padded for PaddedBits {
     fn unpad(&self) -> UnPaddedBits {
         let mut data = UnPaddedBits::with_capacity(self.len());
-        let mut temp_padded_byte = PaddedBits::with_capacity(8);
-        for bit in self {
+        let mut temp_padded_byte = Byte::with_capacity(8);
+        for bit in self.iter() {
             if temp_padded_byte.len() > 7 {
                 let (_, byte) = temp_padded_byte.split_at(1);
                 data.extend_from_slice(byte);
@@ -105,25 +188,27 @@ impl Padded for PaddedBits {
             }
             temp_padded_byte.push(*bit);
         }
-        let (_, byte) = temp_padded_byte.split_at(1);
-        data.extend_from_slice(byte);
+        if !temp_padded_byte.is_empty() {
+            let (_, byte) = temp_padded_byte.split_at(1);
+            data.extend_from_slice(byte);
+        }
         data
     }
 
     fn from_vec_u8(u8_vec: &[u8]) -> PaddedBits {
-        let mut bit_vec = PaddedBits::with_capacity(8 * u8_vec.len());
+        let mut bit_vec = BitVec::with_capacity(8 * u8_vec.len());
 
         for byte in u8_vec {
             bit_vec.extend_from_slice(&Byte::from_byte(*byte));
         }
-        bit_vec
+        PaddedBits(bit_vec)
     }
 
     fn to_vec_u8(&self) -> Result<Vec<u8>> {
         let mut temp_byte = Byte::with_capacity(8);
         let mut u8_vec: Vec<u8> = Vec::with_capacity(self.len() / 8);
 
-        for bit in self {
+        for bit in self.iter() {
             if temp_byte.len() == 8 {
                 u8_vec.push(temp_byte.to_byte()?);
                 temp_byte.clear();
@@ -135,13 +220,22 @@ impl Padded for PaddedBits {
     }
 }
 
+/// Returns how many payload bits padded u8s hold without unpadding them.
+/// Every u8 holds 7 bits except the last, whose highest set bit is the marker in front of its payload bits
+pub fn unpadded_len(padded: &[u8]) -> u64 {
+    match padded.split_last() {
+        Some((&last, rest)) => rest.len() as u64 * 7 + 7 - last.leading_zeros().min(7) as u64,
+        None => 0,
+    }
+}
+
 impl UnPadded for UnPaddedBits {
     fn pad(&self) -> PaddedBits {
-        let mut padded_bits = PaddedBits::new();
+        let mut padded_bits = BitVec::with_capacity(self.len() + self.len().div_ceil(7));
         let mut temp_padded_byte = Byte::with_capacity(8);
         temp_padded_byte.push(1);
 
-        for bit in self {
+        for bit in self.iter() {
             if temp_padded_byte.len() > 7 {
                 padded_bits.append(&mut temp_padded_byte);
                 temp_padded_byte.push(1);
@@ -149,7 +243,7 @@ impl UnPadded for UnPaddedBits {
             temp_padded_byte.push(*bit);
         }
         padded_bits.append(&mut temp_padded_byte);
-        padded_bits
+        PaddedBits(padded_bits)
     }
 }
 
@@ -161,7 +255,7 @@ mod tests {
     fn test_unpadded_bits_pad() {
         let input_data = UnPaddedBits::from_string("1011100101010000010100000110100101110101001010011011111000111001111011101001001010111010111111100001100");
 
-        let expected_data = PaddedBits::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100");
+        let expected_data = PaddedBits(BitVec::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100"));
 
         let test_output = input_data.pad();
 
@@ -170,18 +264,28 @@ mod tests {
 
     #[test]
     fn test_padded_bits_unpad() {
-        let input_data = PaddedBits::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100");
+        let input_data = PaddedBits(BitVec::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100"));
 
-        let expected_data = BitVec::from_string("1011100101010000010100000110100101110101001010011011111000111001111011101001001010111010111111100001100");
+        let expected_data = UnPaddedBits::from_string("1011100101010000010100000110100101110101001010011011111000111001111011101001001010111010111111100001100");
 
         let test_output = input_data.unpad();
 
         assert_eq!(expected_data, test_output);
     }
 
+    #[test]
+    fn test_unpadded_len() {
+        let input_data: Vec<u8> = vec![
+            220, 212, 138, 134, 203, 212, 211, 190, 156, 251, 210, 171, 215, 248, 44,
+        ];
+
+        assert_eq!(103, unpadded_len(&input_data));
+        assert_eq!(0, unpadded_len(&[]));
+    }
+
     #[test]
     fn test_padded_bits_to_u8_vec() {
-        let input_data = PaddedBits::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100");
+        let input_data = PaddedBits(BitVec::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100"));
 
         let expected_data: Vec<u8> = vec![
             220, 212, 138, 134, 203, 212, 211, 190, 156, 251, 210, 171, 215, 248, 44,
@@ -198,7 +302,7 @@ mod tests {
             220, 212, 138, 134, 203, 212, 211, 190, 156, 251, 210, 171, 215, 248, 44,
         ];
 
-        let expected_data = PaddedBits::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100");
+        let expected_data = PaddedBits(BitVec::from_string("1101110011010100100010101000011011001011110101001101001110111110100111001111101111010010101010111101011111111000101100"));
 
         let test_output = PaddedBits::from_vec_u8(&input_data);
 
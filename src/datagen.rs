@@ -0,0 +1,274 @@
+use crate::error::{HuffmanError, Result};
+
+/// Shape of the u8 distribution `DataGen` draws from. Only the first `alphabet` u8s, 1 to 256 of them, are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Every u8 equally likely, `log2(alphabet)` bits of entropy per u8
+    Uniform { alphabet: u16 },
+    /// The u8 of rank k, counted from 1, drawn with a probability proportional to `1 / k^exponent`, the skew of
+    /// words in text and of most real symbol frequencies
+    Zipf { alphabet: u16, exponent: f64 },
+    /// Order-1 Markov chain repeating the previous u8 with probability `repeat`, and otherwise drawing one of the other
+    /// u8s uniformly. Runs make the data compress better than its u8 frequencies say
+    Markov { alphabet: u16, repeat: f64 },
+}
+
+/// Builds reproducible synthetic data from a `Distribution` and a seed, so ratio and performance tests run against
+/// known entropies instead of whatever files are at hand
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = DataGen::with_entropy(4.0).unwrap().seed(7).generate(1 << 16);
+/// let huffman_data = HuffmanData::new(&data).unwrap();
+/// // A Huffman code spends less than a bit per u8 over the entropy
+/// let bits_per_u8 = huffman_data.stats.payload_bits as f64 / data.len() as f64;
+/// assert!(bits_per_u8 >= 3.9 && bits_per_u8 < 5.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataGen {
+    distribution: Distribution,
+    seed: u64,
+}
+
+impl DataGen {
+    /// Creates a `DataGen` drawing from `distribution` with seed 0
+    ///
+    /// # Arguments
+    ///
+    /// * `distribution` - The distribution to draw u8s from
+    pub fn new(distribution: Distribution) -> Result<DataGen> {
+        let (alphabet, parameter_valid) = match distribution {
+            Distribution::Uniform { alphabet } => (alphabet, true),
+            Distribution::Zipf { alphabet, exponent } => {
+                (alphabet, exponent.is_finite() && exponent >= 0.0)
+            }
+            Distribution::Markov { alphabet, repeat } => (alphabet, (0.0..=1.0).contains(&repeat)),
+        };
+        if alphabet == 0 || alphabet > 256 {
+            return Err(HuffmanError::OptionsError(
+                "Alphabet must be between 1 and 256 u8s",
+            ));
+        }
+        if !parameter_valid {
+            return Err(HuffmanError::OptionsError(
+                "Zipf exponents must be finite and not negative, Markov repeat probabilities between 0 and 1",
+            ));
+        }
+        Ok(DataGen {
+            distribution,
+            seed: 0,
+        })
+    }
+
+    /// Creates a `DataGen` drawing every u8 from a Zipf distribution over all 256 u8s, its exponent chosen so the
+    /// data has `bits_per_u8` bits of entropy per u8
+    ///
+    /// # Arguments
+    ///
+    /// * `bits_per_u8` - The entropy to aim for, above 0 and at most 8
+    pub fn with_entropy(bits_per_u8: f64) -> Result<DataGen> {
+        if !(bits_per_u8 > 0.0 && bits_per_u8 <= 8.0) {
+            return Err(HuffmanError::OptionsError(
+                "Entropy must be above 0 and at most 8 bits per u8",
+            ));
+        }
+        // Entropy falls as the exponent grows, from 8 bits at 0 to almost nothing at 64
+        let (mut low, mut high) = (0.0, 64.0);
+        for _ in 0..64 {
+            let exponent = (low + high) / 2.0;
+            match entropy_of(&zipf_weights(256, exponent)) > bits_per_u8 {
+                true => low = exponent,
+                false => high = exponent,
+            }
+        }
+        Self::new(Distribution::Zipf {
+            alphabet: 256,
+            exponent: low,
+        })
+    }
+
+    /// Sets the seed, the same seed and distribution always generating the same data
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Any value
+    pub fn seed(self, seed: u64) -> DataGen {
+        DataGen { seed, ..self }
+    }
+
+    /// Entropy of the distribution in bits per u8, the entropy rate of the chain for `Distribution::Markov`
+    pub fn entropy(&self) -> f64 {
+        match self.distribution {
+            Distribution::Uniform { alphabet } => (alphabet as f64).log2(),
+            Distribution::Zipf { alphabet, exponent } => {
+                entropy_of(&zipf_weights(alphabet, exponent))
+            }
+            Distribution::Markov { alphabet: 1, .. } => 0.0,
+            Distribution::Markov { alphabet, repeat } => {
+                entropy_of(&[repeat, 1.0 - repeat])
+                    + (1.0 - repeat) * ((alphabet - 1) as f64).log2()
+            }
+        }
+    }
+
+    /// Generates `len` u8s
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The number of u8s to generate
+    pub fn generate(&self, len: usize) -> Vec<u8> {
+        let mut rng = SplitMix64(self.seed);
+        match self.distribution {
+            Distribution::Uniform { alphabet } => {
+                let cumulative = cumulative(&vec![1.0; alphabet as usize]);
+                (0..len).map(|_| rng.pick(&cumulative)).collect()
+            }
+            Distribution::Zipf { alphabet, exponent } => {
+                let cumulative = cumulative(&zipf_weights(alphabet, exponent));
+                (0..len).map(|_| rng.pick(&cumulative)).collect()
+            }
+            Distribution::Markov { alphabet, repeat } => {
+                let others = cumulative(&vec![1.0; alphabet as usize - 1]);
+                let mut previous = rng.pick(&cumulative(&vec![1.0; alphabet as usize]));
+                let mut data = Vec::with_capacity(len);
+                for _ in 0..len {
+                    data.push(previous);
+                    if others.is_empty() || rng.next_f64() < repeat {
+                        continue;
+                    }
+                    // Draw among the other u8s by skipping over the previous one
+                    let other = rng.pick(&others);
+                    previous = other + (other >= previous) as u8;
+                }
+                data
+            }
+        }
+    }
+}
+
+/// Weight `1 / k^exponent` of every rank k from 1 to `alphabet`
+fn zipf_weights(alphabet: u16, exponent: f64) -> Vec<f64> {
+    (1..=alphabet as usize)
+        .map(|rank| (rank as f64).powf(-exponent))
+        .collect()
+}
+
+/// Entropy in bits of the distribution with the given weights
+fn entropy_of(weights: &[f64]) -> f64 {
+    let total: f64 = weights.iter().sum();
+    weights
+        .iter()
+        .filter(|&&weight| weight > 0.0)
+        .map(|&weight| {
+            let probability = weight / total;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Running sums of `weights`, for drawing with `SplitMix64::pick`
+fn cumulative(weights: &[f64]) -> Vec<f64> {
+    weights
+        .iter()
+        .scan(0.0, |total, weight| {
+            *total += weight;
+            Some(*total)
+        })
+        .collect()
+}
+
+/// SplitMix64, a small generator that is fast and stable across platforms and releases
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Index drawn with the probabilities the running sums `cumulative` describe
+    fn pick(&mut self, cumulative: &[f64]) -> u8 {
+        let target = self.next_f64() * cumulative[cumulative.len() - 1];
+        let index = cumulative.partition_point(|&total| total <= target);
+        index.min(cumulative.len() - 1) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entropy of the u8 frequencies of `data` in bits per u8
+    fn measured_entropy(data: &[u8]) -> f64 {
+        let mut counts = [0.0; 256];
+        for &byte in data {
+            counts[byte as usize] += 1.0;
+        }
+        entropy_of(&counts)
+    }
+
+    #[test]
+    fn test_generate() {
+        let input_data = [
+            DataGen::new(Distribution::Uniform { alphabet: 16 }).unwrap(),
+            DataGen::new(Distribution::Zipf {
+                alphabet: 256,
+                exponent: 1.0,
+            })
+            .unwrap(),
+            DataGen::with_entropy(2.5).unwrap(),
+        ];
+        let expected_data = [4.0, input_data[1].entropy(), 2.5];
+
+        for (generator, expected_data) in input_data.iter().zip(expected_data) {
+            let test_output = generator.clone().seed(3).generate(1 << 16);
+
+            assert!((expected_data - generator.entropy()).abs() < 1e-6);
+            assert!((expected_data - measured_entropy(&test_output)).abs() < 0.05);
+            assert_eq!(test_output, generator.clone().seed(3).generate(1 << 16));
+            assert_ne!(test_output, generator.clone().seed(4).generate(1 << 16));
+        }
+        assert!(DataGen::new(Distribution::Uniform { alphabet: 257 }).is_err());
+        assert!(DataGen::with_entropy(0.0).is_err());
+    }
+
+    #[test]
+    fn test_markov() {
+        let input_data = DataGen::new(Distribution::Markov {
+            alphabet: 4,
+            repeat: 0.9,
+        })
+        .unwrap();
+
+        let test_output = input_data.generate(1 << 16);
+
+        // Uniform u8 frequencies, but runs averaging 10 u8s
+        assert!((2.0 - measured_entropy(&test_output)).abs() < 0.05);
+        let changes = test_output
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count();
+        assert!((changes as f64 / test_output.len() as f64 - 0.1).abs() < 0.01);
+        assert!(input_data.entropy() < 1.0);
+        assert!(DataGen::new(Distribution::Markov {
+            alphabet: 1,
+            repeat: 0.0
+        })
+        .unwrap()
+        .generate(100)
+        .iter()
+        .all(|&byte| byte == 0));
+    }
+}
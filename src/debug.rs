@@ -0,0 +1,191 @@
+//! Tools for pinning down where two encodings part ways
+
+use crate::block_type::BlockType;
+use crate::data::{BitVec, BitVector, UnPaddedBits};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+
+use core::fmt;
+
+/// Number of symbols shown either side of the first difference
+const CONTEXT_SYMBOLS: usize = 2;
+
+/// Where two `HuffmanData` first disagree, as reported by `bit_diff`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitDiff {
+    /// Index of the first differing symbol in decode order
+    pub symbol_index: usize,
+    /// Stream holding that symbol in `a`, or in `b` when `a` has already ended
+    pub stream: usize,
+    /// Offset of the symbol's code within its unpadded stream, taken from the same side as `stream`
+    pub bit_offset: usize,
+    /// The symbol in `a`, `None` when `a` has fewer symbols
+    pub symbol_a: Option<u8>,
+    /// The symbol in `b`, `None` when `b` has fewer symbols
+    pub symbol_b: Option<u8>,
+    /// Codes of `a` around the difference, the differing one in the middle
+    pub codes_a: Vec<String>,
+    /// Codes of `b` around the difference, the differing one in the middle
+    pub codes_b: Vec<String>,
+}
+
+impl fmt::Display for BitDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = |symbol: Option<u8>| match symbol {
+            Some(symbol) => format!("{:#04x}", symbol),
+            None => "end of data".to_string(),
+        };
+        write!(
+            f,
+            "Symbol {} (stream {}, bit {}): {} vs {}\n  a: {}\n  b: {}",
+            self.symbol_index,
+            self.stream,
+            self.bit_offset,
+            symbol(self.symbol_a),
+            symbol(self.symbol_b),
+            self.codes_a.join(" "),
+            self.codes_b.join(" ")
+        )
+    }
+}
+
+/// A decoded symbol with the stream it came from and the span of its code in that stream
+struct SymbolCode {
+    symbol: u8,
+    stream: usize,
+    offset: usize,
+    code: BitVec,
+}
+
+/// Walks two Huffman blocks symbol by symbol and reports the first symbol whose value or code differs,
+/// or `None` when both hold the same symbols with the same codes.
+/// Useful to pin down where outputs of two versions or implementations part ways.
+///
+/// # Arguments
+///
+/// * `a` - The first `HuffmanData`
+/// * `b` - The `HuffmanData` to compare it with
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let text = b"this is a test string!".repeat(8);
+/// let mut changed = text.clone();
+/// changed.swap(19, 20);
+/// let a = HuffmanData::new(&text).unwrap();
+/// let b = HuffmanData::new(&changed).unwrap();
+/// let diff = debug::bit_diff(&a, &b).unwrap().unwrap();
+/// assert_eq!(diff.symbol_index, 19);
+/// assert_eq!((diff.symbol_a, diff.symbol_b), (Some(b'n'), Some(b'g')));
+/// assert!(debug::bit_diff(&a, &a).unwrap().is_none());
+/// ```
+pub fn bit_diff(a: &HuffmanData, b: &HuffmanData) -> Result<Option<BitDiff>> {
+    let symbols_a = symbol_codes(a)?;
+    let symbols_b = symbol_codes(b)?;
+
+    let differs = |index: usize| match (symbols_a.get(index), symbols_b.get(index)) {
+        (Some(a), Some(b)) => a.symbol != b.symbol || a.code != b.code,
+        _ => true,
+    };
+    let Some(symbol_index) =
+        (0..symbols_a.len().max(symbols_b.len())).find(|&index| differs(index))
+    else {
+        return Ok(None);
+    };
+
+    let located = symbols_a
+        .get(symbol_index)
+        .or(symbols_b.get(symbol_index))
+        .ok_or(HuffmanError::DecodeError("Differing symbol is missing"))?;
+    let context = |symbols: &[SymbolCode]| -> Vec<String> {
+        symbols
+            .iter()
+            .skip(symbol_index.saturating_sub(CONTEXT_SYMBOLS))
+            .take(symbol_index.min(CONTEXT_SYMBOLS) + CONTEXT_SYMBOLS + 1)
+            .map(|symbol| symbol.code.to_string())
+            .collect()
+    };
+
+    Ok(Some(BitDiff {
+        symbol_index,
+        stream: located.stream,
+        bit_offset: located.offset,
+        symbol_a: symbols_a.get(symbol_index).map(|symbol| symbol.symbol),
+        symbol_b: symbols_b.get(symbol_index).map(|symbol| symbol.symbol),
+        codes_a: context(&symbols_a),
+        codes_b: context(&symbols_b),
+    }))
+}
+
+/// Every symbol of a Huffman block in decode order, i.e. round-robin across its streams
+fn symbol_codes(huffman_data: &HuffmanData) -> Result<Vec<SymbolCode>> {
+    if huffman_data.block_type != BlockType::Huffman {
+        return Err(HuffmanError::DecodeError(
+            "Only Huffman blocks can be compared bit by bit",
+        ));
+    }
+    let encoding_map = &huffman_data.encoding_map;
+    let code_lens = (
+        encoding_map.get_shortest_code(),
+        encoding_map.get_longest_code(),
+    );
+
+    let mut streams = Vec::new();
+    for (stream_index, stream) in huffman_data.split_streams()?.into_iter().enumerate() {
+        let stream: UnPaddedBits = huffman_data.packing.unpack(stream);
+        let mut symbols = Vec::new();
+        let mut cursor = 0;
+        let mut code = BitVec::new();
+        while let Some(symbol) =
+            HuffmanData::next_symbol(&stream, &mut cursor, &mut code, code_lens, encoding_map)?
+        {
+            let offset = cursor - code.len() - encoding_map.get(&symbol).map_or(8, |_| 0);
+            symbols.push(SymbolCode {
+                symbol,
+                stream: stream_index,
+                offset,
+                code: stream[offset..cursor].to_vec(),
+            });
+        }
+        streams.push(symbols.into_iter());
+    }
+
+    let mut symbols = Vec::new();
+    'interleave: loop {
+        for stream in streams.iter_mut() {
+            match stream.next() {
+                Some(symbol) => symbols.push(symbol),
+                None => break 'interleave,
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_diff() {
+        let input_data = b"this is a test string!".repeat(8);
+        let mut modified_data = input_data.clone();
+        modified_data[10] = b'g';
+        let a = HuffmanData::new(&input_data).unwrap();
+        let mut b = HuffmanData::new(&input_data).unwrap();
+        // Re-encode with the same map so only the changed symbol differs
+        b.encoded_data =
+            crate::huffman::encode_with_map(&modified_data, &a.encoding_map, b.packing).unwrap();
+
+        let test_output = bit_diff(&a, &b).unwrap().unwrap();
+
+        assert_eq!(10, test_output.symbol_index);
+        assert_eq!(Some(b't'), test_output.symbol_a);
+        assert_eq!(0, test_output.stream);
+        assert_eq!(5, test_output.codes_a.len());
+        assert!(bit_diff(&a, &a).unwrap().is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::block_type::BlockType;
+use crate::decoder::HuffmanDecoder;
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl HuffmanData {
+    /// Decodes the data lazily as a `Stream` of chunks of at most `chunk_size` u8s, each poll decoding only as much
+    /// as the next chunk needs. Payloads split into interleaved streams, and other block types, are decoded whole on
+    /// the first poll and then handed out in chunks.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk_size` - Largest number of decoded u8s per item, at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use futures_core::Stream;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    ///
+    /// let mut stream = pin!(huffman_data.decode_stream(4));
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// let mut decoded_data = Vec::new();
+    /// while let Poll::Ready(Some(chunk)) = stream.as_mut().poll_next(&mut cx) {
+    ///     decoded_data.extend(chunk.unwrap());
+    /// }
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn decode_stream(&self, chunk_size: usize) -> impl Stream<Item = Result<Vec<u8>>> + '_ {
+        DecodeStream {
+            huffman_data: self,
+            chunk_size: chunk_size.max(1),
+            decoder: None,
+            input: &self.encoded_data,
+            decoded: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+}
+
+struct DecodeStream<'a> {
+    huffman_data: &'a HuffmanData,
+    chunk_size: usize,
+    /// Only set for single stream Huffman payloads, which are the ones decoded incrementally
+    decoder: Option<HuffmanDecoder>,
+    /// Encoded u8s not fed to `decoder` yet
+    input: &'a [u8],
+    decoded: Vec<u8>,
+    started: bool,
+    done: bool,
+}
+
+impl DecodeStream<'_> {
+    /// Decodes until a full chunk is ready or the payload is exhausted
+    fn fill(&mut self) -> Result<()> {
+        if !self.started {
+            self.started = true;
+            let huffman_data = self.huffman_data;
+            match huffman_data.block_type {
+                BlockType::Huffman if huffman_data.stream_sizes.is_empty() => {
+                    self.decoder = Some(HuffmanDecoder::with_packing(
+                        huffman_data.encoding_map.clone(),
+                        huffman_data.packing,
+                    ));
+                }
+                _ => self.decoded = huffman_data.decode()?,
+            }
+        }
+        while self.decoded.len() < self.chunk_size {
+            let Some(decoder) = self.decoder.as_mut() else {
+                break;
+            };
+            // Feeding `chunk_size` encoded u8s at a time keeps the work of a poll in proportion to the chunk it returns
+            if self.input.is_empty() {
+                if let Some(decoder) = self.decoder.take() {
+                    self.decoded.extend(decoder.finish()?);
+                }
+            } else {
+                let (chunk, rest) = self.input.split_at(self.chunk_size.min(self.input.len()));
+                self.decoded.extend(decoder.feed(chunk)?);
+                self.input = rest;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Stream for DecodeStream<'_> {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if let Err(e) = this.fill() {
+            this.done = true;
+            return Poll::Ready(Some(Err(e)));
+        }
+        if this.decoded.is_empty() {
+            this.done = true;
+            return Poll::Ready(None);
+        }
+        let rest = this
+            .decoded
+            .split_off(this.chunk_size.min(this.decoded.len()));
+        Poll::Ready(Some(Ok(std::mem::replace(&mut this.decoded, rest))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::huffman::HuffmanData;
+    use crate::options::HuffmanOptions;
+
+    use futures_core::Stream;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    #[test]
+    fn test_decode_stream() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(100);
+
+        for streams in [1, 4] {
+            let huffman_data = HuffmanData::with_options(
+                &input_data,
+                &HuffmanOptions {
+                    streams,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let mut stream = pin!(huffman_data.decode_stream(100));
+            let mut cx = Context::from_waker(Waker::noop());
+
+            let mut test_output = Vec::new();
+            while let Poll::Ready(Some(chunk)) = stream.as_mut().poll_next(&mut cx) {
+                let chunk = chunk.unwrap();
+                assert!(chunk.len() <= 100);
+                test_output.extend(chunk);
+            }
+
+            assert_eq!(input_data, test_output);
+        }
+    }
+}
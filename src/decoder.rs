@@ -0,0 +1,214 @@
+use crate::data::BitVec;
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+
+/// Incremental decoder for a single stream whose encoded u8s arrive in arbitrary chunks.
+/// Partial codes are carried over between calls so chunk boundaries may fall anywhere.
+#[derive(Debug, Clone)]
+pub struct HuffmanDecoder {
+    encoding_map: EncodingMap,
+    packing: Packing,
+    longest_code: usize,
+    code: BitVec,
+    /// Bits of an escaped literal read so far, `None` outside of a literal
+    literal: Option<(u8, u8)>,
+    /// The last u8 only holds as many bits as come before its end bit (or after its marker when padded), so every u8
+    /// is held back until the next one arrives
+    held: Option<u8>,
+    options: DecodeOptions,
+    /// Symbols decoded and bits fed so far, checked against `options.max_symbols_per_bit`
+    symbols: usize,
+    input_bits: usize,
+}
+
+impl HuffmanDecoder {
+    /// Creates a `HuffmanDecoder` for data encoded with `encoding_map` and the default `Packing`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data = b"this is a test string!".repeat(8);
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    ///
+    /// let mut decoder = HuffmanDecoder::new(huffman_data.encoding_map.clone());
+    /// let mut decoded_data = Vec::new();
+    /// for chunk in huffman_data.encoded_data.chunks(3) {
+    ///     decoded_data.extend(decoder.feed(chunk).unwrap());
+    /// }
+    /// decoded_data.extend(decoder.finish().unwrap());
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn new(encoding_map: EncodingMap) -> Self {
+        Self::with_packing(encoding_map, Packing::default())
+    }
+
+    /// Creates a `HuffmanDecoder` for data encoded with `encoding_map` and packed as `packing` says
+    pub fn with_packing(encoding_map: EncodingMap, packing: Packing) -> Self {
+        HuffmanDecoder {
+            packing,
+            longest_code: encoding_map.get_longest_code(),
+            code: BitVec::with_capacity(encoding_map.get_longest_code()),
+            encoding_map,
+            literal: None,
+            held: None,
+            options: DecodeOptions::default(),
+            symbols: 0,
+            input_bits: 0,
+        }
+    }
+
+    /// Decodes the next chunk of encoded u8s, returning every u8 resolved so far
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next encoded u8s, in order
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        let mut decoded = Vec::with_capacity(chunk.len() * 2);
+        for &byte in chunk {
+            if let Some(previous) = self.held.replace(byte) {
+                let bits = self.packing.full_bits(previous)?;
+                self.push_bits(bits, self.packing.bits_per_u8(), &mut decoded)?;
+            }
+        }
+        Ok(decoded)
+    }
+
+    /// Decodes up to `n` u8s from what the decoder holds followed by `chunk`, leaving the decoder as it was, so the
+    /// start of the content can be sniffed (e.g. for magic bytes) before `chunk` is fed for real.
+    /// Only as much of `chunk` is decoded as it takes to resolve `n` u8s, and its last u8 is held back as `feed`
+    /// holds it back, so fewer than `n` may be returned
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next encoded u8s, in order
+    /// * `n` - The most u8s to return
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut data: Vec<u8> = Vec::from("%PDF-1.7 ");
+    /// data.extend(b"this is a test string!".repeat(8));
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    ///
+    /// let mut decoder = HuffmanDecoder::new(huffman_data.encoding_map.clone());
+    /// assert_eq!(decoder.peek(&huffman_data.encoded_data, 4).unwrap(), b"%PDF");
+    /// let mut decoded_data = decoder.feed(&huffman_data.encoded_data).unwrap();
+    /// decoded_data.extend(decoder.finish().unwrap());
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn peek(&self, chunk: &[u8], n: usize) -> Result<Vec<u8>> {
+        let mut decoder = self.clone();
+        let mut decoded = Vec::with_capacity(n);
+        for byte in chunk {
+            if decoded.len() >= n {
+                break;
+            }
+            decoded.extend(decoder.feed(std::slice::from_ref(byte))?);
+        }
+        decoded.truncate(n);
+        Ok(decoded)
+    }
+
+    /// Decodes the held back last u8 and returns the remaining u8s
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        if let Some(last) = self.held.take() {
+            let (bits, count) = self.packing.last_bits(last);
+            self.push_bits(bits, count, &mut decoded)?;
+        }
+        Ok(decoded)
+    }
+
+    /// Feeds the low `count` bits of `bits`, most significant first, pushing every resolved symbol to `decoded`
+    fn push_bits(&mut self, bits: u8, count: u32, decoded: &mut Vec<u8>) -> Result<()> {
+        let decoded_before = decoded.len();
+        self.input_bits += count as usize;
+        for shift in (0..count).rev() {
+            let bit = (bits >> shift) & 1;
+            if let Some((read, value)) = self.literal {
+                let value = (value << 1) | bit;
+                self.literal = match read + 1 {
+                    8 => {
+                        decoded.push(value);
+                        None
+                    }
+                    read => Some((read, value)),
+                };
+                continue;
+            }
+
+            self.code.push(bit);
+            if let Some(&byte) = self.encoding_map.get_inverse(&self.code) {
+                decoded.push(byte);
+                self.code.clear();
+            } else if self.encoding_map.get_escape() == Some(&self.code) {
+                self.literal = Some((0, 0));
+                self.code.clear();
+            } else if self.code.len() >= self.longest_code {
+                return Err(HuffmanError::DecodeError("Invalid code in encoded data"));
+            }
+        }
+        self.symbols += decoded.len() - decoded_before;
+        self.options.check_run(self.symbols, self.input_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::BitVector;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_huffman_decoder() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let encoding_map = EncodingMap::from(map)
+            .unwrap()
+            .with_escape(BitVec::from_string("11"))
+            .unwrap();
+        // "abca" as 0 10 11 01100011 0 split over 2 padded u8s, then over 2 dense u8s
+        let inputs = [
+            (Packing::Padded, [0b1010_1101, 0b1100_0110]),
+            (Packing::Dense, [0b0101_1011, 0b0001_1010]),
+        ];
+
+        for (packing, input_data) in inputs {
+            for split in 0..=input_data.len() {
+                let mut decoder = HuffmanDecoder::with_packing(encoding_map.clone(), packing);
+                let mut test_output = decoder.feed(&input_data[..split]).unwrap();
+                test_output.extend(decoder.feed(&input_data[split..]).unwrap());
+                test_output.extend(decoder.finish().unwrap());
+
+                assert_eq!(b"abca".to_vec(), test_output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_peek() {
+        let input_data = b"this is a test string!".repeat(8);
+        let huffman_data = crate::huffman::HuffmanData::new(&input_data).unwrap();
+        let (head, tail) = huffman_data.encoded_data.split_at(3);
+        let mut decoder = HuffmanDecoder::new(huffman_data.encoding_map.clone());
+        let mut expected_data = decoder.feed(head).unwrap();
+
+        let test_output = decoder.peek(tail, 6).unwrap();
+
+        assert_eq!(input_data[expected_data.len()..][..6], test_output);
+        assert!(decoder.peek(tail, 0).unwrap().is_empty());
+        expected_data.extend(decoder.feed(tail).unwrap());
+        expected_data.extend(decoder.finish().unwrap());
+        assert_eq!(input_data, expected_data);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::block_type::BlockType;
+use crate::crc32::Crc32;
+use crate::data::{BitVec, UnPaddedBits};
+use crate::error::{HuffmanError, Result};
+use crate::huffman::{check_checksum, HuffmanData};
+use crate::options::DecodeOptions;
+
+/// Decodes a `HuffmanData` a bounded number of u8s at a time, so interactive consumers such as UIs or games
+/// streaming assets can interleave decoding with other work under a frame budget.
+///
+/// The payload is unpacked once by `new`, after which every `decode_step` costs in proportion to the u8s it returns.
+#[derive(Debug)]
+pub struct DecoderState<'a> {
+    huffman_data: &'a HuffmanData,
+    /// Unpacked streams of a Huffman block, empty for any other block type
+    streams: Vec<UnPaddedBits>,
+    /// Bits the streams hold between them, bounding the symbols they decode to under `options.max_symbols_per_bit`
+    input_bits: usize,
+    cursors: Vec<usize>,
+    code: BitVec,
+    /// Position in the encoded data of a stored or run-length block
+    encoded_position: usize,
+    /// u8s of the current run of a run-length block still to be produced
+    run_left: usize,
+    /// Number of u8s decoded so far
+    position: usize,
+    crc: Crc32,
+    output: Vec<u8>,
+    options: DecodeOptions,
+}
+
+impl<'a> DecoderState<'a> {
+    /// Prepares to decode `huffman_data` step by step
+    ///
+    /// # Arguments
+    ///
+    /// * `huffman_data` - The data to decode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    ///
+    /// let mut state = DecoderState::new(&huffman_data).unwrap();
+    /// let mut decoded_data = Vec::new();
+    /// while !state.is_finished() {
+    ///     // At most 4 u8s per frame
+    ///     decoded_data.extend_from_slice(state.decode_step(4).unwrap());
+    /// }
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn new(huffman_data: &'a HuffmanData) -> Result<DecoderState<'a>> {
+        let streams = match huffman_data.block_type {
+            BlockType::Huffman => huffman_data
+                .split_streams()?
+                .iter()
+                .map(|stream| huffman_data.packing.unpack(stream))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(DecoderState {
+            huffman_data,
+            cursors: vec![0; streams.len()],
+            input_bits: streams.iter().map(|stream| stream.len()).sum(),
+            streams,
+            code: BitVec::with_capacity(huffman_data.encoding_map.get_longest_code()),
+            encoded_position: 0,
+            run_left: 0,
+            position: 0,
+            crc: Crc32::default(),
+            output: Vec::new(),
+            options: DecodeOptions::default(),
+        })
+    }
+
+    /// True once every u8 of the data has been returned
+    pub fn is_finished(&self) -> bool {
+        self.position == self.huffman_data.data_len
+    }
+
+    /// Decodes and returns the next u8s of the data, at most `max_out` of them and none once finished.
+    /// The checksum the data carries is verified by the step returning its last u8s
+    ///
+    /// # Arguments
+    ///
+    /// * `max_out` - The most u8s to decode in this step
+    pub fn decode_step(&mut self, max_out: usize) -> Result<&[u8]> {
+        let count = max_out.min(self.huffman_data.data_len - self.position);
+        self.output.clear();
+        match self.huffman_data.block_type {
+            BlockType::Stored => self.stored_step(count)?,
+            BlockType::Rle => self.rle_step(count)?,
+            BlockType::Huffman => self.huffman_step(count)?,
+        }
+        self.position += count;
+        self.crc.update(&self.output);
+
+        if let (true, Some(checksum)) =
+            (count > 0 && self.is_finished(), self.huffman_data.checksum)
+        {
+            check_checksum(checksum, self.crc.finish())?;
+        }
+        Ok(&self.output)
+    }
+
+    fn stored_step(&mut self, count: usize) -> Result<()> {
+        let encoded_data = &self.huffman_data.encoded_data;
+        let step = encoded_data
+            .get(self.encoded_position..self.encoded_position + count)
+            .ok_or(HuffmanError::DecodeError("Encoded data is truncated"))?;
+        self.output.extend_from_slice(step);
+        self.encoded_position += count;
+        Ok(())
+    }
+
+    fn rle_step(&mut self, count: usize) -> Result<()> {
+        let encoded_data = &self.huffman_data.encoded_data;
+        while self.output.len() < count {
+            if self.run_left == 0 {
+                match encoded_data.get(self.encoded_position) {
+                    Some(0) => return Err(HuffmanError::DecodeError("Zero length run")),
+                    Some(&run_length) => self.run_left = run_length as usize,
+                    None => return Err(HuffmanError::DecodeError("Encoded data is truncated")),
+                }
+                self.encoded_position += 2;
+            }
+            let byte = *encoded_data
+                .get(self.encoded_position - 1)
+                .ok_or(HuffmanError::DecodeError("Truncated run-length pair"))?;
+            let run = self.run_left.min(count - self.output.len());
+            self.output.resize(self.output.len() + run, byte);
+            self.run_left -= run;
+        }
+        Ok(())
+    }
+
+    fn huffman_step(&mut self, count: usize) -> Result<()> {
+        let encoding_map = &self.huffman_data.encoding_map;
+        let code_lens = (
+            encoding_map.get_shortest_code(),
+            encoding_map.get_longest_code(),
+        );
+        for index in self.position..self.position + count {
+            // Symbols are dealt round robin, so the stream of the next one follows from how many came before it
+            let stream = index % self.streams.len();
+            let byte = HuffmanData::next_symbol(
+                &self.streams[stream],
+                &mut self.cursors[stream],
+                &mut self.code,
+                code_lens,
+                encoding_map,
+            )?
+            .ok_or(HuffmanError::DecodeError("Encoded data is truncated"))?;
+            self.options.check_run(index + 1, self.input_bits)?;
+            self.output.push(byte);
+        }
+        Ok(())
+    }
+}
+
+impl HuffmanData {
+    /// Decodes `len` u8s starting `start` u8s into the data, fewer when the data ends first, stopping as soon as they
+    /// are produced rather than decoding the whole payload. The checksum is only verified when the range reaches the
+    /// end of the data
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Where to start in the decoded data
+    /// * `len` - The most u8s to return
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    /// assert_eq!(huffman_data.decode_range(10, 4).unwrap(), b"test");
+    /// ```
+    pub fn decode_range(&self, start: usize, len: usize) -> Result<Vec<u8>> {
+        let start = start.min(self.data_len);
+        if self.block_type == BlockType::Stored {
+            let end = start.saturating_add(len).min(self.encoded_data.len());
+            return Ok(self.encoded_data.get(start..end).unwrap_or(&[]).to_vec());
+        }
+        let mut state = DecoderState::new(self)?;
+        let mut skipped = 0;
+        while skipped < start {
+            skipped += state.decode_step(start - skipped)?.len();
+        }
+        Ok(state.decode_step(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::HuffmanOptions;
+
+    #[test]
+    fn test_decode_step() {
+        let text: Vec<u8> = Vec::from("this is a test string!");
+        let inputs = [
+            HuffmanData::new(&text).unwrap(),
+            HuffmanData::with_options(
+                &text.repeat(10),
+                &HuffmanOptions {
+                    streams: 3,
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+            HuffmanData::new(&[b'a'; 600]).unwrap(),
+            HuffmanData::new(&(0..=255).collect::<Vec<u8>>()).unwrap(),
+            HuffmanData::new(&[]).unwrap(),
+        ];
+
+        for input_data in &inputs {
+            let expected_data = input_data.decode().unwrap();
+            let mut state = DecoderState::new(input_data).unwrap();
+            let mut test_output = Vec::new();
+            while !state.is_finished() {
+                let step = state.decode_step(7).unwrap();
+                assert!(!step.is_empty() && step.len() <= 7);
+                test_output.extend_from_slice(step);
+            }
+
+            assert_eq!(expected_data, test_output);
+            assert!(state.decode_step(7).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_decode_range() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let huffman_data = HuffmanData::new(&input_data).unwrap();
+
+        for (start, len) in [(0, 4), (10, 4), (20, 10), (30, 1)] {
+            let expected_data = &input_data[start.min(22)..(start + len).min(22)];
+
+            let test_output = huffman_data.decode_range(start, len).unwrap();
+
+            assert_eq!(expected_data, test_output);
+        }
+    }
+
+    #[test]
+    fn test_decode_step_checksum() {
+        let mut input_data = HuffmanData::new(b"this is a test string!").unwrap();
+        input_data.checksum = input_data.checksum.map(|checksum| checksum ^ 1);
+        let mut state = DecoderState::new(&input_data).unwrap();
+
+        assert!(state.decode_step(21).is_ok());
+        assert!(matches!(
+            state.decode_step(21),
+            Err(HuffmanError::ChecksumMismatch(_, _))
+        ));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::coverage::CoverageReport;
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::frequency_accumulator::FrequencyAccumulator;
+use crate::frequency_map::FrequencyMap;
+use crate::huffman::{decode_with_map, encode_with_map};
+use crate::huffman_tree;
+use crate::mapped_dictionary;
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+use crate::stream::{read_chunk, CHUNK_SIZE};
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+/// Total weight training spreads across all samples, fine enough that rare u8s keep distinct weights
+const TRAINING_SCALE: f64 = (1u64 << 24) as f64;
+
+/// A Huffman code trained ahead of time on sample data, shared by many small payloads so none of them has to carry
+/// its own map. The code includes an escape so u8s never seen during training can still be encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HuffmanDictionary {
+    encoding_map: EncodingMap,
+    packing: Packing,
+}
+
+impl HuffmanDictionary {
+    /// Trains a dictionary on `samples`, every u8 of every sample counting the same
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Data representative of the payloads the dictionary will encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let samples: [&[u8]; 2] = [b"GET /index.html", b"GET /about.html"];
+    /// let dictionary = HuffmanDictionary::train(&samples).unwrap();
+    /// let encoded_data = dictionary.encode(b"GET /contact.html").unwrap();
+    /// assert_eq!(dictionary.decode(&encoded_data).unwrap(), b"GET /contact.html");
+    /// ```
+    pub fn train(samples: &[&[u8]]) -> Result<HuffmanDictionary> {
+        let samples: Vec<(&[u8], f64)> = samples
+            .iter()
+            .filter(|sample| !sample.is_empty())
+            .map(|&sample| (sample, sample.len() as f64))
+            .collect();
+        Self::train_weighted(&samples)
+    }
+
+    /// Trains a dictionary on weighted `samples`. Each sample's u8 frequencies are normalized to its length first,
+    /// so a sample's influence depends only on its share of the total weight, never on its size.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Pairs of sample data and a finite, positive weight
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// // Recent traffic counts three times as much as the older corpus
+    /// let samples: [(&[u8], f64); 2] = [(b"aaaabbbb", 1.0), (b"bbbbcccc", 3.0)];
+    /// let dictionary = HuffmanDictionary::train_weighted(&samples).unwrap();
+    /// assert_eq!(dictionary.encoding_map().get(&b'b').map(Vec::len), Some(1));
+    /// ```
+    pub fn train_weighted(samples: &[(&[u8], f64)]) -> Result<HuffmanDictionary> {
+        if samples
+            .iter()
+            .any(|&(_, weight)| !weight.is_finite() || weight <= 0.0)
+        {
+            return Err(HuffmanError::OptionsError(
+                "Sample weights must be finite and positive",
+            ));
+        }
+        let total_weight: f64 = samples
+            .iter()
+            .filter(|(sample, _)| !sample.is_empty())
+            .map(|&(_, weight)| weight)
+            .sum();
+
+        let mut weights = [0f64; 256];
+        for &(sample, weight) in samples.iter().filter(|(sample, _)| !sample.is_empty()) {
+            let per_byte = weight / total_weight / sample.len() as f64;
+            for &byte in sample {
+                weights[byte as usize] += per_byte;
+            }
+        }
+        Self::from_weights(&weights)
+    }
+
+    /// Trains a dictionary on the files at `paths` like `train` would on their contents, reading and counting them on
+    /// `threads` threads. Each thread reads a chunk at a time, so memory stays bounded however large the files are.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Files representative of the payloads the dictionary will encode
+    /// * `threads` - Number of threads to read the files on, at least 1
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let dir = std::env::temp_dir().join("huff_tree_tap_train_files_doc");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let paths = [dir.join("index.html"), dir.join("about.html")];
+    /// std::fs::write(&paths[0], "GET /index.html").unwrap();
+    /// std::fs::write(&paths[1], "GET /about.html").unwrap();
+    ///
+    /// let dictionary = HuffmanDictionary::train_files(&paths, 2).unwrap();
+    /// let samples: [&[u8]; 2] = [b"GET /index.html", b"GET /about.html"];
+    /// assert_eq!(dictionary, HuffmanDictionary::train(&samples).unwrap());
+    /// ```
+    pub fn train_files<P: AsRef<Path> + Sync>(
+        paths: &[P],
+        threads: usize,
+    ) -> Result<HuffmanDictionary> {
+        if threads == 0 {
+            return Err(HuffmanError::OptionsError(
+                "Training needs at least one thread",
+            ));
+        }
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let count_files = || -> Result<FrequencyAccumulator> {
+            let mut accumulator = FrequencyAccumulator::new();
+            let mut chunk = vec![0u8; CHUNK_SIZE];
+            while !failed.load(Ordering::Relaxed) {
+                let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                    break;
+                };
+                // Stops the other threads too, as the training fails as a whole
+                if let Err(e) = count_file(path.as_ref(), &mut chunk, &mut accumulator) {
+                    failed.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+            Ok(accumulator)
+        };
+
+        let mut accumulator = FrequencyAccumulator::new();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.min(paths.len()))
+                .map(|_| scope.spawn(count_files))
+                .collect();
+            for worker in workers {
+                let counted = worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))?;
+                accumulator.merge(&counted);
+            }
+            Ok::<(), HuffmanError<'static>>(())
+        })?;
+
+        let total = accumulator.total() as f64;
+        let mut weights = [0f64; 256];
+        for (byte, count) in accumulator.frequency_map() {
+            weights[byte as usize] = count as f64 / total;
+        }
+        Self::from_weights(&weights)
+    }
+
+    /// Builds the dictionary's code from u8 weights summing to 1
+    fn from_weights(weights: &[f64; 256]) -> Result<HuffmanDictionary> {
+        // Every u8 seen keeps a weight of at least 1 so it is never folded into the escape
+        let frequency_map: FrequencyMap = weights
+            .iter()
+            .enumerate()
+            .filter(|(_, &weight)| weight > 0.0)
+            .map(|(byte, &weight)| (byte as u8, ((weight * TRAINING_SCALE) as i64).max(1)))
+            .collect();
+        let huffman_tree = huffman_tree::build_with_escape(&frequency_map, 1)?;
+
+        Ok(HuffmanDictionary {
+            encoding_map: EncodingMap::new(&huffman_tree)?,
+            packing: Packing::default(),
+        })
+    }
+
+    /// Sets how `encode` packs payloads and how `decode` expects them packed, `Packing::Dense` unless set.
+    /// Only payloads encoded before dictionaries packed densely need `Packing::Padded`
+    ///
+    /// # Arguments
+    ///
+    /// * `packing` - How payloads are packed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let samples: [&[u8]; 2] = [b"GET /index.html", b"GET /about.html"];
+    /// let dictionary = HuffmanDictionary::train(&samples).unwrap();
+    /// let padded = dictionary.clone().with_packing(Packing::Padded);
+    ///
+    /// let encoded_data = padded.encode(b"GET /contact.html").unwrap();
+    /// assert!(encoded_data.len() > dictionary.encode(b"GET /contact.html").unwrap().len());
+    /// assert_eq!(padded.decode(&encoded_data).unwrap(), b"GET /contact.html");
+    /// ```
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    pub fn encoding_map(&self) -> &EncodingMap {
+        &self.encoding_map
+    }
+
+    pub fn packing(&self) -> Packing {
+        self.packing
+    }
+
+    /// Encodes `data` into u8s packed as `packing` says with the dictionary's code
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        encode_with_map(data, &self.encoding_map, self.packing)
+    }
+
+    /// Encodes `data` like `encode` and reports how many of its u8s the dictionary's code covered and which ones
+    /// had to be escaped
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let dictionary = HuffmanDictionary::train(&[b"GET /index.html".as_slice()]).unwrap();
+    /// let (encoded_data, report) = dictionary.encode_with_coverage(b"GET /faq.html").unwrap();
+    /// assert_eq!(report.misses.get(&b'q'), Some(&1));
+    /// assert!(report.coverage() < 1.0);
+    /// assert_eq!(dictionary.decode(&encoded_data).unwrap(), b"GET /faq.html");
+    /// ```
+    pub fn encode_with_coverage(&self, data: &[u8]) -> Result<(Vec<u8>, CoverageReport)> {
+        let encoded_data = self.encode(data)?;
+        Ok((encoded_data, CoverageReport::new(data, &self.encoding_map)))
+    }
+
+    /// Decodes u8s produced by `encode` with the same dictionary
+    pub fn decode(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        decode_with_map(
+            encoded_data,
+            &self.encoding_map,
+            self.packing,
+            &DecodeOptions::default(),
+        )
+    }
+
+    /// Writes the dictionary's decode table and packing in the fixed layout `MappedDictionary` reads in place, meant
+    /// to be stored in a file and memory mapped by decoding services
+    pub fn to_mapped_bytes(&self) -> Result<Vec<u8>> {
+        mapped_dictionary::to_mapped_bytes(&self.encoding_map, self.packing)
+    }
+}
+
+/// Counts the u8s of the file at `path` a chunk at a time
+fn count_file(path: &Path, chunk: &mut [u8], accumulator: &mut FrequencyAccumulator) -> Result<()> {
+    let mut file = File::open(path)?;
+    while let Some(read) = read_chunk(&mut file, chunk)? {
+        accumulator.update(&chunk[..read]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_train() {
+        let samples: [&[u8]; 2] = [b"aaaa", b"ab"];
+
+        let test_output = HuffmanDictionary::train(&samples).unwrap();
+
+        assert_eq!(Some(1), test_output.encoding_map().get(&b'a').map(Vec::len));
+        assert_eq!(
+            b"abz".to_vec(),
+            test_output
+                .decode(&test_output.encode(b"abz").unwrap())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_train_weighted() {
+        // The short sample outweighs the long one once its weight is taken into account
+        let samples: [(&[u8], f64); 2] = [(b"aaaaaaaaaaaaaaab", 1.0), (b"bc", 4.0)];
+
+        let test_output = HuffmanDictionary::train_weighted(&samples).unwrap();
+
+        assert_eq!(Some(1), test_output.encoding_map().get(&b'b').map(Vec::len));
+        assert!(HuffmanDictionary::train_weighted(&[(b"a", 0.0)]).is_err());
+        assert!(HuffmanDictionary::train_weighted(&[(b"a", f64::NAN)]).is_err());
+    }
+
+    #[test]
+    fn test_train_files() {
+        let dir = std::env::temp_dir().join("huff_tree_tap_test_train_files");
+        std::fs::create_dir_all(&dir).unwrap();
+        let input_data: Vec<Vec<u8>> = (0..8)
+            .map(|i| Vec::from("this is a test string!").repeat(i * 5000 + 1))
+            .chain([Vec::new(), b"zzz".to_vec()])
+            .collect();
+        let paths: Vec<_> = (0..input_data.len())
+            .map(|i| dir.join(format!("sample_{}", i)))
+            .collect();
+        for (path, data) in paths.iter().zip(&input_data) {
+            std::fs::write(path, data).unwrap();
+        }
+        let samples: Vec<&[u8]> = input_data.iter().map(Vec::as_slice).collect();
+        let expected_data = HuffmanDictionary::train(&samples).unwrap();
+
+        for threads in [1, 3, 16] {
+            let test_output = HuffmanDictionary::train_files(&paths, threads).unwrap();
+
+            assert_eq!(expected_data, test_output);
+        }
+        assert!(HuffmanDictionary::train_files(&paths, 0).is_err());
+        assert!(HuffmanDictionary::train_files(&[dir.join("missing")], 2).is_err());
+    }
+}
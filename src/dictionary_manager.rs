@@ -0,0 +1,183 @@
+use crate::dictionary::HuffmanDictionary;
+use crate::error::Result;
+
+use std::collections::VecDeque;
+
+/// Watches the ratios a `HuffmanDictionary` achieves on live payloads and signals when it has drifted far enough
+/// from the data to be worth re-training.
+///
+/// The ratio of the first full window after (re)training becomes the baseline. Once the mean ratio of the most
+/// recent window falls more than `threshold` percentage points below it, `needs_retrain` returns true.
+/// With a sample buffer the manager keeps the latest payloads and can re-train from them itself.
+#[derive(Debug, Clone)]
+pub struct DictionaryManager {
+    dictionary: HuffmanDictionary,
+    window_size: usize,
+    threshold: f64,
+    ratios: VecDeque<f64>,
+    baseline: Option<f64>,
+    sample_capacity: usize,
+    samples: VecDeque<Vec<u8>>,
+}
+
+impl DictionaryManager {
+    /// Creates a manager for `dictionary`
+    ///
+    /// # Arguments
+    ///
+    /// * `dictionary` - The dictionary currently in use
+    /// * `window_size` - How many recent payloads the ratio is averaged over
+    /// * `threshold` - How many percentage points the ratio may drop below the baseline before re-training is warranted
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let dictionary = HuffmanDictionary::train(&[b"aaaaaaab".as_slice()]).unwrap();
+    /// let mut manager = DictionaryManager::new(dictionary, 2, 10.0).with_sample_buffer(2);
+    /// manager.encode(b"aaaaaaaaaaaaaaaa").unwrap();
+    /// manager.encode(b"aaaaaaaaaaaaaaaa").unwrap();
+    /// assert!(!manager.needs_retrain());
+    ///
+    /// manager.encode(b"the data changed").unwrap();
+    /// manager.encode(b"the data changed").unwrap();
+    /// assert!(manager.needs_retrain());
+    /// assert!(manager.retrain().unwrap());
+    /// ```
+    pub fn new(dictionary: HuffmanDictionary, window_size: usize, threshold: f64) -> Self {
+        DictionaryManager {
+            dictionary,
+            window_size: window_size.max(1),
+            threshold,
+            ratios: VecDeque::with_capacity(window_size),
+            baseline: None,
+            sample_capacity: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Keeps the latest `capacity` payloads passed to `encode` so `retrain` can use them
+    pub fn with_sample_buffer(mut self, capacity: usize) -> Self {
+        self.sample_capacity = capacity;
+        self.samples = VecDeque::with_capacity(capacity);
+        self
+    }
+
+    pub fn dictionary(&self) -> &HuffmanDictionary {
+        &self.dictionary
+    }
+
+    /// Encodes `data` with the current dictionary, recording the ratio achieved and keeping `data` as a sample
+    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        let encoded_data = self.dictionary.encode(data)?;
+        self.record(data.len(), encoded_data.len());
+        if self.sample_capacity > 0 {
+            if self.samples.len() == self.sample_capacity {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(data.to_vec());
+        }
+        Ok(encoded_data)
+    }
+
+    /// Records the sizes of a payload encoded outside the manager
+    ///
+    /// # Arguments
+    ///
+    /// * `data_size` - Size of the payload in u8s
+    /// * `encoded_size` - Size of the encoded payload in u8s
+    pub fn record(&mut self, data_size: usize, encoded_size: usize) {
+        if data_size == 0 {
+            return;
+        }
+        if self.ratios.len() == self.window_size {
+            self.ratios.pop_front();
+        }
+        self.ratios
+            .push_back((1.0 - encoded_size as f64 / data_size as f64) * 100.0);
+        if self.baseline.is_none() && self.ratios.len() == self.window_size {
+            self.baseline = self.recent_ratio();
+        }
+    }
+
+    /// Mean compression ratio over the recorded window, `None` until a payload has been recorded
+    pub fn recent_ratio(&self) -> Option<f64> {
+        match self.ratios.len() {
+            0 => None,
+            len => Some(self.ratios.iter().sum::<f64>() / len as f64),
+        }
+    }
+
+    /// True once a full window has a mean ratio more than `threshold` points below the baseline
+    pub fn needs_retrain(&self) -> bool {
+        match (self.baseline, self.recent_ratio()) {
+            (Some(baseline), Some(recent)) if self.ratios.len() == self.window_size => {
+                baseline - recent > self.threshold
+            }
+            _ => false,
+        }
+    }
+
+    /// Re-trains the dictionary from the kept samples and starts a fresh baseline.
+    /// Returns false, leaving the dictionary untouched, when no samples have been kept.
+    pub fn retrain(&mut self) -> Result<bool> {
+        if self.samples.is_empty() {
+            return Ok(false);
+        }
+        let samples: Vec<&[u8]> = self.samples.iter().map(Vec::as_slice).collect();
+        self.replace(HuffmanDictionary::train(&samples)?);
+        Ok(true)
+    }
+
+    /// Swaps in a dictionary trained elsewhere and starts a fresh baseline
+    pub fn replace(&mut self, dictionary: HuffmanDictionary) {
+        self.dictionary = dictionary;
+        self.ratios.clear();
+        self.baseline = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_retrain() {
+        let dictionary = HuffmanDictionary::train(&[b"ab".as_slice()]).unwrap();
+        let mut test_output = DictionaryManager::new(dictionary, 2, 5.0);
+
+        test_output.record(100, 50);
+        assert!(!test_output.needs_retrain());
+        test_output.record(100, 50);
+        assert_eq!(Some(50.0), test_output.recent_ratio());
+        test_output.record(100, 54);
+        assert!(!test_output.needs_retrain());
+        test_output.record(100, 60);
+        assert!(test_output.needs_retrain());
+        // Without kept samples there is nothing to re-train from
+        assert!(!test_output.retrain().unwrap());
+    }
+
+    #[test]
+    fn test_retrain() {
+        let dictionary = HuffmanDictionary::train(&[b"ab".as_slice()]).unwrap();
+        let mut test_output =
+            DictionaryManager::new(dictionary.clone(), 1, 5.0).with_sample_buffer(1);
+
+        test_output.encode(b"zzzzzzzz").unwrap();
+        assert!(test_output.retrain().unwrap());
+
+        assert_ne!(&dictionary, test_output.dictionary());
+        assert_eq!(None, test_output.recent_ratio());
+        assert_eq!(
+            Some(1),
+            test_output
+                .dictionary()
+                .encoding_map()
+                .get(&b'z')
+                .map(Vec::len)
+        );
+    }
+}
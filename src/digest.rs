@@ -0,0 +1,140 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::metadata::Metadata;
+
+/// Number of u8s of a `content_digest`
+pub const DIGEST_SIZE: usize = blake3::OUT_LEN;
+
+/// Returns the BLAKE3 digest of `data`, as `b3sum` computes it. Finding two inputs with the same digest is
+/// infeasible, so dedup and caching pipelines can key on it without being poisoned by crafted collisions.
+///
+/// # Arguments
+///
+/// * `data` - The content to digest
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let digest = content_digest(b"abc");
+/// assert_eq!(digest.len(), DIGEST_SIZE);
+/// assert_eq!(digest[..4], [0x64, 0x37, 0xb3, 0xac]);
+/// ```
+pub fn content_digest(data: &[u8]) -> [u8; DIGEST_SIZE] {
+    *blake3::hash(data).as_bytes()
+}
+
+impl HuffmanData {
+    /// Stores the `content_digest` of `data`, the data `encoded_data` decodes to, in the metadata
+    pub(crate) fn with_digest(mut self, data: &[u8]) -> HuffmanData {
+        let digest: String = content_digest(data)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.metadata.insert(Metadata::DIGEST, digest);
+        self
+    }
+
+    /// The `content_digest` of the data `encoded_data` decodes to, stored in the metadata under `Metadata::DIGEST`
+    /// when `HuffmanOptions::digest` asks for it, so it is kept by `to_bytes` and read back by `from_bytes`.
+    /// `None` when no digest is stored, or the one stored is not 64 hex digits
+    pub fn digest(&self) -> Option<[u8; DIGEST_SIZE]> {
+        let hex = self.metadata.get(Metadata::DIGEST)?;
+        if hex.len() != DIGEST_SIZE * 2 {
+            return None;
+        }
+        let mut digest = [0u8; DIGEST_SIZE];
+        for (byte, index) in digest.iter_mut().zip((0..hex.len()).step_by(2)) {
+            *byte = u8::from_str_radix(hex.get(index..index + 2)?, 16).ok()?;
+        }
+        Some(digest)
+    }
+
+    /// Decodes the data and checks it against the `content_digest` stored when it was encoded with
+    /// `HuffmanOptions::digest`, failing if none was stored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let options = HuffmanOptions { digest: true, ..Default::default() };
+    /// let huffman_data = HuffmanData::with_options(&data, &options).unwrap();
+    /// assert_eq!(huffman_data.digest(), Some(content_digest(&data)));
+    ///
+    /// let decoded = HuffmanData::from_bytes(&huffman_data.to_bytes().unwrap()).unwrap();
+    /// assert!(decoded.verify_digest().unwrap());
+    /// ```
+    pub fn verify_digest(&self) -> Result<bool> {
+        let digest = self
+            .digest()
+            .ok_or(HuffmanError::DecodeError("No digest is attached"))?;
+        Ok(content_digest(&self.decode()?) == digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::HuffmanOptions;
+
+    #[test]
+    fn test_content_digest() {
+        // Examples from the BLAKE3 reference implementation
+        let inputs: [(&[u8], &str); 2] = [
+            (
+                b"",
+                "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+            ),
+            (
+                b"abc",
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+        ];
+
+        for (input_data, expected_data) in inputs {
+            let test_output: String = content_digest(input_data)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            assert_eq!(expected_data, test_output);
+        }
+    }
+
+    #[test]
+    fn test_verify_digest() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let options = HuffmanOptions {
+            digest: true,
+            ..Default::default()
+        };
+        let mut test_output = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        assert!(test_output.verify_digest().unwrap());
+        test_output.metadata.insert(Metadata::DIGEST, "00".repeat(DIGEST_SIZE));
+        assert!(!test_output.verify_digest().unwrap());
+        assert!(HuffmanData::new(&input_data)
+            .unwrap()
+            .verify_digest()
+            .is_err());
+    }
+
+    #[test]
+    fn test_digest_round_trip() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let options = HuffmanOptions {
+            digest: true,
+            ..Default::default()
+        };
+        let expected_data = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        let test_output = HuffmanData::from_bytes(&expected_data.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(Some(content_digest(&input_data)), test_output.digest());
+        assert!(test_output.verify_digest().unwrap());
+    }
+}
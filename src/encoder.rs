@@ -0,0 +1,112 @@
+use crate::error::Result;
+use crate::frequency_map::FrequencyMap;
+use crate::huffman::HuffmanData;
+use crate::options::HuffmanOptions;
+
+use std::io::IoSlice;
+
+/// Push-based encoder for data that arrives in chunks, e.g. from a socket or stdin.
+/// Frequencies are counted as chunks arrive so `finish` only has to build the tree and emit codes.
+#[derive(Debug, Clone, Default)]
+pub struct HuffmanEncoder {
+    options: HuffmanOptions,
+    data: Vec<u8>,
+    frequency_map: FrequencyMap,
+}
+
+impl HuffmanEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `HuffmanEncoder` that encodes with the given `HuffmanOptions`
+    pub fn with_options(options: HuffmanOptions) -> Self {
+        HuffmanEncoder {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// Adds the next chunk of data
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next u8s of the data, in order
+    pub fn update(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            *self.frequency_map.entry(byte).or_insert(0) += 1;
+        }
+        self.data.extend_from_slice(chunk);
+    }
+
+    /// Adds the next chunks of data, as gathered from scattered buffers, without coalescing them first
+    ///
+    /// # Arguments
+    ///
+    /// * `bufs` - The next chunks of the data, in order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use std::io::IoSlice;
+    ///
+    /// let mut encoder = HuffmanEncoder::new();
+    /// encoder.update_vectored(&[IoSlice::new(b"this is a "), IoSlice::new(b"test string!")]);
+    /// let huffman_data = encoder.finish().unwrap();
+    /// assert_eq!(huffman_data.decode().unwrap(), b"this is a test string!");
+    /// ```
+    pub fn update_vectored(&mut self, bufs: &[IoSlice<'_>]) {
+        self.data.reserve(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            self.update(buf);
+        }
+    }
+
+    /// Encodes everything passed to `update` into a `HuffmanData`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut encoder = HuffmanEncoder::new();
+    /// encoder.update(b"this is a ");
+    /// encoder.update(b"test string!");
+    /// let huffman_data = encoder.finish().unwrap();
+    /// assert_eq!(huffman_data.decode().unwrap(), b"this is a test string!");
+    /// ```
+    pub fn finish(self) -> Result<HuffmanData> {
+        HuffmanData::with_frequencies(&self.data, &self.frequency_map, &self.options, &mut [])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_type::BlockType;
+
+    #[test]
+    fn test_huffman_encoder() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let options = HuffmanOptions {
+            streams: 2,
+            ..Default::default()
+        };
+        let expected_data = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        let mut encoder = HuffmanEncoder::with_options(options);
+        for chunk in input_data.chunks(5) {
+            encoder.update(chunk);
+        }
+        let test_output = encoder.finish().unwrap();
+
+        assert_eq!(expected_data.encoded_data, test_output.encoded_data);
+        assert_eq!(expected_data.encoding_map, test_output.encoding_map);
+        let empty = HuffmanEncoder::new().finish().unwrap();
+        assert_eq!(BlockType::Stored, empty.block_type);
+        assert!(empty.encoded_data.is_empty());
+    }
+}
@@ -1,12 +1,29 @@
 use crate::data::ToFromChar;
-use crate::data::{Bit, BitVector};
-use crate::huffman_tree::Node;
+use crate::data::{Bit, BitSlice, BitVector};
+use crate::error::HuffmanError;
+use crate::frequency_map::FrequencyMap;
+use crate::huffman_tree::{self, Node};
 use crate::{data::BitVec, error::Result};
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::sync::OnceLock;
 
-type Map = HashMap<u8, BitVec>;
-type InverseMap = HashMap<BitVec, u8>;
+/// Symbol number the escape code is stored and ordered under where codes are keyed by a `u16`, just past every u8
+pub(crate) const ESCAPE_SYMBOL: u16 = 256;
+/// Total frequency `from_weights` spreads across all u8s, fine enough that rare u8s keep distinct frequencies
+const WEIGHT_SCALE: f64 = (1u64 << 24) as f64;
+
+/// Builds the hashers of the maps inside `EncodingMap` and of the counts behind `FrequencyMap::build`: the std
+/// `RandomState` unless the `fast-hash` feature swaps in FNV-1a, cheaper on their short keys but with no defence against crafted collisions. Nothing the crate writes out
+/// depends on their iteration order
+#[cfg(not(feature = "fast-hash"))]
+pub(crate) type MapHasher = std::collections::hash_map::RandomState;
+#[cfg(feature = "fast-hash")]
+pub(crate) type MapHasher = fnv::FnvBuildHasher;
+
+type Map = HashMap<u8, BitVec, MapHasher>;
+type InverseMap = HashMap<BitVec, u8, MapHasher>;
 
 trait MapTrait {
     fn to_string_map(&self) -> HashMap<u8, String>;
@@ -28,41 +45,367 @@ impl InverseMapTrait for InverseMap {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct EncodingMap {
     map: Map,
-    inverse_map: InverseMap,
+    /// Built on first use so encode-only callers never pay for it
+    inverse_map: OnceLock<InverseMap>,
+    /// Built on first use of `as_string_map`
+    string_map: OnceLock<HashMap<u8, String>>,
+    escape: Option<BitVec>,
+}
+
+impl Default for EncodingMap {
+    fn default() -> Self {
+        Self::from_map(Map::default(), None)
+    }
+}
+
+/// The inverse map is derived from `map`, whether it has been built yet does not matter
+impl PartialEq for EncodingMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map && self.escape == other.escape
+    }
 }
 
 impl EncodingMap {
     pub fn new(huffman_tree: &Node) -> Result<Self> {
-        let mut map = Map::new();
-        Self::build_encoding_map(huffman_tree, &mut map, &BitVec::new());
-
-        let inverse_map = map.iter().map(|(k, v)| (v.clone(), *k)).collect();
+        let mut map = Map::default();
+        let mut escape = None;
+        // A tree with a single leaf still needs a one bit code for that leaf
+        let mut root_code = match huffman_tree.is_leaf() {
+            true => vec![Bit::from_char('0')],
+            false => BitVec::new(),
+        };
+        Self::build_encoding_map(huffman_tree, &mut map, &mut escape, &mut root_code);
 
-        Ok(Self { map, inverse_map })
+        Ok(Self::from_map(map, escape))
     }
 
     pub fn extract(&self) -> (HashMap<u8, String>, HashMap<String, u8>) {
-        (self.map.to_string_map(), self.inverse_map.to_string_map())
+        (self.map.to_string_map(), self.inverse_map().to_string_map())
     }
 
-    pub fn from(map: HashMap<u8, String>) -> Self {
+    /// Builds an `EncodingMap` from codes written as strings of 0s and 1s.
+    /// Fails on the first empty code, non-binary character or code that is a prefix of another (identical codes included),
+    /// naming the offending symbols.
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - The code of every u8 as a string of 0s and 1s
+    pub fn from(map: HashMap<u8, String>) -> Result<Self> {
+        let mut codes: Vec<(&String, u8)> = Vec::with_capacity(map.len());
+        for (&symbol, code) in &map {
+            if code.is_empty() {
+                return Err(HuffmanError::SymbolError(symbol, "Code is empty"));
+            }
+            if code.chars().any(|c| c != '0' && c != '1') {
+                return Err(HuffmanError::SymbolError(
+                    symbol,
+                    "Code holds a character other than 0 or 1",
+                ));
+            }
+            codes.push((code, symbol));
+        }
+        // Sorted codes put every code right before the codes it prefixes
+        codes.sort();
+        for pair in codes.windows(2) {
+            let ((prefix, prefix_symbol), (code, symbol)) = (pair[0], pair[1]);
+            if code.starts_with(prefix.as_str()) {
+                return Err(HuffmanError::PrefixCollision(prefix_symbol, symbol));
+            }
+        }
+
         let map: Map = map
             .iter()
             .map(|(k, v)| (*k, BitVec::from_string(v)))
             .collect();
-        let inverse_map = map.iter().map(|(k, v)| (v.clone(), *k)).collect();
-        Self { map, inverse_map }
+        Ok(Self::from_map(map, None))
+    }
+
+    /// Returns a map covering only `symbols`, with codes re-derived for that smaller alphabet.
+    /// Symbols keep their relative weight (implied by their current code lengths) and receive canonical codes,
+    /// so a field known to hold e.g. only digits gets codes no longer than it needs. The escape code is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` - The u8s the restricted map must cover, each must already have a code
+    pub fn restrict_to(&self, symbols: &[u8]) -> Result<EncodingMap> {
+        let symbols: BTreeSet<u8> = symbols.iter().copied().collect();
+        let mut lengths = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            match self.map.get(&symbol) {
+                Some(code) => lengths.push((symbol, code.len())),
+                None => {
+                    return Err(HuffmanError::SymbolError(
+                        symbol,
+                        "Symbol has no code in the encoding map",
+                    ))
+                }
+            }
+        }
+        let Some(&(_, longest)) = lengths.iter().max_by_key(|(_, length)| *length) else {
+            return Err(HuffmanError::TreeError("No symbols to build a tree from"));
+        };
+
+        // A code of length l stands for a probability of 2^-l, scale those into integer weights
+        let frequency_map: FrequencyMap = lengths
+            .iter()
+            .map(|&(symbol, length)| (symbol, 1i64 << (longest - length).min(62)))
+            .collect();
+        let restricted = EncodingMap::new(&huffman_tree::build(&frequency_map)?)?;
+        let lengths: Vec<(u8, usize)> = restricted
+            .map
+            .iter()
+            .map(|(&symbol, code)| (symbol, code.len()))
+            .collect();
+
+        Ok(Self::from_map(canonical_codes(&lengths), None))
+    }
+
+    /// Adds an escape code, failing if it is empty or collides with the prefix of a code already in the map
+    pub(crate) fn with_escape(mut self, escape: BitVec) -> Result<Self> {
+        if escape.is_empty() {
+            return Err(HuffmanError::DecodeError("Escape code is empty"));
+        }
+        if let Some((&symbol, _)) = self
+            .map
+            .iter()
+            .find(|(_, code)| code.starts_with(&escape) || escape.starts_with(code))
+        {
+            return Err(HuffmanError::SymbolError(
+                symbol,
+                "Code collides with the prefix of the escape code",
+            ));
+        }
+        self.escape = Some(escape);
+        Ok(self)
+    }
+
+    /// Builds the map of canonical codes for the given `(symbol, code length)` pairs, `ESCAPE_SYMBOL` standing for the
+    /// escape. Fails on a zero length, a symbol past `ESCAPE_SYMBOL` or lengths that do not make a prefix code.
+    pub(crate) fn from_lengths(lengths: &[(u16, usize)]) -> Result<Self> {
+        if lengths.iter().any(|&(_, length)| length == 0) {
+            return Err(HuffmanError::DecodeError("Code length is zero"));
+        }
+        if lengths.iter().any(|&(symbol, _)| symbol > ESCAPE_SYMBOL) {
+            return Err(HuffmanError::DecodeError("Invalid symbol in code lengths"));
+        }
+        if !fits_prefix_code(lengths) {
+            return Err(HuffmanError::DecodeError(
+                "Code lengths do not make a prefix code",
+            ));
+        }
+        let mut codes = canonical_codes(lengths);
+        let escape = codes.remove(&ESCAPE_SYMBOL);
+        let map: Map = codes
+            .into_iter()
+            .map(|(symbol, code)| (symbol as u8, code))
+            .collect();
+        Ok(Self::from_map(map, escape))
+    }
+
+    /// Builds the map of canonical codes for the given `(u8, code length)` pairs, straight from the lengths without
+    /// going through codes written as strings. The same lengths always give the same codes, so a decoder can be
+    /// built from a compact length table, whether written by `EncodingMap::canonical` or by another tool assigning
+    /// canonical codes the same way: shorter codes first, ties broken by the smaller u8.
+    /// Fails on a zero length, a u8 given twice or lengths too short to make a prefix code.
+    ///
+    /// # Arguments
+    ///
+    /// * `lengths` - The code length of every u8 to encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let encoding_map = EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2)]).unwrap();
+    /// assert_eq!(encoding_map.get(&b'a'), Some(&vec![0]));
+    /// assert_eq!(encoding_map.get(&b'c'), Some(&vec![1, 1]));
+    /// assert!(EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 1), (b'c', 1)]).is_err());
+    /// ```
+    pub fn from_code_lengths(lengths: &[(u8, u8)]) -> Result<EncodingMap> {
+        let mut seen = [false; 256];
+        for &(symbol, _) in lengths {
+            if std::mem::replace(&mut seen[symbol as usize], true) {
+                return Err(HuffmanError::SymbolError(
+                    symbol,
+                    "Symbol has more than one code length",
+                ));
+            }
+        }
+        let lengths: Vec<(u16, usize)> = lengths
+            .iter()
+            .map(|&(symbol, length)| (symbol as u16, length as usize))
+            .collect();
+        Self::from_lengths(&lengths)
+    }
+
+    /// Builds the canonical Huffman code of a known u8 distribution, without scanning any data. Suited to
+    /// distributions known ahead of time, such as protocol fields or sensor readings, whose table is designed once
+    /// and reused: the same weights always give the same codes. Only u8s with a weight above 0 get a code.
+    /// Fails on a weight that is negative or not finite, or when no weight is above 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - The probability of every u8, or any weights proportional to them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use std::collections::HashMap;
+    ///
+    /// let weights = HashMap::from([(b'a', 0.5), (b'b', 0.25), (b'c', 0.125), (b'd', 0.125)]);
+    /// let encoding_map = EncodingMap::from_weights(&weights).unwrap();
+    /// assert_eq!(encoding_map.get(&b'a'), Some(&vec![0]));
+    /// assert_eq!(encoding_map.get(&b'd'), Some(&vec![1, 1, 1]));
+    /// ```
+    pub fn from_weights(weights: &HashMap<u8, f64>) -> Result<EncodingMap> {
+        if weights
+            .values()
+            .any(|&weight| !weight.is_finite() || weight < 0.0)
+        {
+            return Err(HuffmanError::OptionsError(
+                "Weights must be finite and not negative",
+            ));
+        }
+        let total: f64 = weights.values().sum();
+        // A u8 with a weight above 0 keeps a frequency of at least 1, however small its share
+        let frequency_map: FrequencyMap = weights
+            .iter()
+            .filter(|(_, &weight)| weight > 0.0)
+            .map(|(&symbol, &weight)| (symbol, ((weight / total * WEIGHT_SCALE) as i64).max(1)))
+            .collect();
+        Self::from_frequencies(&frequency_map)
+    }
+
+    /// Builds the canonical Huffman code of u8 frequencies counted or estimated ahead of time, like `from_weights`
+    /// with integer weights. Only u8s with a frequency above 0 get a code. Fails on a negative frequency, or when
+    /// no frequency is above 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency_map` - The frequency of every u8
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let frequency_map = FrequencyMap::from([(b'0', 70), (b'1', 20), (b'2', 10)]);
+    /// let encoding_map = EncodingMap::from_frequencies(&frequency_map).unwrap();
+    /// assert_eq!(encoding_map.get(&b'0').map(Vec::len), Some(1));
+    /// assert!(encoding_map.is_canonical());
+    /// ```
+    pub fn from_frequencies(frequency_map: &FrequencyMap) -> Result<EncodingMap> {
+        if frequency_map.values().any(|&freq| freq < 0) {
+            return Err(HuffmanError::OptionsError(
+                "Frequencies must not be negative",
+            ));
+        }
+        let frequency_map: FrequencyMap = frequency_map
+            .iter()
+            .filter(|(_, &freq)| freq > 0)
+            .map(|(&symbol, &freq)| (symbol, freq))
+            .collect();
+        if frequency_map.is_empty() {
+            return Err(HuffmanError::OptionsError(
+                "At least one frequency must be above 0",
+            ));
+        }
+        EncodingMap::new(&huffman_tree::build(&frequency_map)?)?.canonical()
+    }
+
+    /// Returns the code length of every u8 and of the escape under `ESCAPE_SYMBOL`, ordered by symbol
+    pub(crate) fn code_lengths(&self) -> Vec<(u16, usize)> {
+        let mut lengths: Vec<(u16, usize)> = self
+            .map
+            .iter()
+            .map(|(&symbol, code)| (symbol as u16, code.len()))
+            .chain(self.escape.as_ref().map(|code| (ESCAPE_SYMBOL, code.len())))
+            .collect();
+        lengths.sort();
+        lengths
+    }
+
+    /// Returns the map with every code, escape included, replaced by the canonical code of the same length.
+    /// Canonical codes are assigned from the code lengths sorted by length then symbol, rather than read off the
+    /// shape of the tree, so they compress exactly as well, the lengths alone describe them and the same lengths
+    /// always give the same codes, whatever order the tree was built in
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let encoding_map = EncodingMap::from_csv("97,1\n98,01\n99,00").unwrap();
+    /// let canonical = encoding_map.canonical().unwrap();
+    /// assert_eq!(canonical.get(&b'a'), Some(&vec![0]));
+    /// assert_eq!(canonical.get(&b'c'), Some(&vec![1, 1]));
+    /// assert!(canonical.is_canonical());
+    /// ```
+    pub fn canonical(&self) -> Result<EncodingMap> {
+        Self::from_lengths(&self.code_lengths())
+    }
+
+    /// Whether every code is the canonical code for its length, in which case the lengths alone describe the map
+    pub fn is_canonical(&self) -> bool {
+        let lengths = self.code_lengths();
+        let canonical = canonical_codes(&lengths);
+        lengths.iter().all(|&(symbol, _)| {
+            let code = match symbol {
+                ESCAPE_SYMBOL => self.escape.as_ref(),
+                symbol => self.map.get(&(symbol as u8)),
+            };
+            code == canonical.get(&symbol)
+        })
+    }
+
+    pub(crate) fn from_map(map: Map, escape: Option<BitVec>) -> Self {
+        Self {
+            map,
+            inverse_map: OnceLock::new(),
+            string_map: OnceLock::new(),
+            escape,
+        }
     }
 
     pub fn get(&self, key: &u8) -> Option<&BitVec> {
         self.map.get(key)
     }
 
+    /// Iterates over every u8 and its code, without the escape code
+    pub fn iter(&self) -> impl Iterator<Item = (&u8, &BitVec)> {
+        self.map.iter()
+    }
+
+    /// Returns the codes as strings of 0s and 1s, built on the first call and borrowed on every later one
+    pub fn as_string_map(&self) -> &HashMap<u8, String> {
+        self.string_map.get_or_init(|| self.map.to_string_map())
+    }
+
+    /// Number of codes, counting the escape code when there is one
+    pub fn len(&self) -> usize {
+        self.map.len() + self.escape.is_some() as usize
+    }
+
+    /// Number of u8s with a code, without the escape code
+    pub fn symbol_count(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty() && self.escape.is_none()
+    }
+
     pub fn get_shortest_code(&self) -> usize {
-        if let Some(el) = self.inverse_map.keys().min_by_key(|v| v.len()) {
+        if let Some(el) = self.codes().min_by_key(|v| v.len()) {
             el.len()
         } else {
             0
@@ -70,39 +413,129 @@ impl EncodingMap {
     }
 
     pub fn get_longest_code(&self) -> usize {
-        if let Some(el) = self.inverse_map.keys().max_by_key(|v| v.len()) {
+        if let Some(el) = self.codes().max_by_key(|v| v.len()) {
             el.len()
         } else {
             0
         }
     }
 
-    pub fn get_inverse(&self, key: &BitVec) -> Option<&u8> {
-        self.inverse_map.get(key)
+    /// Returns the escape code, emitted ahead of the 8 raw bits of any u8 missing from the map
+    pub fn get_escape(&self) -> Option<&BitVec> {
+        self.escape.as_ref()
+    }
+
+    /// Every code in the map including the escape code
+    fn codes(&self) -> impl Iterator<Item = &BitVec> {
+        self.map.values().chain(self.escape.as_ref())
+    }
+
+    pub fn get_inverse(&self, key: &BitSlice) -> Option<&u8> {
+        self.inverse_map().get(key)
+    }
+
+    fn inverse_map(&self) -> &InverseMap {
+        self.inverse_map
+            .get_or_init(|| self.map.iter().map(|(k, v)| (v.clone(), *k)).collect())
+    }
+
+    /// Returns a stable 64 bit FNV-1a hash of every code in the map, used to tell maps apart without comparing them.
+    /// Maps with the same codes always share a fingerprint regardless of how they were built.
+    pub fn fingerprint(&self) -> u64 {
+        let mut codes: Vec<(u16, &BitVec)> = self
+            .map
+            .iter()
+            .map(|(&symbol, code)| (symbol as u16, code))
+            .collect();
+        // The escape code sorts after every u8
+        codes.extend(self.escape.iter().map(|code| (u8::MAX as u16 + 1, code)));
+        codes.sort();
+
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        };
+        for (symbol, code) in codes {
+            symbol.to_le_bytes().into_iter().for_each(&mut feed);
+            (code.len() as u32)
+                .to_le_bytes()
+                .into_iter()
+                .for_each(&mut feed);
+            code.iter().copied().for_each(&mut feed);
+        }
+        hash
     }
 
     /// Creates a Hash Map of the encoding of every u8 within a given Huffman Tree. Left node edges are 0s and right node edges are 1s
-    fn build_encoding_map(node: &Node, map: &mut Map, code: &BitVec) {
+    /// The leaf without a value is the escape leaf, its code is stored in `escape`.
+    /// `code` is a single stack of bits shared by the whole walk, only leaves copy it out.
+    fn build_encoding_map(
+        node: &Node,
+        map: &mut Map,
+        escape: &mut Option<BitVec>,
+        code: &mut BitVec,
+    ) {
         match node.value {
             Some(value) => {
-                map.insert(value, code.clone());
+                map.insert(value, code.to_vec());
+            }
+            None if node.is_leaf() => {
+                *escape = Some(code.to_vec());
             }
             None => {
                 if let Some(left) = &node.left {
-                    let mut code = code.clone();
                     code.push(Bit::from_char('0'));
-                    Self::build_encoding_map(left, map, &code);
+                    Self::build_encoding_map(left, map, escape, code);
+                    code.pop();
                 }
                 if let Some(right) = &node.right {
-                    let mut code = code.clone();
                     code.push(Bit::from_char('1'));
-                    Self::build_encoding_map(right, map, &code);
+                    Self::build_encoding_map(right, map, escape, code);
+                    code.pop();
                 }
             }
         }
     }
 }
 
+/// Whether codes of the given lengths fit in a prefix code, their Kraft sum of `2^-length` being at most 1.
+/// The sum is folded from the longest length up, rounding up halves, so it never leaves integers.
+fn fits_prefix_code<S>(lengths: &[(S, usize)]) -> bool {
+    let longest = lengths.iter().map(|&(_, length)| length).max().unwrap_or(0);
+    let mut counts = vec![0u64; longest + 1];
+    for &(_, length) in lengths {
+        counts[length] += 1;
+    }
+    let mut slots = 0u64;
+    for count in counts.iter().skip(1).rev() {
+        slots = slots.div_ceil(2) + count;
+    }
+    slots <= 2
+}
+
+/// Assigns canonical codes to the given `(symbol, code length)` pairs.
+/// Symbols are ordered by code length then value and each receives the previous code plus one,
+/// shifted left whenever the length grows, so the codes depend only on the lengths.
+pub(crate) fn canonical_codes<S: Copy + Ord + Hash>(
+    lengths: &[(S, usize)],
+) -> HashMap<S, BitVec, MapHasher> {
+    let mut lengths = lengths.to_vec();
+    lengths.sort_by_key(|&(symbol, length)| (length, symbol));
+
+    let mut map = HashMap::with_capacity_and_hasher(lengths.len(), MapHasher::default());
+    let mut code = BitVec::new();
+    for (symbol, length) in lengths {
+        if let Some(last_zero) = code.iter().rposition(|&bit| bit == 0) {
+            code.truncate(last_zero);
+            code.push(1);
+        }
+        code.resize(length.max(1), 0);
+        map.insert(symbol, code.clone());
+    }
+    map
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -131,7 +564,7 @@ mod tests {
         .iter()
         .map(|(k, v)| (*k, v.to_string()))
         .collect();
-        let expected_data = EncodingMap::from(expected_data);
+        let expected_data = EncodingMap::from(expected_data).unwrap();
 
         let frequency_map = FrequencyMap::build(&input_data);
         let huffman_tree = huffman_tree::build(&frequency_map).unwrap();
@@ -141,4 +574,186 @@ mod tests {
         assert_eq!(test_output.get_shortest_code(), 2);
         assert_eq!(expected_data, test_output);
     }
+
+    #[test]
+    fn test_lazy_maps() {
+        let input_map: HashMap<u8, String> = [(b'a', "0"), (b'b', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+
+        let test_output = EncodingMap::from(input_map.clone()).unwrap();
+
+        assert!(test_output.string_map.get().is_none());
+        assert_eq!(&input_map, test_output.as_string_map());
+        assert!(test_output.inverse_map.get().is_none());
+        assert_eq!(
+            Some(&b'b'),
+            test_output.get_inverse(&BitVec::from_string("1"))
+        );
+        assert!(test_output.inverse_map.get().is_some());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let input_map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10"), (b'c', "11")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let swapped_map: HashMap<u8, String> = [(b'a', "0"), (b'b', "11"), (b'c', "10")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+
+        let test_output = EncodingMap::from(input_map.clone()).unwrap().fingerprint();
+
+        assert_eq!(
+            test_output,
+            EncodingMap::from(input_map).unwrap().fingerprint()
+        );
+        assert_ne!(
+            test_output,
+            EncodingMap::from(swapped_map).unwrap().fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_canonical_codes() {
+        let input_data = [(b'a', 2), (b'b', 1), (b'c', 3), (b'd', 3)];
+        let expected_data: Map = [(b'b', "0"), (b'a', "10"), (b'c', "110"), (b'd', "111")]
+            .iter()
+            .map(|(k, v)| (*k, BitVec::from_string(v)))
+            .collect();
+
+        let test_output = canonical_codes(&input_data);
+
+        assert_eq!(expected_data, test_output);
+    }
+
+    #[test]
+    fn test_from_lengths() {
+        let input_data = [(b'a' as u16, 2), (b'b' as u16, 1), (ESCAPE_SYMBOL, 2)];
+
+        let test_output = EncodingMap::from_lengths(&input_data).unwrap();
+
+        assert_eq!(Some(&BitVec::from_string("10")), test_output.get(&b'a'));
+        assert_eq!(Some(&BitVec::from_string("11")), test_output.get_escape());
+        assert_eq!(input_data.to_vec(), test_output.code_lengths());
+        assert!(test_output.is_canonical());
+        let swapped: HashMap<u8, String> = [(b'a', "1"), (b'b', "0")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        assert!(!EncodingMap::from(swapped.clone()).unwrap().is_canonical());
+        assert!(EncodingMap::from_lengths(&[(0, 1), (1, 1), (2, 1)]).is_err());
+        assert!(EncodingMap::from_lengths(&[(0, 0)]).is_err());
+        let canonical = EncodingMap::from(swapped).unwrap().canonical().unwrap();
+        assert!(canonical.is_canonical());
+        assert_eq!(Some(&BitVec::from_string("0")), canonical.get(&b'a'));
+    }
+
+    #[test]
+    fn test_from_code_lengths() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let frequency_map = FrequencyMap::build(&input_data);
+        let huffman_tree = huffman_tree::build(&frequency_map).unwrap();
+        let expected_data = EncodingMap::new(&huffman_tree)
+            .unwrap()
+            .canonical()
+            .unwrap();
+        let lengths: Vec<(u8, u8)> = expected_data
+            .iter()
+            .map(|(&symbol, code)| (symbol, code.len() as u8))
+            .collect();
+
+        let test_output = EncodingMap::from_code_lengths(&lengths).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert!(EncodingMap::from_code_lengths(&[(b'a', 1), (b'a', 1)]).is_err());
+        assert!(EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 0)]).is_err());
+        assert!(
+            EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2), (b'd', 3)]).is_err()
+        );
+        // An incomplete code is still a prefix code
+        assert!(EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 3)]).is_ok());
+        assert!(EncodingMap::from_code_lengths(&[(b'a', 255), (b'b', 255)]).is_ok());
+    }
+
+    #[test]
+    fn test_from_weights() {
+        let input_data = HashMap::from([(b'a', 0.6), (b'b', 0.3), (b'c', 0.1), (b'd', 0.0)]);
+        let expected_data =
+            EncodingMap::from_code_lengths(&[(b'a', 1), (b'b', 2), (b'c', 2)]).unwrap();
+
+        let test_output = EncodingMap::from_weights(&input_data).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        let frequency_map = FrequencyMap::from([(b'a', 60), (b'b', 30), (b'c', 10)]);
+        assert_eq!(
+            expected_data,
+            EncodingMap::from_frequencies(&frequency_map).unwrap()
+        );
+        assert!(EncodingMap::from_weights(&HashMap::from([(b'a', f64::NAN)])).is_err());
+        assert!(EncodingMap::from_weights(&HashMap::from([(b'a', 0.0)])).is_err());
+        assert!(EncodingMap::from_frequencies(&FrequencyMap::from([(b'a', -1)])).is_err());
+    }
+
+    #[test]
+    fn test_restrict_to() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let frequency_map = FrequencyMap::build(&input_data);
+        let huffman_tree = huffman_tree::build(&frequency_map).unwrap();
+        let encoding_map = EncodingMap::new(&huffman_tree).unwrap();
+        let expected_data: HashMap<u8, String> = [(b' ', "0"), (b's', "10"), (b't', "11")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+
+        let test_output = encoding_map.restrict_to(b"t s t").unwrap();
+
+        assert_eq!(EncodingMap::from(expected_data).unwrap(), test_output);
+        assert!(encoding_map.restrict_to(b"xyz").is_err());
+        assert!(encoding_map.restrict_to(&[]).is_err());
+    }
+
+    #[test]
+    fn test_encoding_map_from_invalid() {
+        let to_map = |codes: &[(u8, &str)]| -> HashMap<u8, String> {
+            codes.iter().map(|(k, v)| (*k, v.to_string())).collect()
+        };
+
+        assert!(matches!(
+            EncodingMap::from(to_map(&[(b'a', "0"), (b'b', "")])),
+            Err(HuffmanError::SymbolError(b'b', _))
+        ));
+        assert!(matches!(
+            EncodingMap::from(to_map(&[(b'a', "0"), (b'b', "12")])),
+            Err(HuffmanError::SymbolError(b'b', _))
+        ));
+        assert!(matches!(
+            EncodingMap::from(to_map(&[(b'a', "10"), (b'b', "101"), (b'c', "0")])),
+            Err(HuffmanError::PrefixCollision(b'a', b'b'))
+        ));
+        assert!(matches!(
+            EncodingMap::from(to_map(&[(b'a', "1"), (b'b', "0"), (b'c', "0")])),
+            Err(HuffmanError::PrefixCollision(b'b', b'c'))
+        ));
+    }
+
+    #[test]
+    fn test_encoding_map_with_escape() {
+        let input_data: Vec<u8> = Vec::from("aaaabbc");
+        let mut frequency_map = FrequencyMap::build(&input_data);
+        let escape_freq = frequency_map.prune_below(2);
+
+        let huffman_tree = huffman_tree::build_with_escape(&frequency_map, escape_freq).unwrap();
+        let test_output = EncodingMap::new(&huffman_tree).unwrap();
+
+        assert_eq!(None, test_output.get(&b'c'));
+        assert_eq!(Some(&BitVec::from_string("00")), test_output.get_escape());
+        assert_eq!(Some(&BitVec::from_string("01")), test_output.get(&b'b'));
+        assert_eq!(Some(&BitVec::from_string("1")), test_output.get(&b'a'));
+        assert_eq!(3, test_output.len());
+        assert_eq!(2, test_output.symbol_count());
+    }
 }
@@ -0,0 +1,179 @@
+use crate::data::{BitVec, BitVector};
+use crate::encoding_map::{EncodingMap, ESCAPE_SYMBOL};
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The serialized forms of an `EncodingMap`, both are accepted when deserializing
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EncodingMapForm {
+    /// The code length of every u8 and of the escape, standing for the canonical codes of those lengths
+    Lengths {
+        lengths: Vec<(u8, u32)>,
+        escape: Option<u32>,
+    },
+    /// Every code written out as a string of 0s and 1s
+    Codes {
+        codes: Vec<(u8, String)>,
+        escape: Option<String>,
+    },
+}
+
+impl EncodingMapForm {
+    fn lengths(encoding_map: &EncodingMap) -> Self {
+        let mut lengths: Vec<(u8, u32)> = encoding_map
+            .iter()
+            .map(|(&symbol, code)| (symbol, code.len() as u32))
+            .collect();
+        lengths.sort();
+        EncodingMapForm::Lengths {
+            lengths,
+            escape: encoding_map.get_escape().map(|code| code.len() as u32),
+        }
+    }
+
+    fn codes(encoding_map: &EncodingMap) -> Self {
+        let mut codes: Vec<(u8, String)> = encoding_map
+            .iter()
+            .map(|(&symbol, code)| (symbol, code.to_string()))
+            .collect();
+        codes.sort();
+        EncodingMapForm::Codes {
+            codes,
+            escape: encoding_map.get_escape().map(BitVector::to_string),
+        }
+    }
+
+    fn into_encoding_map(self) -> crate::error::Result<EncodingMap> {
+        match self {
+            EncodingMapForm::Lengths { lengths, escape } => {
+                let lengths: Vec<(u16, usize)> = lengths
+                    .into_iter()
+                    .map(|(symbol, length)| (symbol as u16, length as usize))
+                    .chain(escape.map(|length| (ESCAPE_SYMBOL, length as usize)))
+                    .collect();
+                EncodingMap::from_lengths(&lengths)
+            }
+            EncodingMapForm::Codes { codes, escape } => {
+                // `from` and `with_escape` reject codes that are not a prefix code
+                let encoding_map = EncodingMap::from(codes.into_iter().collect())?;
+                match escape {
+                    Some(escape) => encoding_map.with_escape(BitVec::from_string(&escape)),
+                    None => Ok(encoding_map),
+                }
+            }
+        }
+    }
+}
+
+/// Written as code lengths when the codes are the canonical codes for their lengths, which is all it takes to
+/// rebuild them, and as full codes otherwise so any map round trips unchanged
+impl Serialize for EncodingMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.is_canonical() {
+            true => EncodingMapForm::lengths(self),
+            false => EncodingMapForm::codes(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EncodingMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        EncodingMapForm::deserialize(deserializer)?
+            .into_encoding_map()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Serializes an `EncodingMap` with every code written out, for `#[serde(with = "huff_tree_tap::full_codes")]`
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Dictionary {
+///     #[serde(with = "huff_tree_tap::full_codes")]
+///     encoding_map: EncodingMap,
+/// }
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let encoding_map = HuffmanData::new(&data).unwrap().encoding_map;
+/// let json = serde_json::to_string(&Dictionary { encoding_map: encoding_map.clone() }).unwrap();
+/// let dictionary: Dictionary = serde_json::from_str(&json).unwrap();
+/// assert_eq!(dictionary.encoding_map, encoding_map);
+/// ```
+pub mod full_codes {
+    use super::EncodingMapForm;
+    use crate::encoding_map::EncodingMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        encoding_map: &EncodingMap,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        EncodingMapForm::codes(encoding_map).serialize(serializer)
+    }
+
+    /// Accepts either form, like `EncodingMap::deserialize`
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<EncodingMap, D::Error> {
+        EncodingMap::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn encoding_map(codes: &[(u8, &str)]) -> EncodingMap {
+        let map: HashMap<u8, String> = codes.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        EncodingMap::from(map).unwrap()
+    }
+
+    #[test]
+    fn test_serialize_encoding_map() {
+        let canonical = encoding_map(&[(b'a', "0"), (b'b', "10")])
+            .with_escape(BitVec::from_string("11"))
+            .unwrap();
+        let non_canonical = encoding_map(&[(b'a', "1"), (b'b', "0")]);
+
+        let test_output = serde_json::to_string(&canonical).unwrap();
+
+        assert_eq!(
+            r#"{"lengths":{"lengths":[[97,1],[98,2]],"escape":2}}"#,
+            test_output
+        );
+        assert_eq!(
+            canonical,
+            serde_json::from_str::<EncodingMap>(&test_output).unwrap()
+        );
+
+        let test_output = serde_json::to_string(&non_canonical).unwrap();
+
+        assert_eq!(
+            r#"{"codes":{"codes":[[97,"1"],[98,"0"]],"escape":null}}"#,
+            test_output
+        );
+        assert_eq!(
+            non_canonical,
+            serde_json::from_str::<EncodingMap>(&test_output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_invalid_lengths() {
+        // Three codes of length 1 cannot form a prefix code
+        let input_data = r#"{"lengths":{"lengths":[[97,1],[98,1],[99,1]],"escape":null}}"#;
+
+        assert!(serde_json::from_str::<EncodingMap>(input_data).is_err());
+    }
+}
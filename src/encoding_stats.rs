@@ -1,5 +1,6 @@
-/// Encoding stats for a given data size and endcoded data size
-#[derive(Debug, PartialEq)]
+/// Encoding stats for a given data size and endcoded data size.
+/// The default is the stats of empty data, the starting point for `accumulate`
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct EncodingStats {
     /// Size of the data
     pub data_size: f32,
@@ -7,6 +8,12 @@ pub struct EncodingStats {
     pub encoded_size: f32,
     /// Compression ratio
     pub ratio: f32,
+    /// Exact number of encoded payload bits, excluding any padding
+    pub payload_bits: u64,
+    /// Exact size of the data in u8s, as the `f32` sizes lose precision on large data
+    pub data_len: u64,
+    /// Exact size of the encoded data in u8s
+    pub encoded_len: u64,
 }
 
 impl EncodingStats {
@@ -16,15 +23,68 @@ impl EncodingStats {
     ///
     /// * `data` - A reference to `Vec<u8>` containing the data
     /// * `encoded_data` - A reference to `Vec<u8>` containing the data encoded
-    pub fn new(data: &[u8], encoded_data: &[u8]) -> EncodingStats {
-        let data_size = (data.len() * 8) as f32;
-        let encoded_size = (encoded_data.len() * 8) as f32;
-        let ratio = (1_f32 - (encoded_size / data_size)) * 100_f32;
+    /// * `payload_bits` - The number of bits the encoded payload takes up before padding
+    pub fn new(data: &[u8], encoded_data: &[u8], payload_bits: u64) -> EncodingStats {
+        Self::from_sizes(data.len() as u64, encoded_data.len() as u64, payload_bits)
+    }
+
+    /// Returns the `EncodingStats` for data and encoded data of the given sizes in u8s, for callers that never hold either in memory
+    pub(crate) fn from_sizes(data_len: u64, encoded_len: u64, payload_bits: u64) -> EncodingStats {
+        let data_size = data_len as f32 * 8.0;
+        let encoded_size = encoded_len as f32 * 8.0;
         EncodingStats {
             data_size,
             encoded_size,
-            ratio,
+            ratio: ratio(data_size, encoded_size),
+            payload_bits,
+            data_len,
+            encoded_len,
+        }
+    }
+
+    /// Adds the sizes of `other` to these stats and recomputes the ratio, so the stats of every block or chunk an
+    /// encoder writes can be summed into overall stats as they are produced, without keeping the blocks around
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The stats of the next block
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut total = EncodingStats::default();
+    /// for block in b"this is a test string!".repeat(20).chunks(128) {
+    ///     total.accumulate(&HuffmanData::new(block).unwrap().stats);
+    /// }
+    /// assert_eq!(total.data_size, (440 * 8) as f32);
+    /// ```
+    pub fn accumulate(&mut self, other: &EncodingStats) {
+        // Summed from the exact sizes, so the `f32` sizes do not drift however many blocks are added
+        *self = Self::from_sizes(
+            self.data_len + other.data_len,
+            self.encoded_len + other.encoded_len,
+            self.payload_bits + other.payload_bits,
+        );
+    }
+
+    /// Returns the compression ratio computed from the exact payload bits rather than the padded bytes
+    pub fn payload_ratio(&self) -> f32 {
+        if self.data_size == 0_f32 {
+            return 0_f32;
         }
+        (1_f32 - (self.payload_bits as f32 / self.data_size)) * 100_f32
+    }
+}
+
+/// Compression ratio of data and encoded data of the given sizes in bits
+fn ratio(data_size: f32, encoded_size: f32) -> f32 {
+    // Empty data has nothing to compress, a 0 ratio keeps the stats comparable instead of NaN
+    match data_size == 0_f32 {
+        true => 0_f32,
+        false => (1_f32 - (encoded_size / data_size)) * 100_f32,
     }
 }
 
@@ -41,10 +101,37 @@ mod tests {
             data_size: 80_f32,
             encoded_size: 40_f32,
             ratio: 50_f32,
+            payload_bits: 34,
+            data_len: 10,
+            encoded_len: 5,
         };
 
-        let test_ouput = EncodingStats::new(&data, &encoded_data);
+        let test_ouput = EncodingStats::new(&data, &encoded_data, 34);
 
         assert_eq!(expected_data, test_ouput);
+        assert_eq!(test_ouput.payload_ratio(), 57.5);
+    }
+
+    #[test]
+    fn test_accumulate() {
+        let input_data = [
+            EncodingStats::new(&[0; 10], &[0; 5], 34),
+            EncodingStats::new(&[0; 30], &[0; 5], 36),
+        ];
+        let expected_data = EncodingStats {
+            data_size: 320_f32,
+            encoded_size: 80_f32,
+            ratio: 75_f32,
+            payload_bits: 70,
+            data_len: 40,
+            encoded_len: 10,
+        };
+
+        let mut test_output = EncodingStats::default();
+        for stats in &input_data {
+            test_output.accumulate(stats);
+        }
+
+        assert_eq!(expected_data, test_output);
     }
 }
@@ -1,11 +1,25 @@
 use core::fmt;
 
-pub type Result<T> = std::result::Result<T, HuffmanError<'static>>;
+pub type Result<T> = core::result::Result<T, HuffmanError<'static>>;
 
 #[derive(Debug)]
 pub enum HuffmanError<'a> {
     TreeError(&'a str),
     ByteStringConversionError(&'a str),
+    OptionsError(&'a str),
+    DecodeError(&'a str),
+    SymbolError(u8, &'a str),
+    PrefixCollision(u8, u8),
+    /// The size, saturating at `u64::MAX`, then the largest size allowed where it was hit
+    PayloadTooLarge(u64, u64),
+    /// The CRC-32 the data carries, then the CRC-32 of what it decoded to
+    ChecksumMismatch(u32, u32),
+    TransformError(&'a str),
+    /// The line of a text code table the error is on, counting from 1
+    TableParseError(usize, &'a str),
+    CryptoError(&'a str),
+    #[cfg(feature = "std")]
+    IoError(std::io::Error),
 }
 
 impl fmt::Display for HuffmanError<'_> {
@@ -15,6 +29,50 @@ impl fmt::Display for HuffmanError<'_> {
                 write!(f, "Binary String Conversion Error: {}", e)
             }
             HuffmanError::TreeError(e) => write!(f, "Tree Error: {}", e),
+            HuffmanError::OptionsError(e) => write!(f, "Options Error: {}", e),
+            HuffmanError::DecodeError(e) => write!(f, "Decode Error: {}", e),
+            HuffmanError::SymbolError(symbol, e) => {
+                write!(f, "Symbol Error: {:#04x}: {}", symbol, e)
+            }
+            HuffmanError::PrefixCollision(prefix, symbol) => write!(
+                f,
+                "Prefix Collision: the code of {:#04x} is a prefix of the code of {:#04x}",
+                prefix, symbol
+            ),
+            HuffmanError::PayloadTooLarge(size, limit) => write!(
+                f,
+                "Payload Too Large: {} u8s exceeds the limit of {} u8s",
+                size, limit
+            ),
+            HuffmanError::ChecksumMismatch(expected, actual) => write!(
+                f,
+                "Checksum Mismatch: expected a CRC-32 of {:#010x} but the decoded data has {:#010x}",
+                expected, actual
+            ),
+            HuffmanError::TransformError(e) => write!(f, "Transform Error: {}", e),
+            HuffmanError::TableParseError(line, e) => {
+                write!(f, "Table Parse Error: line {}: {}", line, e)
+            }
+            HuffmanError::CryptoError(e) => write!(f, "Crypto Error: {}", e),
+            #[cfg(feature = "std")]
+            HuffmanError::IoError(e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for HuffmanError<'_> {
+    fn from(e: std::io::Error) -> Self {
+        HuffmanError::IoError(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<HuffmanError<'static>> for std::io::Error {
+    fn from(e: HuffmanError<'static>) -> Self {
+        match e {
+            HuffmanError::IoError(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
         }
     }
 }
@@ -0,0 +1,85 @@
+use crate::error::{HuffmanError, Result};
+
+use core::ops::Deref;
+
+/// A fixed-capacity u8 buffer living entirely inline, used to decode without touching the heap.
+/// Pushing past `CAP` is an error rather than a reallocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixedBuffer<const CAP: usize> {
+    data: [u8; CAP],
+    len: usize,
+}
+
+impl<const CAP: usize> FixedBuffer<CAP> {
+    /// Creates an empty buffer, usable in const context
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut buffer = FixedBuffer::<4>::new();
+    /// buffer.push(b'a').unwrap();
+    /// assert_eq!(buffer.as_slice(), b"a");
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            data: [0; CAP],
+            len: 0,
+        }
+    }
+
+    /// Appends `byte`, failing once the buffer holds `CAP` u8s
+    pub fn push(&mut self, byte: u8) -> Result<()> {
+        if self.len == CAP {
+            return Err(HuffmanError::DecodeError(
+                "Decoded data exceeds the buffer capacity",
+            ));
+        }
+        self.data[self.len] = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The most u8s the buffer holds, `CAP`
+    pub const fn capacity(&self) -> usize {
+        CAP
+    }
+
+    /// The u8s pushed so far
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<const CAP: usize> Default for FixedBuffer<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAP: usize> Deref for FixedBuffer<CAP> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_buffer() {
+        let mut test_output = FixedBuffer::<2>::new();
+
+        test_output.push(b'a').unwrap();
+        test_output.push(b'b').unwrap();
+
+        assert_eq!(b"ab", test_output.as_slice());
+        assert!(test_output.push(b'c').is_err());
+        assert_eq!(2, test_output.len());
+    }
+}
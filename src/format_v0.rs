@@ -0,0 +1,187 @@
+use crate::container::{payload_size, Header};
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+use crate::metadata::Metadata;
+use crate::options::HuffmanOptions;
+use crate::packing::Packing;
+
+/// The encoding the doc examples have always produced, frozen so payloads written with it stay readable however
+/// the default encoding changes.
+///
+/// A V0 payload is a version 1 container holding a single Huffman block: its payload padded with one bit in every 8
+/// (7 data bits a u8), a single stream, every code stored in full and no checksum or metadata. Empty data is a stored
+/// block with an empty payload. `decode` and `HuffmanData::from_bytes` recognise V0 payloads by their version and
+/// always read them, `FormatV0::encode` writes them bit for bit as they were written when the format was frozen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatV0;
+
+impl FormatV0 {
+    /// The container version V0 payloads are written with
+    pub const VERSION: u8 = 1;
+
+    /// Encodes `data` as a V0 payload, readable by `decode`
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let encoded_data = FormatV0::encode(&data).unwrap();
+    /// assert!(FormatV0::detect(&encoded_data));
+    /// assert_eq!(decode(&encoded_data).unwrap(), data);
+    /// ```
+    pub fn encode(data: &[u8]) -> Result<Vec<u8>> {
+        // Fail before spending time on an encode version 1 cannot store
+        let data_len = payload_size(data.len() as u64)? as u64;
+        let options = HuffmanOptions {
+            packing: Packing::Padded,
+            ..Default::default()
+        };
+        let huffman_data = match data.is_empty() {
+            true => HuffmanData::stored(data, &options)?,
+            false => HuffmanData::with_options(data, &options)?,
+        };
+        let header = Header {
+            block_type: huffman_data.block_type,
+            data_len,
+            encoding_map: huffman_data.encoding_map,
+            stream_sizes: huffman_data.stream_sizes,
+            packing: Packing::Padded,
+            checksum: None,
+            metadata: Metadata::new(),
+        };
+        let mut bytes = Vec::with_capacity(huffman_data.encoded_data.len() + 64);
+        header.write_v0_to(&mut bytes)?;
+        bytes.extend_from_slice(&huffman_data.encoded_data);
+        Ok(bytes)
+    }
+
+    /// True when `encoded_data` starts like a V0 payload, with the container magic and version 1
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded_data` - The encoding to check
+    pub fn detect(encoded_data: &[u8]) -> bool {
+        encoded_data.len() > 4 && encoded_data[..4] == *b"HTTC" && encoded_data[4] == Self::VERSION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{decode, encode};
+    use std::collections::HashMap;
+
+    /// The first release's encoding of `My super test string`, padded u8s with no header
+    const BASELINE_PAYLOAD: &[u8] = &[182, 188, 239, 160, 190, 196, 223, 148, 209, 87];
+    /// The string keyed map `BASELINE_PAYLOAD` was written with
+    const BASELINE_MAP: [(u8, &str); 12] = [
+        (b'M', "0110"),
+        (b'g', "0111"),
+        (b' ', "111"),
+        (b'y', "1100"),
+        (b'u', "11011"),
+        (b'p', "11010"),
+        (b'e', "000"),
+        (b'n', "0101"),
+        (b't', "101"),
+        (b'r', "001"),
+        (b'i', "0100"),
+        (b's', "100"),
+    ];
+
+    /// V0 payloads of the strings used throughout the test suite, as written when the format was frozen
+    const VECTORS: [(&[u8], &[u8]); 4] = [
+        (b"", &[72, 84, 84, 67, 1, 0, 0, 0, 0, 0, 0, 0, 1]),
+        (
+            b"a",
+            &[72, 84, 84, 67, 1, 1, 1, 0, 0, 0, 1, 0, 97, 0, 1, 0, 1, 2],
+        ),
+        (
+            b"this is a test string!",
+            &[
+                72, 84, 84, 67, 1, 1, 22, 0, 0, 0, 11, 0, 32, 0, 2, 64, 33, 0, 4, 32, 97, 0, 4, 48,
+                101, 0, 4, 0, 103, 0, 4, 16, 104, 0, 5, 144, 105, 0, 3, 160, 110, 0, 5, 152, 114,
+                0, 4, 128, 115, 0, 3, 192, 116, 0, 3, 224, 1, 249, 174, 183, 147, 188, 155, 221,
+                241, 179, 137, 2,
+            ],
+        ),
+        // The baseline payload behind the map of its codes
+        (
+            b"My super test string",
+            &[
+                72, 84, 84, 67, 1, 1, 20, 0, 0, 0, 12, 0, 32, 0, 3, 224, 77, 0, 4, 96, 101, 0, 3, 0,
+                103, 0, 4, 112, 105, 0, 4, 64, 110, 0, 4, 80, 112, 0, 5, 208, 114, 0, 3, 32, 115, 0,
+                3, 128, 116, 0, 3, 160, 117, 0, 5, 216, 121, 0, 4, 192, 1, 182, 188, 239, 160, 190,
+                196, 223, 148, 209, 87,
+            ],
+        ),
+    ];
+
+    #[test]
+    fn test_decode_vectors() {
+        for (expected_data, input_data) in VECTORS {
+            let test_output = decode(input_data).unwrap();
+
+            assert!(FormatV0::detect(input_data));
+            assert_eq!(expected_data, test_output);
+        }
+    }
+
+    #[test]
+    fn test_encode_vectors() {
+        for (input_data, expected_data) in VECTORS {
+            let test_output = FormatV0::encode(input_data).unwrap();
+
+            assert_eq!(expected_data, test_output);
+        }
+        assert!(!FormatV0::detect(
+            &encode(b"this is a test string!").unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_baseline_vector() {
+        let (expected_data, input_data) = VECTORS[3];
+        let expected_map: HashMap<u8, String> = BASELINE_MAP
+            .iter()
+            .map(|(byte, code)| (*byte, code.to_string()))
+            .collect();
+
+        let test_output = HuffmanData::from_bytes(input_data).unwrap();
+
+        assert!(input_data.ends_with(BASELINE_PAYLOAD));
+        assert_eq!(&expected_map, test_output.encoding_map.as_string_map());
+        assert_eq!(expected_data, test_output.decode().unwrap());
+    }
+
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn test_legacy_baseline() {
+        use crate::encoding_stats::EncodingStats;
+        use crate::legacy;
+
+        let expected_data = b"My super test string";
+        let input_data = legacy::HuffmanData {
+            encoded_data: BASELINE_PAYLOAD.to_vec(),
+            encoding_map: BASELINE_MAP
+                .iter()
+                .map(|(byte, code)| (*byte, code.to_string()))
+                .collect(),
+            stats: EncodingStats::new(expected_data, BASELINE_PAYLOAD, 69),
+        };
+
+        let test_output = input_data.decode().unwrap();
+        let reencoded = legacy::HuffmanData::new(&test_output).unwrap();
+
+        assert_eq!(expected_data.to_vec(), test_output);
+        assert_eq!(BASELINE_PAYLOAD, reencoded.encoded_data);
+        assert_eq!(input_data.encoding_map, reencoded.encoding_map);
+    }
+}
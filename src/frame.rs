@@ -0,0 +1,253 @@
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::{decode_with_map, encode_with_map};
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+
+use std::io::{ErrorKind, Read, Write};
+
+/// Flag set when a frame carries the fingerprint of the map it was encoded with
+const FINGERPRINT_FLAG: u8 = 0b1;
+/// Flag set when the payload is packed as `Packing::Dense`, clear when it is padded
+const DENSE_FLAG: u8 = 0b10;
+
+/// Default upper bound on the size of a frame a `FrameReader` accepts
+pub const DEFAULT_MAX_FRAME_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Writes length prefixed frames, each holding one message Huffman encoded with a shared `EncodingMap`.
+///
+/// A frame is laid out as:
+/// * `u32` little endian length of the rest of the frame
+/// * `u8` flags, bit 0 set when a fingerprint follows and bit 1 set when the payload is packed densely
+/// * `u64` little endian `EncodingMap::fingerprint()` when flagged
+/// * the Huffman payload, packed as the flags say
+pub struct FrameWriter<W: Write> {
+    writer: W,
+    encoding_map: EncodingMap,
+    fingerprint: bool,
+    packing: Packing,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Creates a `FrameWriter` encoding every message with `encoding_map`
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where frames are written
+    /// * `encoding_map` - The map shared with the reading side, every message byte needs a code unless it has an escape code
+    /// * `fingerprint` - Whether each frame carries the fingerprint of `encoding_map`
+    pub fn new(writer: W, encoding_map: EncodingMap, fingerprint: bool) -> Self {
+        FrameWriter {
+            writer,
+            encoding_map,
+            fingerprint,
+            packing: Packing::default(),
+        }
+    }
+
+    /// Sets how payloads are packed, `Packing::Dense` unless set. The packing is flagged in every frame, so
+    /// `FrameReader` reads either
+    pub fn with_packing(mut self, packing: Packing) -> Self {
+        self.packing = packing;
+        self
+    }
+
+    /// Encodes `message` and writes it as a single frame
+    pub fn write_frame(&mut self, message: &[u8]) -> Result<()> {
+        let payload = encode_with_map(message, &self.encoding_map, self.packing)?;
+
+        let mut header = Vec::with_capacity(13);
+        let (mut flags, fingerprint_size) = match self.fingerprint {
+            true => (FINGERPRINT_FLAG, 8),
+            false => (0, 0),
+        };
+        if self.packing == Packing::Dense {
+            flags |= DENSE_FLAG;
+        }
+        let frame_size = 1 + fingerprint_size + payload.len();
+        let frame_size = u32::try_from(frame_size)
+            .map_err(|_| HuffmanError::OptionsError("Message is too large for a single frame"))?;
+        header.extend_from_slice(&frame_size.to_le_bytes());
+        header.push(flags);
+        if self.fingerprint {
+            header.extend_from_slice(&self.encoding_map.fingerprint().to_le_bytes());
+        }
+
+        self.writer.write_all(&header)?;
+        self.writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads frames written by `FrameWriter`, decoding each back into its message
+pub struct FrameReader<R: Read> {
+    reader: R,
+    encoding_maps: Vec<EncodingMap>,
+    max_frame_size: u32,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// Creates a `FrameReader` decoding frames with `encoding_map`
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where frames are read from
+    /// * `encoding_map` - The map used for frames without a fingerprint
+    pub fn new(reader: R, encoding_map: EncodingMap) -> Self {
+        FrameReader {
+            reader,
+            encoding_maps: vec![encoding_map],
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Registers another map, selected for frames carrying its fingerprint
+    pub fn with_encoding_map(mut self, encoding_map: EncodingMap) -> Self {
+        self.encoding_maps.push(encoding_map);
+        self
+    }
+
+    /// Sets the largest frame accepted, larger frames fail instead of being buffered
+    pub fn with_max_frame_size(mut self, max_frame_size: u32) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Reads and decodes the next frame, returning `None` once the reader is exhausted between frames
+    pub fn read_frame(&mut self) -> Result<Option<Vec<u8>>> {
+        let Some(frame_size) = read_length_prefix(&mut self.reader)? else {
+            return Ok(None);
+        };
+        if frame_size == 0 || frame_size > self.max_frame_size {
+            return Err(HuffmanError::DecodeError("Invalid frame size"));
+        }
+
+        let mut frame = vec![0u8; frame_size as usize];
+        self.reader.read_exact(&mut frame)?;
+        let (flags, mut payload) = (frame[0], &frame[1..]);
+
+        let encoding_map = if flags & FINGERPRINT_FLAG != 0 {
+            let Some((fingerprint, rest)) = payload.split_first_chunk::<8>() else {
+                return Err(HuffmanError::DecodeError("Truncated frame fingerprint"));
+            };
+            payload = rest;
+            let fingerprint = u64::from_le_bytes(*fingerprint);
+            self.encoding_maps
+                .iter()
+                .find(|encoding_map| encoding_map.fingerprint() == fingerprint)
+                .ok_or(HuffmanError::DecodeError(
+                    "No encoding map matches the frame fingerprint",
+                ))?
+        } else {
+            &self.encoding_maps[0]
+        };
+
+        let packing = match flags & DENSE_FLAG {
+            0 => Packing::Padded,
+            _ => Packing::Dense,
+        };
+        let options = DecodeOptions {
+            strict: true,
+            ..Default::default()
+        };
+        decode_with_map(payload, encoding_map, packing, &options).map(Some)
+    }
+}
+
+/// Reads a `u32` little endian length prefix, returning `None` when the reader ends right before it. A reader
+/// ending partway through the prefix is truncated rather than exhausted
+pub(crate) fn read_length_prefix(reader: &mut impl Read) -> Result<Option<u32>> {
+    let mut prefix = [0u8; 4];
+    let mut read = 0;
+    while read < prefix.len() {
+        match reader.read(&mut prefix[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(HuffmanError::DecodeError("Length prefix is truncated")),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(Some(u32::from_le_bytes(prefix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn encoding_map(codes: &[(u8, &str)]) -> EncodingMap {
+        let map: HashMap<u8, String> = codes.iter().map(|(k, v)| (*k, v.to_string())).collect();
+        EncodingMap::from(map).unwrap()
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let input_map = encoding_map(&[(b'a', "0"), (b'b', "10"), (b'c', "11")]);
+        let input_data: [&[u8]; 3] = [b"abc", b"", b"cab"];
+
+        for packing in [Packing::Dense, Packing::Padded] {
+            let mut frame_writer =
+                FrameWriter::new(Vec::new(), input_map.clone(), false).with_packing(packing);
+            for message in input_data {
+                frame_writer.write_frame(message).unwrap();
+            }
+            let frames = frame_writer.into_inner().unwrap();
+
+            let mut frame_reader = FrameReader::new(frames.as_slice(), input_map.clone());
+            for message in input_data {
+                assert_eq!(message, frame_reader.read_frame().unwrap().unwrap());
+            }
+            assert!(frame_reader.read_frame().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_frame_fingerprint() {
+        let first_map = encoding_map(&[(b'a', "0"), (b'b', "1")]);
+        let second_map = encoding_map(&[(b'a', "1"), (b'b', "0")]);
+
+        let mut frame_writer = FrameWriter::new(Vec::new(), second_map.clone(), true);
+        frame_writer.write_frame(b"aab").unwrap();
+        let frames = frame_writer.into_inner().unwrap();
+
+        let mut frame_reader = FrameReader::new(frames.as_slice(), first_map.clone());
+        assert!(frame_reader.read_frame().is_err());
+
+        let mut frame_reader =
+            FrameReader::new(frames.as_slice(), first_map).with_encoding_map(second_map);
+        assert_eq!(b"aab".to_vec(), frame_reader.read_frame().unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_frame_missing_symbol() {
+        let input_map = encoding_map(&[(b'a', "0"), (b'b', "1")]);
+        let mut frame_writer = FrameWriter::new(Vec::new(), input_map, false);
+
+        assert!(frame_writer.write_frame(b"abc").is_err());
+    }
+
+    #[test]
+    fn test_frame_truncated_length() {
+        let input_map = encoding_map(&[(b'a', "0"), (b'b', "1")]);
+        let mut frame_writer = FrameWriter::new(Vec::new(), input_map.clone(), false);
+        frame_writer.write_frame(b"ab").unwrap();
+        let frames = frame_writer.into_inner().unwrap();
+
+        for cut in 1..4 {
+            let truncated = [frames.as_slice(), &frames[..cut]].concat();
+            let mut frame_reader = FrameReader::new(truncated.as_slice(), input_map.clone());
+            assert_eq!(b"ab".to_vec(), frame_reader.read_frame().unwrap().unwrap());
+            assert!(matches!(
+                frame_reader.read_frame(),
+                Err(HuffmanError::DecodeError("Length prefix is truncated"))
+            ));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::crc32::crc32;
+use crate::error::{HuffmanError, Result};
+use crate::frequency_map::FrequencyMap;
+
+/// Identifies a checkpoint written by `FrequencyAccumulator::checkpoint`
+const MAGIC: [u8; 4] = *b"HTFA";
+/// Version of the checkpoint layout, bumped whenever it changes
+const VERSION: u8 = 1;
+/// Magic, version, 256 `u64` counts and the trailing CRC-32
+const CHECKPOINT_LEN: usize = 4 + 1 + 256 * 8 + 4;
+
+/// Counts u8 frequencies over a corpus fed a chunk at a time, for training jobs over more data than fits in memory.
+/// The partial counts can be checkpointed and restored, so a job that restarts carries on where it left off rather
+/// than recounting from scratch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrequencyAccumulator {
+    counts: [u64; 256],
+}
+
+impl Default for FrequencyAccumulator {
+    fn default() -> Self {
+        FrequencyAccumulator { counts: [0; 256] }
+    }
+}
+
+impl FrequencyAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the u8s of the next chunk of the corpus
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next u8s of the corpus
+    pub fn update(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            self.counts[byte as usize] += 1;
+        }
+    }
+
+    /// Adds the counts of `other`, for corpora counted in parts such as one part per thread
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Counts over another part of the corpus
+    pub fn merge(&mut self, other: &FrequencyAccumulator) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+    }
+
+    /// Number of u8s counted so far
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the counts so far, holding only the u8s seen. Counts past `i64::MAX` are capped at it.
+    pub fn frequency_map(&self) -> FrequencyMap {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(byte, &count)| (byte as u8, count.min(i64::MAX as u64) as i64))
+            .collect()
+    }
+
+    /// Serializes the counts so far, to be written somewhere that survives a restart and passed to `restore`.
+    ///
+    /// Laid out as the magic `HTFA`, a `u8` version, the `u64` little endian count of every u8 in order and a
+    /// little endian CRC-32 of everything before it, so a checkpoint torn by a crash mid-write is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut accumulator = FrequencyAccumulator::new();
+    /// accumulator.update(b"this is a ");
+    /// let checkpoint = accumulator.checkpoint();
+    ///
+    /// // After a restart
+    /// let mut accumulator = FrequencyAccumulator::restore(&checkpoint).unwrap();
+    /// accumulator.update(b"test string!");
+    /// assert_eq!(accumulator.frequency_map(), FrequencyMap::build(b"this is a test string!"));
+    /// ```
+    pub fn checkpoint(&self) -> Vec<u8> {
+        let mut checkpoint = Vec::with_capacity(CHECKPOINT_LEN);
+        checkpoint.extend_from_slice(&MAGIC);
+        checkpoint.push(VERSION);
+        for count in self.counts {
+            checkpoint.extend_from_slice(&count.to_le_bytes());
+        }
+        let checksum = crc32(&checkpoint);
+        checkpoint.extend_from_slice(&checksum.to_le_bytes());
+        checkpoint
+    }
+
+    /// Rebuilds the accumulator a checkpoint was taken of
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - The bytes returned by `checkpoint`
+    pub fn restore(checkpoint: &[u8]) -> Result<FrequencyAccumulator> {
+        if checkpoint.len() < MAGIC.len() + 1 || checkpoint[..MAGIC.len()] != MAGIC {
+            return Err(HuffmanError::DecodeError("Not a frequency checkpoint"));
+        }
+        if checkpoint[MAGIC.len()] != VERSION {
+            return Err(HuffmanError::DecodeError(
+                "Unsupported frequency checkpoint version",
+            ));
+        }
+        if checkpoint.len() != CHECKPOINT_LEN {
+            return Err(HuffmanError::DecodeError(
+                "Frequency checkpoint has the wrong size",
+            ));
+        }
+        let (body, checksum) = checkpoint.split_at(CHECKPOINT_LEN - 4);
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap_or_default()) {
+            return Err(HuffmanError::DecodeError(
+                "Frequency checkpoint is corrupted",
+            ));
+        }
+
+        let mut accumulator = FrequencyAccumulator::new();
+        for (count, bytes) in accumulator
+            .counts
+            .iter_mut()
+            .zip(body[MAGIC.len() + 1..].chunks_exact(8))
+        {
+            *count = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+        }
+        Ok(accumulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frequency_map::FrequencyMapping;
+
+    #[test]
+    fn test_frequency_accumulator() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let expected_data = FrequencyMap::build(&input_data);
+
+        let mut accumulator = FrequencyAccumulator::new();
+        for chunk in input_data.chunks(5) {
+            accumulator.update(chunk);
+        }
+
+        assert_eq!(expected_data, accumulator.frequency_map());
+        assert_eq!(input_data.len() as u64, accumulator.total());
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let mut input_data = FrequencyAccumulator::new();
+        input_data.update(b"this is a test string!");
+
+        let test_output = input_data.checkpoint();
+
+        assert_eq!(CHECKPOINT_LEN, test_output.len());
+        assert_eq!(
+            input_data,
+            FrequencyAccumulator::restore(&test_output).unwrap()
+        );
+
+        let mut corrupted = test_output.clone();
+        corrupted[100] ^= 1;
+        let mut other_version = test_output.clone();
+        other_version[4] = VERSION + 1;
+        for checkpoint in [
+            &corrupted[..],
+            &other_version,
+            &test_output[..test_output.len() - 1],
+            b"HTTC",
+            &[],
+        ] {
+            assert!(FrequencyAccumulator::restore(checkpoint).is_err());
+        }
+    }
+}
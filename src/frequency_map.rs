@@ -1,19 +1,74 @@
+use crate::encoding_map::MapHasher;
+
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
 
-pub type FrequencyMap = HashMap<u8, i64>;
+/// Count of every u8 of some data. Hashed with the std hasher whatever features are on, the crate only counts with
+/// the `MapHasher` of the maps inside `EncodingMap` internally
+pub type FrequencyMap<S = RandomState> = HashMap<u8, i64, S>;
 
 pub trait FrequencyMapping {
     fn build(data: &[u8]) -> Self;
+    /// Removes every byte seen fewer than `min_count` times and returns their combined count,
+    /// which is the weight the escape symbol takes on when building a tree
+    fn prune_below(&mut self, min_count: i64) -> i64;
+    /// Average bits per byte needed to code the distribution of `self` with codes fitted to `other`.
+    /// Infinite when `self` holds a byte `other` never saw, 0 when `self` is empty.
+    fn cross_entropy(&self, other: &Self) -> f64;
+    /// Bits per byte wasted coding `self` with codes fitted to `other` rather than to itself,
+    /// i.e. `self.cross_entropy(other) - self.cross_entropy(self)`
+    fn kl_divergence(&self, other: &Self) -> f64;
 }
 
 impl FrequencyMapping for FrequencyMap {
     fn build(data: &[u8]) -> Self {
-        let mut frequency_map: FrequencyMap = FrequencyMap::new();
-        for &byte in data {
-            *frequency_map.entry(byte).or_insert(0) += 1;
+        count(data).into_iter().collect()
+    }
+
+    fn prune_below(&mut self, min_count: i64) -> i64 {
+        let mut escape_freq = 0;
+        self.retain(|_, &mut freq| {
+            if freq < min_count {
+                escape_freq += freq;
+                false
+            } else {
+                true
+            }
+        });
+        escape_freq
+    }
+
+    fn cross_entropy(&self, other: &Self) -> f64 {
+        let total: i64 = self.values().sum();
+        let other_total: i64 = other.values().sum();
+        if total == 0 {
+            return 0.0;
         }
-        frequency_map
+        self.iter()
+            .filter(|(_, &freq)| freq > 0)
+            .map(|(byte, &freq)| {
+                let p = freq as f64 / total as f64;
+                let q = other
+                    .get(byte)
+                    .map_or(0.0, |&other_freq| other_freq as f64 / other_total as f64);
+                -p * q.log2()
+            })
+            .sum()
+    }
+
+    fn kl_divergence(&self, other: &Self) -> f64 {
+        self.cross_entropy(other) - self.cross_entropy(self)
+    }
+}
+
+/// Counts every u8 of `data` into a `FrequencyMap` hashed with `MapHasher`, the hot loop behind
+/// `FrequencyMapping::build`
+pub(crate) fn count(data: &[u8]) -> FrequencyMap<MapHasher> {
+    let mut frequency_map = FrequencyMap::with_hasher(MapHasher::default());
+    for &byte in data {
+        *frequency_map.entry(byte).or_insert(0) += 1;
     }
+    frequency_map
 }
 
 #[cfg(test)]
@@ -24,7 +79,7 @@ mod tests {
     #[test]
     fn test_build_frequency_map() {
         let input_data: Vec<u8> = Vec::from("this is a test string!");
-        let expected_data: FrequencyMap = vec![
+        let expected_data: FrequencyMap = [
             (116, 4),
             (103, 1),
             (104, 1),
@@ -43,5 +98,40 @@ mod tests {
         let test_ouput = FrequencyMap::build(&input_data);
 
         assert_eq!(expected_data, test_ouput);
+        // The public map keeps the std hasher whatever features are on, only the internal counts swap it
+        let _: HashMap<u8, i64> = FrequencyMap::build(&input_data);
+        assert_eq!(
+            expected_data,
+            count(&input_data).into_iter().collect::<FrequencyMap>()
+        );
+    }
+
+    #[test]
+    fn test_prune_below() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let mut test_output = FrequencyMap::build(&input_data);
+        let expected_data: FrequencyMap = [(116, 4), (32, 4), (105, 3), (115, 4)]
+            .iter()
+            .cloned()
+            .collect();
+
+        let escape_freq = test_output.prune_below(2);
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(7, escape_freq);
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let input_data = FrequencyMap::build(b"aabb");
+        let skewed = FrequencyMap::build(b"abbb");
+
+        assert_eq!(1.0, input_data.cross_entropy(&input_data));
+        assert_eq!(0.0, input_data.kl_divergence(&input_data));
+        assert!(input_data.kl_divergence(&skewed) > 0.0);
+        assert!(skewed
+            .cross_entropy(&FrequencyMap::build(b"b"))
+            .is_infinite());
+        assert_eq!(0.0, FrequencyMap::new().cross_entropy(&skewed));
     }
 }
@@ -0,0 +1,161 @@
+//! File to file helpers around `encode_to_writer` and `decode_from_buf_read`
+
+use crate::block_type::BlockType;
+use crate::container::Header;
+use crate::encoding_stats::EncodingStats;
+use crate::error::Result;
+use crate::stream::{decode_from_buf_read, encode_to_writer};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Huffman encodes the file at `input` into a self-contained encoding written to `output`, creating or truncating it.
+/// Neither file is held in memory, see `encode_to_writer`.
+///
+/// # Arguments
+///
+/// * `input` - Path of the file to encode
+/// * `output` - Path the encoding is written to
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let dir = std::env::temp_dir();
+/// let input = dir.join(format!("huff-tree-tap-doc-compress-{}", std::process::id()));
+/// let output = input.with_extension("huff");
+/// std::fs::write(&input, "this is a test string!").unwrap();
+///
+/// let stats = fs::compress_file(&input, &output).unwrap();
+/// assert_eq!(stats.data_size, 22.0 * 8.0);
+/// # std::fs::remove_file(&input).unwrap();
+/// # std::fs::remove_file(&output).unwrap();
+/// ```
+pub fn compress_file(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<EncodingStats> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let stats = encode_to_writer(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(stats)
+}
+
+/// Decodes the self-contained encoding in the file at `input` into `output`, creating or truncating it.
+/// Returns the same `EncodingStats` `compress_file` returned for the original data.
+///
+/// # Arguments
+///
+/// * `input` - Path of the encoding, as written by `compress_file`, `encode_to_writer` or `encode`
+/// * `output` - Path the decoded data is written to
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let dir = std::env::temp_dir();
+/// let input = dir.join(format!("huff-tree-tap-doc-decompress-{}", std::process::id()));
+/// let encoded = input.with_extension("huff");
+/// let decoded = input.with_extension("out");
+/// std::fs::write(&input, "this is a test string!").unwrap();
+///
+/// let stats = fs::compress_file(&input, &encoded).unwrap();
+/// assert_eq!(fs::decompress_file(&encoded, &decoded).unwrap(), stats);
+/// assert_eq!(std::fs::read(&decoded).unwrap(), std::fs::read(&input).unwrap());
+/// # std::fs::remove_file(&input).unwrap();
+/// # std::fs::remove_file(&encoded).unwrap();
+/// # std::fs::remove_file(&decoded).unwrap();
+/// ```
+pub fn decompress_file(input: impl AsRef<Path>, output: impl AsRef<Path>) -> Result<EncodingStats> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let encoded_len = reader.get_ref().metadata()?.len();
+    let payload_bits = payload_bits(&mut reader, encoded_len)?;
+
+    reader.seek(SeekFrom::Start(0))?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    let decoded_len = decode_from_buf_read(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(EncodingStats::from_sizes(
+        decoded_len,
+        encoded_len,
+        payload_bits,
+    ))
+}
+
+/// Counts the payload bits of the encoding `reader` holds by reading only its header and the last u8 of every stream
+fn payload_bits(reader: &mut (impl Read + Seek), encoded_len: u64) -> Result<u64> {
+    let header = Header::read_from(reader)?;
+    let mut stream_start = reader.stream_position()?;
+    if header.block_type != BlockType::Huffman {
+        return Ok(encoded_len.saturating_sub(stream_start) * 8);
+    }
+    let mut stream_sizes: Vec<u64> = header
+        .stream_sizes
+        .iter()
+        .map(|&size| size as u64)
+        .collect();
+    stream_sizes.push(encoded_len.saturating_sub(stream_start + stream_sizes.iter().sum::<u64>()));
+
+    let mut payload_bits = 0;
+    for size in stream_sizes {
+        if size > 0 {
+            let mut last = [0u8];
+            reader.seek(SeekFrom::Start(stream_start + size - 1))?;
+            reader.read_exact(&mut last)?;
+            let last_bits = header.packing.last_bits(last[0]).1 as u64;
+            payload_bits += (size - 1) * header.packing.bits_per_u8() as u64 + last_bits;
+        }
+        stream_start += size;
+    }
+    Ok(payload_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::HuffmanData;
+    use crate::options::HuffmanOptions;
+
+    #[test]
+    fn test_file_round_trip() {
+        let dir = std::env::temp_dir().join(format!("huff-tree-tap-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input_data: Vec<u8> =
+            Vec::from("this is a test string! this is a longer test string!!");
+        std::fs::write(dir.join("input"), &input_data).unwrap();
+
+        let expected_data = compress_file(dir.join("input"), dir.join("encoded")).unwrap();
+        let test_output = decompress_file(dir.join("encoded"), dir.join("decoded")).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(input_data, std::fs::read(dir.join("decoded")).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_payload_bits_streams() {
+        let input_data: Vec<u8> =
+            Vec::from("this is a test string! this is a longer test string!!");
+        let huffman_data = HuffmanData::with_options(
+            &input_data,
+            &HuffmanOptions {
+                streams: 3,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let expected_data = huffman_data.stats.payload_bits;
+        let encoded_data = huffman_data.to_bytes().unwrap();
+
+        let test_output = payload_bits(
+            &mut std::io::Cursor::new(&encoded_data),
+            encoded_data.len() as u64,
+        )
+        .unwrap();
+
+        assert_eq!(expected_data, test_output);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::block_type::BlockType;
+use crate::encoding_map::EncodingMap;
+use crate::frequency_map::FrequencyMap;
+use crate::huffman::HuffmanData;
+
+/// Callbacks run at each stage of `HuffmanData::with_hooks`, to observe the encoder or override the block type it
+/// picks without forking the pipeline.
+///
+/// Every callback returns `None` to let encoding carry on, or the `BlockType` the data has to be encoded with.
+/// Forcing `Stored` or `Rle` skips the stages still left. Without a forced block type the data is Huffman encoded,
+/// so forcing `Huffman` only keeps later hooks from forcing another one. When several hooks are registered every one of them sees every stage that runs and
+/// the first block type forced wins.
+pub trait EncodeHook {
+    /// Called with the u8 frequencies of the data, before the tree is built
+    fn on_frequency_map(&mut self, _frequency_map: &FrequencyMap) -> Option<BlockType> {
+        None
+    }
+
+    /// Called with the codes of the built tree, before any data is encoded
+    fn on_tree_built(&mut self, _encoding_map: &EncodingMap) -> Option<BlockType> {
+        None
+    }
+
+    /// Called with the Huffman encoded block, before it is returned
+    fn on_block_encoded(&mut self, _huffman_data: &HuffmanData) -> Option<BlockType> {
+        None
+    }
+}
+
+/// Runs `stage` on every hook, returning the block type forced by the first hook that forces one
+pub(crate) fn run_hooks(
+    hooks: &mut [&mut dyn EncodeHook],
+    mut stage: impl FnMut(&mut dyn EncodeHook) -> Option<BlockType>,
+) -> Option<BlockType> {
+    let mut forced = None;
+    for hook in hooks.iter_mut() {
+        let block_type = stage(&mut **hook);
+        forced = forced.or(block_type);
+    }
+    forced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::HuffmanOptions;
+
+    /// Records the stages it sees and forces `force` at `force_stage`
+    #[derive(Default)]
+    struct RecordingHook {
+        stages: Vec<&'static str>,
+        force: Option<(&'static str, BlockType)>,
+    }
+
+    impl RecordingHook {
+        fn stage(&mut self, stage: &'static str) -> Option<BlockType> {
+            self.stages.push(stage);
+            self.force
+                .filter(|&(force_stage, _)| force_stage == stage)
+                .map(|(_, block_type)| block_type)
+        }
+    }
+
+    impl EncodeHook for RecordingHook {
+        fn on_frequency_map(&mut self, _frequency_map: &FrequencyMap) -> Option<BlockType> {
+            self.stage("frequency_map")
+        }
+
+        fn on_tree_built(&mut self, _encoding_map: &EncodingMap) -> Option<BlockType> {
+            self.stage("tree_built")
+        }
+
+        fn on_block_encoded(&mut self, _huffman_data: &HuffmanData) -> Option<BlockType> {
+            self.stage("block_encoded")
+        }
+    }
+
+    #[test]
+    fn test_encode_hooks() {
+        // Every u8 once, Huffman codes cannot beat storing it
+        let input_data: Vec<u8> = (0..=u8::MAX).collect();
+        let options = HuffmanOptions::default();
+
+        let mut observer = RecordingHook::default();
+        let mut forcing = RecordingHook {
+            force: Some(("tree_built", BlockType::Huffman)),
+            ..Default::default()
+        };
+        let mut ignored = RecordingHook {
+            force: Some(("block_encoded", BlockType::Rle)),
+            ..Default::default()
+        };
+        let test_output = HuffmanData::with_hooks(
+            &input_data,
+            &options,
+            &mut [&mut observer, &mut forcing, &mut ignored],
+        )
+        .unwrap();
+
+        assert_eq!(BlockType::Huffman, test_output.block_type);
+        assert_eq!(input_data, test_output.decode().unwrap());
+        assert_eq!(
+            vec!["frequency_map", "tree_built", "block_encoded"],
+            observer.stages
+        );
+        assert_eq!(observer.stages, ignored.stages);
+
+        let mut store = RecordingHook {
+            force: Some(("frequency_map", BlockType::Stored)),
+            ..Default::default()
+        };
+        let test_output =
+            HuffmanData::with_hooks(b"aaaaaaab", &options, &mut [&mut store]).unwrap();
+
+        assert_eq!(BlockType::Stored, test_output.block_type);
+        assert_eq!(vec!["frequency_map"], store.stages);
+    }
+}
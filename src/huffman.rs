@@ -1,21 +1,42 @@
-use crate::data::{BitVec, Padded, PaddedBits, UnPadded, UnPaddedBits};
+use crate::block_type::BlockType;
+use crate::crc32::crc32;
+use crate::data::{BitVec, UnPaddedBits};
 use crate::encoding_map::EncodingMap;
 use crate::encoding_stats::EncodingStats;
-use crate::error::Result;
+use crate::error::{HuffmanError, Result};
 use crate::frequency_map::{FrequencyMap, FrequencyMapping};
-use crate::huffman_tree::{self, Node};
-
-use std::collections::HashMap;
+use crate::hooks::{run_hooks, EncodeHook};
+use crate::huffman_tree;
+use crate::metadata::Metadata;
+use crate::options::{DecodeOptions, HuffmanOptions};
+use crate::packing::Packing;
+use crate::rle;
+#[cfg(feature = "simd")]
+use crate::word_pack;
 
 /// Huffman encoded data
 #[derive(Debug)]
 pub struct HuffmanData {
     /// The encoded data as a `Vec<u8>`
     pub encoded_data: Vec<u8>,
-    /// Encoding map stored as a `EncodingMap` required for decoding the data
-    pub encoding_map: HashMap<u8, String>,
+    /// Encoding map required for decoding the data, empty for blocks that are not Huffman encoded
+    pub encoding_map: EncodingMap,
     /// Encoding stats for the data
     pub stats: EncodingStats,
+    /// Byte sizes of every interleaved stream but the last, empty when the data was encoded as a single stream
+    pub stream_sizes: Vec<usize>,
+    /// How `encoded_data` is encoded, always `Huffman` unless a hook forced another block or the block came from
+    /// `encode_with` or `HuffmanBlocks`, which pick whichever method gives the smallest output
+    pub block_type: BlockType,
+    /// Number of u8s `encoded_data` decodes to
+    pub data_len: usize,
+    /// How the payload bits of a Huffman block are laid out in `encoded_data`
+    pub packing: Packing,
+    /// CRC-32 of the data `encoded_data` decodes to, checked when decoding unless `DecodeOptions` says otherwise.
+    /// `None` for data read from an encoding that carries no checksum
+    pub checksum: Option<u32>,
+    /// Key/value attributes stored alongside the data by `to_bytes`, empty unless set
+    pub metadata: Metadata,
 }
 
 impl HuffmanData {
@@ -38,21 +59,336 @@ impl HuffmanData {
     /// assert_eq!(decoded_data,data);
     /// ```
     pub fn new(data: &[u8]) -> Result<HuffmanData> {
-        let frequency_map: FrequencyMap = FrequencyMap::build(data);
-        let huffman_tree: Node = huffman_tree::build(&frequency_map)?;
-        let encoding_map: EncodingMap = EncodingMap::new(&huffman_tree)?;
+        Self::with_options(data, &HuffmanOptions::default())
+    }
+
+    /// Huffman encodes a `Vec<u8>` with the given `HuffmanOptions` returning a `HuffmanData` struct.
+    /// A `HuffmanData` is a single block, so options setting a `block_size` fail with `OptionsError`, only
+    /// `encode_with` splits data into blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A reference to `Vec<u8>` containing the data you want to encode
+    /// * `options` - A reference to `HuffmanOptions` controlling the encoding
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let options = HuffmanOptions { streams: 4, ..Default::default() };
+    /// let huffman_data: HuffmanData = HuffmanData::with_options(&data, &options).unwrap();
+    /// let decoded_data: Vec<u8> = huffman_data.decode().unwrap();
+    /// assert_eq!(decoded_data,data);
+    /// ```
+    pub fn with_options(data: &[u8], options: &HuffmanOptions) -> Result<HuffmanData> {
+        Self::with_hooks(data, options, &mut [])
+    }
+
+    /// Encodes `data` like `with_options`, running `hooks` at every stage of the encoder. Options setting a
+    /// `block_size` fail with `OptionsError` as they do there
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A reference to `Vec<u8>` containing the data you want to encode
+    /// * `options` - A reference to `HuffmanOptions` controlling the encoding
+    /// * `hooks` - The hooks to run, in order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// /// Stores data with fewer than 4 distinct u8s as is
+    /// struct StoreSmallAlphabets;
+    ///
+    /// impl EncodeHook for StoreSmallAlphabets {
+    ///     fn on_frequency_map(&mut self, frequency_map: &FrequencyMap) -> Option<BlockType> {
+    ///         (frequency_map.len() < 4).then_some(BlockType::Stored)
+    ///     }
+    /// }
+    ///
+    /// let data: Vec<u8> = Vec::from("abababab");
+    /// let huffman_data =
+    ///     HuffmanData::with_hooks(&data, &HuffmanOptions::default(), &mut [&mut StoreSmallAlphabets]).unwrap();
+    /// assert_eq!(huffman_data.block_type, BlockType::Stored);
+    /// assert_eq!(huffman_data.decode().unwrap(), data);
+    /// ```
+    pub fn with_hooks(
+        data: &[u8],
+        options: &HuffmanOptions,
+        hooks: &mut [&mut dyn EncodeHook],
+    ) -> Result<HuffmanData> {
+        if options.block_size.is_some() {
+            return Err(HuffmanError::OptionsError(
+                "block_size is only honored by encode_with",
+            ));
+        }
+        Self::with_frequencies(data, &FrequencyMap::build(data), options, hooks)
+    }
+
+    /// Encodes `data` using an already counted `frequency_map`, which must match `data`
+    pub(crate) fn with_frequencies(
+        data: &[u8],
+        frequency_map: &FrequencyMap,
+        options: &HuffmanOptions,
+        hooks: &mut [&mut dyn EncodeHook],
+    ) -> Result<HuffmanData> {
+        Self::with_map_builder(data, frequency_map, options, hooks, |frequency_map| {
+            EncodingMap::new(&huffman_tree::build(frequency_map)?)
+        })
+    }
+
+    /// Encodes `data` like `with_frequencies`, getting the encoding map for `frequency_map` from `build_map`
+    pub(crate) fn with_map_builder(
+        data: &[u8],
+        frequency_map: &FrequencyMap,
+        options: &HuffmanOptions,
+        hooks: &mut [&mut dyn EncodeHook],
+        build_map: impl FnOnce(&FrequencyMap) -> Result<EncodingMap>,
+    ) -> Result<HuffmanData> {
+        let huffman_data =
+            Self::select_block(data, frequency_map, options, hooks, build_map, false)?;
+        Ok(huffman_data.with_checks(data, options))
+    }
+
+    /// Encodes `data` as whichever of a Huffman, stored or run-length block is smallest once written out, the
+    /// blocks of `encode_with` and `HuffmanBlocks`
+    pub(crate) fn with_block_selection(
+        data: &[u8],
+        options: &HuffmanOptions,
+    ) -> Result<HuffmanData> {
+        let huffman_data = Self::select_block(
+            data,
+            &FrequencyMap::build(data),
+            options,
+            &mut [],
+            |frequency_map| EncodingMap::new(&huffman_tree::build(frequency_map)?),
+            true,
+        )?;
+        Ok(huffman_data.with_checks(data, options))
+    }
+
+    /// Stores `data` as is, without counting its u8s or building a tree, for data already known not to compress
+    pub(crate) fn stored(data: &[u8], options: &HuffmanOptions) -> Result<HuffmanData> {
+        options.validate()?;
+        let huffman_data = Self::raw_block(data, data.to_vec(), BlockType::Stored, options.packing);
+        Ok(huffman_data.with_checks(data, options))
+    }
+
+    /// Drops the checksum unless `options` asks for one, and attaches the digest of `data` when it does
+    #[cfg_attr(not(feature = "digest"), allow(unused_variables))]
+    pub(crate) fn with_checks(self, data: &[u8], options: &HuffmanOptions) -> HuffmanData {
+        let huffman_data = HuffmanData {
+            checksum: self.checksum.filter(|_| options.checksum),
+            ..self
+        };
+        #[cfg(feature = "digest")]
+        let huffman_data = match options.digest {
+            true => huffman_data.with_digest(data),
+            false => huffman_data,
+        };
+        huffman_data
+    }
+
+    /// Encodes `data` as whichever block the hooks force, otherwise as a Huffman block or, when `pick_smallest` is
+    /// set, as whichever block comes out smallest
+    fn select_block(
+        data: &[u8],
+        frequency_map: &FrequencyMap,
+        options: &HuffmanOptions,
+        hooks: &mut [&mut dyn EncodeHook],
+        build_map: impl FnOnce(&FrequencyMap) -> Result<EncodingMap>,
+        pick_smallest: bool,
+    ) -> Result<HuffmanData> {
+        options.validate()?;
+        // There is no tree to build without a symbol, so empty data is always a stored block without a map
+        if data.is_empty() {
+            return Ok(Self::raw_block(
+                data,
+                Vec::new(),
+                BlockType::Stored,
+                options.packing,
+            ));
+        }
+        let mut forced = run_hooks(hooks, |hook| hook.on_frequency_map(frequency_map));
+        if let Some(block) = Self::forced_raw_block(data, forced, options.packing) {
+            return Ok(block);
+        }
+        let encoding_map = Self::fit_map(build_map(frequency_map)?, frequency_map, options)?;
+        forced = forced.or(run_hooks(hooks, |hook| hook.on_tree_built(&encoding_map)));
+        if let Some(block) = Self::forced_raw_block(data, forced, options.packing) {
+            return Ok(block);
+        }
+        let huffman_data = Self::huffman_block(data, encoding_map, options)?;
+        forced = forced.or(run_hooks(hooks, |hook| {
+            hook.on_block_encoded(&huffman_data)
+        }));
+        if let Some(block) = Self::forced_raw_block(data, forced, options.packing) {
+            return Ok(block);
+        }
 
-        let encoded_data: UnPaddedBits = Self::huffman_encode(data, &encoding_map);
-        let encoded_data: PaddedBits = encoded_data.pad();
-        let encoded_data = encoded_data.to_vec_u8()?;
-        let stats: EncodingStats = EncodingStats::new(data, &encoded_data);
+        match forced {
+            None if pick_smallest => Ok(Self::smallest_block(data, huffman_data)),
+            _ => Ok(huffman_data),
+        }
+    }
+
+    /// Applies the code length limit and canonical codes `options` ask for to the map built for `frequency_map`
+    pub(crate) fn fit_map(
+        encoding_map: EncodingMap,
+        frequency_map: &FrequencyMap,
+        options: &HuffmanOptions,
+    ) -> Result<EncodingMap> {
+        match options.max_code_len {
+            Some(max_code_len) if encoding_map.get_longest_code() > max_code_len => {
+                let lengths: Vec<(u16, usize)> =
+                    huffman_tree::limited_code_lengths(frequency_map, max_code_len)?
+                        .into_iter()
+                        .map(|(symbol, length)| (symbol as u16, length))
+                        .collect();
+                EncodingMap::from_lengths(&lengths)
+            }
+            _ if options.canonical => encoding_map.canonical(),
+            _ => Ok(encoding_map),
+        }
+    }
+
+    /// Returns the block a hook forced, unless it forced `Huffman` or nothing at all
+    fn forced_raw_block(
+        data: &[u8],
+        forced: Option<BlockType>,
+        packing: Packing,
+    ) -> Option<HuffmanData> {
+        match forced? {
+            BlockType::Stored => Some(Self::raw_block(
+                data,
+                data.to_vec(),
+                BlockType::Stored,
+                packing,
+            )),
+            BlockType::Rle => Some(Self::raw_block(
+                data,
+                rle::encode(data),
+                BlockType::Rle,
+                packing,
+            )),
+            BlockType::Huffman => None,
+        }
+    }
 
-        let huffman_encoded_data = HuffmanData {
+    /// Returns whichever of `huffman_data`, the stored data or its run-length encoding is smallest once written out,
+    /// counting the encoding map the Huffman block has to carry, so incompressible data never expands past a stored block
+    fn smallest_block(data: &[u8], huffman_data: HuffmanData) -> HuffmanData {
+        let packing = huffman_data.packing;
+        let stored = Self::raw_block(data, data.to_vec(), BlockType::Stored, packing);
+        let rle = Self::raw_block(data, rle::encode(data), BlockType::Rle, packing);
+
+        // Ties go to the Huffman block, then to the stored one
+        [huffman_data, stored, rle]
+            .into_iter()
+            .min_by_key(HuffmanData::block_size)
+            .unwrap()
+    }
+
+    fn huffman_block(
+        data: &[u8],
+        encoding_map: EncodingMap,
+        options: &HuffmanOptions,
+    ) -> Result<HuffmanData> {
+        let mut streams = Vec::with_capacity(options.streams);
+        for stream in 0..options.streams {
+            let stream_data: Vec<u8> = data
+                .iter()
+                .skip(stream)
+                .step_by(options.streams)
+                .copied()
+                .collect();
+            streams.push(Self::pack_stream(
+                &stream_data,
+                &encoding_map,
+                options.packing,
+            )?);
+        }
+        Ok(Self::from_packed_streams(
+            data,
+            encoding_map,
+            streams,
+            options,
+        ))
+    }
+
+    /// Assembles the Huffman block of `data` from the packed u8s and number of payload bits of every stream
+    pub(crate) fn from_packed_streams(
+        data: &[u8],
+        encoding_map: EncodingMap,
+        streams: Vec<(Vec<u8>, u64)>,
+        options: &HuffmanOptions,
+    ) -> HuffmanData {
+        let mut encoded_data = Vec::new();
+        let mut stream_sizes = Vec::with_capacity(options.streams - 1);
+        let mut payload_bits = 0;
+        let stream_count = streams.len();
+        for (stream, (stream_bytes, stream_payload_bits)) in streams.into_iter().enumerate() {
+            payload_bits += stream_payload_bits;
+            if stream + 1 < stream_count {
+                stream_sizes.push(stream_bytes.len());
+            }
+            encoded_data.extend_from_slice(&stream_bytes);
+        }
+        let stats: EncodingStats = EncodingStats::new(data, &encoded_data, payload_bits);
+
+        HuffmanData {
             encoded_data,
-            encoding_map: encoding_map.extract().0,
+            encoding_map,
             stats,
-        };
-        Ok(huffman_encoded_data)
+            stream_sizes,
+            block_type: BlockType::Huffman,
+            data_len: data.len(),
+            packing: options.packing,
+            checksum: Some(crc32(data)),
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// Huffman encodes one stream into packed u8s, returning them with the number of payload bits
+    fn pack_stream(
+        data: &[u8],
+        encoding_map: &EncodingMap,
+        packing: Packing,
+    ) -> Result<(Vec<u8>, u64)> {
+        #[cfg(feature = "simd")]
+        if let Some(packed) = word_pack::pack(data, encoding_map, packing) {
+            return Ok(packed);
+        }
+        let stream_bits: UnPaddedBits = Self::huffman_encode(data, encoding_map);
+        let payload_bits = stream_bits.len() as u64;
+        Ok((packing.pack(stream_bits)?, payload_bits))
+    }
+
+    /// Wraps data that was not Huffman encoded, these blocks carry no encoding map
+    fn raw_block(
+        data: &[u8],
+        encoded_data: Vec<u8>,
+        block_type: BlockType,
+        packing: Packing,
+    ) -> HuffmanData {
+        let payload_bits = encoded_data.len() as u64 * 8;
+        let stats = EncodingStats::new(data, &encoded_data, payload_bits);
+        HuffmanData {
+            encoded_data,
+            encoding_map: EncodingMap::default(),
+            stats,
+            stream_sizes: Vec::new(),
+            block_type,
+            data_len: data.len(),
+            packing,
+            checksum: Some(crc32(data)),
+            metadata: Metadata::new(),
+        }
     }
 
     /// Huffman decodes a `HuffmanData` struct and returns a decoded `Vec<u8>`
@@ -74,50 +410,274 @@ impl HuffmanData {
     /// assert_eq!(decoded_data,data);
     /// ```
     pub fn decode(&self) -> Result<Vec<u8>> {
-        let encoded_data: PaddedBits = PaddedBits::from_vec_u8(&self.encoded_data);
-        let encoded_data: UnPaddedBits = encoded_data.unpad();
-        let encoding_map: EncodingMap = EncodingMap::from(self.encoding_map.clone());
-        let decoded_data = Self::huffman_decode(&encoded_data, &encoding_map);
+        self.decode_with(&DecodeOptions::default())
+    }
 
-        Ok(decoded_data)
+    /// Huffman decodes a `HuffmanData` struct under the given `DecodeOptions` and returns a decoded `Vec<u8>`
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - A reference to `DecodeOptions` controlling strictness and output limits
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data: HuffmanData = HuffmanData::new(&data).unwrap();
+    /// let options = DecodeOptions { strict: true, max_output: Some(64), ..Default::default() };
+    /// let decoded_data: Vec<u8> = huffman_data.decode_with(&options).unwrap();
+    /// assert_eq!(decoded_data,data);
+    /// ```
+    pub fn decode_with(&self, options: &DecodeOptions) -> Result<Vec<u8>> {
+        let decoded = self.decode_block(options)?;
+        match self.checksum {
+            Some(checksum) if options.verify_checksum => check_checksum(checksum, crc32(&decoded))?,
+            _ => {}
+        }
+        Ok(decoded)
     }
 
-    fn huffman_decode(encoded_data: &UnPaddedBits, encoding_map: &EncodingMap) -> Vec<u8> {
-        let mut data: Vec<u8> = Vec::with_capacity(encoded_data.len());
-        let mut code = BitVec::with_capacity(encoding_map.get_longest_code());
-        let min_len = encoding_map.get_shortest_code();
+    /// Decodes the block as its block type says, leaving the checksum to `decode_with`
+    fn decode_block(&self, options: &DecodeOptions) -> Result<Vec<u8>> {
+        if options.strict
+            && self.block_type != BlockType::Huffman
+            && (!self.encoding_map.is_empty() || !self.stream_sizes.is_empty())
+        {
+            return Err(HuffmanError::DecodeError(
+                "Only Huffman blocks carry an encoding map or streams",
+            ));
+        }
 
-        for code_bit in encoded_data {
-            code.push(*code_bit);
+        match self.block_type {
+            BlockType::Stored => {
+                options.check_output(self.encoded_data.len(), self.encoded_data.len())?;
+                return Ok(self.encoded_data.clone());
+            }
+            BlockType::Rle => {
+                let decoded_size = rle::decoded_size(&self.encoded_data);
+                options.check_output(decoded_size, self.encoded_data.len())?;
+                return rle::decode(&self.encoded_data);
+            }
+            BlockType::Huffman => {}
+        }
+
+        let streams = self
+            .split_streams()?
+            .iter()
+            .map(|stream| self.packing.unpack(stream))
+            .collect::<Vec<UnPaddedBits>>();
+        Self::huffman_decode(&streams, &self.encoding_map, options)
+    }
+
+    /// Length in bits of the shortest code in the encoding map, escape included, `None` for a block without codes.
+    /// Together with `longest_code` it bounds the bits a decoder consumes per symbol
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let huffman_data = HuffmanData::new(&b"this is a test string!".repeat(8)).unwrap();
+    /// assert_eq!(huffman_data.shortest_code(), Some(2));
+    /// assert_eq!(huffman_data.longest_code(), Some(5));
+    /// assert!(huffman_data.is_complete_code());
+    /// ```
+    pub fn shortest_code(&self) -> Option<usize> {
+        self.encoding_map
+            .code_lengths()
+            .into_iter()
+            .map(|(_, length)| length)
+            .min()
+    }
+
+    /// Length in bits of the longest code in the encoding map, escape included, `None` for a block without codes.
+    /// A table driven decoder indexing on this many bits resolves every code in one lookup
+    pub fn longest_code(&self) -> Option<usize> {
+        self.encoding_map
+            .code_lengths()
+            .into_iter()
+            .map(|(_, length)| length)
+            .max()
+    }
+
+    /// True when the codes form a complete prefix code, their Kraft sum being exactly 1, so every bit pattern starts
+    /// with a code and a decode table built from them has no empty entries. False for a block without codes and for
+    /// a single code, which leaves half the code space unused
+    pub fn is_complete_code(&self) -> bool {
+        self.shortest_code().is_some() && self.encoding_map.diagnostics().is_complete()
+    }
+
+    /// Splits `encoded_data` into its interleaved streams using `stream_sizes`
+    pub(crate) fn split_streams(&self) -> Result<Vec<&[u8]>> {
+        let mut streams = Vec::with_capacity(self.stream_sizes.len() + 1);
+        let mut rest = self.encoded_data.as_slice();
+        for &size in &self.stream_sizes {
+            if size > rest.len() {
+                return Err(HuffmanError::DecodeError(
+                    "Stream sizes exceed the encoded data",
+                ));
+            }
+            let (stream, remainder) = rest.split_at(size);
+            streams.push(stream);
+            rest = remainder;
+        }
+        streams.push(rest);
+        Ok(streams)
+    }
+
+    /// Decodes round-robin interleaved streams, taking one symbol from each stream per pass of the loop.
+    /// A single stream is simply the degenerate case of one stream per pass.
+    fn huffman_decode(
+        streams: &[UnPaddedBits],
+        encoding_map: &EncodingMap,
+        options: &DecodeOptions,
+    ) -> Result<Vec<u8>> {
+        let input_bits: usize = streams.iter().map(|stream| stream.len()).sum();
+        let mut data: Vec<u8> = Vec::with_capacity(input_bits);
+        let mut cursors = vec![0; streams.len()];
+        let code_lens = (
+            encoding_map.get_shortest_code(),
+            encoding_map.get_longest_code(),
+        );
+        let mut code = BitVec::with_capacity(code_lens.1);
+
+        'decode: loop {
+            for (stream, cursor) in streams.iter().zip(cursors.iter_mut()) {
+                match Self::next_symbol(stream, cursor, &mut code, code_lens, encoding_map)? {
+                    Some(byte) => {
+                        options.check_output(data.len() + 1, input_bits.div_ceil(8))?;
+                        options.check_run(data.len() + 1, input_bits)?;
+                        data.push(byte);
+                    }
+                    None => break 'decode,
+                }
+            }
+        }
+
+        if options.strict {
+            if !code.is_empty() {
+                return Err(HuffmanError::DecodeError(
+                    "Encoded data ends in an incomplete code",
+                ));
+            }
+            if cursors
+                .iter()
+                .zip(streams)
+                .any(|(&cursor, stream)| cursor != stream.len())
+            {
+                return Err(HuffmanError::DecodeError(
+                    "Interleaved streams hold unbalanced symbol counts",
+                ));
+            }
+        }
+        Ok(data)
+    }
+
+    /// Reads bits from `stream` starting at `cursor` until they form a known code, returning `None` once the stream runs out.
+    /// Any bits read without completing a code are left in `code`. Fails once `max_len` bits, the longest code of
+    /// `encoding_map`, are read without completing one, as no more bits can complete it
+    pub(crate) fn next_symbol(
+        stream: &UnPaddedBits,
+        cursor: &mut usize,
+        code: &mut BitVec,
+        (min_len, max_len): (usize, usize),
+        encoding_map: &EncodingMap,
+    ) -> Result<Option<u8>> {
+        code.clear();
+        while let Some(&code_bit) = stream.get(*cursor) {
+            *cursor += 1;
+            code.push(code_bit);
             if code.len() < min_len {
                 continue;
             }
-
-            if let Some(&byte) = encoding_map.get_inverse(&code) {
-                code.clear();
-                data.push(byte);
+            if let Some(&byte) = encoding_map.get_inverse(code) {
+                return Ok(Some(byte));
+            }
+            if encoding_map.get_escape() == Some(code) {
+                return Ok(Self::next_literal(stream, cursor, code));
+            }
+            if code.len() >= max_len {
+                return Err(HuffmanError::DecodeError(
+                    "Encoded data holds a code longer than any in the map",
+                ));
             }
         }
-        data
+        Ok(None)
     }
 
-    fn huffman_encode(data: &[u8], encoding_map: &EncodingMap) -> UnPaddedBits {
+    /// Reads the 8 raw bits following an escape code, leaving them in `code` if the stream runs out first
+    fn next_literal(stream: &UnPaddedBits, cursor: &mut usize, code: &mut BitVec) -> Option<u8> {
+        let Some(literal) = stream.get(*cursor..*cursor + 8) else {
+            code.extend_from_slice(&stream[*cursor..]);
+            *cursor = stream.len();
+            return None;
+        };
+        *cursor += 8;
+        Some(literal.iter().fold(0, |byte, &bit| (byte << 1) | bit))
+    }
+
+    pub(crate) fn huffman_encode(data: &[u8], encoding_map: &EncodingMap) -> UnPaddedBits {
         let mut encoded_data = UnPaddedBits::new();
         for c in data {
             if let Some(code) = encoding_map.get(c) {
                 encoded_data.extend_from_slice(code);
+            } else if let Some(escape) = encoding_map.get_escape() {
+                encoded_data.extend_from_slice(escape);
+                encoded_data.extend((0..8).rev().map(|shift| (c >> shift) & 1));
             }
         }
         encoded_data
     }
 }
 
+/// Huffman encodes `data` with an existing `EncodingMap` into bytes packed as `packing` says.
+/// Every u8 in `data` needs a code unless the map has an escape code.
+pub(crate) fn encode_with_map(
+    data: &[u8],
+    encoding_map: &EncodingMap,
+    packing: Packing,
+) -> Result<Vec<u8>> {
+    if encoding_map.get_escape().is_none() {
+        if let Some(&symbol) = data.iter().find(|byte| encoding_map.get(byte).is_none()) {
+            return Err(HuffmanError::SymbolError(
+                symbol,
+                "Symbol has no code in the encoding map",
+            ));
+        }
+    }
+    packing.pack(HuffmanData::huffman_encode(data, encoding_map))
+}
+
+/// Fails with `ChecksumMismatch` unless the CRC-32 of the decoded data is the one the data carries
+pub(crate) fn check_checksum(expected: u32, actual: u32) -> Result<()> {
+    match expected == actual {
+        true => Ok(()),
+        false => Err(HuffmanError::ChecksumMismatch(expected, actual)),
+    }
+}
+
+/// Decodes bytes produced by `encode_with_map` with the same `EncodingMap` and `Packing`
+pub(crate) fn decode_with_map(
+    encoded_data: &[u8],
+    encoding_map: &EncodingMap,
+    packing: Packing,
+    options: &DecodeOptions,
+) -> Result<Vec<u8>> {
+    let encoded_data = packing.unpack(encoded_data);
+    HuffmanData::huffman_decode(&[encoded_data], encoding_map, options)
+}
+
 // Unit Tests all internal functions must be tested here. One test per function unless impossible
 #[cfg(test)]
 mod tests {
     use crate::data::BitVector;
 
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_huffman_encode() {
@@ -138,7 +698,7 @@ mod tests {
         .iter()
         .map(|(k, v)| (*k, v.to_string()))
         .collect();
-        let input_encoding_map = EncodingMap::from(input_encoding_map);
+        let input_encoding_map = EncodingMap::from(input_encoding_map).unwrap();
 
         let expected_data = UnPaddedBits::from_string(
             "11110010101110011011100100110111100001101110111011110001011001100010010",
@@ -170,11 +730,16 @@ mod tests {
         .iter()
         .map(|(k, v)| (*k, v.to_string()))
         .collect();
-        let input_encoding_map = EncodingMap::from(input_encoding_map);
+        let input_encoding_map = EncodingMap::from(input_encoding_map).unwrap();
 
         let expected_data: Vec<u8> = Vec::from("this is a test string!");
 
-        let test_output = HuffmanData::huffman_decode(&input_data, &input_encoding_map);
+        let test_output = HuffmanData::huffman_decode(
+            &[input_data],
+            &input_encoding_map,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
         println!("{:?}", input_encoding_map.extract());
         assert_eq!(expected_data, test_output);
         assert_eq!(
@@ -182,4 +747,240 @@ mod tests {
             String::from_utf8(test_output).unwrap()
         );
     }
+
+    #[test]
+    fn test_next_symbol() {
+        // No code starts with 11, so the decoder has to stop after 2 bits rather than read the whole stream
+        let input_data = UnPaddedBits::from_string(&("0".to_string() + &"1".repeat(1000)));
+        let input_encoding_map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_encoding_map = EncodingMap::from(input_encoding_map).unwrap();
+        let mut cursor = 0;
+        let mut code = BitVec::new();
+
+        let test_output =
+            HuffmanData::next_symbol(&input_data, &mut cursor, &mut code, (1, 2), &input_encoding_map);
+
+        assert_eq!(Some(b'a'), test_output.unwrap());
+        assert!(HuffmanData::next_symbol(
+            &input_data,
+            &mut cursor,
+            &mut code,
+            (1, 2),
+            &input_encoding_map
+        )
+        .is_err());
+        assert_eq!(3, cursor);
+        assert!(HuffmanData::huffman_decode(
+            &[input_data],
+            &input_encoding_map,
+            &DecodeOptions::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_huffman_encode_decode_escape() {
+        let input_data: Vec<u8> = Vec::from("aaaabbc");
+        let mut frequency_map = FrequencyMap::build(&input_data);
+        let escape_freq = frequency_map.prune_below(2);
+        let huffman_tree = huffman_tree::build_with_escape(&frequency_map, escape_freq).unwrap();
+        let encoding_map = EncodingMap::new(&huffman_tree).unwrap();
+
+        let expected_data = UnPaddedBits::from_string("111101010001100011");
+
+        let test_output = HuffmanData::huffman_encode(&input_data, &encoding_map);
+
+        assert_eq!(expected_data, test_output);
+
+        let test_output =
+            HuffmanData::huffman_decode(&[test_output], &encoding_map, &DecodeOptions::default())
+                .unwrap();
+
+        assert_eq!(input_data, test_output);
+    }
+
+    #[test]
+    fn test_huffman_long_codes() {
+        // Fibonacci frequencies give the deepest possible tree, one level per symbol
+        let mut fibonacci = (1i64, 1i64);
+        let frequency_map: FrequencyMap = (0..88u8)
+            .map(|symbol| {
+                fibonacci = (fibonacci.1, fibonacci.0 + fibonacci.1);
+                (symbol, fibonacci.0)
+            })
+            .collect();
+        let input_data: Vec<u8> = (0..88u8).rev().chain(0..88u8).collect();
+
+        let encoding_map = EncodingMap::new(&huffman_tree::build(&frequency_map).unwrap()).unwrap();
+        let huffman_data =
+            HuffmanData::huffman_block(&input_data, encoding_map, &HuffmanOptions::default())
+                .unwrap();
+
+        assert_eq!(87, huffman_data.encoding_map.get_longest_code());
+        assert_eq!(input_data, huffman_data.decode().unwrap());
+
+        let encoded_data = huffman_data.to_bytes().unwrap();
+        let mut test_output = Vec::new();
+        crate::stream::decode_from_buf_read(&mut encoded_data.as_slice(), &mut test_output)
+            .unwrap();
+        assert_eq!(input_data, test_output);
+    }
+
+    #[test]
+    fn test_huffman_decode_interleaved() {
+        let input_encoding_map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10"), (b'c', "11")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_encoding_map = EncodingMap::from(input_encoding_map).unwrap();
+        let input_data = vec![
+            UnPaddedBits::from_string("000"),
+            UnPaddedBits::from_string("1011"),
+            UnPaddedBits::from_string("1110"),
+        ];
+
+        let expected_data: Vec<u8> = Vec::from("abcacba");
+
+        let test_output = HuffmanData::huffman_decode(
+            &input_data,
+            &input_encoding_map,
+            &DecodeOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(expected_data, test_output);
+    }
+
+    #[test]
+    fn test_canonical() {
+        let input_data = b"this is a test string!".repeat(8);
+        let options = HuffmanOptions {
+            canonical: true,
+            ..Default::default()
+        };
+        let expected_data = HuffmanData::new(&input_data).unwrap();
+
+        let test_output = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        assert!(test_output.encoding_map.is_canonical());
+        assert_eq!(
+            expected_data.encoding_map.code_lengths(),
+            test_output.encoding_map.code_lengths()
+        );
+        assert_eq!(
+            expected_data.stats.payload_bits,
+            test_output.stats.payload_bits
+        );
+        assert!(test_output.to_bytes().unwrap().len() <= expected_data.to_bytes().unwrap().len());
+        assert_eq!(input_data, test_output.decode().unwrap());
+    }
+
+    #[test]
+    fn test_max_code_len() {
+        let mut input_data = Vec::new();
+        for (byte, count) in (0u8..).zip([1, 1, 2, 3, 5, 8, 13, 21, 34, 55]) {
+            input_data.extend(std::iter::repeat_n(byte, count));
+        }
+        let options = HuffmanOptions {
+            max_code_len: Some(5),
+            ..Default::default()
+        };
+        let unlimited = HuffmanData::new(&input_data).unwrap();
+
+        let test_output = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        assert_eq!(Some(9), unlimited.longest_code());
+        assert_eq!(Some(5), test_output.longest_code());
+        assert!(test_output.encoding_map.is_canonical());
+        assert_eq!(input_data, test_output.decode().unwrap());
+        assert!(HuffmanData::with_options(
+            &(0..=255).collect::<Vec<u8>>(),
+            &HuffmanOptions {
+                max_code_len: Some(7),
+                ..Default::default()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_with_options_block_size() {
+        let input_data = b"this is a test string!".repeat(8);
+        let options = HuffmanOptions {
+            block_size: Some(64),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            HuffmanData::with_options(&input_data, &options),
+            Err(HuffmanError::OptionsError(_))
+        ));
+        assert!(matches!(
+            HuffmanData::with_hooks(&input_data, &options, &mut []),
+            Err(HuffmanError::OptionsError(_))
+        ));
+        // encode_with is where the blocks come from
+        let encoded_data = crate::container::encode_with(&input_data, &options).unwrap();
+        assert_eq!(
+            input_data,
+            crate::members::decode_all_members(&mut encoded_data.as_slice()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_smallest_block() {
+        // Short text whose map outweighs the u8s its codes save, then the same text long enough to pay for it
+        let text = b"this is a test string!";
+        let mut state = 7u32;
+        let noise: Vec<u8> = (0..300)
+            .map(|_| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                (state >> 24) as u8
+            })
+            .collect();
+
+        for (input_data, expected_block_type) in [
+            (text.to_vec(), BlockType::Stored),
+            (text.repeat(8), BlockType::Huffman),
+            (noise, BlockType::Stored),
+        ] {
+            let test_output =
+                HuffmanData::with_block_selection(&input_data, &HuffmanOptions::default())
+                    .unwrap();
+
+            assert_eq!(expected_block_type, test_output.block_type);
+            assert_eq!(
+                BlockType::Huffman,
+                HuffmanData::new(&input_data).unwrap().block_type
+            );
+            // An encoding never takes more than the header of a stored block over the data
+            assert!(test_output.to_bytes().unwrap().len() <= input_data.len() + 19);
+        }
+    }
+
+    #[test]
+    fn test_code_lengths() {
+        let input_data = [b"aaaa".as_slice(), b"aaaabbc", b""];
+        let expected_data = [
+            (Some(1), Some(1), false),
+            (Some(1), Some(2), true),
+            (None, None, false),
+        ];
+
+        for (input_data, expected_data) in input_data.iter().zip(expected_data) {
+            let test_output = HuffmanData::new(input_data).unwrap();
+
+            assert_eq!(
+                expected_data,
+                (
+                    test_output.shortest_code(),
+                    test_output.longest_code(),
+                    test_output.is_complete_code()
+                )
+            );
+        }
+    }
 }
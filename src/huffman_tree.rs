@@ -1,6 +1,9 @@
 use crate::error::{HuffmanError, Result};
 use crate::frequency_map::FrequencyMap;
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
 #[derive(Debug)]
 pub struct Node {
     pub left: Option<Box<Node>>,
@@ -19,8 +22,14 @@ impl Node {
         }
     }
 
+    /// Returns true for leaves, including the escape leaf which carries no value
+    pub fn is_leaf(&self) -> bool {
+        self.left.is_none() && self.right.is_none()
+    }
+
     fn new_branch(left: Node, right: Node) -> Node {
-        let freq = left.freq + right.freq;
+        // Saturates so adversarial frequency maps cannot overflow, the tree shape is all that matters past this point
+        let freq = left.freq.saturating_add(right.freq);
         Node {
             left: Some(Box::new(left)),
             right: Some(Box::new(right)),
@@ -32,35 +41,225 @@ impl Node {
 
 /// Creates a a Huffman Coding Tree with given Frequency Map
 /// We sort the frequency list alphabetically then we sort it by frequency to give us consitancy in the tree we generate
-pub fn build(frequency_map: &FrequencyMap) -> Result<Node> {
+pub fn build<S>(frequency_map: &FrequencyMap<S>) -> Result<Node> {
+    build_with_escape(frequency_map, 0)
+}
+
+/// Creates a Huffman Coding Tree with given Frequency Map plus an escape leaf weighted `escape_freq`.
+/// The escape leaf stands in for every u8 missing from the map, it is left out when `escape_freq` is 0
+pub fn build_with_escape<S>(frequency_map: &FrequencyMap<S>, escape_freq: i64) -> Result<Node> {
+    build_traced(frequency_map, escape_freq, None)
+}
+
+/// One node taking part in a `MergeStep`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeNode {
+    /// The leaf of a u8, or the escape leaf for `None`
+    Leaf(Option<u8>),
+    /// The branch made by the merge step of that index
+    Branch(usize),
+}
+
+/// A merge of the two least frequent nodes left into a branch, as recorded by `merge_steps`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeStep {
+    /// The node taking the 0 side of the branch
+    pub left: MergeNode,
+    /// Frequency of `left`
+    pub left_freq: i64,
+    /// The node taking the 1 side of the branch
+    pub right: MergeNode,
+    /// Frequency of `right`
+    pub right_freq: i64,
+    /// Frequency of the branch made, the sum of both sides
+    pub freq: i64,
+}
+
+/// Returns every merge building the Huffman tree of `frequency_map` takes, in the order they happen, so tools can
+/// replay the construction step by step. The last step makes the root, a map of a single u8 takes none.
+///
+/// # Arguments
+///
+/// * `frequency_map` - The frequency of every u8, as `FrequencyMap::build` counts them
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let merge_steps = merge_steps(&FrequencyMap::build(b"aaaabbc")).unwrap();
+///
+/// assert_eq!(merge_steps.len(), 2);
+/// assert_eq!(merge_steps[0].left, MergeNode::Leaf(Some(b'c')));
+/// assert_eq!(merge_steps[0].right, MergeNode::Leaf(Some(b'b')));
+/// assert_eq!(merge_steps[1].left, MergeNode::Branch(0));
+/// assert_eq!(merge_steps[1].freq, 7);
+/// ```
+pub fn merge_steps<S>(frequency_map: &FrequencyMap<S>) -> Result<Vec<MergeStep>> {
+    let mut trace = Vec::new();
+    build_traced(frequency_map, 0, Some(&mut trace))?;
+    Ok(trace)
+}
+
+/// Builds the tree as `build_with_escape` does, pushing every merge onto `trace` when there is one
+fn build_traced<S>(
+    frequency_map: &FrequencyMap<S>,
+    escape_freq: i64,
+    mut trace: Option<&mut Vec<MergeStep>>,
+) -> Result<Node> {
     //Create a Vector of Nodes containing each u8 and their frequency
-    let mut freq_list: Vec<Node> = Vec::with_capacity(frequency_map.len());
+    let mut freq_list: Vec<Node> = Vec::with_capacity(frequency_map.len() + 1);
     for (&data, &freq) in frequency_map {
         freq_list.push(Node::new_leaf(freq, Some(data)));
     }
+    if escape_freq > 0 {
+        freq_list.push(Node::new_leaf(escape_freq, None));
+    }
 
     //Sort the Vector
-    freq_list.sort_by(|a, b| b.value.cmp(&a.value));
-    freq_list.sort_by(|a, b| b.freq.cmp(&a.freq));
-
-    while freq_list.len() != 1 {
-        let left_node = freq_list
-            .pop()
-            .ok_or(HuffmanError::TreeError("Missing Left Node"))?;
-        let right_node = freq_list
-            .pop()
-            .ok_or(HuffmanError::TreeError("Missing Right Node"))?;
+    freq_list.sort_by_key(|node| Reverse(node.value));
+    freq_list.sort_by_key(|node| Reverse(node.freq));
+
+    // Merge the two least frequent nodes until one is left. Of equally frequent nodes the one furthest down the sorted
+    // list goes first, new branches being added at its end, which keeps the tree the one re-sorting the list by
+    // frequency after every merge gave
+    let mut heap: BinaryHeap<(Reverse<i64>, usize)> = freq_list
+        .iter()
+        .enumerate()
+        .map(|(order, node)| (Reverse(node.freq), order))
+        .collect();
+    // How a trace names each node, leaves by their value and branches by the step that made them
+    let mut ids: Vec<MergeNode> = freq_list
+        .iter()
+        .map(|node| MergeNode::Leaf(node.value))
+        .collect();
+    let mut nodes: Vec<Option<Node>> = freq_list.into_iter().map(Some).collect();
+    while heap.len() != 1 {
+        let (left, left_node) =
+            pop_node(&mut heap, &mut nodes).ok_or(HuffmanError::TreeError("Missing Left Node"))?;
+        let (right, right_node) =
+            pop_node(&mut heap, &mut nodes).ok_or(HuffmanError::TreeError("Missing Right Node"))?;
         let new_node = Node::new_branch(left_node, right_node);
-        freq_list.push(new_node);
-        freq_list.sort_by(|a, b| b.freq.cmp(&a.freq));
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.push(MergeStep {
+                left: ids[left],
+                left_freq: new_node.left.as_ref().map_or(0, |node| node.freq),
+                right: ids[right],
+                right_freq: new_node.right.as_ref().map_or(0, |node| node.freq),
+                freq: new_node.freq,
+            });
+            ids.push(MergeNode::Branch(trace.len() - 1));
+        }
+        heap.push((Reverse(new_node.freq), nodes.len()));
+        nodes.push(Some(new_node));
     }
-    freq_list
-        .pop()
+    pop_node(&mut heap, &mut nodes)
+        .map(|(_, node)| node)
         .ok_or(HuffmanError::TreeError("Missing Root Node"))
 }
 
+/// Takes the least frequent node out of the heap, the one added last of equally frequent nodes, with its place in
+/// the order nodes were added
+fn pop_node(
+    heap: &mut BinaryHeap<(Reverse<i64>, usize)>,
+    nodes: &mut [Option<Node>],
+) -> Option<(usize, Node)> {
+    heap.pop()
+        .and_then(|(_, order)| nodes[order].take().map(|node| (order, node)))
+}
+
+/// An item of a package-merge list, a leaf or a package of two items of the list before
+#[derive(Clone, Copy)]
+enum Item {
+    Leaf(usize),
+    Package(usize, usize),
+}
+
+/// Returns the code length of every u8 in `frequency_map` for the cheapest prefix code with no code longer than
+/// `max_code_len` bits, ordered by u8, using the package-merge algorithm.
+///
+/// Coins of every u8 at every denomination 2^-1 to 2^-max_code_len are laid out from the smallest: the cheapest pairs
+/// of each denomination are packaged into coins of the next and merged with the leaves there, and the 2n - 2 cheapest
+/// items of the last list pick the codes. The code length of a u8 is the number of its coins those items hold.
+/// Fails when `max_code_len` bits cannot give every u8 a code of its own.
+pub fn limited_code_lengths<S>(
+    frequency_map: &FrequencyMap<S>,
+    max_code_len: usize,
+) -> Result<Vec<(u8, usize)>> {
+    let mut leaves: Vec<(u128, u8)> = frequency_map
+        .iter()
+        .map(|(&value, &freq)| (freq.max(0) as u128, value))
+        .collect();
+    leaves.sort();
+    let count = leaves.len();
+    if count == 0 {
+        return Err(HuffmanError::TreeError("No symbols to build a tree from"));
+    }
+    if count == 1 {
+        return Ok(vec![(leaves[0].1, 1)]);
+    }
+    if max_code_len == 0 || (max_code_len < usize::BITS as usize && count > 1 << max_code_len) {
+        return Err(HuffmanError::TreeError(
+            "Maximum code length is too short to give every symbol a code",
+        ));
+    }
+    // No Huffman code is longer than count - 1 bits, a longer limit changes nothing
+    let max_code_len = max_code_len.min(count - 1);
+
+    let leaf_items: Vec<(u128, Item)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(index, &(freq, _))| (freq, Item::Leaf(index)))
+        .collect();
+    let mut lists = vec![leaf_items.clone()];
+    for _ in 1..max_code_len {
+        let previous = &lists[lists.len() - 1];
+        let packages = previous.chunks_exact(2).enumerate().map(|(index, pair)| {
+            (
+                pair[0].0 + pair[1].0,
+                Item::Package(2 * index, 2 * index + 1),
+            )
+        });
+        // Leaves go first on equal weights, which keeps codes as short as they can be
+        let mut merged = Vec::with_capacity(count + previous.len() / 2);
+        let mut leaf_items = leaf_items.iter().copied().peekable();
+        for package in packages {
+            while let Some(leaf) = leaf_items.next_if(|leaf| leaf.0 <= package.0) {
+                merged.push(leaf);
+            }
+            merged.push(package);
+        }
+        merged.extend(leaf_items);
+        lists.push(merged);
+    }
+
+    let mut lengths = vec![0; count];
+    let mut pending: Vec<(usize, usize)> = (0..2 * count - 2)
+        .map(|index| (lists.len() - 1, index))
+        .collect();
+    while let Some((list, index)) = pending.pop() {
+        match lists[list][index].1 {
+            Item::Leaf(leaf) => lengths[leaf] += 1,
+            Item::Package(first, second) => {
+                pending.push((list - 1, first));
+                pending.push((list - 1, second));
+            }
+        }
+    }
+
+    let mut code_lengths: Vec<(u8, usize)> = leaves
+        .iter()
+        .zip(lengths)
+        .map(|(&(_, value), length)| (value, length))
+        .collect();
+    code_lengths.sort();
+    Ok(code_lengths)
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::encoding_map::EncodingMap;
     use crate::frequency_map::FrequencyMapping;
 
     use super::*;
@@ -73,4 +272,149 @@ mod tests {
         // Create a huffman tree (Can't really test the output of this without coming up with a way to print it and build it manually)
         let _test_output_tree = build(&frequency_map).unwrap();
     }
+
+    #[test]
+    fn test_build_huffman_tree_with_escape() {
+        let input_data: Vec<u8> = Vec::from("aaaabbc");
+        let frequency_map = FrequencyMap::build(&input_data);
+
+        let test_output_tree = build_with_escape(&frequency_map, 2).unwrap();
+
+        let mut leaves = vec![&test_output_tree];
+        let mut escape_leaves = 0;
+        while let Some(node) = leaves.pop() {
+            if node.is_leaf() && node.value.is_none() {
+                escape_leaves += 1;
+                assert_eq!(2, node.freq);
+            }
+            leaves.extend(node.left.as_deref());
+            leaves.extend(node.right.as_deref());
+        }
+        assert_eq!(1, escape_leaves);
+        assert_eq!(9, test_output_tree.freq);
+    }
+
+    #[test]
+    fn test_build_matches_sorted_merges() {
+        // Re-sorts the whole list after every merge, the way trees were built before the heap
+        fn build_sorted(frequency_map: &FrequencyMap, escape_freq: i64) -> Node {
+            let mut freq_list: Vec<Node> = frequency_map
+                .iter()
+                .map(|(&data, &freq)| Node::new_leaf(freq, Some(data)))
+                .chain((escape_freq > 0).then(|| Node::new_leaf(escape_freq, None)))
+                .collect();
+            freq_list.sort_by_key(|node| Reverse(node.value));
+            freq_list.sort_by_key(|node| Reverse(node.freq));
+            while freq_list.len() != 1 {
+                let left_node = freq_list.pop().unwrap();
+                let right_node = freq_list.pop().unwrap();
+                freq_list.push(Node::new_branch(left_node, right_node));
+                freq_list.sort_by_key(|node| Reverse(node.freq));
+            }
+            freq_list.pop().unwrap()
+        }
+
+        // Ties between leaves, between branches and between leaves and branches
+        let input_data: Vec<FrequencyMap> = vec![
+            (0..=255).map(|byte| (byte, 1)).collect(),
+            (0..=255).map(|byte| (byte, byte as i64 % 5 + 1)).collect(),
+            (0..40).map(|byte| (byte, 1 << (byte % 7))).collect(),
+            FrequencyMap::build(b"this is a test string!"),
+        ];
+
+        for frequency_map in &input_data {
+            for escape_freq in [0, 1, 2] {
+                let expected_data = EncodingMap::new(&build_sorted(frequency_map, escape_freq));
+                let test_output =
+                    EncodingMap::new(&build_with_escape(frequency_map, escape_freq).unwrap());
+                assert_eq!(expected_data.unwrap(), test_output.unwrap());
+            }
+        }
+        assert!(build(&FrequencyMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_merge_steps() {
+        let input_data = FrequencyMap::build(b"aaaabbcd");
+        let expected_data = vec![
+            MergeStep {
+                left: MergeNode::Leaf(Some(b'c')),
+                left_freq: 1,
+                right: MergeNode::Leaf(Some(b'd')),
+                right_freq: 1,
+                freq: 2,
+            },
+            MergeStep {
+                left: MergeNode::Branch(0),
+                left_freq: 2,
+                right: MergeNode::Leaf(Some(b'b')),
+                right_freq: 2,
+                freq: 4,
+            },
+            MergeStep {
+                left: MergeNode::Branch(1),
+                left_freq: 4,
+                right: MergeNode::Leaf(Some(b'a')),
+                right_freq: 4,
+                freq: 8,
+            },
+        ];
+
+        let test_output = merge_steps(&input_data).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert!(merge_steps(&FrequencyMap::build(b"a")).unwrap().is_empty());
+        assert!(merge_steps(&FrequencyMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_limited_code_lengths() {
+        // Fibonacci frequencies give the most skewed tree, one code per length up to 7 bits
+        let fibonacci = [1, 1, 2, 3, 5, 8, 13, 21];
+        let frequency_map: FrequencyMap = (0u8..).zip(fibonacci).collect();
+        let expected_data = vec![
+            (0, 4),
+            (1, 4),
+            (2, 4),
+            (3, 4),
+            (4, 3),
+            (5, 3),
+            (6, 2),
+            (7, 2),
+        ];
+
+        let test_output = limited_code_lengths(&frequency_map, 4).unwrap();
+        let unlimited = limited_code_lengths(&frequency_map, 64).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        let kraft_sum: f64 = test_output
+            .iter()
+            .map(|&(_, length)| (-(length as f64)).exp2())
+            .sum();
+        assert_eq!(1.0, kraft_sum);
+        // Without a binding limit package-merge finds lengths as cheap as the tree's
+        let tree_lengths = crate::encoding_map::EncodingMap::new(&build(&frequency_map).unwrap())
+            .unwrap()
+            .code_lengths();
+        let cost = |lengths: Vec<(u16, usize)>| -> i64 {
+            lengths
+                .iter()
+                .map(|&(symbol, length)| fibonacci[symbol as usize] * length as i64)
+                .sum()
+        };
+        assert_eq!(
+            cost(tree_lengths),
+            cost(
+                unlimited
+                    .iter()
+                    .map(|&(symbol, length)| (symbol as u16, length))
+                    .collect()
+            )
+        );
+        assert!(limited_code_lengths(&frequency_map, 2).is_err());
+        assert_eq!(
+            vec![(b'a', 1)],
+            limited_code_lengths(&FrequencyMap::build(b"aaa"), 1).unwrap()
+        );
+    }
 }
@@ -0,0 +1,116 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+
+/// Most u8s a varint of a u64 takes up
+const MAX_VARINT_LEN: usize = 10;
+
+/// Encodes integers by zig-zag mapping them to unsigned values, writing those as LEB128 varints and Huffman
+/// encoding the varint u8s. Small magnitudes of either sign, such as the deltas of a time series, become short
+/// varints whose u8s repeat often and compress well.
+///
+/// # Arguments
+///
+/// * `values` - The integers to encode
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let values: Vec<i64> = vec![0, -1, 1, -64, 64, i64::MIN, i64::MAX];
+/// let huffman_data = encode_ints(&values).unwrap();
+/// assert_eq!(decode_ints(&huffman_data).unwrap(), values);
+/// ```
+pub fn encode_ints(values: &[i64]) -> Result<HuffmanData> {
+    let mut varints = Vec::with_capacity(values.len());
+    for &value in values {
+        write_varint(&mut varints, zigzag(value));
+    }
+    HuffmanData::new(&varints)
+}
+
+/// Decodes a `HuffmanData` produced by `encode_ints` back into integers
+pub fn decode_ints(huffman_data: &HuffmanData) -> Result<Vec<i64>> {
+    let varints = huffman_data.decode()?;
+    let mut values = Vec::with_capacity(varints.len());
+    let mut value: u64 = 0;
+    let mut len = 0;
+    for &byte in &varints {
+        if len == MAX_VARINT_LEN - 1 && byte > 1 {
+            return Err(HuffmanError::DecodeError("Varint overflows 64 bits"));
+        }
+        value |= ((byte & 0x7f) as u64) << (7 * len);
+        len += 1;
+        if byte & 0x80 == 0 {
+            values.push(unzigzag(value));
+            value = 0;
+            len = 0;
+        }
+    }
+    if len > 0 {
+        return Err(HuffmanError::DecodeError(
+            "Data ends part way through a varint",
+        ));
+    }
+    Ok(values)
+}
+
+/// Maps signed integers onto unsigned ones so that small magnitudes stay small: 0, -1, 1, -2 become 0, 1, 2, 3
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Writes `value` 7 bits at a time, least significant group first, setting the high bit on every u8 but the last
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zigzag() {
+        let input_data: [i64; 6] = [0, -1, 1, -2, i64::MAX, i64::MIN];
+        let expected_data: [u64; 6] = [0, 1, 2, 3, u64::MAX - 1, u64::MAX];
+
+        let test_output = input_data.map(zigzag);
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(input_data, test_output.map(unzigzag));
+    }
+
+    #[test]
+    fn test_write_varint() {
+        let mut test_output = Vec::new();
+
+        write_varint(&mut test_output, 1);
+        write_varint(&mut test_output, 300);
+        write_varint(&mut test_output, u64::MAX);
+
+        assert_eq!(
+            vec![0x01, 0xac, 0x02, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            test_output
+        );
+    }
+
+    #[test]
+    fn test_decode_ints_invalid() {
+        // A continuation bit on the last u8
+        let truncated = HuffmanData::new(&[0x01, 0x80]).unwrap();
+        // An 11th varint u8
+        let overflow = HuffmanData::new(&[0xff; 11]).unwrap();
+
+        assert!(decode_ints(&truncated).is_err());
+        assert!(decode_ints(&overflow).is_err());
+    }
+}
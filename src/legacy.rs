@@ -0,0 +1,167 @@
+//! The original string keyed API, kept so data encoded before the container format existed still decodes the same way.
+//!
+//! `legacy::HuffmanData` encodes exactly as the first releases did: padded u8s with no header, and an encoding map of
+//! codes written out as strings of 0s and 1s that has to be stored alongside them. Its output is bit-for-bit what
+//! those releases produced, so it can keep serving existing consumers while they move to the main API, which
+//! `upgrade` helps with.
+
+use crate::data::{Bit, BitVec, BitVector, Padded, PaddedBits, UnPadded, UnPaddedBits};
+use crate::encoding_stats::EncodingStats;
+use crate::error::Result;
+use crate::frequency_map;
+use crate::huffman_tree::{self, Node};
+
+use std::collections::HashMap;
+
+/// Huffman encoded data, as the first releases laid it out
+#[derive(Debug)]
+pub struct HuffmanData {
+    /// The encoded data as padded u8s
+    pub encoded_data: Vec<u8>,
+    /// Encoding map required for decoding the data, every code written out as a string of 0s and 1s
+    pub encoding_map: HashMap<u8, String>,
+    /// Encoding stats for the data
+    pub stats: EncodingStats,
+}
+
+impl HuffmanData {
+    /// Huffman encodes a `Vec<u8>` returning a `HuffmanData` struct
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A reference to `Vec<u8>` containing the data you want to encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = legacy::HuffmanData::new(&data).unwrap();
+    /// assert_eq!(huffman_data.encoding_map[&b' '], "01");
+    /// assert_eq!(huffman_data.decode().unwrap(), data);
+    /// ```
+    pub fn new(data: &[u8]) -> Result<HuffmanData> {
+        let frequency_map = frequency_map::count(data);
+        let huffman_tree: Node = huffman_tree::build(&frequency_map)?;
+        let mut codes: HashMap<u8, BitVec> = HashMap::new();
+        build_codes(&huffman_tree, &mut codes, &BitVec::new());
+
+        let mut encoded_data = UnPaddedBits::new();
+        for byte in data {
+            if let Some(code) = codes.get(byte) {
+                encoded_data.extend_from_slice(code);
+            }
+        }
+        let payload_bits = encoded_data.len() as u64;
+        let encoded_data: PaddedBits = encoded_data.pad();
+        let encoded_data = encoded_data.to_vec_u8()?;
+        let stats = EncodingStats::new(data, &encoded_data, payload_bits);
+
+        Ok(HuffmanData {
+            encoded_data,
+            encoding_map: codes
+                .iter()
+                .map(|(&byte, code)| (byte, code.to_string()))
+                .collect(),
+            stats,
+        })
+    }
+
+    /// Huffman decodes a `HuffmanData` struct and returns a decoded `Vec<u8>`.
+    /// Bits left over that do not form a code are dropped, as the first releases did
+    pub fn decode(&self) -> Result<Vec<u8>> {
+        let encoded_data: UnPaddedBits = PaddedBits::from_vec_u8(&self.encoded_data).unpad();
+        let inverse_map: HashMap<BitVec, u8> = self
+            .encoding_map
+            .iter()
+            .map(|(&byte, code)| (BitVec::from_string(code), byte))
+            .collect();
+        let min_len = inverse_map.keys().map(Vec::len).min().unwrap_or(0);
+
+        let mut data: Vec<u8> = Vec::with_capacity(encoded_data.len());
+        let mut code = BitVec::new();
+        for &code_bit in encoded_data.iter() {
+            code.push(code_bit);
+            if code.len() < min_len {
+                continue;
+            }
+            if let Some(&byte) = inverse_map.get(&code) {
+                code.clear();
+                data.push(byte);
+            }
+        }
+        Ok(data)
+    }
+
+    /// Decodes the data and encodes it again with the main API, for moving stored data off the legacy layout
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = legacy::HuffmanData::new(&data).unwrap().upgrade().unwrap();
+    /// assert_eq!(decode(&huffman_data.to_bytes().unwrap()).unwrap(), data);
+    /// ```
+    pub fn upgrade(&self) -> Result<crate::HuffmanData> {
+        crate::HuffmanData::new(&self.decode()?)
+    }
+}
+
+/// Walks the tree collecting the code of every u8, left edges being 0s and right edges 1s
+fn build_codes(node: &Node, codes: &mut HashMap<u8, BitVec>, code: &BitVec) {
+    match node.value {
+        Some(value) => {
+            codes.insert(value, code.clone());
+        }
+        None => {
+            for (child, bit) in [(&node.left, 0 as Bit), (&node.right, 1)] {
+                if let Some(child) = child {
+                    let mut code = code.clone();
+                    code.push(bit);
+                    build_codes(child, codes, &code);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_huffman_data() {
+        // Encoded by the first release
+        let input_data = "My super test string".to_string().into_bytes();
+        let expected_data = vec![182, 188, 239, 160, 190, 196, 223, 148, 209, 87];
+        let expected_map: HashMap<u8, String> = [
+            (b'M', "0110"),
+            (b'g', "0111"),
+            (b' ', "111"),
+            (b'y', "1100"),
+            (b'u', "11011"),
+            (b'p', "11010"),
+            (b'e', "000"),
+            (b'n', "0101"),
+            (b't', "101"),
+            (b'r', "001"),
+            (b'i', "0100"),
+            (b's', "100"),
+        ]
+        .iter()
+        .map(|(k, v)| (*k, v.to_string()))
+        .collect();
+
+        let test_output = HuffmanData::new(&input_data).unwrap();
+
+        assert_eq!(expected_data, test_output.encoded_data);
+        assert_eq!(expected_map, test_output.encoding_map);
+        assert_eq!(input_data, test_output.decode().unwrap());
+        assert_eq!(input_data, test_output.upgrade().unwrap().decode().unwrap());
+    }
+}
@@ -1,11 +1,175 @@
-mod data;
-mod encoding_map;
-mod encoding_stats;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// The heap-free decode path, all that is built without the `std` feature
+mod const_table;
 mod error;
-mod frequency_map;
-mod huffman;
-mod huffman_tree;
+mod fixed_buffer;
 
-pub use encoding_stats::EncodingStats;
+pub use const_table::{ConstDecodeTable, MAX_CONST_CODE_LEN};
 pub use error::HuffmanError;
-pub use huffman::HuffmanData;
+pub use fixed_buffer::FixedBuffer;
+
+/// Declares every item that needs `std`, which is everything but the heap-free decode path
+macro_rules! with_std {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "std")]
+            $item
+        )*
+    };
+}
+
+with_std! {
+    mod adaptive;
+    mod archive;
+    mod archive_editor;
+    #[cfg(feature = "tokio")]
+    mod async_io;
+    mod block_encoder;
+    mod block_sizer;
+    mod block_type;
+    mod blocks;
+    mod capabilities;
+    mod code_table;
+    mod compat;
+    mod container;
+    #[cfg(feature = "test-vectors")]
+    mod container_vectors;
+    #[cfg(feature = "test-utils")]
+    mod corrupt;
+    mod coverage;
+    mod crc32;
+    #[cfg(feature = "crypto")]
+    mod crypto;
+    mod data;
+    #[cfg(feature = "test-utils")]
+    mod datagen;
+    pub mod debug;
+    #[cfg(feature = "futures")]
+    mod decode_stream;
+    mod decoder;
+    mod decoder_state;
+    mod dictionary;
+    mod dictionary_manager;
+    #[cfg(feature = "digest")]
+    mod digest;
+    mod encoder;
+    mod encoding_map;
+    #[cfg(feature = "serde")]
+    mod encoding_map_serde;
+    mod encoding_stats;
+    mod format_v0;
+    mod frame;
+    mod frequency_accumulator;
+    mod frequency_map;
+    pub mod fs;
+    mod hooks;
+    mod huffman;
+    mod huffman_tree;
+    mod ints;
+    #[cfg(feature = "legacy")]
+    pub mod legacy;
+    #[cfg(feature = "cache")]
+    mod map_cache;
+    mod map_diagnostics;
+    mod mapped_dictionary;
+    mod members;
+    mod metadata;
+    mod options;
+    mod packing;
+    #[cfg(feature = "rayon")]
+    mod parallel_encoder;
+    #[cfg(feature = "plugin")]
+    mod plugin;
+    mod preview;
+    mod qpack;
+    mod reader;
+    mod record_file;
+    mod rle;
+    mod runtime_stats;
+    mod segment_router;
+    pub mod spec;
+    mod splice;
+    mod stream;
+    mod table_decoder;
+    mod transform;
+    mod utf8;
+    mod vectors;
+    #[cfg(feature = "simd")]
+    mod word_pack;
+    mod writer;
+
+    pub use adaptive::{adaptive_decode, adaptive_encode};
+    pub use archive::HuffmanArchive;
+    pub use archive_editor::{ArchiveEditor, Patch};
+    #[cfg(feature = "tokio")]
+    pub use async_io::{AsyncHuffmanReader, AsyncHuffmanWriter};
+    pub use block_encoder::encode_blocks;
+    pub use block_sizer::adaptive_block_sizes;
+    pub use block_type::BlockType;
+    pub use blocks::HuffmanBlocks;
+    pub use capabilities::{capabilities, Capabilities};
+    pub use compat::{decode_huffman_compress, decode_huffman_compress_with, encoding_map_from_book};
+    pub use container::{decode, encode, encode_with, MAX_PAYLOAD_SIZE};
+    #[cfg(feature = "test-vectors")]
+    pub use container_vectors::{
+        container_vectors, gen_container_vectors, ContainerVector, LayoutField,
+    };
+    #[cfg(feature = "test-utils")]
+    pub use corrupt::{assert_decode_fails, corrupt, decode_without_panic, CorruptionKind};
+    pub use coverage::CoverageReport;
+    #[cfg(feature = "crypto")]
+    pub use crypto::{decode_encrypted, encode_encrypted, KEY_SIZE, NONCE_SIZE};
+    #[cfg(feature = "test-utils")]
+    pub use datagen::{DataGen, Distribution};
+    pub use decoder::HuffmanDecoder;
+    pub use decoder_state::DecoderState;
+    pub use dictionary::HuffmanDictionary;
+    pub use dictionary_manager::DictionaryManager;
+    #[cfg(feature = "digest")]
+    pub use digest::{content_digest, DIGEST_SIZE};
+    pub use encoder::HuffmanEncoder;
+    pub use encoding_map::EncodingMap;
+    #[cfg(feature = "serde")]
+    pub use encoding_map_serde::full_codes;
+    pub use encoding_stats::EncodingStats;
+    pub use format_v0::FormatV0;
+    pub use frame::{FrameReader, FrameWriter, DEFAULT_MAX_FRAME_SIZE};
+    pub use frequency_accumulator::FrequencyAccumulator;
+    pub use frequency_map::{FrequencyMap, FrequencyMapping};
+    pub use hooks::EncodeHook;
+    pub use huffman::HuffmanData;
+    pub use huffman_tree::{merge_steps, MergeNode, MergeStep};
+    pub use ints::{decode_ints, encode_ints};
+    #[cfg(feature = "cache")]
+    pub use map_cache::EncodingMapCache;
+    pub use map_diagnostics::MapDiagnostics;
+    pub use mapped_dictionary::{MappedDictionary, MAPPED_DICTIONARY_SIZE};
+    pub use members::decode_all_members;
+    pub use metadata::Metadata;
+    #[cfg(feature = "rayon")]
+    pub use options::ParallelOptions;
+    pub use options::{
+        BlockOptions, BlockSizerOptions, ContainerOptions, DecodeOptions, HuffmanOptions, MapForm,
+        SegmentRouterOptions, MAX_STREAMS,
+    };
+    pub use packing::Packing;
+    #[cfg(feature = "rayon")]
+    pub use parallel_encoder::encode_parallel;
+    #[cfg(feature = "plugin")]
+    pub use plugin::{
+        PluginBuffer, PluginFilter, PluginTransform, TransformVTable, PLUGIN_ABI_VERSION,
+    };
+    pub use preview::{preview_codes, CodePreview, PREVIEW_SAMPLE_SIZE};
+    pub use qpack::{qpack_decode, qpack_encode, qpack_encoding_map, QPACK_HUFFMAN_CODES};
+    pub use reader::HuffmanReader;
+    pub use record_file::{RecordReader, RecordWriter};
+    pub use runtime_stats::{BenchReport, RuntimeStats};
+    pub use segment_router::route_segments;
+    pub use splice::{join_encoded, merge_blocks, split_blocks, split_encoded};
+    pub use stream::{decode_from_buf_read, decode_from_reader, encode_to_writer, TwoPassEncoder};
+    pub use transform::{Transform, TransformPipeline};
+    pub use utf8::{decode_utf8, encode_utf8};
+    pub use vectors::{gen_vectors, test_vectors, TestVector};
+    pub use writer::HuffmanWriter;
+}
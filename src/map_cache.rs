@@ -0,0 +1,189 @@
+use crate::encoding_map::EncodingMap;
+use crate::error::Result;
+use crate::frequency_map::{FrequencyMap, FrequencyMapping};
+use crate::huffman::HuffmanData;
+use crate::huffman_tree;
+use crate::options::HuffmanOptions;
+
+use std::collections::HashMap;
+
+struct CacheEntry {
+    /// Kept to tell apart frequency maps whose fingerprints collide
+    frequency_map: FrequencyMap,
+    encoding_map: EncodingMap,
+    last_used: u64,
+}
+
+/// A least recently used cache of the `EncodingMap`s built for frequency maps, keyed by a fingerprint of the
+/// frequencies, so payloads with a distribution seen before skip building a tree.
+/// Evicting scans every entry, which is cheap next to building a tree for the small capacities this is meant for.
+pub struct EncodingMapCache {
+    capacity: usize,
+    entries: HashMap<u64, CacheEntry>,
+    /// Bumped on every lookup, the entry used longest ago is evicted first
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl EncodingMapCache {
+    /// Creates a cache holding up to `capacity` encoding maps, at least one
+    pub fn new(capacity: usize) -> Self {
+        EncodingMapCache {
+            capacity: capacity.max(1),
+            entries: HashMap::with_capacity(capacity.max(1)),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the encoding map built for `frequency_map`, building and caching it when it is not cached yet
+    ///
+    /// # Arguments
+    ///
+    /// * `frequency_map` - The frequencies to get the encoding map for
+    pub fn get_or_build(&mut self, frequency_map: &FrequencyMap) -> Result<EncodingMap> {
+        self.clock += 1;
+        let fingerprint = frequency_fingerprint(frequency_map);
+        if let Some(entry) = self.entries.get_mut(&fingerprint) {
+            if entry.frequency_map == *frequency_map {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                return Ok(entry.encoding_map.clone());
+            }
+        }
+
+        self.misses += 1;
+        let encoding_map = EncodingMap::new(&huffman_tree::build(frequency_map)?)?;
+        if self.entries.len() == self.capacity && !self.entries.contains_key(&fingerprint) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(&fingerprint, _)| fingerprint);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(
+            fingerprint,
+            CacheEntry {
+                frequency_map: frequency_map.clone(),
+                encoding_map: encoding_map.clone(),
+                last_used: self.clock,
+            },
+        );
+        Ok(encoding_map)
+    }
+
+    /// Number of encoding maps cached
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of lookups answered from the cache
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Number of lookups that had to build a tree
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+/// Returns a 64 bit FNV-1a hash of every u8 and its frequency, in u8 order
+fn frequency_fingerprint(frequency_map: &FrequencyMap) -> u64 {
+    let mut frequencies: Vec<(&u8, &i64)> = frequency_map.iter().collect();
+    frequencies.sort();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (&byte, &freq) in frequencies {
+        for byte in [byte].into_iter().chain(freq.to_le_bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+impl HuffmanData {
+    /// Encodes `data` like `with_options`, taking the encoding map from `cache` when data with the same frequencies
+    /// was encoded before
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    /// * `options` - A reference to `HuffmanOptions` controlling the encoding
+    /// * `cache` - The cache to look the encoding map up in and add it to
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let mut cache = EncodingMapCache::new(16);
+    /// let options = HuffmanOptions::default();
+    /// for data in [b"this is a test string!", b"this is a test string!"] {
+    ///     let huffman_data = HuffmanData::with_cache(data, &options, &mut cache).unwrap();
+    ///     assert_eq!(huffman_data.decode().unwrap(), data);
+    /// }
+    /// assert_eq!(cache.hits(), 1);
+    /// ```
+    pub fn with_cache(
+        data: &[u8],
+        options: &HuffmanOptions,
+        cache: &mut EncodingMapCache,
+    ) -> Result<HuffmanData> {
+        let frequency_map = FrequencyMap::build(data);
+        Self::with_map_builder(data, &frequency_map, options, &mut [], |frequency_map| {
+            cache.get_or_build(frequency_map)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_or_build() {
+        let input_data = [
+            FrequencyMap::build(b"aab"),
+            FrequencyMap::build(b"abb"),
+            FrequencyMap::build(b"aab"),
+            FrequencyMap::build(b"abc"),
+            FrequencyMap::build(b"abb"),
+        ];
+        let mut cache = EncodingMapCache::new(2);
+
+        for frequency_map in &input_data {
+            let test_output = cache.get_or_build(frequency_map).unwrap();
+            let expected_data = EncodingMap::new(&huffman_tree::build(frequency_map).unwrap());
+            assert_eq!(expected_data.unwrap(), test_output);
+        }
+
+        // "abc" evicted "abb", used longest ago, so only the second "aab" was a hit
+        assert_eq!(1, cache.hits());
+        assert_eq!(4, cache.misses());
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn test_frequency_fingerprint() {
+        assert_eq!(
+            frequency_fingerprint(&FrequencyMap::build(b"abab")),
+            frequency_fingerprint(&FrequencyMap::build(b"baba"))
+        );
+        assert_ne!(
+            frequency_fingerprint(&FrequencyMap::build(b"aab")),
+            frequency_fingerprint(&FrequencyMap::build(b"abb"))
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::data::BitVec;
+use crate::encoding_map::EncodingMap;
+
+use std::collections::BTreeSet;
+
+/// How far an encoding map is from a complete prefix code, returned by `EncodingMap::diagnostics`.
+/// Hand built and external tables that leave code space unused decode garbage, or fail part way, as soon as the
+/// payload holds a bit pattern no code starts with. Codes sharing a prefix never get this far, loading them fails with
+/// `HuffmanError::PrefixCollision` naming both symbols.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapDiagnostics {
+    /// Sum of 2^-length over every code including the escape, 1 for a complete code and below 1 when code space is
+    /// left unused. A prefix code can never exceed 1
+    pub kraft_sum: f64,
+    /// The shortest bit patterns no code starts with, in order. A decoder meeting one of them has no code to match
+    pub unreachable: Vec<BitVec>,
+    /// Bits per symbol the codes are longer than they need to be, `-log2(kraft_sum)`. Scaling every code
+    /// probability up to fill the unused space would shorten the codes by this much on average
+    pub redundancy: f64,
+}
+
+impl MapDiagnostics {
+    /// True when every bit pattern starts with some code, so any payload decodes
+    pub fn is_complete(&self) -> bool {
+        self.unreachable.is_empty()
+    }
+}
+
+impl EncodingMap {
+    /// Reports the Kraft sum, unreachable bit patterns and redundancy of the codes, including the escape code, to
+    /// find why a hand built or external table decodes incorrectly
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let encoding_map = EncodingMap::from_csv("97,0\n98,10").unwrap();
+    /// let diagnostics = encoding_map.diagnostics();
+    /// assert_eq!(diagnostics.kraft_sum, 0.75);
+    /// assert_eq!(diagnostics.unreachable, vec![vec![1, 1]]);
+    /// assert!(!diagnostics.is_complete());
+    /// ```
+    pub fn diagnostics(&self) -> MapDiagnostics {
+        let codes: BTreeSet<&BitVec> = self
+            .iter()
+            .map(|(_, code)| code)
+            .chain(self.get_escape())
+            .collect();
+        let kraft_sum: f64 = codes.iter().map(|code| (-(code.len() as f64)).exp2()).sum();
+
+        let mut unreachable = Vec::new();
+        if !codes.is_empty() {
+            find_unreachable(&codes, &mut BitVec::new(), &mut unreachable);
+        }
+
+        MapDiagnostics {
+            kraft_sum,
+            unreachable,
+            redundancy: match kraft_sum > 0.0 {
+                true => -kraft_sum.log2(),
+                false => 0.0,
+            },
+        }
+    }
+}
+
+/// Walks the code tree below `prefix`, collecting the branches that no code reaches
+fn find_unreachable(codes: &BTreeSet<&BitVec>, prefix: &mut BitVec, unreachable: &mut Vec<BitVec>) {
+    if codes.contains(prefix) {
+        return;
+    }
+    // Codes sort right after their prefixes, so the first code from `prefix` on is the only one to check
+    match codes.range::<BitVec, _>(&*prefix..).next() {
+        Some(code) if code.starts_with(prefix) => {
+            for bit in [0, 1] {
+                prefix.push(bit);
+                find_unreachable(codes, prefix, unreachable);
+                prefix.pop();
+            }
+        }
+        _ => unreachable.push(prefix.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::BitVector;
+    use crate::huffman::HuffmanData;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_diagnostics() {
+        let complete = HuffmanData::new(&b"this is a test string!".repeat(8))
+            .unwrap()
+            .encoding_map;
+        let map: HashMap<u8, String> = [(b'a', "00"), (b'b', "010"), (b'c', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let input_data = EncodingMap::from(map).unwrap();
+        let expected_data = MapDiagnostics {
+            kraft_sum: 0.875,
+            unreachable: vec![BitVec::from_string("011")],
+            redundancy: -(0.875f64.log2()),
+        };
+
+        let test_output = input_data.diagnostics();
+
+        assert_eq!(expected_data, test_output);
+        assert!(complete.diagnostics().is_complete());
+        assert_eq!(1.0, complete.diagnostics().kraft_sum);
+        assert_eq!(0.0, complete.diagnostics().redundancy);
+        assert!(EncodingMap::default().diagnostics().unreachable.is_empty());
+    }
+}
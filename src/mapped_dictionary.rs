@@ -0,0 +1,258 @@
+use crate::container::{DENSE_PACKING, PADDED_PACKING};
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::packing::Packing;
+
+/// Identifies the bytes written by `HuffmanDictionary::to_mapped_bytes`
+const MAGIC: [u8; 4] = *b"HTTD";
+/// Version 1 has no packing u8, its payloads are always padded
+const PADDED_VERSION: u8 = 1;
+const VERSION: u8 = 2;
+
+/// A prefix code over 256 u8s and the escape has at most 257 leaves, so at most 256 branching nodes
+const NODE_COUNT: usize = 256;
+const NODE_SIZE: usize = 4;
+const NODES_OFFSET: usize = 8;
+
+/// Size of every mapped dictionary, unused nodes are left empty so each node sits at a fixed offset
+pub const MAPPED_DICTIONARY_SIZE: usize = NODES_OFFSET + NODE_COUNT * NODE_SIZE;
+
+/// Child value marking a leaf, the low bits hold the symbol (256 for the escape)
+const LEAF_FLAG: u16 = 0x8000;
+const ESCAPE_LEAF: u16 = LEAF_FLAG | 256;
+/// Child value of a branch no code takes
+const EMPTY_CHILD: u16 = u16::MAX;
+
+/// A `HuffmanDictionary` decoded straight from the bytes of `HuffmanDictionary::to_mapped_bytes`, typically a
+/// memory mapped file, without deserializing them first.
+///
+/// The bytes are laid out as:
+/// * `[u8; 4]` magic `HTTD`
+/// * `u8` version, 2
+/// * `u8` packing of the payloads, 0 for `Packing::Padded` and 1 for `Packing::Dense`, then 2 reserved u8s
+/// * 256 nodes of 4 u8s each, node 0 being the root. A node holds the `u16` little endian child reached by a 0 bit,
+///   then the one reached by a 1 bit. A child is either the index of another node, `0x8000 | symbol` for a leaf
+///   (symbol 256 being the escape) or `0xFFFF` when no code takes that branch
+///
+/// Only the header is checked when loading, nodes are read in place as the encoded data walks them, so keeping many
+/// dictionaries resident costs no more than their pages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MappedDictionary<'a> {
+    bytes: &'a [u8],
+    packing: Packing,
+}
+
+impl<'a> MappedDictionary<'a> {
+    /// Borrows the bytes of a mapped dictionary, checking only its header and size
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Bytes written by `HuffmanDictionary::to_mapped_bytes`, such as a memory mapped file holding them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let samples: [&[u8]; 2] = [b"GET /index.html", b"GET /about.html"];
+    /// let dictionary = HuffmanDictionary::train(&samples).unwrap();
+    /// let encoded_data = dictionary.encode(b"GET /contact.html").unwrap();
+    ///
+    /// // In a service these bytes would come from a memory mapped file
+    /// let mapped_bytes = dictionary.to_mapped_bytes().unwrap();
+    /// let mapped_dictionary = MappedDictionary::new(&mapped_bytes).unwrap();
+    /// assert_eq!(mapped_dictionary.decode(&encoded_data).unwrap(), b"GET /contact.html");
+    /// ```
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() != MAPPED_DICTIONARY_SIZE || bytes[..4] != MAGIC {
+            return Err(HuffmanError::DecodeError("Not a mapped dictionary"));
+        }
+        let packing = match (bytes[4], bytes[5]) {
+            (PADDED_VERSION, _) | (VERSION, PADDED_PACKING) => Packing::Padded,
+            (VERSION, DENSE_PACKING) => Packing::Dense,
+            (VERSION, _) => return Err(HuffmanError::DecodeError("Unknown payload packing")),
+            _ => {
+                return Err(HuffmanError::DecodeError(
+                    "Unsupported mapped dictionary version",
+                ))
+            }
+        };
+        Ok(MappedDictionary { bytes, packing })
+    }
+
+    /// Decodes u8s produced by `HuffmanDictionary::encode` with the dictionary these bytes were written from
+    pub fn decode(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        let mut decoded = Vec::with_capacity(encoded_data.len() * 2);
+        let mut node = 0;
+        // Bits of an escaped literal read so far, `None` outside of a literal
+        let mut literal: Option<(u8, u8)> = None;
+
+        for (index, &byte) in encoded_data.iter().enumerate() {
+            let (byte, count) = match index + 1 == encoded_data.len() {
+                true => self.packing.last_bits(byte),
+                false => (
+                    self.packing.full_bits(byte)?,
+                    self.packing.bits_per_u8(),
+                ),
+            };
+            for shift in (0..count).rev() {
+                let bit = (byte >> shift) & 1;
+                if let Some((read, value)) = literal {
+                    let value = (value << 1) | bit;
+                    literal = match read + 1 {
+                        8 => {
+                            decoded.push(value);
+                            None
+                        }
+                        read => Some((read, value)),
+                    };
+                    continue;
+                }
+
+                match self.child(node, bit) {
+                    ESCAPE_LEAF => {
+                        literal = Some((0, 0));
+                        node = 0;
+                    }
+                    EMPTY_CHILD => {
+                        return Err(HuffmanError::DecodeError("Invalid code in encoded data"))
+                    }
+                    child if child & LEAF_FLAG != 0 && child < ESCAPE_LEAF => {
+                        decoded.push(child as u8);
+                        node = 0;
+                    }
+                    child if (child as usize) < NODE_COUNT => node = child as usize,
+                    _ => return Err(HuffmanError::DecodeError("Invalid mapped dictionary node")),
+                }
+            }
+        }
+        if node != 0 || literal.is_some() {
+            return Err(HuffmanError::DecodeError(
+                "Encoded data ends in an incomplete code",
+            ));
+        }
+        Ok(decoded)
+    }
+
+    fn child(&self, node: usize, bit: u8) -> u16 {
+        let offset = NODES_OFFSET + node * NODE_SIZE + bit as usize * 2;
+        u16::from_le_bytes([self.bytes[offset], self.bytes[offset + 1]])
+    }
+}
+
+/// Lays `encoding_map` and `packing` out as the bytes `MappedDictionary` reads
+pub(crate) fn to_mapped_bytes(encoding_map: &EncodingMap, packing: Packing) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; MAPPED_DICTIONARY_SIZE];
+    bytes[..4].copy_from_slice(&MAGIC);
+    bytes[4] = VERSION;
+    bytes[5] = match packing {
+        Packing::Padded => PADDED_PACKING,
+        Packing::Dense => DENSE_PACKING,
+    };
+    for child in bytes[NODES_OFFSET..].chunks_exact_mut(2) {
+        child.copy_from_slice(&EMPTY_CHILD.to_le_bytes());
+    }
+
+    let codes = encoding_map
+        .iter()
+        .map(|(&symbol, code)| (LEAF_FLAG | symbol as u16, code))
+        .chain(encoding_map.get_escape().map(|code| (ESCAPE_LEAF, code)));
+    let mut node_count = 1;
+    for (leaf, code) in codes {
+        let Some((&last, path)) = code.split_last() else {
+            return Err(HuffmanError::TreeError("Empty code in encoding map"));
+        };
+        let mut node = 0;
+        for &bit in path {
+            let offset = NODES_OFFSET + node * NODE_SIZE + bit as usize * 2;
+            node = match u16::from_le_bytes([bytes[offset], bytes[offset + 1]]) {
+                EMPTY_CHILD if node_count < NODE_COUNT => {
+                    bytes[offset..offset + 2].copy_from_slice(&(node_count as u16).to_le_bytes());
+                    node_count += 1;
+                    node_count - 1
+                }
+                child if child & LEAF_FLAG == 0 && child != EMPTY_CHILD => child as usize,
+                _ => return Err(HuffmanError::TreeError("Encoding map is not a prefix code")),
+            };
+        }
+        let offset = NODES_OFFSET + node * NODE_SIZE + last as usize * 2;
+        bytes[offset..offset + 2].copy_from_slice(&leaf.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{BitVec, BitVector};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_mapped_dictionary() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let encoding_map = EncodingMap::from(map)
+            .unwrap()
+            .with_escape(BitVec::from_string("11"))
+            .unwrap();
+        // "abca" as 0 10 11 01100011 0 split over 2 padded u8s
+        let input_data = [0b1010_1101, 0b1100_0110];
+
+        let mut mapped_bytes = to_mapped_bytes(&encoding_map, Packing::Padded).unwrap();
+        let test_output = MappedDictionary::new(&mapped_bytes)
+            .unwrap()
+            .decode(&input_data)
+            .unwrap();
+
+        assert_eq!(b"abca".to_vec(), test_output);
+        assert!(MappedDictionary::new(&mapped_bytes[1..]).is_err());
+
+        // The same codes packed densely, then an end bit
+        let dense_bytes = to_mapped_bytes(&encoding_map, Packing::Dense).unwrap();
+        let test_output = MappedDictionary::new(&dense_bytes)
+            .unwrap()
+            .decode(&[0b0101_1011, 0b0001_1010])
+            .unwrap();
+        assert_eq!(b"abca".to_vec(), test_output);
+
+        // Version 1 has no packing u8 and is always padded
+        mapped_bytes[4..6].copy_from_slice(&[PADDED_VERSION, DENSE_PACKING]);
+        let test_output = MappedDictionary::new(&mapped_bytes)
+            .unwrap()
+            .decode(&input_data)
+            .unwrap();
+        assert_eq!(b"abca".to_vec(), test_output);
+
+        // Data ending partway through a code or an escaped literal
+        let mapped_dictionary = MappedDictionary::new(&mapped_bytes).unwrap();
+        for input_data in [[0b0000_0101], [0b0011_1011]] {
+            assert!(matches!(
+                mapped_dictionary.decode(&input_data),
+                Err(HuffmanError::DecodeError(
+                    "Encoded data ends in an incomplete code"
+                ))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_mapped_dictionary_invalid_leaf() {
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "1")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let mut mapped_bytes =
+            to_mapped_bytes(&EncodingMap::from(map).unwrap(), Packing::Padded).unwrap();
+        // Point the 0 branch of the root at a leaf past the escape
+        mapped_bytes[NODES_OFFSET..NODES_OFFSET + 2].copy_from_slice(&0x8161u16.to_le_bytes());
+
+        let test_output = MappedDictionary::new(&mapped_bytes)
+            .unwrap()
+            .decode(&[0b0000_0010]);
+
+        assert!(test_output.is_err());
+    }
+}
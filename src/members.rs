@@ -0,0 +1,208 @@
+use crate::block_type::BlockType;
+use crate::container::{in_memory_size, Header};
+use crate::data::{BitVec, UnPaddedBits};
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+
+use std::io::Read;
+
+/// Decodes self-contained encodings appended back to back, as `cat a.htt b.htt` would leave them, returning their
+/// decoded data concatenated in order. Every member is checked like `decode` checks a single one.
+///
+/// A payload runs to the end of its encoding without storing its size, so each member's end is found by decoding
+/// exactly the number of u8s its header gives. The whole input is read into memory first.
+///
+/// # Arguments
+///
+/// * `reader` - One or more encodings, as written by `encode` or `encode_to_writer`
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let mut members = encode(b"this is a test string!").unwrap();
+/// members.extend(encode(b" and another one").unwrap());
+///
+/// let decoded_data = decode_all_members(&mut members.as_slice()).unwrap();
+/// assert_eq!(decoded_data, b"this is a test string! and another one");
+/// ```
+pub fn decode_all_members(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let mut encoded_data = Vec::new();
+    reader.read_to_end(&mut encoded_data)?;
+
+    let mut rest = encoded_data.as_slice();
+    let mut data = Vec::new();
+    loop {
+        let header = Header::read_from(&mut rest)?;
+        let len = payload_len(&header, rest)?;
+        let (payload, remainder) = rest.split_at(len);
+        data.extend(HuffmanData::from_header(header, payload.to_vec())?.decode_checked()?);
+        rest = remainder;
+        if rest.is_empty() {
+            return Ok(data);
+        }
+    }
+}
+
+/// Returns how many of the u8s following `header` belong to its payload
+fn payload_len(header: &Header, rest: &[u8]) -> Result<usize> {
+    let data_len = in_memory_size(header.data_len)?;
+    let len = match header.block_type {
+        BlockType::Stored => data_len,
+        BlockType::Rle => rle_len(data_len, rest)?,
+        BlockType::Huffman => {
+            let leading: usize = header.stream_sizes.iter().sum();
+            let last_stream = rest
+                .get(leading..)
+                .ok_or(HuffmanError::DecodeError("Member payload is truncated"))?;
+            // Symbols are dealt round robin, so the last stream holds one in every `streams`
+            let symbols = data_len / (header.stream_sizes.len() + 1);
+            leading + stream_len(last_stream, symbols, &header.encoding_map, header.packing)?
+        }
+    };
+    match len <= rest.len() {
+        true => Ok(len),
+        false => Err(HuffmanError::DecodeError("Member payload is truncated")),
+    }
+}
+
+/// Returns how many run-length pairs it takes to add up to `data_len` u8s, times 2
+fn rle_len(data_len: usize, pairs: &[u8]) -> Result<usize> {
+    let mut decoded = 0;
+    for (index, pair) in pairs.chunks_exact(2).enumerate() {
+        if decoded == data_len {
+            return Ok(index * 2);
+        }
+        decoded += pair[0] as usize;
+    }
+    match decoded == data_len {
+        true => Ok(pairs.len() / 2 * 2),
+        false => Err(HuffmanError::DecodeError("Member payload is truncated")),
+    }
+}
+
+/// Returns how many u8s the stream at the start of `packed` takes up, by decoding `symbols` symbols and finding where
+/// the packing ends them
+fn stream_len(
+    packed: &[u8],
+    symbols: usize,
+    encoding_map: &EncodingMap,
+    packing: Packing,
+) -> Result<usize> {
+    // No stream can hold more bits than every symbol escaped, which bounds how much of `packed` is worth unpacking
+    let max_bits = symbols * (encoding_map.get_longest_code() + 8);
+    let window = &packed[..packed
+        .len()
+        .min(max_bits / packing.bits_per_u8() as usize + 2)];
+
+    // Only the last u8 of a padded stream lacks the marker bit, so the u8s ahead of it are unpacked whole
+    let last = match packing {
+        Packing::Padded => window.iter().position(|&byte| byte & 0x80 == 0),
+        Packing::Dense => None,
+    };
+    let full = last.unwrap_or(window.len());
+    let mut bits = UnPaddedBits::with_capacity(window.len() * 8);
+    for &byte in &window[..full] {
+        let byte = packing.full_bits(byte)?;
+        let count = packing.bits_per_u8();
+        bits.extend((0..count).rev().map(|shift| (byte >> shift) & 1));
+    }
+    if let Some(last) = last {
+        let (byte, count) = packing.last_bits(window[last]);
+        bits.extend((0..count).rev().map(|shift| (byte >> shift) & 1));
+    }
+
+    DecodeOptions::default().check_run(symbols, bits.len())?;
+    let mut cursor = 0;
+    let code_lens = (
+        encoding_map.get_shortest_code(),
+        encoding_map.get_longest_code(),
+    );
+    let mut code = BitVec::with_capacity(code_lens.1);
+    for _ in 0..symbols {
+        if HuffmanData::next_symbol(&bits, &mut cursor, &mut code, code_lens, encoding_map)?.is_none()
+        {
+            return Err(HuffmanError::DecodeError("Member payload is truncated"));
+        }
+    }
+
+    match packing {
+        // The end bit follows the last code, then zeros up to the u8 boundary
+        Packing::Dense => {
+            let end = cursor / 8 + 1;
+            match window.get(end - 1) {
+                Some(&byte) if byte << (cursor % 8) == 0x80 => Ok(end),
+                Some(_) => Err(HuffmanError::DecodeError(
+                    "Member payload lacks its end bit",
+                )),
+                None => Err(HuffmanError::DecodeError("Member payload is truncated")),
+            }
+        }
+        // An empty stream is a lone marker, a stream ending on a u8 boundary ends with a full u8 and any other in a
+        // last u8 holding just the remaining bits
+        Packing::Padded => match (cursor, last) {
+            (0, _) => Ok(1),
+            (cursor, _) if cursor <= full * 7 && cursor % 7 == 0 => Ok(cursor / 7),
+            (cursor, Some(last)) if cursor == bits.len() => Ok(last + 1),
+            _ => Err(HuffmanError::DecodeError(
+                "Member payload does not end on its last u8",
+            )),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode;
+    use crate::options::HuffmanOptions;
+    use crate::stream::encode_to_writer;
+
+    #[test]
+    fn test_decode_all_members() {
+        let text: Vec<u8> = Vec::from("this is a test string!");
+        let mut input_data = Vec::new();
+        let mut expected_data = Vec::new();
+        let mut add_member = |data: &[u8], member: Vec<u8>| {
+            expected_data.extend_from_slice(data);
+            input_data.extend(member);
+        };
+
+        // Every block type, packing and stream count, with payloads ending on and off a u8 boundary
+        let options = [
+            HuffmanOptions::default(),
+            HuffmanOptions {
+                packing: Packing::Padded,
+                ..Default::default()
+            },
+            HuffmanOptions {
+                streams: 3,
+                ..Default::default()
+            },
+        ];
+        for len in [0, 1, 2].into_iter().chain(200..216) {
+            let data = text.repeat(10)[..len].to_vec();
+            for options in &options {
+                let huffman_data = HuffmanData::with_options(&data, options).unwrap();
+                add_member(&data, huffman_data.to_bytes().unwrap());
+            }
+        }
+        let runs = [b'a'; 600];
+        add_member(&runs, encode(&runs).unwrap());
+        let mut streamed = Vec::new();
+        encode_to_writer(&mut std::io::Cursor::new(&text), &mut streamed).unwrap();
+        add_member(&text, streamed);
+
+        let test_output = decode_all_members(&mut input_data.as_slice()).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        input_data.pop();
+        assert!(decode_all_members(&mut input_data.as_slice()).is_err());
+        assert!(decode_all_members(&mut [].as_slice()).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+/// Key/value attributes a self-contained encoding carries alongside the data, such as the name and modification time
+/// of the file it was encoded from. Keys are kept in order so the same metadata always serializes the same way.
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let mut huffman_data = HuffmanData::new(&data).unwrap();
+/// huffman_data.metadata.insert(Metadata::FILENAME, "test.txt");
+///
+/// let bytes = huffman_data.to_bytes().unwrap();
+/// let decoded = HuffmanData::from_bytes(&bytes).unwrap();
+/// assert_eq!(decoded.metadata().get(Metadata::FILENAME), Some("test.txt"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    entries: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// Name of the file the data was encoded from
+    pub const FILENAME: &'static str = "filename";
+    /// Modification time of the file the data was encoded from, in seconds since the Unix epoch
+    pub const MTIME: &'static str = "mtime";
+    /// Free form comment
+    pub const COMMENT: &'static str = "comment";
+    /// Comma separated names of the transforms `TransformPipeline` ran the data through, in the order they ran
+    pub const TRANSFORMS: &'static str = "transforms";
+    /// BLAKE3 digest of the decoded data as 64 lowercase hex digits, stored when `HuffmanOptions::digest` asks for it
+    pub const DIGEST: &'static str = "blake3";
+
+    /// Creates empty `Metadata`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `key` to `value`, returning the value it replaced
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key, `Metadata::FILENAME`, `Metadata::MTIME`, `Metadata::COMMENT` or one of your own
+    /// * `value` - The value to store under `key`
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.entries.insert(key.into(), value.into())
+    }
+
+    /// Returns the value stored under `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+
+    /// Removes `key`, returning the value stored under it
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.entries.remove(key)
+    }
+
+    /// Iterates over the entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Checks every entry fits the sizes the header stores
+    pub(crate) fn check(&self) -> std::result::Result<(), &'static str> {
+        if self.entries.len() > u16::MAX as usize {
+            return Err("Metadata holds too many entries, at most 65535 fit the header");
+        }
+        if self.entries.keys().any(|key| key.len() > u16::MAX as usize) {
+            return Err("Metadata key is too long, keys are limited to 65535 u8s");
+        }
+        if self
+            .entries
+            .values()
+            .any(|value| value.len() > u32::MAX as usize)
+        {
+            return Err("Metadata value is too long, values are limited to u32::MAX u8s");
+        }
+        Ok(())
+    }
+
+    /// Appends the entries as a `u16` little endian number of entries, then per entry a `u16` little endian key
+    /// length, the UTF-8 key, a `u32` little endian value length and the UTF-8 value
+    pub(crate) fn write_to(&self, header: &mut Vec<u8>) {
+        header.extend_from_slice(&(self.entries.len() as u16).to_le_bytes());
+        for (key, value) in &self.entries {
+            header.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            header.extend_from_slice(key.as_bytes());
+            header.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            header.extend_from_slice(value.as_bytes());
+        }
+    }
+
+    /// Reads entries written by `write_to`
+    pub(crate) fn read_from(reader: &mut impl Read) -> Result<Metadata> {
+        let mut metadata = Metadata::new();
+        let count = u16::from_le_bytes(read_array(reader)?);
+        for _ in 0..count {
+            let key_len = u16::from_le_bytes(read_array(reader)?) as u64;
+            let key = read_string(reader, key_len)?;
+            let value_len = u32::from_le_bytes(read_array(reader)?) as u64;
+            let value = read_string(reader, value_len)?;
+            if metadata.entries.insert(key, value).is_some() {
+                return Err(HuffmanError::DecodeError("Metadata key appears twice"));
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+impl HuffmanData {
+    /// Returns the key/value attributes stored alongside the data, read back by `from_bytes`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let mut huffman_data = HuffmanData::new(&data).unwrap();
+    /// huffman_data.metadata.insert(Metadata::COMMENT, "a test string");
+    ///
+    /// let decoded = HuffmanData::from_bytes(&huffman_data.to_bytes().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     decoded.metadata().iter().collect::<Vec<_>>(),
+    ///     [(Metadata::COMMENT, "a test string")]
+    /// );
+    /// ```
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Reads `len` u8s of UTF-8, without trusting `len` for the allocation so truncated data fails rather than allocating
+fn read_string(reader: &mut impl Read, len: u64) -> Result<String> {
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(HuffmanError::DecodeError("Metadata is truncated"));
+    }
+    String::from_utf8(bytes).map_err(|_| HuffmanError::DecodeError("Metadata is not UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut input_data = Metadata::new();
+        input_data.insert(Metadata::MTIME, "1700000000");
+        input_data.insert(Metadata::FILENAME, "a.txt");
+        let expected_data: Vec<u8> = [
+            &[2, 0][..],
+            &[8, 0],
+            b"filename",
+            &[5, 0, 0, 0],
+            b"a.txt",
+            &[5, 0],
+            b"mtime",
+            &[10, 0, 0, 0],
+            b"1700000000",
+        ]
+        .concat();
+
+        let mut test_output = Vec::new();
+        input_data.write_to(&mut test_output);
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(
+            input_data,
+            Metadata::read_from(&mut test_output.as_slice()).unwrap()
+        );
+        assert!(Metadata::read_from(&mut &test_output[..test_output.len() - 1]).is_err());
+    }
+}
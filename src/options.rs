@@ -0,0 +1,371 @@
+use crate::error::{HuffmanError, Result};
+use crate::packing::Packing;
+
+/// Maximum number of interleaved streams supported by the encoder
+pub const MAX_STREAMS: usize = 4;
+
+/// Options controlling how `HuffmanData` encodes its input
+#[derive(Debug, Clone, PartialEq)]
+pub struct HuffmanOptions {
+    /// Number of interleaved bitstreams the data is split into (1 to 4).
+    /// Symbols are dealt round-robin across streams so they can be decoded side by side in one loop.
+    pub streams: usize,
+    /// How payload bits are laid out in the encoded u8s, `Packing::Padded` only to produce data for older decoders
+    pub packing: Packing,
+    /// Split the data into independently coded blocks of at most this many u8s in `encode_with`, each block getting
+    /// codes fitted to its own u8s. `None` keeps the data in a single block. `HuffmanData` is always a single block,
+    /// so `HuffmanData::with_options` rejects it, and the blocks of `encode_blocks` ignore it
+    pub block_size: Option<usize>,
+    /// Store the CRC-32 of the data so decoding can detect corruption, 4 u8s a block
+    pub checksum: bool,
+    /// Replace the codes read off the tree with canonical codes of the same lengths, see `EncodingMap::canonical`.
+    /// The header then stores code lengths alone, and the output no longer depends on the shape of the tree
+    pub canonical: bool,
+    /// Longest code the encoder may assign, in bits (1 to 255). Skewed data otherwise gets codes as long as the number
+    /// of distinct u8s, a limit keeps decode tables to `2^max_code_len` entries. When the tree has longer codes,
+    /// package-merge finds the cheapest codes within the limit and they are written as canonical codes
+    pub max_code_len: Option<usize>,
+    /// Store the `content_digest` of the data in the metadata, read back with `HuffmanData::digest`
+    #[cfg(feature = "digest")]
+    pub digest: bool,
+}
+
+impl Default for HuffmanOptions {
+    fn default() -> Self {
+        HuffmanOptions {
+            streams: 1,
+            packing: Packing::Dense,
+            block_size: None,
+            checksum: true,
+            canonical: false,
+            max_code_len: None,
+            #[cfg(feature = "digest")]
+            digest: false,
+        }
+    }
+}
+
+impl HuffmanOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.streams == 0 || self.streams > MAX_STREAMS {
+            return Err(HuffmanError::OptionsError(
+                "Stream count must be between 1 and 4",
+            ));
+        }
+        if self.block_size == Some(0) {
+            return Err(HuffmanError::OptionsError("Block size must be at least 1"));
+        }
+        if matches!(self.max_code_len, Some(max_code_len) if max_code_len == 0 || max_code_len > 255)
+        {
+            return Err(HuffmanError::OptionsError(
+                "Maximum code length must be between 1 and 255 bits",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling how `encode_blocks` splits data into blocks and encodes them
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockOptions {
+    /// Size of every block but the last, in u8s
+    pub block_size: usize,
+    /// Number of threads blocks are encoded on, at least 1
+    pub threads: usize,
+    /// Keep block boundaries at multiples of `block_size`, so the output is byte-identical whatever `threads` is
+    /// and matches a serial encode. When false the data is split evenly between the threads instead, still in
+    /// blocks of at most `block_size`, which saves headers but makes the output depend on the thread count of the
+    /// machine that encoded it
+    pub deterministic: bool,
+    /// Options every block is encoded with
+    pub huffman: HuffmanOptions,
+}
+
+impl Default for BlockOptions {
+    fn default() -> Self {
+        BlockOptions {
+            block_size: 1024 * 1024,
+            threads: 1,
+            deterministic: true,
+            huffman: HuffmanOptions::default(),
+        }
+    }
+}
+
+impl BlockOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.block_size == 0 {
+            return Err(HuffmanError::OptionsError("Block size must be at least 1"));
+        }
+        if self.threads == 0 {
+            return Err(HuffmanError::OptionsError(
+                "Block encoding needs at least one thread",
+            ));
+        }
+        self.huffman.validate()
+    }
+}
+
+/// Options controlling how `adaptive_block_sizes` splits data into blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSizerOptions {
+    /// Smallest block the sizer starts from, only the last block may be smaller
+    pub min_block_size: usize,
+    /// Largest block the sizer grows to
+    pub max_block_size: usize,
+    /// Fraction of encoded bits a doubled block has to save over its two halves to be kept, e.g. 0.01 for 1%
+    pub min_gain: f64,
+}
+
+impl Default for BlockSizerOptions {
+    fn default() -> Self {
+        BlockSizerOptions {
+            min_block_size: 4 * 1024,
+            max_block_size: 1024 * 1024,
+            min_gain: 0.01,
+        }
+    }
+}
+
+impl BlockSizerOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.min_block_size == 0 || self.min_block_size > self.max_block_size {
+            return Err(HuffmanError::OptionsError(
+                "Block sizes must satisfy 0 < min_block_size <= max_block_size",
+            ));
+        }
+        if !self.min_gain.is_finite() || self.min_gain < 0.0 {
+            return Err(HuffmanError::OptionsError(
+                "Minimum gain must be finite and not negative",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling how `route_segments` tells compressible regions of data from incompressible ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentRouterOptions {
+    /// Size of the windows entropy is measured over, in u8s
+    pub window: usize,
+    /// Entropy in bits per u8, from 0 to 8, at or above which a window is routed to a stored block
+    pub max_entropy: f64,
+    /// Largest block a segment is cut into
+    pub max_block_size: usize,
+    /// Options the Huffman blocks are encoded with
+    pub huffman: HuffmanOptions,
+}
+
+impl Default for SegmentRouterOptions {
+    fn default() -> Self {
+        SegmentRouterOptions {
+            window: 4096,
+            max_entropy: 7.5,
+            max_block_size: 1024 * 1024,
+            huffman: HuffmanOptions::default(),
+        }
+    }
+}
+
+impl SegmentRouterOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.window == 0 || self.max_block_size == 0 {
+            return Err(HuffmanError::OptionsError(
+                "Window and block sizes must be at least 1",
+            ));
+        }
+        if !(0.0..=8.0).contains(&self.max_entropy) {
+            return Err(HuffmanError::OptionsError(
+                "Maximum entropy must be between 0 and 8 bits per u8",
+            ));
+        }
+        self.huffman.validate()
+    }
+}
+
+/// Options controlling how `encode_parallel` splits data across threads
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelOptions {
+    /// Size of the chunks counted and encoded on each thread, rounded up to a multiple of `huffman.streams`
+    pub chunk_size: usize,
+    /// Options the data is encoded with
+    pub huffman: HuffmanOptions,
+}
+
+#[cfg(feature = "rayon")]
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            chunk_size: 1024 * 1024,
+            huffman: HuffmanOptions::default(),
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl ParallelOptions {
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(HuffmanError::OptionsError("Chunk size must be at least 1"));
+        }
+        self.huffman.validate()
+    }
+}
+
+/// How the encoding map is stored in the header of a self-contained encoding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapForm {
+    /// Code lengths when the codes are canonical, as they are the smallest form, and every code in full otherwise
+    #[default]
+    Compact,
+    /// The code tree in pre-order, 0 for a branch and 1 for a leaf followed by its u8, keeping its exact shape
+    Tree,
+}
+
+/// Options controlling how `HuffmanData::to_bytes_with` lays out a self-contained encoding
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerOptions {
+    /// How the encoding map is stored
+    pub map_form: MapForm,
+}
+
+/// Options controlling how `HuffmanData` is decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeOptions {
+    /// Reject structurally inconsistent data (leftover bits, unbalanced streams, stray maps) instead of decoding what can be decoded
+    pub strict: bool,
+    /// Maximum number of bytes the decoder may produce before failing
+    pub max_output: Option<u64>,
+    /// Maximum number of bytes the decoder may produce per encoded byte before failing.
+    /// Measured against the encoded data itself rather than the decoded size the header claims, so it bounds the
+    /// memory a decompression bomb can claim even when that size is forged or absent
+    pub max_growth: Option<u64>,
+    /// Compare the decoded data against the CRC-32 the encoding carries, failing with `ChecksumMismatch` when they
+    /// differ. Encodings written without a checksum decode unchecked either way
+    pub verify_checksum: bool,
+    /// Maximum number of symbols the decoder may produce per input bit.
+    /// A sanity cap so a malformed map (e.g. one holding an empty code) cannot spin producing unbounded output
+    pub max_symbols_per_bit: Option<u32>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            strict: false,
+            max_output: None,
+            max_growth: None,
+            verify_checksum: true,
+            max_symbols_per_bit: Some(1),
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Fails once `output_size` decoded bytes would exceed `max_output`, or `max_growth` times the `input_size`
+    /// encoded bytes they are decoded from
+    pub(crate) fn check_output(&self, output_size: usize, input_size: usize) -> Result<()> {
+        match self.max_output {
+            Some(max_output) if output_size as u64 > max_output => {
+                return Err(HuffmanError::DecodeError(
+                    "Decoded output exceeds the configured maximum",
+                ))
+            }
+            _ => {}
+        }
+        match self.max_growth {
+            Some(max_growth)
+                if output_size as u64 > (input_size as u64).saturating_mul(max_growth) =>
+            {
+                Err(HuffmanError::DecodeError(
+                    "Decoded output exceeds the configured growth over the encoded size",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails once `symbols` decoded symbols exceed `max_symbols_per_bit` for every one of the `input_bits`
+    pub(crate) fn check_run(&self, symbols: usize, input_bits: usize) -> Result<()> {
+        match self.max_symbols_per_bit {
+            Some(cap) if symbols as u64 > input_bits as u64 * cap as u64 => Err(
+                HuffmanError::DecodeError("Decoded more symbols than the input bits allow"),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_streams() {
+        assert!(HuffmanOptions::default().validate().is_ok());
+        assert!(HuffmanOptions {
+            streams: 4,
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(HuffmanOptions {
+            streams: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(HuffmanOptions {
+            streams: 5,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(HuffmanOptions {
+            block_size: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(HuffmanOptions {
+            max_code_len: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_check_output() {
+        let options = DecodeOptions {
+            max_output: Some(10),
+            ..Default::default()
+        };
+
+        let growth = DecodeOptions {
+            max_growth: Some(4),
+            ..Default::default()
+        };
+
+        assert!(options.check_output(10, 1).is_ok());
+        assert!(options.check_output(11, 100).is_err());
+        assert!(DecodeOptions::default().check_output(usize::MAX, 0).is_ok());
+        assert!(growth.check_output(12, 3).is_ok());
+        assert!(growth.check_output(13, 3).is_err());
+        assert!(growth.check_output(1, 0).is_err());
+    }
+
+    #[test]
+    fn test_check_run() {
+        let options = DecodeOptions::default();
+
+        assert!(options.check_run(8, 8).is_ok());
+        assert!(options.check_run(9, 8).is_err());
+        assert!(DecodeOptions {
+            max_symbols_per_bit: None,
+            ..Default::default()
+        }
+        .check_run(9, 0)
+        .is_ok());
+    }
+}
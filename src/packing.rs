@@ -0,0 +1,131 @@
+use crate::data::{unpadded_len, Padded, PaddedBits, UnPadded, UnPaddedBits};
+use crate::error::{HuffmanError, Result};
+
+/// How the payload bits of a Huffman block are laid out in u8s
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Packing {
+    /// A marker bit in front of every 7 payload bits, the last u8's highest set bit being the marker in front of
+    /// whatever payload bits remain. Wastes 1 bit in every u8, only kept to decode data encoded with it
+    Padded,
+    /// 8 payload bits per u8, most significant first, ended by a single 1 bit and zeros up to the next u8 boundary
+    #[default]
+    Dense,
+}
+
+impl Packing {
+    /// Payload bits held by every u8 but the last
+    pub(crate) fn bits_per_u8(self) -> u32 {
+        match self {
+            Packing::Padded => 7,
+            Packing::Dense => 8,
+        }
+    }
+
+    /// Returns the payload bits of a u8 that is not the last one
+    pub(crate) fn full_bits(self, byte: u8) -> Result<u8> {
+        match self {
+            Packing::Padded if byte & 0x80 == 0 => {
+                Err(HuffmanError::DecodeError("Missing padding marker"))
+            }
+            Packing::Padded => Ok(byte & 0x7f),
+            Packing::Dense => Ok(byte),
+        }
+    }
+
+    /// Returns the payload bits of the last u8, right aligned, along with how many there are
+    pub(crate) fn last_bits(self, byte: u8) -> (u8, u32) {
+        match self {
+            Packing::Padded => {
+                let count = 7 - byte.leading_zeros().min(7);
+                (byte & ((1 << count) - 1), count)
+            }
+            // A u8 without the end bit is malformed, it is read as holding nothing
+            Packing::Dense if byte == 0 => (0, 0),
+            Packing::Dense => {
+                let count = 7 - byte.trailing_zeros();
+                ((byte as u16 >> (8 - count)) as u8, count)
+            }
+        }
+    }
+
+    /// Returns how many payload bits `packed` holds without unpacking it
+    pub(crate) fn payload_len(self, packed: &[u8]) -> u64 {
+        match (self, packed.split_last()) {
+            (Packing::Padded, _) => unpadded_len(packed),
+            (Packing::Dense, Some((&last, rest))) => {
+                rest.len() as u64 * 8 + self.last_bits(last).1 as u64
+            }
+            (Packing::Dense, None) => 0,
+        }
+    }
+
+    /// Lays payload bits out as u8s
+    pub(crate) fn pack(self, bits: UnPaddedBits) -> Result<Vec<u8>> {
+        if self == Packing::Padded {
+            return bits.pad().to_vec_u8();
+        }
+        let mut packed = Vec::with_capacity(bits.len() / 8 + 1);
+        for chunk in bits.chunks(8) {
+            let byte = chunk.iter().fold(0u8, |byte, &bit| (byte << 1) | bit);
+            packed.push(byte << (8 - chunk.len()));
+        }
+        match bits.len() % 8 {
+            0 => packed.push(0x80),
+            used => *packed.last_mut().unwrap() |= 0x80 >> used,
+        }
+        Ok(packed)
+    }
+
+    /// Reads the payload bits back out of u8s written by `pack`
+    pub(crate) fn unpack(self, packed: &[u8]) -> UnPaddedBits {
+        if self == Packing::Padded {
+            return PaddedBits::from_vec_u8(packed).unpad();
+        }
+        let Some((&last, rest)) = packed.split_last() else {
+            return UnPaddedBits::new();
+        };
+        let mut bits = UnPaddedBits::with_capacity(packed.len() * 8);
+        for &byte in rest {
+            bits.extend((0..8).rev().map(|shift| (byte >> shift) & 1));
+        }
+        let (last, count) = self.last_bits(last);
+        bits.extend((0..count).rev().map(|shift| (last >> shift) & 1));
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::BitVector;
+
+    #[test]
+    fn test_dense_pack() {
+        for (input_data, expected_data) in [
+            ("", vec![0b1000_0000]),
+            ("101", vec![0b1011_0000]),
+            ("10110110", vec![0b1011_0110, 0b1000_0000]),
+            ("101101101", vec![0b1011_0110, 0b1100_0000]),
+        ] {
+            let input_data = UnPaddedBits::from_string(input_data);
+
+            let test_output = Packing::Dense.pack(input_data.clone()).unwrap();
+
+            assert_eq!(expected_data, test_output);
+            assert_eq!(input_data, Packing::Dense.unpack(&test_output));
+            assert_eq!(
+                input_data.len() as u64,
+                Packing::Dense.payload_len(&test_output)
+            );
+        }
+    }
+
+    #[test]
+    fn test_last_bits() {
+        assert_eq!((0b101, 3), Packing::Padded.last_bits(0b0000_1101));
+        assert_eq!((0b101, 3), Packing::Dense.last_bits(0b1011_0000));
+        assert_eq!((0, 0), Packing::Dense.last_bits(0b1000_0000));
+        assert_eq!((0b101_1011, 7), Packing::Dense.last_bits(0b1011_0111));
+        assert!(Packing::Padded.full_bits(0b0111_1111).is_err());
+    }
+}
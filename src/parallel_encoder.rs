@@ -0,0 +1,135 @@
+use crate::data::UnPaddedBits;
+use crate::encoding_map::EncodingMap;
+use crate::error::Result;
+use crate::frequency_accumulator::FrequencyAccumulator;
+use crate::huffman::HuffmanData;
+use crate::huffman_tree;
+use crate::options::ParallelOptions;
+
+use rayon::prelude::*;
+
+/// Huffman encodes `data` on the rayon thread pool, giving the same `HuffmanData` as `HuffmanData::with_options`.
+///
+/// The u8s of every chunk are counted in parallel and the counts summed into one frequency map, then every chunk
+/// is encoded with the resulting map in parallel and the bits of the chunks are concatenated in order. Unlike
+/// `encode_blocks` the output is a single payload with a single map, only the work is split.
+///
+/// # Arguments
+///
+/// * `data` - The data to encode
+/// * `options` - A reference to `ParallelOptions` setting the chunk size and how the data is encoded
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = b"this is a test string!".repeat(1000);
+/// let options = ParallelOptions { chunk_size: 4096, ..Default::default() };
+/// let huffman_data = encode_parallel(&data, &options).unwrap();
+/// assert_eq!(huffman_data.encoded_data, HuffmanData::new(&data).unwrap().encoded_data);
+/// assert_eq!(huffman_data.decode().unwrap(), data);
+/// ```
+pub fn encode_parallel(data: &[u8], options: &ParallelOptions) -> Result<HuffmanData> {
+    options.validate()?;
+    let huffman = &options.huffman;
+    if data.is_empty() {
+        return HuffmanData::with_options(data, huffman);
+    }
+    // Chunks holding a whole number of rounds keep every u8 in the stream it is dealt to in a serial encode
+    let chunk_size = options.chunk_size.div_ceil(huffman.streams) * huffman.streams;
+
+    let counts = data
+        .par_chunks(chunk_size)
+        .map(|chunk| {
+            let mut counts = FrequencyAccumulator::new();
+            counts.update(chunk);
+            counts
+        })
+        .reduce(FrequencyAccumulator::new, |mut counts, other| {
+            counts.merge(&other);
+            counts
+        });
+    let frequency_map = counts.frequency_map();
+    let encoding_map = HuffmanData::fit_map(
+        EncodingMap::new(&huffman_tree::build(&frequency_map)?)?,
+        &frequency_map,
+        huffman,
+    )?;
+
+    let chunk_bits: Vec<Vec<UnPaddedBits>> = data
+        .par_chunks(chunk_size)
+        .map(|chunk| {
+            (0..huffman.streams)
+                .map(|stream| {
+                    let stream_data: Vec<u8> = chunk
+                        .iter()
+                        .skip(stream)
+                        .step_by(huffman.streams)
+                        .copied()
+                        .collect();
+                    HuffmanData::huffman_encode(&stream_data, &encoding_map)
+                })
+                .collect()
+        })
+        .collect();
+    let mut streams = Vec::with_capacity(huffman.streams);
+    for stream in 0..huffman.streams {
+        let mut bits = UnPaddedBits::new();
+        for chunk in &chunk_bits {
+            bits.extend_from_slice(&chunk[stream]);
+        }
+        let payload_bits = bits.len() as u64;
+        streams.push((huffman.packing.pack(bits)?, payload_bits));
+    }
+
+    let huffman_data = HuffmanData::from_packed_streams(data, encoding_map, streams, huffman);
+    Ok(huffman_data.with_checks(data, huffman))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::HuffmanOptions;
+    use crate::packing::Packing;
+
+    #[test]
+    fn test_encode_parallel() {
+        let mut input_data = b"this is a test string!".repeat(300);
+        input_data.extend((0..=255).cycle().take(3001));
+
+        for (chunk_size, streams, packing) in [
+            (1000, 1, Packing::Dense),
+            (333, 3, Packing::Dense),
+            (1, 4, Packing::Padded),
+            (1 << 20, 2, Packing::Dense),
+        ] {
+            let huffman = HuffmanOptions {
+                streams,
+                packing,
+                ..Default::default()
+            };
+            let expected_data = HuffmanData::with_options(&input_data, &huffman).unwrap();
+
+            let test_output = encode_parallel(
+                &input_data,
+                &ParallelOptions {
+                    chunk_size,
+                    huffman,
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                expected_data.to_bytes().unwrap(),
+                test_output.to_bytes().unwrap()
+            );
+        }
+        assert!(encode_parallel(&[], &ParallelOptions::default())
+            .unwrap()
+            .decode()
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::error::{HuffmanError, Result};
+use crate::transform::{Transform, TransformPipeline};
+
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+
+/// Version of the `TransformVTable` layout, bumped whenever it changes
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Bytes a plugin hands back from a filter, allocated by the plugin and returned to it through `free_buffer`
+#[repr(C)]
+#[derive(Debug)]
+pub struct PluginBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Runs a plugin filter over `input_len` u8s at `input`, filling `output` and returning 0 on success.
+/// On failure the plugin returns any other value and leaves `output` empty or allocated for `free_buffer`
+pub type PluginFilter = unsafe extern "C" fn(
+    state: *mut c_void,
+    input: *const u8,
+    input_len: usize,
+    output: *mut PluginBuffer,
+) -> i32;
+
+/// The function table a plugin fills in to take part in a `TransformPipeline`, matching this C declaration:
+///
+/// ```c
+/// typedef struct { uint8_t *data; size_t len; } PluginBuffer;
+/// typedef int32_t (*PluginFilter)(void *state, const uint8_t *input, size_t input_len, PluginBuffer *output);
+///
+/// typedef struct {
+///     uint32_t abi_version;  /* PLUGIN_ABI_VERSION */
+///     const char *name;      /* NUL terminated UTF-8, copied at registration */
+///     void *state;           /* passed back to every function */
+///     PluginFilter forward;
+///     PluginFilter inverse;
+///     void (*free_buffer)(void *state, PluginBuffer buffer);
+///     void (*release)(void *state);  /* may be NULL, called once the transform is dropped */
+/// } TransformVTable;
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TransformVTable {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub state: *mut c_void,
+    pub forward: PluginFilter,
+    pub inverse: PluginFilter,
+    pub free_buffer: unsafe extern "C" fn(state: *mut c_void, buffer: PluginBuffer),
+    pub release: Option<unsafe extern "C" fn(state: *mut c_void)>,
+}
+
+/// A `Transform` implemented behind a `TransformVTable`, by a closed-source library or another language
+#[derive(Debug)]
+pub struct PluginTransform {
+    name: String,
+    vtable: TransformVTable,
+}
+
+impl PluginTransform {
+    /// Wraps a plugin's function table, copying its name
+    ///
+    /// # Arguments
+    ///
+    /// * `vtable` - The plugin's function table, its `abi_version` set to `PLUGIN_ABI_VERSION`
+    ///
+    /// # Safety
+    ///
+    /// `vtable.name` has to point to a NUL terminated string, and every function has to behave as `TransformVTable`
+    /// documents for as long as the `PluginTransform` lives, with `vtable.state` valid until `release` is called
+    pub unsafe fn new(vtable: TransformVTable) -> Result<PluginTransform> {
+        if vtable.abi_version != PLUGIN_ABI_VERSION {
+            return Err(HuffmanError::TransformError(
+                "Plugin was built against another plugin ABI version",
+            ));
+        }
+        if vtable.name.is_null() {
+            return Err(HuffmanError::TransformError("Plugin has no name"));
+        }
+        let name = CStr::from_ptr(vtable.name)
+            .to_str()
+            .map_err(|_| HuffmanError::TransformError("Plugin name is not UTF-8"))?
+            .to_string();
+        Ok(PluginTransform { name, vtable })
+    }
+
+    fn filter(&self, filter: PluginFilter, data: &[u8]) -> Result<Vec<u8>> {
+        let mut output = PluginBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        // The caller of `new` vouched for the functions and the state
+        let status = unsafe { filter(self.vtable.state, data.as_ptr(), data.len(), &mut output) };
+        let filtered = match output.data.is_null() {
+            true => Vec::new(),
+            false => {
+                let filtered =
+                    unsafe { std::slice::from_raw_parts(output.data, output.len) }.to_vec();
+                unsafe { (self.vtable.free_buffer)(self.vtable.state, output) };
+                filtered
+            }
+        };
+        match status {
+            0 => Ok(filtered),
+            _ => Err(HuffmanError::TransformError("Plugin transform failed")),
+        }
+    }
+}
+
+impl Transform for PluginTransform {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn forward(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.filter(self.vtable.forward, data)
+    }
+
+    fn inverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.filter(self.vtable.inverse, data)
+    }
+}
+
+impl Drop for PluginTransform {
+    fn drop(&mut self) {
+        if let Some(release) = self.vtable.release {
+            unsafe { release(self.vtable.state) };
+        }
+    }
+}
+
+impl TransformPipeline {
+    /// Registers the transform a plugin's function table implements, see `PluginTransform::new`
+    ///
+    /// # Arguments
+    ///
+    /// * `vtable` - The plugin's function table
+    ///
+    /// # Safety
+    ///
+    /// Same as `PluginTransform::new`
+    pub unsafe fn register_plugin(&mut self, vtable: TransformVTable) -> Result<()> {
+        self.register(Box::new(PluginTransform::new(vtable)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+    /// XORs every u8 with the key `state` points to, failing on empty input
+    unsafe extern "C" fn xor(
+        state: *mut c_void,
+        input: *const u8,
+        input_len: usize,
+        output: *mut PluginBuffer,
+    ) -> i32 {
+        if input_len == 0 {
+            return 1;
+        }
+        let key = *(state as *const u8);
+        let input = std::slice::from_raw_parts(input, input_len);
+        let filtered: Box<[u8]> = input.iter().map(|byte| byte ^ key).collect();
+        *output = PluginBuffer {
+            len: filtered.len(),
+            data: Box::into_raw(filtered) as *mut u8,
+        };
+        0
+    }
+
+    unsafe extern "C" fn free_buffer(_state: *mut c_void, buffer: PluginBuffer) {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+
+    unsafe extern "C" fn release(_state: *mut c_void) {
+        RELEASED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_plugin_transform() {
+        let mut key = 0x5au8;
+        let vtable = TransformVTable {
+            abi_version: PLUGIN_ABI_VERSION,
+            name: c"xor".as_ptr(),
+            state: &mut key as *mut u8 as *mut c_void,
+            forward: xor,
+            inverse: xor,
+            free_buffer,
+            release: Some(release),
+        };
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+
+        let mut pipeline = TransformPipeline::new();
+        unsafe { pipeline.register_plugin(vtable) }.unwrap();
+        let test_output = pipeline.encode(&input_data).unwrap();
+
+        assert_eq!(vec!["xor"], pipeline.names());
+        assert_eq!(input_data, pipeline.decode(&test_output).unwrap());
+        assert!(matches!(
+            pipeline.encode(&[]),
+            Err(HuffmanError::TransformError(_))
+        ));
+        drop(pipeline);
+        assert_eq!(1, RELEASED.load(Ordering::SeqCst));
+        let other_version = TransformVTable {
+            abi_version: PLUGIN_ABI_VERSION + 1,
+            release: None,
+            ..vtable
+        };
+        assert!(unsafe { PluginTransform::new(other_version) }.is_err());
+    }
+}
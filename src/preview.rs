@@ -0,0 +1,111 @@
+use crate::data::BitVector;
+use crate::encoding_map::EncodingMap;
+use crate::error::Result;
+use crate::frequency_map;
+use crate::huffman_tree;
+
+/// Inputs longer than this are sampled at an even stride rather than counted in full
+pub const PREVIEW_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// The code a u8 would receive and what it would save, as reported by `preview_codes`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodePreview {
+    /// The u8 being previewed
+    pub byte: u8,
+    /// Estimated number of times the u8 appears in the data
+    pub count: i64,
+    /// The code the u8 would be encoded as
+    pub code: String,
+    /// Estimated bits saved over storing every occurrence as 8 bits, negative when the code is longer than 8 bits
+    pub saved_bits: i64,
+}
+
+/// Returns the `top_n` most frequent u8s in `data` with their would-be codes and estimated savings, most frequent
+/// first, without encoding the data. Counts and savings are estimates once `data` exceeds `PREVIEW_SAMPLE_SIZE`.
+///
+/// # Arguments
+///
+/// * `data` - The data to preview
+/// * `top_n` - How many u8s to report
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data: Vec<u8> = Vec::from("aaaabbc");
+/// let preview = preview_codes(&data, 1).unwrap();
+/// assert_eq!(preview[0].byte, b'a');
+/// assert_eq!(preview[0].code, "1");
+/// assert_eq!(preview[0].saved_bits, 28);
+/// ```
+pub fn preview_codes(data: &[u8], top_n: usize) -> Result<Vec<CodePreview>> {
+    let stride = data.len().div_ceil(PREVIEW_SAMPLE_SIZE).max(1);
+    let sample: Vec<u8> = data.iter().step_by(stride).copied().collect();
+    let frequency_map = frequency_map::count(&sample);
+    let encoding_map = EncodingMap::new(&huffman_tree::build(&frequency_map)?)?;
+
+    let mut counts: Vec<(u8, i64)> = frequency_map.into_iter().collect();
+    counts.sort_by_key(|&(byte, count)| (std::cmp::Reverse(count), byte));
+
+    Ok(counts
+        .into_iter()
+        .take(top_n)
+        .filter_map(|(byte, count)| {
+            let code = encoding_map.get(&byte)?;
+            let count = count * stride as i64;
+            Some(CodePreview {
+                byte,
+                count,
+                code: code.to_string(),
+                saved_bits: count * (8 - code.len() as i64),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_codes() {
+        let input_data: Vec<u8> = Vec::from("aaaabbc");
+        let expected_data = vec![
+            CodePreview {
+                byte: b'a',
+                count: 4,
+                code: "1".to_string(),
+                saved_bits: 28,
+            },
+            CodePreview {
+                byte: b'b',
+                count: 2,
+                code: "01".to_string(),
+                saved_bits: 12,
+            },
+        ];
+
+        let test_output = preview_codes(&input_data, 2).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(3, preview_codes(&input_data, 10).unwrap().len());
+    }
+
+    #[test]
+    fn test_preview_codes_sampled() {
+        let input_data: Vec<u8> = (0..2 * PREVIEW_SAMPLE_SIZE)
+            .map(|i| (i % 3) as u8)
+            .collect();
+
+        let test_output = preview_codes(&input_data, 3).unwrap();
+
+        // Every other u8 is sampled and each sampled count stands for two u8s
+        assert_eq!(3, test_output.len());
+        assert_eq!(
+            input_data.len() as i64,
+            test_output.iter().map(|preview| preview.count).sum::<i64>()
+        );
+    }
+}
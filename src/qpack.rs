@@ -0,0 +1,430 @@
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Symbol index of the end-of-string code, which must never appear in an encoded string
+const EOS: usize = 256;
+
+/// Length of the longest code in the table
+const MAX_CODE_LEN: usize = 30;
+
+/// The static Huffman code shared by QPACK (RFC 9204) and HPACK (RFC 7541 Appendix B) as `(code, code length)`.
+/// Entries 0 to 255 are the u8 values and entry 256 is EOS. The code is canonical.
+pub const QPACK_HUFFMAN_CODES: [(u32, u8); 257] = [
+    (0x1ff8, 13),
+    (0x7fffd8, 23),
+    (0xfffffe2, 28),
+    (0xfffffe3, 28),
+    (0xfffffe4, 28),
+    (0xfffffe5, 28),
+    (0xfffffe6, 28),
+    (0xfffffe7, 28),
+    (0xfffffe8, 28),
+    (0xffffea, 24),
+    (0x3ffffffc, 30),
+    (0xfffffe9, 28),
+    (0xfffffea, 28),
+    (0x3ffffffd, 30),
+    (0xfffffeb, 28),
+    (0xfffffec, 28),
+    (0xfffffed, 28),
+    (0xfffffee, 28),
+    (0xfffffef, 28),
+    (0xffffff0, 28),
+    (0xffffff1, 28),
+    (0xffffff2, 28),
+    (0x3ffffffe, 30),
+    (0xffffff3, 28),
+    (0xffffff4, 28),
+    (0xffffff5, 28),
+    (0xffffff6, 28),
+    (0xffffff7, 28),
+    (0xffffff8, 28),
+    (0xffffff9, 28),
+    (0xffffffa, 28),
+    (0xffffffb, 28),
+    (0x14, 6),
+    (0x3f8, 10),
+    (0x3f9, 10),
+    (0xffa, 12),
+    (0x1ff9, 13),
+    (0x15, 6),
+    (0xf8, 8),
+    (0x7fa, 11),
+    (0x3fa, 10),
+    (0x3fb, 10),
+    (0xf9, 8),
+    (0x7fb, 11),
+    (0xfa, 8),
+    (0x16, 6),
+    (0x17, 6),
+    (0x18, 6),
+    (0x0, 5),
+    (0x1, 5),
+    (0x2, 5),
+    (0x19, 6),
+    (0x1a, 6),
+    (0x1b, 6),
+    (0x1c, 6),
+    (0x1d, 6),
+    (0x1e, 6),
+    (0x1f, 6),
+    (0x5c, 7),
+    (0xfb, 8),
+    (0x7ffc, 15),
+    (0x20, 6),
+    (0xffb, 12),
+    (0x3fc, 10),
+    (0x1ffa, 13),
+    (0x21, 6),
+    (0x5d, 7),
+    (0x5e, 7),
+    (0x5f, 7),
+    (0x60, 7),
+    (0x61, 7),
+    (0x62, 7),
+    (0x63, 7),
+    (0x64, 7),
+    (0x65, 7),
+    (0x66, 7),
+    (0x67, 7),
+    (0x68, 7),
+    (0x69, 7),
+    (0x6a, 7),
+    (0x6b, 7),
+    (0x6c, 7),
+    (0x6d, 7),
+    (0x6e, 7),
+    (0x6f, 7),
+    (0x70, 7),
+    (0x71, 7),
+    (0x72, 7),
+    (0xfc, 8),
+    (0x73, 7),
+    (0xfd, 8),
+    (0x1ffb, 13),
+    (0x7fff0, 19),
+    (0x1ffc, 13),
+    (0x3ffc, 14),
+    (0x22, 6),
+    (0x7ffd, 15),
+    (0x3, 5),
+    (0x23, 6),
+    (0x4, 5),
+    (0x24, 6),
+    (0x5, 5),
+    (0x25, 6),
+    (0x26, 6),
+    (0x27, 6),
+    (0x6, 5),
+    (0x74, 7),
+    (0x75, 7),
+    (0x28, 6),
+    (0x29, 6),
+    (0x2a, 6),
+    (0x7, 5),
+    (0x2b, 6),
+    (0x76, 7),
+    (0x2c, 6),
+    (0x8, 5),
+    (0x9, 5),
+    (0x2d, 6),
+    (0x77, 7),
+    (0x78, 7),
+    (0x79, 7),
+    (0x7a, 7),
+    (0x7b, 7),
+    (0x7ffe, 15),
+    (0x7fc, 11),
+    (0x3ffd, 14),
+    (0x1ffd, 13),
+    (0xffffffc, 28),
+    (0xfffe6, 20),
+    (0x3fffd2, 22),
+    (0xfffe7, 20),
+    (0xfffe8, 20),
+    (0x3fffd3, 22),
+    (0x3fffd4, 22),
+    (0x3fffd5, 22),
+    (0x7fffd9, 23),
+    (0x3fffd6, 22),
+    (0x7fffda, 23),
+    (0x7fffdb, 23),
+    (0x7fffdc, 23),
+    (0x7fffdd, 23),
+    (0x7fffde, 23),
+    (0xffffeb, 24),
+    (0x7fffdf, 23),
+    (0xffffec, 24),
+    (0xffffed, 24),
+    (0x3fffd7, 22),
+    (0x7fffe0, 23),
+    (0xffffee, 24),
+    (0x7fffe1, 23),
+    (0x7fffe2, 23),
+    (0x7fffe3, 23),
+    (0x7fffe4, 23),
+    (0x1fffdc, 21),
+    (0x3fffd8, 22),
+    (0x7fffe5, 23),
+    (0x3fffd9, 22),
+    (0x7fffe6, 23),
+    (0x7fffe7, 23),
+    (0xffffef, 24),
+    (0x3fffda, 22),
+    (0x1fffdd, 21),
+    (0xfffe9, 20),
+    (0x3fffdb, 22),
+    (0x3fffdc, 22),
+    (0x7fffe8, 23),
+    (0x7fffe9, 23),
+    (0x1fffde, 21),
+    (0x7fffea, 23),
+    (0x3fffdd, 22),
+    (0x3fffde, 22),
+    (0xfffff0, 24),
+    (0x1fffdf, 21),
+    (0x3fffdf, 22),
+    (0x7fffeb, 23),
+    (0x7fffec, 23),
+    (0x1fffe0, 21),
+    (0x1fffe1, 21),
+    (0x3fffe0, 22),
+    (0x1fffe2, 21),
+    (0x7fffed, 23),
+    (0x3fffe1, 22),
+    (0x7fffee, 23),
+    (0x7fffef, 23),
+    (0xfffea, 20),
+    (0x3fffe2, 22),
+    (0x3fffe3, 22),
+    (0x3fffe4, 22),
+    (0x7ffff0, 23),
+    (0x3fffe5, 22),
+    (0x3fffe6, 22),
+    (0x7ffff1, 23),
+    (0x3ffffe0, 26),
+    (0x3ffffe1, 26),
+    (0xfffeb, 20),
+    (0x7fff1, 19),
+    (0x3fffe7, 22),
+    (0x7ffff2, 23),
+    (0x3fffe8, 22),
+    (0x1ffffec, 25),
+    (0x3ffffe2, 26),
+    (0x3ffffe3, 26),
+    (0x3ffffe4, 26),
+    (0x7ffffde, 27),
+    (0x7ffffdf, 27),
+    (0x3ffffe5, 26),
+    (0xfffff1, 24),
+    (0x1ffffed, 25),
+    (0x7fff2, 19),
+    (0x1fffe3, 21),
+    (0x3ffffe6, 26),
+    (0x7ffffe0, 27),
+    (0x7ffffe1, 27),
+    (0x3ffffe7, 26),
+    (0x7ffffe2, 27),
+    (0xfffff2, 24),
+    (0x1fffe4, 21),
+    (0x1fffe5, 21),
+    (0x3ffffe8, 26),
+    (0x3ffffe9, 26),
+    (0xffffffd, 28),
+    (0x7ffffe3, 27),
+    (0x7ffffe4, 27),
+    (0x7ffffe5, 27),
+    (0xfffec, 20),
+    (0xfffff3, 24),
+    (0xfffed, 20),
+    (0x1fffe6, 21),
+    (0x3fffe9, 22),
+    (0x1fffe7, 21),
+    (0x1fffe8, 21),
+    (0x7ffff3, 23),
+    (0x3fffea, 22),
+    (0x3fffeb, 22),
+    (0x1ffffee, 25),
+    (0x1ffffef, 25),
+    (0xfffff4, 24),
+    (0xfffff5, 24),
+    (0x3ffffea, 26),
+    (0x7ffff4, 23),
+    (0x3ffffeb, 26),
+    (0x7ffffe6, 27),
+    (0x3ffffec, 26),
+    (0x3ffffed, 26),
+    (0x7ffffe7, 27),
+    (0x7ffffe8, 27),
+    (0x7ffffe9, 27),
+    (0x7ffffea, 27),
+    (0x7ffffeb, 27),
+    (0xffffffe, 28),
+    (0x7ffffec, 27),
+    (0x7ffffed, 27),
+    (0x7ffffee, 27),
+    (0x7ffffef, 27),
+    (0x7fffff0, 27),
+    (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
+
+/// Encodes `data` with the QPACK static Huffman code, padding the last byte with the most significant bits of EOS (all 1s)
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let encoded_data = qpack_encode(b"www.example.com");
+/// assert_eq!(encoded_data, [0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff]);
+/// assert_eq!(qpack_decode(&encoded_data).unwrap(), b"www.example.com");
+/// ```
+pub fn qpack_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded_data = Vec::with_capacity(data.len());
+    let mut bits: u64 = 0;
+    let mut bit_count = 0;
+    for &byte in data {
+        let (code, code_len) = QPACK_HUFFMAN_CODES[byte as usize];
+        bits = (bits << code_len) | code as u64;
+        bit_count += code_len;
+        while bit_count >= 8 {
+            bit_count -= 8;
+            encoded_data.push((bits >> bit_count) as u8);
+        }
+    }
+    if bit_count > 0 {
+        let padding = 8 - bit_count;
+        encoded_data.push(((bits << padding) | ((1 << padding) - 1)) as u8);
+    }
+    encoded_data
+}
+
+/// Decodes a QPACK Huffman encoded string, rejecting EOS, padding longer than 7 bits and padding that is not all 1s
+pub fn qpack_decode(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    let decoder = decoder();
+    let mut data = Vec::with_capacity(encoded_data.len() * 8 / 5);
+
+    // Canonical decoding: `first` is the first code of the current length and `index` the position of its symbol
+    let (mut code, mut first, mut index, mut code_len) = (0usize, 0usize, 0usize, 0usize);
+    let mut padding_ones = true;
+    for &byte in encoded_data {
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) & 1;
+            padding_ones &= bit == 1;
+            code |= bit as usize;
+            code_len += 1;
+            let count = decoder.counts[code_len] as usize;
+            if code < first + count {
+                let symbol = decoder.symbols[index + code - first];
+                if symbol as usize == EOS {
+                    return Err(HuffmanError::DecodeError("EOS found in QPACK string"));
+                }
+                data.push(symbol as u8);
+                (code, first, index, code_len) = (0, 0, 0, 0);
+                padding_ones = true;
+            } else if code_len == MAX_CODE_LEN {
+                return Err(HuffmanError::DecodeError("Invalid QPACK code"));
+            } else {
+                index += count;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+        }
+    }
+
+    if code_len > 7 || !padding_ones {
+        return Err(HuffmanError::DecodeError("Invalid QPACK padding"));
+    }
+    Ok(data)
+}
+
+/// Returns the QPACK static code as an `EncodingMap` so it can drive this crate's own encoder and decoder.
+/// EOS is left out as it is never encoded.
+pub fn qpack_encoding_map() -> Result<EncodingMap> {
+    let map: HashMap<u8, String> = QPACK_HUFFMAN_CODES[..EOS]
+        .iter()
+        .enumerate()
+        .map(|(symbol, &(code, code_len))| {
+            (
+                symbol as u8,
+                format!("{:0width$b}", code, width = code_len as usize),
+            )
+        })
+        .collect();
+    EncodingMap::from(map)
+}
+
+/// Number of codes of each length and the symbols ordered by (code length, symbol), all canonical decoding needs
+struct Decoder {
+    counts: [u16; MAX_CODE_LEN + 1],
+    symbols: Vec<u16>,
+}
+
+fn decoder() -> &'static Decoder {
+    static DECODER: OnceLock<Decoder> = OnceLock::new();
+    DECODER.get_or_init(|| {
+        let mut counts = [0u16; MAX_CODE_LEN + 1];
+        for &(_, code_len) in &QPACK_HUFFMAN_CODES {
+            counts[code_len as usize] += 1;
+        }
+        let mut symbols: Vec<u16> = (0..=EOS as u16).collect();
+        symbols.sort_by_key(|&symbol| (QPACK_HUFFMAN_CODES[symbol as usize].1, symbol));
+        Decoder { counts, symbols }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qpack_encode() {
+        // RFC 7541 Appendix C.4
+        let input_data: [&[u8]; 4] = [
+            b"www.example.com",
+            b"no-cache",
+            b"custom-key",
+            b"custom-value",
+        ];
+        let expected_data: [&[u8]; 4] = [
+            &[
+                0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff,
+            ],
+            &[0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf],
+            &[0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f],
+            &[0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf],
+        ];
+
+        for (input, expected) in input_data.iter().zip(expected_data) {
+            assert_eq!(expected, qpack_encode(input));
+        }
+    }
+
+    #[test]
+    fn test_qpack_decode() {
+        let input_data: Vec<u8> = (0..=255).collect();
+
+        let test_output = qpack_decode(&qpack_encode(&input_data)).unwrap();
+
+        assert_eq!(input_data, test_output);
+        // 'a' is 00011, padding with 0s instead of 1s is invalid
+        assert!(qpack_decode(&[0b0001_1000]).is_err());
+        // A full byte of padding is invalid
+        assert!(qpack_decode(&[0b0001_1111, 0xff]).is_err());
+        // EOS must not be encoded
+        assert!(qpack_decode(&[0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn test_qpack_encoding_map() {
+        let encoding_map = qpack_encoding_map().unwrap();
+
+        assert_eq!(Some(5), encoding_map.get(&b'a').map(Vec::len));
+        assert_eq!(30, encoding_map.get_longest_code());
+    }
+}
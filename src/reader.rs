@@ -0,0 +1,148 @@
+use crate::container::Header;
+use crate::error::Result;
+use crate::stream::{read_chunk, DecodedCheck, PayloadDecoder, CHUNK_SIZE};
+
+use std::io::{self, Read};
+
+/// Decodes a self-contained encoding as it is read, for code that consumes `impl Read`.
+///
+/// The header is read on the first call to `read`, then the payload is decoded a chunk at a time as the decoded u8s
+/// are asked for. Errors surface as `io::Error`s, with the `HuffmanError` message for invalid data.
+pub struct HuffmanReader<R: Read> {
+    reader: R,
+    /// `None` until the header has been read and again once the payload is exhausted
+    decoder: Option<PayloadDecoder>,
+    started: bool,
+    chunk: Vec<u8>,
+    decoded: Vec<u8>,
+    position: usize,
+    check: DecodedCheck,
+}
+
+impl<R: Read> HuffmanReader<R> {
+    /// Creates a `HuffmanReader` decoding the encoding `reader` holds
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The encoding, as written by `encode_to_writer` or `encode`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use std::io::Read;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let encoded_data = encode(&data).unwrap();
+    ///
+    /// let mut decoded_data = String::new();
+    /// HuffmanReader::new(encoded_data.as_slice()).read_to_string(&mut decoded_data).unwrap();
+    /// assert_eq!(decoded_data.as_bytes(), data);
+    /// ```
+    pub fn new(reader: R) -> Self {
+        HuffmanReader {
+            reader,
+            decoder: None,
+            started: false,
+            chunk: Vec::new(),
+            decoded: Vec::new(),
+            position: 0,
+            check: DecodedCheck::default(),
+        }
+    }
+
+    /// Returns the underlying reader, positioned wherever decoding left it
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decodes the next chunk of payload into `decoded`, returning false once the payload is exhausted
+    fn fill(&mut self) -> Result<bool> {
+        if !self.started {
+            let header = Header::read_from(&mut self.reader)?;
+            self.check = DecodedCheck::new(&header);
+            self.decoder = Some(PayloadDecoder::new(header));
+            self.chunk = vec![0u8; CHUNK_SIZE];
+            self.started = true;
+        }
+        let Some(decoder) = self.decoder.as_mut() else {
+            return Ok(false);
+        };
+
+        self.decoded.clear();
+        self.position = 0;
+        match read_chunk(&mut self.reader, &mut self.chunk)? {
+            Some(read) => decoder.feed(&self.chunk[..read], &mut self.decoded)?,
+            None => {
+                if let Some(decoder) = self.decoder.take() {
+                    decoder.finish(&mut self.decoded)?;
+                }
+            }
+        }
+        self.check.update(&self.decoded);
+        if self.decoder.is_none() {
+            self.check.finish()?;
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for HuffmanReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            if buf.is_empty() || !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let read = buf.len().min(self.decoded.len() - self.position);
+        buf[..read].copy_from_slice(&self.decoded[self.position..self.position + read]);
+        self.position += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::encode;
+    use crate::huffman::HuffmanData;
+    use crate::options::HuffmanOptions;
+
+    #[test]
+    fn test_huffman_reader() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let interleaved = HuffmanData::with_options(
+            &input_data,
+            &HuffmanOptions {
+                streams: 4,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+
+        for encoded_data in [encode(&input_data).unwrap(), interleaved] {
+            let mut reader = HuffmanReader::new(encoded_data.as_slice());
+            let mut test_output = Vec::new();
+            // Reads smaller than a chunk have to pick up where the previous one stopped
+            let mut buf = [0u8; 100];
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break,
+                    read => test_output.extend_from_slice(&buf[..read]),
+                }
+            }
+
+            assert_eq!(input_data, test_output);
+        }
+
+        let mut encoded_data = encode(&input_data).unwrap();
+        encoded_data.truncate(encoded_data.len() / 2);
+        let error = HuffmanReader::new(encoded_data.as_slice())
+            .read_to_end(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+    }
+}
@@ -0,0 +1,293 @@
+use crate::dictionary::HuffmanDictionary;
+use crate::encoding_map::{EncodingMap, ESCAPE_SYMBOL};
+use crate::error::{HuffmanError, Result};
+use crate::frame::{read_length_prefix, DEFAULT_MAX_FRAME_SIZE};
+use crate::huffman::{decode_with_map, encode_with_map};
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+
+use std::io::{Read, Write};
+
+/// Identifies a record file, ahead of everything else
+pub(crate) const MAGIC: [u8; 4] = *b"HTTR";
+pub(crate) const VERSION: u8 = 2;
+/// Version of the files written before records were packed densely, still read
+pub(crate) const PADDED_VERSION: u8 = 1;
+
+/// Writes many small records to a single file sharing one table: the file starts with the canonical code lengths of
+/// a `HuffmanDictionary`, written once, and every record that follows is just its length and payload. Event logs
+/// of short, similar messages stay readable on their own without paying for a table per message.
+///
+/// The layout is:
+/// * `[u8; 4]` magic `HTTR`
+/// * `u8` version, 2
+/// * `u16` little endian number of codes, then for every code its `u16` little endian symbol, 256 for the escape,
+///   and `u8` code length, the codes being the canonical codes of those lengths
+/// * every record as a `u32` little endian payload length and the dense Huffman payload, whose end bit gives its
+///   exact length in bits
+///
+/// Version 1 files, whose payloads are padded, are still read and appended to.
+pub struct RecordWriter<W: Write> {
+    writer: W,
+    encoding_map: EncodingMap,
+    packing: Packing,
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// Starts a record file encoding every record with `dictionary`, writing its table header
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the file is written
+    /// * `dictionary` - The code shared by every record, its escape covering u8s it was not trained on
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let samples: [&[u8]; 2] = [b"user=1 action=login", b"user=2 action=logout"];
+    /// let dictionary = HuffmanDictionary::train(&samples).unwrap();
+    ///
+    /// let mut record_writer = RecordWriter::new(Vec::new(), &dictionary).unwrap();
+    /// record_writer.append(b"user=3 action=login").unwrap();
+    /// record_writer.append(b"user=3 action=purchase").unwrap();
+    /// let file = record_writer.into_inner().unwrap();
+    ///
+    /// let records: Vec<Vec<u8>> = RecordReader::new(file.as_slice()).unwrap().collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(records, [b"user=3 action=login".to_vec(), b"user=3 action=purchase".to_vec()]);
+    /// ```
+    pub fn new(mut writer: W, dictionary: &HuffmanDictionary) -> Result<Self> {
+        let encoding_map = dictionary.encoding_map().canonical()?;
+        let lengths = encoding_map.code_lengths();
+
+        let mut header = MAGIC.to_vec();
+        header.push(VERSION);
+        header.extend_from_slice(&(lengths.len() as u16).to_le_bytes());
+        for (symbol, length) in lengths {
+            let length = u8::try_from(length).map_err(|_| {
+                HuffmanError::OptionsError("Record file codes are at most 255 bits long")
+            })?;
+            header.extend_from_slice(&symbol.to_le_bytes());
+            header.push(length);
+        }
+        writer.write_all(&header)?;
+        Ok(RecordWriter {
+            writer,
+            encoding_map,
+            packing: Packing::Dense,
+        })
+    }
+
+    /// Continues a record file whose header has already been written, such as one opened for appending. The map
+    /// and packing must be the ones the file was started with, `RecordReader::encoding_map` and
+    /// `RecordReader::packing` return them
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where records are written, right after the last record of the file
+    /// * `encoding_map` - The map of the file's header
+    /// * `packing` - The packing of the file's records
+    pub fn append_to(writer: W, encoding_map: EncodingMap, packing: Packing) -> Self {
+        RecordWriter {
+            writer,
+            encoding_map,
+            packing,
+        }
+    }
+
+    /// Encodes `record` and writes it after the records written so far
+    pub fn append(&mut self, record: &[u8]) -> Result<()> {
+        let payload = encode_with_map(record, &self.encoding_map, self.packing)?;
+        let payload_size = u32::try_from(payload.len())
+            .map_err(|_| HuffmanError::OptionsError("Record is too large for a record file"))?;
+        self.writer.write_all(&payload_size.to_le_bytes())?;
+        self.writer.write_all(&payload)?;
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the records of a file written by `RecordWriter`, decoding each with the table of the file's header.
+/// Iterating yields every record in the order it was appended
+pub struct RecordReader<R: Read> {
+    reader: R,
+    encoding_map: EncodingMap,
+    packing: Packing,
+    max_record_size: u32,
+}
+
+impl<R: Read> RecordReader<R> {
+    /// Reads the table header of a record file, leaving the reader at its first record
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Where the file is read from
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut start = [0u8; 7];
+        reader.read_exact(&mut start)?;
+        if start[..4] != MAGIC {
+            return Err(HuffmanError::DecodeError("Not a record file"));
+        }
+        let packing = match start[4] {
+            VERSION => Packing::Dense,
+            PADDED_VERSION => Packing::Padded,
+            _ => return Err(HuffmanError::DecodeError("Unsupported record file version")),
+        };
+        let code_count = u16::from_le_bytes([start[5], start[6]]);
+        if code_count > ESCAPE_SYMBOL + 1 {
+            return Err(HuffmanError::DecodeError("Header holds too many codes"));
+        }
+        let mut table = vec![0u8; code_count as usize * 3];
+        reader.read_exact(&mut table)?;
+        let lengths: Vec<(u16, usize)> = table
+            .chunks_exact(3)
+            .map(|code| (u16::from_le_bytes([code[0], code[1]]), code[2] as usize))
+            .collect();
+
+        Ok(RecordReader {
+            reader,
+            encoding_map: EncodingMap::from_lengths(&lengths)?,
+            packing,
+            max_record_size: DEFAULT_MAX_FRAME_SIZE,
+        })
+    }
+
+    /// The map every record is decoded with, for `RecordWriter::append_to`
+    pub fn encoding_map(&self) -> &EncodingMap {
+        &self.encoding_map
+    }
+
+    /// How the file's records are packed, for `RecordWriter::append_to`
+    pub fn packing(&self) -> Packing {
+        self.packing
+    }
+
+    /// Sets the largest record payload accepted, larger payloads fail instead of being buffered
+    pub fn with_max_record_size(mut self, max_record_size: u32) -> Self {
+        self.max_record_size = max_record_size;
+        self
+    }
+
+    /// Reads and decodes the next record, returning `None` once the reader is exhausted between records. A reader
+    /// ending partway through a record, its size included, is truncated and fails
+    pub fn read_record(&mut self) -> Result<Option<Vec<u8>>> {
+        let Some(payload_size) = read_length_prefix(&mut self.reader)? else {
+            return Ok(None);
+        };
+        if payload_size == 0 || payload_size > self.max_record_size {
+            return Err(HuffmanError::DecodeError("Invalid record size"));
+        }
+
+        let mut payload = vec![0u8; payload_size as usize];
+        self.reader.read_exact(&mut payload)?;
+        let options = DecodeOptions {
+            strict: true,
+            ..Default::default()
+        };
+        decode_with_map(&payload, &self.encoding_map, self.packing, &options).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for RecordReader<R> {
+    type Item = Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip() {
+        let samples: [&[u8]; 2] = [b"level=info msg=started", b"level=warn msg=slow"];
+        let dictionary = HuffmanDictionary::train(&samples).unwrap();
+        let input_data: [&[u8]; 4] = [b"level=info msg=ok", b"", b"level=error msg=~", b"x"];
+
+        let mut record_writer = RecordWriter::new(Vec::new(), &dictionary).unwrap();
+        for record in &input_data[..2] {
+            record_writer.append(record).unwrap();
+        }
+        let mut file = record_writer.into_inner().unwrap();
+        let two_records = file.len();
+        // Appending later reuses the table of the header
+        let record_reader = RecordReader::new(file.as_slice()).unwrap();
+        assert_eq!(record_reader.packing(), Packing::Dense);
+        let encoding_map = record_reader.encoding_map().clone();
+        let mut record_writer = RecordWriter::append_to(&mut file, encoding_map, Packing::Dense);
+        for record in &input_data[2..] {
+            record_writer.append(record).unwrap();
+        }
+        record_writer.into_inner().unwrap();
+
+        let test_output: Vec<Vec<u8>> = RecordReader::new(file.as_slice())
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(input_data.to_vec(), test_output);
+        assert!(RecordReader::new(&file[..6]).is_err());
+        let mut truncated = RecordReader::new(&file[..file.len() - 1]).unwrap();
+        assert!(truncated.nth(3).unwrap().is_err());
+        // Cut inside the size of the third record, which must not read as the end of the records
+        for cut in two_records + 1..two_records + 4 {
+            let mut truncated = RecordReader::new(&file[..cut]).unwrap();
+            assert!(truncated.nth(2).unwrap().is_err());
+        }
+        assert!(RecordReader::new(&file[..two_records])
+            .unwrap()
+            .nth(2)
+            .is_none());
+    }
+
+    #[test]
+    fn test_record_max_size() {
+        let dictionary = HuffmanDictionary::train(&[b"abc".as_slice()]).unwrap();
+        let mut record_writer = RecordWriter::new(Vec::new(), &dictionary).unwrap();
+        record_writer.append(&b"abc".repeat(100)).unwrap();
+        let file = record_writer.into_inner().unwrap();
+
+        let mut record_reader = RecordReader::new(file.as_slice())
+            .unwrap()
+            .with_max_record_size(16);
+
+        assert!(record_reader.read_record().is_err());
+    }
+
+    #[test]
+    fn test_record_padded_version() {
+        let dictionary = HuffmanDictionary::train(&[b"abcd".as_slice()]).unwrap();
+        let input_data: [&[u8]; 2] = [b"abca", b"dd"];
+
+        let mut file = RecordWriter::new(Vec::new(), &dictionary)
+            .unwrap()
+            .into_inner()
+            .unwrap();
+        file[4] = PADDED_VERSION;
+        let encoding_map = RecordReader::new(file.as_slice())
+            .unwrap()
+            .encoding_map()
+            .clone();
+        let mut record_writer = RecordWriter::append_to(&mut file, encoding_map, Packing::Padded);
+        for record in input_data {
+            record_writer.append(record).unwrap();
+        }
+        record_writer.into_inner().unwrap();
+
+        let record_reader = RecordReader::new(file.as_slice()).unwrap();
+        assert_eq!(record_reader.packing(), Packing::Padded);
+        let test_output: Vec<Vec<u8>> = record_reader.collect::<Result<_>>().unwrap();
+
+        assert_eq!(input_data.to_vec(), test_output);
+    }
+
+}
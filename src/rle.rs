@@ -0,0 +1,71 @@
+use crate::error::{HuffmanError, Result};
+
+/// Run-length encodes `data` as `(run length, byte)` pairs with runs of at most 255 bytes
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded_data = Vec::new();
+    let mut runs = data.iter().peekable();
+    while let Some(&byte) = runs.next() {
+        let mut run_length = 1u8;
+        while run_length < u8::MAX && runs.next_if_eq(&&byte).is_some() {
+            run_length += 1;
+        }
+        encoded_data.push(run_length);
+        encoded_data.push(byte);
+    }
+    encoded_data
+}
+
+/// Expands `(run length, byte)` pairs produced by `encode`
+pub fn decode(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    if !encoded_data.len().is_multiple_of(2) {
+        return Err(HuffmanError::DecodeError("Truncated run-length pair"));
+    }
+    let mut data = Vec::with_capacity(encoded_data.len());
+    for pair in encoded_data.chunks_exact(2) {
+        if pair[0] == 0 {
+            return Err(HuffmanError::DecodeError("Zero length run"));
+        }
+        data.resize(data.len() + pair[0] as usize, pair[1]);
+    }
+    Ok(data)
+}
+
+/// Returns how many bytes `decode` will produce without expanding the runs
+pub fn decoded_size(encoded_data: &[u8]) -> usize {
+    encoded_data
+        .chunks_exact(2)
+        .map(|pair| pair[0] as usize)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rle_encode() {
+        let mut input_data = vec![b'a'; 300];
+        input_data.extend_from_slice(b"bbc");
+
+        let expected_data: Vec<u8> = vec![255, b'a', 45, b'a', 2, b'b', 1, b'c'];
+
+        let test_output = encode(&input_data);
+
+        assert_eq!(expected_data, test_output);
+    }
+
+    #[test]
+    fn test_rle_decode() {
+        let input_data: Vec<u8> = vec![255, b'a', 45, b'a', 2, b'b', 1, b'c'];
+
+        let mut expected_data = vec![b'a'; 300];
+        expected_data.extend_from_slice(b"bbc");
+
+        let test_output = decode(&input_data).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(expected_data.len(), decoded_size(&input_data));
+        assert!(decode(&[2]).is_err());
+        assert!(decode(&[0, b'a']).is_err());
+    }
+}
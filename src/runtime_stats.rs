@@ -0,0 +1,211 @@
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+use crate::options::HuffmanOptions;
+
+use std::time::{Duration, Instant};
+
+/// Wall clock timings of encoding and decoding one input
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeStats {
+    /// Size of the input in u8s
+    pub data_size: usize,
+    /// Size of the encoded data in u8s
+    pub encoded_size: usize,
+    /// Time taken by `HuffmanData::with_options`
+    pub encode_time: Duration,
+    /// Time taken by `HuffmanData::decode`
+    pub decode_time: Duration,
+}
+
+impl RuntimeStats {
+    /// Encodes then decodes `data`, timing each step
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    /// * `options` - A reference to `HuffmanOptions` used for the encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let stats = RuntimeStats::measure(&data, &HuffmanOptions::default()).unwrap();
+    /// assert_eq!(stats.data_size, data.len());
+    /// assert!(stats.to_json().starts_with("{\"data_size\":22,"));
+    /// ```
+    pub fn measure(data: &[u8], options: &HuffmanOptions) -> Result<RuntimeStats> {
+        let start = Instant::now();
+        let huffman_data = HuffmanData::with_options(data, options)?;
+        let encode_time = start.elapsed();
+
+        let start = Instant::now();
+        huffman_data.decode()?;
+        let decode_time = start.elapsed();
+
+        Ok(RuntimeStats {
+            data_size: data.len(),
+            encoded_size: huffman_data.encoded_data.len(),
+            encode_time,
+            decode_time,
+        })
+    }
+
+    /// Input u8s encoded per second
+    pub fn encode_throughput(&self) -> f64 {
+        self.data_size as f64 / self.encode_time.as_secs_f64()
+    }
+
+    /// Output u8s decoded per second
+    pub fn decode_throughput(&self) -> f64 {
+        self.data_size as f64 / self.decode_time.as_secs_f64()
+    }
+
+    /// Serializes the stats as a single line JSON object, timings in nanoseconds
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"data_size\":{},\"encoded_size\":{},\"encode_ns\":{},\"decode_ns\":{}}}",
+            self.data_size,
+            self.encoded_size,
+            self.encode_time.as_nanos(),
+            self.decode_time.as_nanos()
+        )
+    }
+}
+
+/// Repeated `RuntimeStats` runs under one name, summarised by their medians so they can be stored and compared
+/// across environments
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// Name identifying the input or setup being measured
+    pub name: String,
+    /// Every measured run in order
+    pub runs: Vec<RuntimeStats>,
+}
+
+impl BenchReport {
+    pub fn new(name: &str) -> BenchReport {
+        BenchReport {
+            name: name.to_string(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Measures `iterations` encode and decode runs of `data` into a new report
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name identifying the report
+    /// * `data` - The data to encode
+    /// * `options` - A reference to `HuffmanOptions` used for every encode
+    /// * `iterations` - How many runs to measure
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let report = BenchReport::run("short", &data, &HuffmanOptions::default(), 3).unwrap();
+    /// assert_eq!(report.runs.len(), 3);
+    /// assert!(report.to_json().starts_with("{\"name\":\"short\","));
+    /// ```
+    pub fn run(
+        name: &str,
+        data: &[u8],
+        options: &HuffmanOptions,
+        iterations: usize,
+    ) -> Result<BenchReport> {
+        let mut report = BenchReport::new(name);
+        for _ in 0..iterations {
+            report.push(RuntimeStats::measure(data, options)?);
+        }
+        Ok(report)
+    }
+
+    pub fn push(&mut self, stats: RuntimeStats) {
+        self.runs.push(stats);
+    }
+
+    /// Median encode time of all runs, zero when there are none
+    pub fn median_encode_time(&self) -> Duration {
+        median(self.runs.iter().map(|run| run.encode_time).collect())
+    }
+
+    /// Median decode time of all runs, zero when there are none
+    pub fn median_decode_time(&self) -> Duration {
+        median(self.runs.iter().map(|run| run.decode_time).collect())
+    }
+
+    /// Serializes the report and every run as a single line JSON object, timings in nanoseconds
+    pub fn to_json(&self) -> String {
+        let runs: Vec<String> = self.runs.iter().map(RuntimeStats::to_json).collect();
+        format!(
+            "{{\"name\":\"{}\",\"median_encode_ns\":{},\"median_decode_ns\":{},\"runs\":[{}]}}",
+            escape_json(&self.name),
+            self.median_encode_time().as_nanos(),
+            self.median_decode_time().as_nanos(),
+            runs.join(",")
+        )
+    }
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times.get(times.len() / 2).copied().unwrap_or_default()
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(encode_ms: u64, decode_ms: u64) -> RuntimeStats {
+        RuntimeStats {
+            data_size: 10,
+            encoded_size: 5,
+            encode_time: Duration::from_millis(encode_ms),
+            decode_time: Duration::from_millis(decode_ms),
+        }
+    }
+
+    #[test]
+    fn test_runtime_stats_to_json() {
+        let expected_data =
+            "{\"data_size\":10,\"encoded_size\":5,\"encode_ns\":2000000,\"decode_ns\":1000000}";
+
+        let test_output = stats(2, 1);
+
+        assert_eq!(expected_data, test_output.to_json());
+        assert_eq!(5000.0, test_output.encode_throughput());
+    }
+
+    #[test]
+    fn test_bench_report() {
+        let mut test_output = BenchReport::new("a \"quoted\" name");
+        test_output.push(stats(3, 1));
+        test_output.push(stats(1, 2));
+        test_output.push(stats(2, 3));
+
+        assert_eq!(Duration::from_millis(2), test_output.median_encode_time());
+        assert_eq!(Duration::from_millis(2), test_output.median_decode_time());
+        assert!(test_output
+            .to_json()
+            .starts_with("{\"name\":\"a \\\"quoted\\\" name\",\"median_encode_ns\":2000000,"));
+    }
+}
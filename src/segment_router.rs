@@ -0,0 +1,153 @@
+use crate::block_type::BlockType;
+use crate::blocks::HuffmanBlocks;
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+use crate::options::SegmentRouterOptions;
+
+/// Splits `data` into segments worth Huffman encoding and segments to store as they are, returning the size and
+/// block type of every segment in order.
+///
+/// The Shannon entropy of every window of `window` u8s is measured, windows at or above `max_entropy` bits per u8
+/// (compressed, encrypted or otherwise random looking data) are routed to stored blocks and the rest to Huffman
+/// blocks, and neighbouring windows routed alike are merged into one segment. A window too short to measure,
+/// at the end of the data, goes with the segment before it.
+///
+/// # Arguments
+///
+/// * `data` - The data to split
+/// * `options` - A reference to `SegmentRouterOptions` setting the window size and entropy threshold
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let mut data = b"this is a test string!".repeat(186);
+/// data.extend_from_slice(b"this");
+/// let mut state = 1u32;
+/// data.extend((0..8192).map(|_| {
+///     state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+///     (state >> 24) as u8
+/// }));
+/// let segments = route_segments(&data, &SegmentRouterOptions::default()).unwrap();
+/// assert_eq!(segments, vec![(4096, BlockType::Huffman), (8192, BlockType::Stored)]);
+/// ```
+pub fn route_segments(
+    data: &[u8],
+    options: &SegmentRouterOptions,
+) -> Result<Vec<(usize, BlockType)>> {
+    options.validate()?;
+    let mut segments: Vec<(usize, BlockType)> = Vec::new();
+    for window in data.chunks(options.window) {
+        let block_type = match (window.len() < options.window, segments.last()) {
+            (true, Some(&(_, previous))) => previous,
+            _ if entropy(window) >= options.max_entropy => BlockType::Stored,
+            _ => BlockType::Huffman,
+        };
+        match segments.last_mut() {
+            Some((size, previous)) if *previous == block_type => *size += window.len(),
+            _ => segments.push((window.len(), block_type)),
+        }
+    }
+    Ok(segments)
+}
+
+impl HuffmanBlocks {
+    /// Splits `data` into segments with `route_segments`, storing the incompressible ones without counting their
+    /// u8s or building a tree and Huffman encoding the rest, each segment cut into blocks of at most
+    /// `max_block_size` u8s. Mixed binaries such as installers or disk images encode faster, and the compressible
+    /// regions get codes fitted to them alone.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encode
+    /// * `options` - A reference to `SegmentRouterOptions` setting how segments are found and encoded
+    pub fn with_routing(data: &[u8], options: &SegmentRouterOptions) -> Result<HuffmanBlocks> {
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        for (size, block_type) in route_segments(data, options)? {
+            for block in data[start..start + size].chunks(options.max_block_size) {
+                blocks.push(match block_type {
+                    BlockType::Stored => HuffmanData::stored(block, &options.huffman)?,
+                    _ => HuffmanData::with_block_selection(block, &options.huffman)?,
+                });
+            }
+            start += size;
+        }
+        // Empty data still gets one block, so the blocks always decode
+        if blocks.is_empty() {
+            blocks.push(HuffmanData::with_block_selection(data, &options.huffman)?);
+        }
+        Ok(HuffmanBlocks { blocks })
+    }
+}
+
+/// Shannon entropy of `window` in bits per u8
+fn entropy(window: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    for &byte in window {
+        counts[byte as usize] += 1;
+    }
+    let len = window.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let probability = count as f64 / len;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_segments() {
+        let mut state = 7u32;
+        let mut noise = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        };
+        let mut input_data = b"abcd".repeat(1024);
+        input_data.extend((0..4096).map(|_| noise()));
+        input_data.extend(b"efgh".repeat(512));
+        input_data.extend((0..100).map(|_| noise()));
+        let options = SegmentRouterOptions {
+            window: 1024,
+            max_block_size: 3000,
+            ..Default::default()
+        };
+        let expected_data = vec![
+            (4096, BlockType::Huffman),
+            (4096, BlockType::Stored),
+            (2148, BlockType::Huffman),
+        ];
+
+        let test_output = route_segments(&input_data, &options).unwrap();
+        let huffman_blocks = HuffmanBlocks::with_routing(&input_data, &options).unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(5, huffman_blocks.blocks.len());
+        assert_eq!(BlockType::Stored, huffman_blocks.blocks[2].block_type);
+        assert_eq!(input_data, huffman_blocks.decode().unwrap());
+        assert_eq!(
+            Vec::<(usize, BlockType)>::new(),
+            route_segments(&[], &options).unwrap()
+        );
+        assert!(HuffmanBlocks::with_routing(&[], &options)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(0.0, entropy(b"aaaa"));
+        assert_eq!(2.0, entropy(b"abcd"));
+        assert_eq!(8.0, entropy(&(0..=255).collect::<Vec<u8>>()));
+    }
+}
@@ -0,0 +1,379 @@
+//! Machine readable description of the formats this crate writes: the fields of each layout in order with their
+//! names and widths, the values of every enumerated field and the limits decoders enforce. It is built from the
+//! constants the encoders and decoders use, so ports in other languages can be generated from `format_spec` and
+//! checked against it as the formats change.
+
+use crate::adaptive;
+use crate::block_type::BlockType;
+use crate::blocks;
+use crate::container::{
+    self, CODES_FORM, DENSE_PACKING, LENGTH_TABLE_FORM, MAX_HEADER_CODE_LEN, MAX_PAYLOAD_SIZE,
+    PADDED_PACKING, SPARSE_LENGTHS_FORM, TREE_FORM,
+};
+use crate::encoding_map::ESCAPE_SYMBOL;
+use crate::record_file;
+
+use std::fmt::Write;
+
+/// One field of a layout, every integer of which is little endian
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    /// `bytes`, `u8`, `u16`, `u32` or `u64`
+    pub kind: &'static str,
+    /// Size in u8s, `None` for a field whose size depends on the data
+    pub width: Option<usize>,
+    pub description: &'static str,
+}
+
+/// The fields of one format, in the order they are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub name: &'static str,
+    pub magic: [u8; 4],
+    /// The version written today, older versions are described in the docs of the type writing them
+    pub version: u8,
+    pub fields: Vec<Field>,
+}
+
+/// The values an enumerated field takes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enumeration {
+    /// Name of the field, as the layouts name it
+    pub name: &'static str,
+    pub values: Vec<(&'static str, u64)>,
+}
+
+/// Every format this crate writes, see `format_spec`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSpec {
+    /// Version of the crate the spec was generated from
+    pub crate_version: &'static str,
+    pub layouts: Vec<Layout>,
+    pub enumerations: Vec<Enumeration>,
+    /// Limits a decoder enforces
+    pub constants: Vec<(&'static str, u64)>,
+}
+
+impl Field {
+    fn fixed(
+        name: &'static str,
+        kind: &'static str,
+        width: usize,
+        description: &'static str,
+    ) -> Self {
+        Field {
+            name,
+            kind,
+            width: Some(width),
+            description,
+        }
+    }
+
+    fn sized_by_data(name: &'static str, description: &'static str) -> Self {
+        Field {
+            name,
+            kind: "bytes",
+            width: None,
+            description,
+        }
+    }
+}
+
+/// Returns the spec of every format this crate writes
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let format_spec = spec::format_spec();
+/// let container = &format_spec.layouts[0];
+/// assert_eq!(&container.magic, b"HTTC");
+/// assert_eq!(&encode(b"this is a test string!").unwrap()[..4], &container.magic);
+/// assert!(format_spec.to_json().starts_with('{'));
+/// ```
+pub fn format_spec() -> FormatSpec {
+    FormatSpec {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        layouts: vec![
+            container_layout(),
+            blocks_layout(),
+            adaptive_layout(),
+            record_file_layout(),
+        ],
+        enumerations: vec![
+            Enumeration {
+                name: "packing",
+                values: vec![
+                    ("padded", PADDED_PACKING as u64),
+                    ("dense", DENSE_PACKING as u64),
+                ],
+            },
+            Enumeration {
+                name: "block_type",
+                values: vec![
+                    ("stored", BlockType::Stored.to_bits() as u64),
+                    ("huffman", BlockType::Huffman.to_bits() as u64),
+                    ("rle", BlockType::Rle.to_bits() as u64),
+                ],
+            },
+            Enumeration {
+                name: "map_form",
+                values: vec![
+                    ("codes", CODES_FORM as u64),
+                    ("sparse_lengths", SPARSE_LENGTHS_FORM as u64),
+                    ("length_table", LENGTH_TABLE_FORM as u64),
+                    ("tree", TREE_FORM as u64),
+                ],
+            },
+        ],
+        constants: vec![
+            ("escape_symbol", ESCAPE_SYMBOL as u64),
+            ("max_code_len", MAX_HEADER_CODE_LEN as u64),
+            ("max_payload_size", MAX_PAYLOAD_SIZE),
+        ],
+    }
+}
+
+/// A self-contained encoding, as `Header` documents it
+fn container_layout() -> Layout {
+    Layout {
+        name: "container",
+        magic: container::MAGIC,
+        version: container::VERSION,
+        fields: vec![
+            Field::fixed("magic", "bytes", 4, "Identifies the format"),
+            Field::fixed("version", "u8", 1, "Version of the layout"),
+            Field::fixed("packing", "u8", 1, "Packing of a Huffman payload, see the packing enumeration"),
+            Field::fixed("block_type", "u8", 1, "How the payload is encoded, see the block_type enumeration"),
+            Field::fixed("data_len", "u64", 8, "Number of decoded u8s"),
+            Field::fixed("checksum", "u32", 4, "CRC-32 of the decoded u8s"),
+            Field::fixed("map_form", "u8", 1, "How the encoding map is stored, see the map_form enumeration"),
+            Field::sized_by_data("map", "The encoding map in its map form"),
+            Field::fixed("stream_count", "u8", 1, "Number of streams the payload is split into, 1 to 4"),
+            Field::sized_by_data("stream_sizes", "A u64 size in u8s for every stream but the last"),
+            Field::sized_by_data(
+                "metadata",
+                "A u16 entry count, then per entry a u16 key length, the key, a u32 value length and the value",
+            ),
+            Field::sized_by_data("payload", "The encoded data, running to the end of the encoding"),
+        ],
+    }
+}
+
+/// A block mode encoding, as `HuffmanBlocks` documents it
+fn blocks_layout() -> Layout {
+    Layout {
+        name: "blocks",
+        magic: blocks::MAGIC,
+        version: blocks::VERSION,
+        fields: vec![
+            Field::fixed("magic", "bytes", 4, "Identifies the format"),
+            Field::fixed("version", "u8", 1, "Version of the layout"),
+            Field::sized_by_data("blocks", "Every block as a container encoding, back to back"),
+            Field::sized_by_data(
+                "seek_index",
+                "Per block, then once for the end, a u64 offset in the encoding and a u64 offset in the decoded data",
+            ),
+            Field::fixed("block_count", "u32", 4, "Number of blocks"),
+            Field::fixed(
+                "index_offset",
+                "u64",
+                blocks::FOOTER_LEN as usize - 4,
+                "Offset of the seek index in the encoding",
+            ),
+        ],
+    }
+}
+
+/// An adaptive encoding, as `adaptive_encode` documents it
+fn adaptive_layout() -> Layout {
+    Layout {
+        name: "adaptive",
+        magic: adaptive::MAGIC,
+        version: adaptive::VERSION,
+        fields: vec![
+            Field::fixed("magic", "bytes", 4, "Identifies the format"),
+            Field::fixed("version", "u8", 1, "Version of the layout"),
+            Field::fixed("data_len", "u32", 4, "Number of decoded u8s"),
+            Field::fixed("checksum", "u32", 4, "CRC-32 of the decoded u8s"),
+            Field::sized_by_data("payload", "The FGK codes, packed dense"),
+        ],
+    }
+}
+
+/// A record file, as `RecordWriter` documents it
+fn record_file_layout() -> Layout {
+    Layout {
+        name: "record_file",
+        magic: record_file::MAGIC,
+        version: record_file::VERSION,
+        fields: vec![
+            Field::fixed("magic", "bytes", 4, "Identifies the format"),
+            Field::fixed("version", "u8", 1, "Version of the layout"),
+            Field::fixed(
+                "code_count",
+                "u16",
+                2,
+                "Number of codes in the shared table",
+            ),
+            Field::sized_by_data(
+                "codes",
+                "Per code a u16 symbol and a u8 canonical code length",
+            ),
+            Field::sized_by_data(
+                "records",
+                "Per record a u32 payload size and the dense payload",
+            ),
+        ],
+    }
+}
+
+impl FormatSpec {
+    /// Writes the spec as a JSON object holding `crate_version`, `layouts`, `enumerations` and `constants`, every
+    /// layout with its `magic` as a string and its `fields` as objects with a `width` of `null` when sized by data
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(
+            json,
+            "{{\"crate_version\":{}",
+            json_string(self.crate_version)
+        );
+
+        json.push_str(",\"layouts\":[");
+        for (i, layout) in self.layouts.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"name\":{},\"magic\":{},\"version\":{},\"fields\":[",
+                json_string(layout.name),
+                json_string(&String::from_utf8_lossy(&layout.magic)),
+                layout.version
+            );
+            for (j, field) in layout.fields.iter().enumerate() {
+                if j > 0 {
+                    json.push(',');
+                }
+                let width = field
+                    .width
+                    .map_or("null".to_string(), |width| width.to_string());
+                let _ = write!(
+                    json,
+                    "{{\"name\":{},\"kind\":{},\"width\":{},\"description\":{}}}",
+                    json_string(field.name),
+                    json_string(field.kind),
+                    width,
+                    json_string(field.description)
+                );
+            }
+            json.push_str("]}");
+        }
+
+        json.push_str("],\"enumerations\":[");
+        for (i, enumeration) in self.enumerations.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                "{{\"name\":{},\"values\":{}}}",
+                json_string(enumeration.name),
+                json_object(&enumeration.values)
+            );
+        }
+        let _ = write!(json, "],\"constants\":{}}}", json_object(&self.constants));
+        json
+    }
+}
+
+/// `values` as a JSON object of numbers, in order
+fn json_object(values: &[(&str, u64)]) -> String {
+    let entries: Vec<String> = values
+        .iter()
+        .map(|&(name, value)| format!("{}:{}", json_string(name), value))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// `value` as a JSON string, quoted and escaped
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::Header;
+    use crate::encoding_map::EncodingMap;
+    use crate::metadata::Metadata;
+    use crate::packing::Packing;
+
+    #[test]
+    fn test_format_spec() {
+        // Only data past MAX_PAYLOAD_SIZE is written with the latest version
+        let input_data = Header {
+            block_type: BlockType::Stored,
+            data_len: MAX_PAYLOAD_SIZE + 1,
+            encoding_map: EncodingMap::default(),
+            stream_sizes: Vec::new(),
+            packing: Packing::Dense,
+            checksum: Some(0x01020304),
+            metadata: Metadata::new(),
+        };
+        let mut encoded = Vec::new();
+        input_data.write_to(&mut encoded).unwrap();
+
+        let test_output = format_spec();
+
+        // The fields up to the map are fixed, and match where an encoding puts them
+        let container = &test_output.layouts[0];
+        let fixed: usize = container
+            .fields
+            .iter()
+            .take_while(|field| field.name != "map")
+            .map(|field| field.width.unwrap())
+            .sum();
+        assert_eq!(20, fixed);
+        assert_eq!(&container.magic, &encoded[..4]);
+        assert_eq!(container.version, encoded[4]);
+        assert_eq!((MAX_PAYLOAD_SIZE + 1).to_le_bytes(), encoded[7..15]);
+        assert_eq!([4, 3, 2, 1], encoded[15..19]);
+        for layout in &test_output.layouts {
+            assert_eq!(Some(4), layout.fields[0].width);
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let input_data = format_spec();
+
+        let test_output = input_data.to_json();
+
+        let json: serde_json::Value = serde_json::from_str(&test_output).unwrap();
+        assert_eq!("HTTB", json["layouts"][1]["magic"]);
+        assert_eq!(
+            serde_json::Value::Null,
+            json["layouts"][0]["fields"][7]["width"]
+        );
+        assert_eq!(256, json["constants"]["escape_symbol"]);
+        assert_eq!(1, json["enumerations"][0]["values"]["dense"]);
+        assert_eq!("\"a\\\"b\\u000a\"", json_string("a\"b\n"));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::blocks::{block_slices, write_blocks};
+use crate::data::{BitVec, UnPaddedBits};
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::packing::Packing;
+
+/// Cuts a Huffman payload after its first `symbols` symbols, returning both halves packed again so each decodes on
+/// its own with the same map. Lets repair tools carve a damaged payload at points an index says symbols align.
+///
+/// # Arguments
+///
+/// * `encoded_data` - A single stream, e.g. a frame payload
+/// * `encoding_map` - The map the payload was encoded with
+/// * `symbols` - How many symbols go into the first half
+/// * `packing` - How the payload is packed, both halves are packed the same way
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let encoding_map = EncodingMap::from(
+///     [(b'a', "0"), (b'b', "10"), (b'c', "11")]
+///         .iter()
+///         .map(|(k, v)| (*k, v.to_string()))
+///         .collect(),
+/// )
+/// .unwrap();
+/// // "abca" as 0 10 11 0 then the end bit
+/// let encoded_data = [0b0101_1010];
+///
+/// let (head, tail) = split_encoded(&encoded_data, &encoding_map, 2, Packing::Dense).unwrap();
+/// assert_eq!(head, [0b0101_0000]);
+/// assert_eq!(join_encoded(&[&head, &tail], Packing::Dense), encoded_data);
+/// ```
+pub fn split_encoded(
+    encoded_data: &[u8],
+    encoding_map: &EncodingMap,
+    symbols: usize,
+    packing: Packing,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let bits = packing.unpack(encoded_data);
+    let mut cursor = 0;
+    let code_lens = (
+        encoding_map.get_shortest_code(),
+        encoding_map.get_longest_code(),
+    );
+    let mut code = BitVec::with_capacity(code_lens.1);
+    for _ in 0..symbols {
+        if HuffmanData::next_symbol(&bits, &mut cursor, &mut code, code_lens, encoding_map)?.is_none()
+        {
+            return Err(HuffmanError::DecodeError(
+                "Encoded data holds fewer symbols than the split point",
+            ));
+        }
+    }
+
+    let (head, tail) = bits.split_at(cursor);
+    Ok((
+        packing.pack(UnPaddedBits::from(head.to_vec()))?,
+        packing.pack(UnPaddedBits::from(tail.to_vec()))?,
+    ))
+}
+
+/// Joins Huffman payloads encoded with the same map into one payload decoding to their concatenation
+///
+/// # Arguments
+///
+/// * `parts` - The payloads in order
+/// * `packing` - How the payloads are packed, the joined payload is packed the same way
+pub fn join_encoded(parts: &[&[u8]], packing: Packing) -> Vec<u8> {
+    let bits: UnPaddedBits = parts
+        .iter()
+        .flat_map(|part| packing.unpack(part))
+        .collect();
+    // Payload bits only hold 0s and 1s so this cannot fail
+    packing.pack(bits).unwrap_or_default()
+}
+
+/// Splits a block encoding written by `HuffmanBlocks::to_bytes` into independent block encodings of
+/// `blocks_per_part` blocks each, the last holding what is left. Blocks are copied as they are, never decoded.
+///
+/// # Arguments
+///
+/// * `bytes` - The block encoding to split
+/// * `blocks_per_part` - How many blocks go into each part, at least 1
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = b"this is a test string!".repeat(100);
+/// let options = BlockOptions { block_size: 500, ..Default::default() };
+/// let bytes = HuffmanBlocks::new(&data, &options).unwrap().to_bytes().unwrap();
+///
+/// let parts = split_blocks(&bytes, 3).unwrap();
+/// assert_eq!(parts.len(), 2);
+/// let parts: Vec<&[u8]> = parts.iter().map(|part| part.as_slice()).collect();
+/// assert_eq!(merge_blocks(&parts).unwrap(), bytes);
+/// ```
+pub fn split_blocks(bytes: &[u8], blocks_per_part: usize) -> Result<Vec<Vec<u8>>> {
+    if blocks_per_part == 0 {
+        return Err(HuffmanError::OptionsError(
+            "A part needs at least one block",
+        ));
+    }
+    block_slices(bytes)?
+        .chunks(blocks_per_part)
+        .map(|part| write_blocks(part.iter().copied()))
+        .collect()
+}
+
+/// Merges block encodings into one holding all of their blocks in order, decoding to their data concatenated.
+/// Every block carries its own map, so any block encodings can be merged whatever options they were encoded with,
+/// including those of older versions. Blocks are copied as they are, never decoded.
+///
+/// # Arguments
+///
+/// * `parts` - The block encodings in order
+pub fn merge_blocks(parts: &[&[u8]]) -> Result<Vec<u8>> {
+    let mut blocks = Vec::new();
+    for part in parts {
+        blocks.extend(block_slices(part)?);
+    }
+    write_blocks(blocks.into_iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::HuffmanBlocks;
+    use crate::huffman::{decode_with_map, encode_with_map};
+    use crate::options::{BlockOptions, DecodeOptions, HuffmanOptions};
+
+    #[test]
+    fn test_split_join_encoded() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!");
+        let encoding_map = HuffmanData::new(&input_data.repeat(8))
+            .unwrap()
+            .encoding_map;
+        let options = DecodeOptions::default();
+
+        for packing in [Packing::Dense, Packing::Padded] {
+            let encoded_data = encode_with_map(&input_data, &encoding_map, packing).unwrap();
+            for symbols in 0..=input_data.len() {
+                let (head, tail) =
+                    split_encoded(&encoded_data, &encoding_map, symbols, packing).unwrap();
+
+                assert_eq!(
+                    input_data[..symbols],
+                    decode_with_map(&head, &encoding_map, packing, &options).unwrap()
+                );
+                assert_eq!(
+                    input_data[symbols..],
+                    decode_with_map(&tail, &encoding_map, packing, &options).unwrap()
+                );
+                assert_eq!(encoded_data, join_encoded(&[&head, &tail], packing));
+            }
+            assert!(
+                split_encoded(&encoded_data, &encoding_map, input_data.len() + 1, packing)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_merge_blocks() {
+        let input_data = b"this is a test string!".repeat(100);
+        let options = BlockOptions {
+            block_size: 300,
+            ..Default::default()
+        };
+        let bytes = HuffmanBlocks::new(&input_data, &options)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+
+        let test_output = split_blocks(&bytes, 3).unwrap();
+
+        assert_eq!(3, test_output.len());
+        let mut decoded_data = Vec::new();
+        for part in &test_output {
+            decoded_data.extend(HuffmanBlocks::from_bytes(part).unwrap().decode().unwrap());
+        }
+        assert_eq!(input_data, decoded_data);
+        assert!(split_blocks(&bytes, 0).is_err());
+
+        // Blocks encoded with other options still merge
+        let other = HuffmanBlocks::new(
+            b"and another test string?",
+            &BlockOptions {
+                huffman: HuffmanOptions {
+                    streams: 2,
+                    ..Default::default()
+                },
+                ..options
+            },
+        )
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+        let mut expected_data = input_data.clone();
+        expected_data.extend_from_slice(b"and another test string?");
+        let merged =
+            merge_blocks(&[&test_output[0], &test_output[1], &test_output[2], &other]).unwrap();
+        assert_eq!(
+            expected_data,
+            HuffmanBlocks::from_bytes(&merged)
+                .unwrap()
+                .decode()
+                .unwrap()
+        );
+    }
+}
@@ -0,0 +1,849 @@
+use crate::block_type::BlockType;
+use crate::container::{in_memory_size, Header, WIDE_CHECKSUM_OFFSET, WIDE_DATA_LEN_OFFSET};
+use crate::crc32::Crc32;
+use crate::data::BitSlice;
+use crate::decoder::HuffmanDecoder;
+use crate::encoding_map::EncodingMap;
+use crate::encoding_stats::EncodingStats;
+use crate::error::{HuffmanError, Result};
+use crate::frequency_map::FrequencyMap;
+use crate::huffman::{check_checksum, HuffmanData};
+use crate::huffman_tree;
+use crate::metadata::Metadata;
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+use crate::rle;
+use crate::table_decoder::TableDecoder;
+
+use std::io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write};
+
+/// Size of the chunks read from the input and written to the output
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Huffman encodes everything `input` holds into `output` as a self-contained encoding (header then payload),
+/// holding only a chunk of input and output in memory at a time.
+///
+/// The input is read twice, once to count u8 frequencies and once more after seeking back to where it started to
+/// emit codes, so it has to be seekable (e.g. a `File`). Unlike `HuffmanData::new` the payload is always a single
+/// Huffman stream.
+///
+/// # Arguments
+///
+/// * `input` - The data to encode, read from its current position to the end
+/// * `output` - Where the encoding is written
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use std::io::Cursor;
+///
+/// let data = b"this is a test string!".repeat(8);
+/// let mut output = Vec::new();
+/// let stats = encode_to_writer(&mut Cursor::new(&data), &mut output).unwrap();
+///
+/// let huffman_data = HuffmanData::new(&data).unwrap();
+/// assert_eq!(stats.payload_bits, huffman_data.stats.payload_bits);
+/// assert!(output.ends_with(&huffman_data.encoded_data));
+/// ```
+pub fn encode_to_writer<R: Read + Seek, W: Write>(
+    input: &mut R,
+    output: &mut W,
+) -> Result<EncodingStats> {
+    let start = input.stream_position()?;
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let (frequency_map, data_len, checksum) = count_frequencies(input, &mut chunk)?;
+
+    let header = Header {
+        data_len,
+        checksum: Some(checksum),
+        ..stream_header(&frequency_map, 0)?
+    };
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes)?;
+    output.write_all(&header_bytes)?;
+
+    input.seek(SeekFrom::Start(start))?;
+    let (_, payload_bits, payload_size) = write_codes(input, output, &header, &mut chunk, None)?;
+
+    Ok(EncodingStats::from_sizes(
+        data_len,
+        header_bytes.len() as u64 + payload_size,
+        payload_bits,
+    ))
+}
+
+/// Huffman encodes data too large to hold in memory from a source opened once per pass, so it only has to be an
+/// `io::Read` such as a `File` or a decompressing reader, never a buffer. The first pass counts u8 frequencies, the
+/// second emits codes into a single Huffman stream, as `encode_to_writer` does.
+///
+/// With a sample size set, frequencies are estimated from that many u8s at the start of the source and the tree
+/// gets an escape code covering u8s the sample missed. Only that prefix is read in the first pass, and the size and
+/// checksum of the data, which go in the header ahead of the payload, are filled in by seeking back once the
+/// payload is written, so sampled encodings are written by `encode_to_seekable` while `encode_to` takes any
+/// `io::Write`.
+pub struct TwoPassEncoder<F> {
+    open: F,
+    sample_size: Option<u64>,
+}
+
+impl<R: Read, F: FnMut() -> io::Result<R>> TwoPassEncoder<F> {
+    /// Creates an encoder reading its input from whatever `open` returns, called once per pass
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - Opens the data to encode from its start, every call has to yield the same data
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use std::io::Cursor;
+    ///
+    /// let data = b"this is a test string!".repeat(8);
+    /// let mut output = Vec::new();
+    /// TwoPassEncoder::new(|| Ok(data.as_slice()))
+    ///     .encode_to(&mut output)
+    ///     .unwrap();
+    /// assert_eq!(decode(&output).unwrap(), data);
+    ///
+    /// let mut output = Cursor::new(Vec::new());
+    /// TwoPassEncoder::new(|| Ok(data.as_slice()))
+    ///     .with_sample_size(16)
+    ///     .encode_to_seekable(&mut output)
+    ///     .unwrap();
+    /// assert_eq!(decode(&output.into_inner()).unwrap(), data);
+    /// ```
+    pub fn new(open: F) -> Self {
+        TwoPassEncoder {
+            open,
+            sample_size: None,
+        }
+    }
+
+    /// Estimates frequencies from the first `sample_size` u8s of the input instead of all of it
+    pub fn with_sample_size(mut self, sample_size: u64) -> Self {
+        self.sample_size = Some(sample_size);
+        self
+    }
+
+    /// Encodes the input into `output`, written front to back so any `io::Write` such as a socket or pipe will do.
+    /// Fails with `OptionsError` when a sample size is set, as only `encode_to_seekable` can complete that header
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Where the encoding is written
+    pub fn encode_to<W: Write>(&mut self, output: &mut W) -> Result<EncodingStats> {
+        if self.sample_size.is_some() {
+            return Err(HuffmanError::OptionsError(
+                "Sampled encodings need a seekable output, see encode_to_seekable",
+            ));
+        }
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        let (frequency_map, data_len, checksum) =
+            count_frequencies(&mut (self.open)()?, &mut chunk)?;
+        let header = Header {
+            data_len,
+            checksum: Some(checksum),
+            ..stream_header(&frequency_map, 0)?
+        };
+        let mut header_bytes = Vec::new();
+        header.write_to(&mut header_bytes)?;
+        output.write_all(&header_bytes)?;
+
+        let (_, payload_bits, payload_size) =
+            write_codes(&mut (self.open)()?, output, &header, &mut chunk, None)?;
+        Ok(EncodingStats::from_sizes(
+            data_len,
+            header_bytes.len() as u64 + payload_size,
+            payload_bits,
+        ))
+    }
+
+    /// Encodes the input into `output` like `encode_to`, sampling it when a sample size is set and then seeking back
+    /// to complete the header
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Where the encoding is written, from its current position
+    pub fn encode_to_seekable<W: Write + Seek>(&mut self, output: &mut W) -> Result<EncodingStats> {
+        let Some(sample_size) = self.sample_size else {
+            return self.encode_to(output);
+        };
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        if sample_size == 0 {
+            return Err(HuffmanError::OptionsError("Sample size must be at least 1"));
+        }
+
+        let (frequency_map, ..) =
+            count_frequencies(&mut (self.open)()?.take(sample_size), &mut chunk)?;
+        let header = Header {
+            checksum: Some(0),
+            ..stream_header(&frequency_map, 1)?
+        };
+        let start = output.stream_position()?;
+        let mut header_bytes = Vec::new();
+        header.write_wide_to(&mut header_bytes)?;
+        output.write_all(&header_bytes)?;
+
+        let mut crc = Crc32::default();
+        let (data_len, payload_bits, payload_size) = write_codes(
+            &mut (self.open)()?,
+            output,
+            &header,
+            &mut chunk,
+            Some(&mut crc),
+        )?;
+        let end = output.stream_position()?;
+        output.seek(SeekFrom::Start(start + WIDE_DATA_LEN_OFFSET))?;
+        output.write_all(&data_len.to_le_bytes())?;
+        output.seek(SeekFrom::Start(start + WIDE_CHECKSUM_OFFSET))?;
+        output.write_all(&crc.finish().to_le_bytes())?;
+        output.seek(SeekFrom::Start(end))?;
+
+        Ok(EncodingStats::from_sizes(
+            data_len,
+            header_bytes.len() as u64 + payload_size,
+            payload_bits,
+        ))
+    }
+}
+
+/// Counts the u8 frequencies of everything `input` holds, returning them with its size and checksum
+fn count_frequencies(input: &mut impl Read, chunk: &mut [u8]) -> Result<(FrequencyMap, u64, u32)> {
+    let mut frequencies = [0i64; 256];
+    let mut data_len: u64 = 0;
+    let mut crc = Crc32::default();
+    while let Some(read) = read_chunk(input, chunk)? {
+        for &byte in &chunk[..read] {
+            frequencies[byte as usize] += 1;
+        }
+        crc.update(&chunk[..read]);
+        data_len += read as u64;
+    }
+
+    let frequency_map = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, &freq)| freq > 0)
+        .map(|(byte, &freq)| (byte as u8, freq))
+        .collect();
+    Ok((frequency_map, data_len, crc.finish()))
+}
+
+/// Builds the header of a single dense stream coded from `frequency_map`, its escape weighted `escape_freq`, with no
+/// size or checksum yet. Empty data gets a stored block
+fn stream_header(frequency_map: &FrequencyMap, escape_freq: i64) -> Result<Header> {
+    let (block_type, encoding_map) = match frequency_map.is_empty() {
+        true => (BlockType::Stored, EncodingMap::default()),
+        false => (
+            BlockType::Huffman,
+            EncodingMap::new(&huffman_tree::build_with_escape(
+                frequency_map,
+                escape_freq,
+            )?)?,
+        ),
+    };
+    Ok(Header {
+        block_type,
+        data_len: 0,
+        encoding_map,
+        stream_sizes: Vec::new(),
+        packing: Packing::Dense,
+        checksum: None,
+        metadata: Metadata::new(),
+    })
+}
+
+/// Writes the code of every u8 `input` holds as the payload of `header`, updating `crc` along the way, and returns
+/// the number of u8s read, the payload bit count and the number of u8s written.
+/// Fails with `SymbolError` on a u8 the map has no code for, which only input changed since it was counted holds
+fn write_codes<W: Write>(
+    input: &mut impl Read,
+    output: &mut W,
+    header: &Header,
+    chunk: &mut [u8],
+    mut crc: Option<&mut Crc32>,
+) -> Result<(u64, u64, u64)> {
+    let escape = header.encoding_map.get_escape();
+    let mut data_len: u64 = 0;
+    let mut writer = PackedWriter::new(output, header.packing);
+    while let Some(read) = read_chunk(input, chunk)? {
+        for &byte in &chunk[..read] {
+            match (header.encoding_map.get(&byte), escape) {
+                (Some(code), _) => writer.write_bits(code)?,
+                (None, Some(escape)) => {
+                    writer.write_bits(escape)?;
+                    writer
+                        .write_bits(&std::array::from_fn::<u8, 8, _>(|i| (byte >> (7 - i)) & 1))?;
+                }
+                (None, None) => {
+                    return Err(HuffmanError::SymbolError(
+                        byte,
+                        "Symbol has no code in the encoding map, the input changed between passes",
+                    ))
+                }
+            }
+        }
+        if let Some(crc) = crc.as_deref_mut() {
+            crc.update(&chunk[..read]);
+        }
+        data_len += read as u64;
+    }
+    let (payload_bits, payload_size) = match header.block_type {
+        BlockType::Huffman => writer.finish()?,
+        _ => (0, 0),
+    };
+    Ok((data_len, payload_bits, payload_size))
+}
+
+/// Decodes a self-contained encoding read from `input`, writing decoded u8s to `output` as soon as they are resolved
+/// so neither side has to fit in memory. Returns the number of u8s decoded.
+///
+/// Single stream payloads, such as those written by `encode_to_writer`, are decoded a chunk at a time.
+/// Payloads split into several interleaved streams are read whole first, as their streams cannot be consumed side by side
+/// from a single reader.
+///
+/// # Arguments
+///
+/// * `input` - The encoding, as written by `encode_to_writer` or `encode`
+/// * `output` - Where decoded u8s are written
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use std::io::Cursor;
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let mut encoded_data = Vec::new();
+/// encode_to_writer(&mut Cursor::new(&data), &mut encoded_data).unwrap();
+///
+/// let mut decoded_data = Vec::new();
+/// decode_from_reader(&mut encoded_data.as_slice(), &mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, data);
+/// ```
+pub fn decode_from_reader<R: Read, W: Write>(input: &mut R, output: &mut W) -> Result<u64> {
+    let header = Header::read_from(input)?;
+    decode_payload(header, input, output)
+}
+
+/// Decodes the payload following `header` a chunk at a time, whatever its block type
+fn decode_payload(header: Header, input: &mut impl Read, output: &mut impl Write) -> Result<u64> {
+    let mut check = DecodedCheck::new(&header);
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    let mut decoded = Vec::with_capacity(CHUNK_SIZE);
+
+    let mut decoder = PayloadDecoder::new(header);
+    while let Some(read) = read_chunk(input, &mut chunk)? {
+        decoder.feed(&chunk[..read], &mut decoded)?;
+        flush_decoded(output, &mut decoded, &mut check)?;
+    }
+    decoder.finish(&mut decoded)?;
+    flush_decoded(output, &mut decoded, &mut check)?;
+
+    check.finish()
+}
+
+/// Decodes an encoding from a buffered reader into `output`, like `decode_from_reader`, returning the number of decoded
+/// u8s written.
+///
+/// Single stream Huffman payloads are decoded straight out of the reader's internal buffer, a whole buffer at a time,
+/// with a table lookup per code instead of a map probe per bit.
+///
+/// # Arguments
+///
+/// * `input` - The encoding, as written by `encode_to_writer` or `encode`
+/// * `output` - Where decoded u8s are written
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+/// use std::io::BufReader;
+///
+/// let data: Vec<u8> = Vec::from("this is a test string!");
+/// let encoded_data = encode(&data).unwrap();
+///
+/// let mut decoded_data = Vec::new();
+/// decode_from_buf_read(&mut BufReader::new(encoded_data.as_slice()), &mut decoded_data).unwrap();
+/// assert_eq!(decoded_data, data);
+/// ```
+pub fn decode_from_buf_read<R: BufRead, W: Write>(input: &mut R, output: &mut W) -> Result<u64> {
+    let header = Header::read_from(input)?;
+    let decoder = match header.block_type {
+        BlockType::Huffman if header.stream_sizes.is_empty() => {
+            TableDecoder::new(
+                header.encoding_map.clone(),
+                header.packing,
+                &DecodeOptions::default(),
+            )
+        }
+        _ => None,
+    };
+    let Some(mut decoder) = decoder else {
+        return decode_payload(header, input, output);
+    };
+
+    let mut check = DecodedCheck::new(&header);
+    let mut decoded = Vec::with_capacity(CHUNK_SIZE);
+    loop {
+        let buffer = match input.fill_buf() {
+            Ok([]) => break,
+            Ok(buffer) => buffer,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        decoder.feed(buffer, &mut decoded)?;
+        let consumed = buffer.len();
+        input.consume(consumed);
+        flush_decoded(output, &mut decoded, &mut check)?;
+    }
+    decoder.finish(&mut decoded)?;
+    flush_decoded(output, &mut decoded, &mut check)?;
+
+    check.finish()
+}
+
+impl HuffmanData {
+    /// Decodes the data into `output` a chunk at a time, returning the number of decoded u8s written.
+    /// Only a chunk of decoded data is held at once, except for payloads split into interleaved streams which are
+    /// decoded whole first.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Where decoded u8s are written
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let huffman_data = HuffmanData::new(&data).unwrap();
+    ///
+    /// let mut decoded_data = Vec::new();
+    /// assert_eq!(huffman_data.decode_to_writer(&mut decoded_data).unwrap(), 22);
+    /// assert_eq!(decoded_data, data);
+    /// ```
+    pub fn decode_to_writer(&self, output: &mut impl Write) -> Result<u64> {
+        let header = Header {
+            block_type: self.block_type,
+            data_len: self.data_len as u64,
+            encoding_map: self.encoding_map.clone(),
+            stream_sizes: self.stream_sizes.clone(),
+            packing: self.packing,
+            checksum: self.checksum,
+            metadata: Metadata::new(),
+        };
+        decode_payload(header, &mut self.encoded_data.as_slice(), output)
+    }
+}
+
+/// Decodes the payload following a `Header` from chunks of any size, whatever its block type
+pub(crate) enum PayloadDecoder {
+    Huffman(HuffmanDecoder),
+    /// Interleaved streams are buffered whole, as they cannot be consumed side by side from a single reader
+    Streams(Header, Vec<u8>),
+    Stored,
+    /// A chunk may end between the run length and the u8 of a pair, which is held until the next chunk
+    Rle(Option<u8>),
+}
+
+impl PayloadDecoder {
+    pub fn new(header: Header) -> Self {
+        match header.block_type {
+            BlockType::Huffman if header.stream_sizes.is_empty() => PayloadDecoder::Huffman(
+                HuffmanDecoder::with_packing(header.encoding_map, header.packing),
+            ),
+            BlockType::Huffman => PayloadDecoder::Streams(header, Vec::new()),
+            BlockType::Stored => PayloadDecoder::Stored,
+            BlockType::Rle => PayloadDecoder::Rle(None),
+        }
+    }
+
+    /// Decodes the next chunk of payload, pushing every u8 resolved so far to `decoded`
+    pub fn feed(&mut self, chunk: &[u8], decoded: &mut Vec<u8>) -> Result<()> {
+        match self {
+            PayloadDecoder::Huffman(decoder) => decoded.extend(decoder.feed(chunk)?),
+            PayloadDecoder::Streams(_, encoded_data) => encoded_data.extend_from_slice(chunk),
+            PayloadDecoder::Stored => decoded.extend_from_slice(chunk),
+            PayloadDecoder::Rle(held) => {
+                let mut pairs = chunk;
+                if let Some(run) = held.take() {
+                    let Some((&byte, rest)) = pairs.split_first() else {
+                        *held = Some(run);
+                        return Ok(());
+                    };
+                    decoded.extend(rle::decode(&[run, byte])?);
+                    pairs = rest;
+                }
+                if pairs.len() % 2 == 1 {
+                    *held = pairs.last().copied();
+                    pairs = &pairs[..pairs.len() - 1];
+                }
+                decoded.extend(rle::decode(pairs)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes whatever the payload still holds once its last chunk has been fed
+    pub fn finish(self, decoded: &mut Vec<u8>) -> Result<()> {
+        match self {
+            PayloadDecoder::Huffman(decoder) => decoded.extend(decoder.finish()?),
+            PayloadDecoder::Streams(header, encoded_data) => {
+                let huffman_data = HuffmanData {
+                    encoded_data,
+                    encoding_map: header.encoding_map,
+                    stats: EncodingStats::from_sizes(header.data_len, 0, 0),
+                    stream_sizes: header.stream_sizes,
+                    block_type: header.block_type,
+                    data_len: in_memory_size(header.data_len)?,
+                    packing: header.packing,
+                    // Checked by whoever feeds the decoder, along with the decoded size
+                    checksum: None,
+                    metadata: Metadata::new(),
+                };
+                decoded.extend(huffman_data.decode()?);
+            }
+            PayloadDecoder::Stored => {}
+            PayloadDecoder::Rle(held) => decoded.extend(rle::decode(held.as_slice())?),
+        }
+        Ok(())
+    }
+}
+
+/// Counts decoded u8s and their CRC-32 as they are produced, so both can be checked against the header at the end
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DecodedCheck {
+    data_len: u64,
+    checksum: Option<u32>,
+    decoded_len: u64,
+    crc: Crc32,
+}
+
+impl DecodedCheck {
+    pub fn new(header: &Header) -> Self {
+        DecodedCheck {
+            data_len: header.data_len,
+            checksum: header.checksum,
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, decoded: &[u8]) {
+        self.decoded_len += decoded.len() as u64;
+        if self.checksum.is_some() {
+            self.crc.update(decoded);
+        }
+    }
+
+    /// Fails unless everything decoded matches the size and checksum of the header, returning the decoded size
+    pub fn finish(&self) -> Result<u64> {
+        if self.decoded_len != self.data_len {
+            return Err(HuffmanError::DecodeError(
+                "Decoded size does not match the header",
+            ));
+        }
+        if let Some(checksum) = self.checksum {
+            check_checksum(checksum, self.crc.finish())?;
+        }
+        Ok(self.decoded_len)
+    }
+}
+
+fn flush_decoded(
+    output: &mut impl Write,
+    decoded: &mut Vec<u8>,
+    check: &mut DecodedCheck,
+) -> Result<()> {
+    output.write_all(decoded)?;
+    check.update(decoded);
+    decoded.clear();
+    Ok(())
+}
+
+/// Fills `chunk` as far as the reader allows, returning `None` at the end of the input
+pub(crate) fn read_chunk(input: &mut impl Read, chunk: &mut [u8]) -> Result<Option<usize>> {
+    loop {
+        match input.read(chunk) {
+            Ok(0) => return Ok(None),
+            Ok(read) => return Ok(Some(read)),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Packs payload bits into u8s laid out as `packing` says and flushes them to a writer a chunk at a time
+struct PackedWriter<'a, W: Write> {
+    writer: &'a mut W,
+    packing: Packing,
+    buffer: Vec<u8>,
+    byte: u8,
+    bit_count: u32,
+    payload_bits: u64,
+    written: u64,
+}
+
+impl<'a, W: Write> PackedWriter<'a, W> {
+    fn new(writer: &'a mut W, packing: Packing) -> Self {
+        PackedWriter {
+            writer,
+            packing,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            byte: Self::empty_byte(packing),
+            bit_count: 0,
+            payload_bits: 0,
+            written: 0,
+        }
+    }
+
+    /// A padded u8 starts out holding its marker bit, which every payload bit shifts up
+    fn empty_byte(packing: Packing) -> u8 {
+        match packing {
+            Packing::Padded => 1,
+            Packing::Dense => 0,
+        }
+    }
+
+    fn write_bits(&mut self, bits: &BitSlice) -> Result<()> {
+        for &bit in bits {
+            if self.bit_count == self.packing.bits_per_u8() {
+                self.buffer.push(self.byte);
+                self.byte = Self::empty_byte(self.packing);
+                self.bit_count = 0;
+                if self.buffer.len() == CHUNK_SIZE {
+                    self.flush()?;
+                }
+            }
+            self.byte = (self.byte << 1) | bit;
+            self.bit_count += 1;
+        }
+        self.payload_bits += bits.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the last, possibly partial, u8 and returns the payload bit count and the number of u8s written
+    fn finish(mut self) -> Result<(u64, u64)> {
+        if self.packing == Packing::Dense {
+            if self.bit_count == 8 {
+                self.buffer.push(self.byte);
+                self.byte = 0;
+                self.bit_count = 0;
+            }
+            // The end bit, then zeros up to the u8 boundary
+            self.byte = ((self.byte << 1) | 1) << (7 - self.bit_count);
+        }
+        self.buffer.push(self.byte);
+        self.flush()?;
+        Ok((self.payload_bits, self.written))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.written += self.buffer.len() as u64;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{decode, encode};
+    use crate::data::{BitVector, UnPaddedBits};
+    use crate::options::HuffmanOptions;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_encode_to_writer() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let huffman_data = HuffmanData::new(&input_data).unwrap();
+
+        let mut test_output = Vec::new();
+        let stats = encode_to_writer(&mut Cursor::new(&input_data), &mut test_output).unwrap();
+        let mut expected_header = Vec::new();
+        Header {
+            block_type: BlockType::Huffman,
+            data_len: input_data.len() as u64,
+            encoding_map: huffman_data.encoding_map.clone(),
+            stream_sizes: Vec::new(),
+            packing: Packing::Dense,
+            checksum: huffman_data.checksum,
+            metadata: Metadata::new(),
+        }
+        .write_to(&mut expected_header)
+        .unwrap();
+
+        assert_eq!(
+            [expected_header, huffman_data.encoded_data].concat(),
+            test_output
+        );
+        assert_eq!(huffman_data.stats.payload_bits, stats.payload_bits);
+    }
+
+    #[test]
+    fn test_two_pass_encoder() {
+        // The sample only covers the lowercase letters, the rest escapes
+        let input_data: Vec<u8> = [b"aaaabbbc".repeat(100), (0..=255).collect()].concat();
+        let opened = std::cell::Cell::new(0);
+        let mut open = || {
+            opened.set(opened.get() + 1);
+            Ok(input_data.as_slice())
+        };
+
+        let mut expected_data = Vec::new();
+        encode_to_writer(&mut Cursor::new(&input_data), &mut expected_data).unwrap();
+        let mut test_output = Vec::new();
+        TwoPassEncoder::new(&mut open)
+            .encode_to(&mut test_output)
+            .unwrap();
+        assert_eq!(expected_data, test_output);
+        let mut test_output = Cursor::new(Vec::new());
+        TwoPassEncoder::new(&mut open)
+            .encode_to_seekable(&mut test_output)
+            .unwrap();
+        assert_eq!(expected_data, test_output.into_inner());
+
+        // Sampled encodings land after whatever the output already holds
+        let mut test_output = Cursor::new(b"prefix".to_vec());
+        test_output.seek(SeekFrom::End(0)).unwrap();
+        let stats = TwoPassEncoder::new(&mut open)
+            .with_sample_size(800)
+            .encode_to_seekable(&mut test_output)
+            .unwrap();
+        let test_output = test_output.into_inner();
+        assert_eq!(6, opened.get());
+        assert_eq!(input_data.len() as u64, stats.data_len);
+        assert_eq!(test_output.len() as u64 - 6, stats.encoded_len);
+        assert_eq!(input_data, decode(&test_output[6..]).unwrap());
+        let mut decoded = Vec::new();
+        decode_from_reader(&mut &test_output[6..], &mut decoded).unwrap();
+        assert_eq!(input_data, decoded);
+
+        assert!(TwoPassEncoder::new(&mut open)
+            .with_sample_size(0)
+            .encode_to_seekable(&mut Cursor::new(Vec::new()))
+            .is_err());
+        assert!(matches!(
+            TwoPassEncoder::new(&mut open)
+                .with_sample_size(800)
+                .encode_to(&mut Vec::new()),
+            Err(HuffmanError::OptionsError(_))
+        ));
+
+        // A u8 the first pass never counted is an encoding error, not a decoding one
+        let mut passes = [b"ab".as_slice(), b"abc".as_slice()].into_iter();
+        assert!(matches!(
+            TwoPassEncoder::new(|| Ok(passes.next().unwrap_or_default()))
+                .encode_to(&mut Vec::new()),
+            Err(HuffmanError::SymbolError(b'c', _))
+        ));
+    }
+
+    #[test]
+    fn test_packed_writer() {
+        // Lengths filling the last u8 exactly with either packing
+        for input_data in ["10110110101", "1011011", "10110110", ""] {
+            let input_data = UnPaddedBits::from_string(input_data);
+
+            for packing in [Packing::Padded, Packing::Dense] {
+                let expected_data = packing.pack(input_data.clone()).unwrap();
+
+                let mut test_output = Vec::new();
+                let mut writer = PackedWriter::new(&mut test_output, packing);
+                writer.write_bits(&input_data).unwrap();
+
+                assert_eq!(
+                    (input_data.len() as u64, expected_data.len() as u64),
+                    writer.finish().unwrap()
+                );
+                assert_eq!(expected_data, test_output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_from_reader() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let mut encoded_data = Vec::new();
+        encode_to_writer(&mut Cursor::new(&input_data), &mut encoded_data).unwrap();
+        let interleaved = HuffmanData::with_options(
+            &input_data,
+            &HuffmanOptions {
+                streams: 4,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+        // Long runs pick the run-length block type
+        let rle_data = vec![b'a'; 1_000_000];
+
+        for encoded_data in [encoded_data, interleaved] {
+            let mut test_output = Vec::new();
+            let decoded_len =
+                decode_from_reader(&mut encoded_data.as_slice(), &mut test_output).unwrap();
+
+            assert_eq!(input_data, test_output);
+            assert_eq!(input_data.len() as u64, decoded_len);
+        }
+
+        let mut test_output = Vec::new();
+        decode_from_reader(&mut encode(&rle_data).unwrap().as_slice(), &mut test_output).unwrap();
+        assert_eq!(rle_data, test_output);
+    }
+
+    #[test]
+    fn test_decode_from_buf_read() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let mut encoded_data = Vec::new();
+        encode_to_writer(&mut Cursor::new(&input_data), &mut encoded_data).unwrap();
+
+        // Buffers small enough to split codes and the header across refills
+        for capacity in [1, 7, 8192] {
+            let mut input = BufReader::with_capacity(capacity, encoded_data.as_slice());
+            let mut test_output = Vec::new();
+            let decoded_len = decode_from_buf_read(&mut input, &mut test_output).unwrap();
+
+            assert_eq!(input_data, test_output);
+            assert_eq!(input_data.len() as u64, decoded_len);
+        }
+
+        let rle_data = vec![b'a'; 1_000_000];
+        let mut test_output = Vec::new();
+        decode_from_buf_read(&mut encode(&rle_data).unwrap().as_slice(), &mut test_output).unwrap();
+        assert_eq!(rle_data, test_output);
+    }
+
+    #[test]
+    fn test_decode_to_writer() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let rle_data = vec![b'a'; 1_000_000];
+
+        for (input_data, streams) in [(&input_data, 1), (&input_data, 4), (&rle_data, 1)] {
+            let huffman_data = HuffmanData::with_options(
+                input_data,
+                &HuffmanOptions {
+                    streams,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            let mut test_output = Vec::new();
+            let decoded_len = huffman_data.decode_to_writer(&mut test_output).unwrap();
+
+            assert_eq!(*input_data, test_output);
+            assert_eq!(input_data.len() as u64, decoded_len);
+        }
+    }
+}
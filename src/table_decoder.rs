@@ -0,0 +1,243 @@
+use crate::data::BitVec;
+use crate::encoding_map::EncodingMap;
+use crate::error::{HuffmanError, Result};
+use crate::options::DecodeOptions;
+use crate::packing::Packing;
+
+/// Bits looked up at once, codes up to this long resolve with a single table probe
+const TABLE_BITS: u32 = 11;
+
+/// Bits the u64 accumulator holds, a single step may need all of them but the ones the next u8 brings in
+const ACCUMULATOR_BITS: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Invalid,
+    /// The symbol and its code length
+    Symbol(u8, u32),
+    /// The escape code length, 8 literal bits follow
+    Escape(u32),
+    /// The code is longer than the table, resolved bit by bit
+    Long,
+}
+
+/// Decodes a single stream whose encoded u8s arrive in chunks, like `HuffmanDecoder`, but resolves codes with
+/// a table lookup on a bit accumulator rather than a map probe per bit. Bits left over at the end of a chunk stay in
+/// the accumulator, only the tail of the stream and codes longer than the table are resolved bit by bit.
+pub(crate) struct TableDecoder {
+    encoding_map: EncodingMap,
+    packing: Packing,
+    table: Vec<Entry>,
+    table_bits: u32,
+    longest_code: u32,
+    /// Bits a step may consume: the longest code, plus 8 literal bits when there is an escape
+    step_bits: u32,
+    accumulator: u64,
+    bits: u32,
+    /// The last u8 only holds as many bits as its packing says, so every u8 is held back until the next one arrives
+    held: Option<u8>,
+    options: DecodeOptions,
+    /// Symbols decoded and bits fed so far, checked against `options.max_symbols_per_bit`
+    symbols: usize,
+    input_bits: usize,
+}
+
+impl TableDecoder {
+    /// Returns `None` when the map has no codes, or codes too long for the accumulator
+    pub fn new(encoding_map: EncodingMap, packing: Packing, options: &DecodeOptions) -> Option<Self> {
+        let longest_code = encoding_map.get_longest_code() as u32;
+        let step_bits = match encoding_map.get_escape() {
+            Some(_) => longest_code + 8,
+            None => longest_code,
+        };
+        if longest_code == 0 || step_bits > ACCUMULATOR_BITS - packing.bits_per_u8() {
+            return None;
+        }
+
+        let table_bits = longest_code.min(TABLE_BITS);
+        let mut table = vec![Entry::Invalid; 1 << table_bits];
+        let codes = encoding_map
+            .iter()
+            .map(|(&symbol, code)| (Entry::Symbol(symbol, code.len() as u32), code))
+            .chain(
+                encoding_map
+                    .get_escape()
+                    .map(|code| (Entry::Escape(code.len() as u32), code)),
+            );
+        for (entry, code) in codes {
+            let (entry, prefix) = match code.len() as u32 <= table_bits {
+                true => (entry, code.as_slice()),
+                false => (Entry::Long, &code[..table_bits as usize]),
+            };
+            let unused = table_bits - prefix.len() as u32;
+            let first = prefix
+                .iter()
+                .fold(0usize, |index, &bit| (index << 1) | bit as usize)
+                << unused;
+            table[first..first + (1 << unused)].fill(entry);
+        }
+
+        Some(TableDecoder {
+            encoding_map,
+            packing,
+            table,
+            table_bits,
+            longest_code,
+            step_bits,
+            accumulator: 0,
+            bits: 0,
+            held: None,
+            options: options.clone(),
+            symbols: 0,
+            input_bits: 0,
+        })
+    }
+
+    /// Decodes the next chunk of encoded u8s, pushing every u8 resolved so far to `decoded`
+    pub fn feed(&mut self, chunk: &[u8], decoded: &mut Vec<u8>) -> Result<()> {
+        for &byte in chunk {
+            let Some(previous) = self.held.replace(byte) else {
+                continue;
+            };
+            let count = self.packing.bits_per_u8();
+            self.accumulator =
+                (self.accumulator << count) | self.packing.full_bits(previous)? as u64;
+            self.bits += count;
+            self.input_bits += count as usize;
+            while self.bits >= self.step_bits {
+                let index = (self.accumulator >> (self.bits - self.table_bits)) as usize
+                    & (self.table.len() - 1);
+                let entry = match self.table[index] {
+                    Entry::Long => self
+                        .match_bits(self.table_bits + 1)?
+                        .ok_or(HuffmanError::DecodeError("Invalid code in encoded data"))?,
+                    entry => entry,
+                };
+                self.consume(entry, decoded)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the held back last u8 and the bits left in the accumulator. Leftover bits that do not complete a code
+    /// are ignored, as they are by a non-strict decode.
+    pub fn finish(mut self, decoded: &mut Vec<u8>) -> Result<()> {
+        if let Some(last) = self.held.take() {
+            let (last, count) = self.packing.last_bits(last);
+            self.accumulator = (self.accumulator << count) | last as u64;
+            self.bits += count;
+            self.input_bits += count as usize;
+        }
+        while let Some(entry) = self.match_bits(1)? {
+            if !self.consume(entry, decoded)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the code at the top of the accumulator bit by bit, trying lengths from `min_len` up.
+    /// Returns `None` when the accumulator runs out first.
+    fn match_bits(&self, min_len: u32) -> Result<Option<Entry>> {
+        let available = self.bits.min(self.longest_code);
+        let mut code = BitVec::with_capacity(available as usize);
+        for len in 1..=available {
+            code.push((self.accumulator >> (self.bits - len)) as u8 & 1);
+            if len < min_len {
+                continue;
+            }
+            if let Some(&symbol) = self.encoding_map.get_inverse(&code) {
+                return Ok(Some(Entry::Symbol(symbol, len)));
+            }
+            if self.encoding_map.get_escape() == Some(&code) {
+                return Ok(Some(Entry::Escape(len)));
+            }
+        }
+        match available == self.longest_code {
+            true => Err(HuffmanError::DecodeError("Invalid code in encoded data")),
+            false => Ok(None),
+        }
+    }
+
+    /// Removes the bits of `entry` from the accumulator and pushes its u8, returning false if an escape is missing
+    /// some of its literal bits
+    fn consume(&mut self, entry: Entry, decoded: &mut Vec<u8>) -> Result<bool> {
+        match entry {
+            Entry::Symbol(symbol, len) => {
+                self.bits -= len;
+                decoded.push(symbol);
+            }
+            Entry::Escape(len) if self.bits >= len + 8 => {
+                self.bits -= len + 8;
+                decoded.push((self.accumulator >> self.bits) as u8);
+            }
+            Entry::Escape(_) => return Ok(false),
+            Entry::Invalid | Entry::Long => {
+                return Err(HuffmanError::DecodeError("Invalid code in encoded data"))
+            }
+        }
+        self.accumulator &= (1 << self.bits) - 1;
+        self.symbols += 1;
+        self.options.check_run(self.symbols, self.input_bits)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::BitVector;
+    use crate::huffman::encode_with_map;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_table_decoder() {
+        // Codes both shorter and longer than the table, plus an escape
+        let map: HashMap<u8, String> = [(b'a', "0"), (b'b', "10"), (b'c', "1100000000000")]
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect();
+        let encoding_map = EncodingMap::from(map)
+            .unwrap()
+            .with_escape(BitVec::from_string("111"))
+            .unwrap();
+        let expected_data: Vec<u8> = Vec::from("abcabczaabbcc!").repeat(20);
+        for packing in [Packing::Padded, Packing::Dense] {
+            let input_data = encode_with_map(&expected_data, &encoding_map, packing).unwrap();
+            for chunk_size in [1, 3, 64] {
+                let mut decoder =
+                    TableDecoder::new(encoding_map.clone(), packing, &DecodeOptions::default())
+                        .unwrap();
+                let mut test_output = Vec::new();
+                for chunk in input_data.chunks(chunk_size) {
+                    decoder.feed(chunk, &mut test_output).unwrap();
+                }
+                decoder.finish(&mut test_output).unwrap();
+
+                assert_eq!(expected_data, test_output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_decoder_run_cap() {
+        // An empty code resolves without consuming any bits, so only the run cap stops the decoder
+        let encoding_map = EncodingMap::from_map(
+            [(b'a', BitVec::new()), (b'b', BitVec::from_string("1"))]
+                .into_iter()
+                .collect(),
+            None,
+        );
+        let mut decoder =
+            TableDecoder::new(encoding_map, Packing::Dense, &DecodeOptions::default()).unwrap();
+        let mut test_output = Vec::new();
+
+        assert!(matches!(
+            decoder.feed(&[0, 0, 0x80], &mut test_output),
+            Err(HuffmanError::DecodeError(
+                "Decoded more symbols than the input bits allow"
+            ))
+        ));
+        assert_eq!(9, test_output.len());
+    }
+}
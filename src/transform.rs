@@ -0,0 +1,185 @@
+use crate::error::{HuffmanError, Result};
+use crate::huffman::HuffmanData;
+use crate::metadata::Metadata;
+
+/// A reversible filter applied to the data ahead of Huffman coding, such as a delta filter that turns slowly changing
+/// samples into small, frequent values
+pub trait Transform {
+    /// Name the transform is recorded under in the encoding, so the decoding side can find it again
+    fn name(&self) -> &str;
+
+    /// Transforms the data ahead of coding
+    fn forward(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Undoes `forward` on decoded data
+    fn inverse(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Transforms registered by name, run in order ahead of coding and undone in reverse after decoding.
+///
+/// The names of the transforms an encoding went through are stored in its metadata under `Metadata::TRANSFORMS`,
+/// so decoding only needs every one of them registered, in any order.
+#[derive(Default)]
+pub struct TransformPipeline {
+    transforms: Vec<Box<dyn Transform>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `transform` to the pipeline, failing if a transform of the same name is registered already
+    ///
+    /// # Arguments
+    ///
+    /// * `transform` - The transform to run after every one registered before it
+    pub fn register(&mut self, transform: Box<dyn Transform>) -> Result<()> {
+        let name = transform.name();
+        if name.is_empty() || name.contains(',') {
+            return Err(HuffmanError::TransformError(
+                "Transform names must be non-empty and free of commas",
+            ));
+        }
+        if self.get(name).is_some() {
+            return Err(HuffmanError::TransformError(
+                "A transform of that name is registered already",
+            ));
+        }
+        self.transforms.push(transform);
+        Ok(())
+    }
+
+    /// Names of the registered transforms, in the order they run
+    pub fn names(&self) -> Vec<&str> {
+        self.transforms
+            .iter()
+            .map(|transform| transform.name())
+            .collect()
+    }
+
+    fn get(&self, name: &str) -> Option<&dyn Transform> {
+        self.transforms
+            .iter()
+            .find(|transform| transform.name() == name)
+            .map(|transform| &**transform)
+    }
+
+    /// Runs every transform over `data` then encodes the result into a self-contained encoding recording them
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to transform and encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    ///
+    /// struct Reverse;
+    ///
+    /// impl Transform for Reverse {
+    ///     fn name(&self) -> &str {
+    ///         "reverse"
+    ///     }
+    ///
+    ///     fn forward(&self, data: &[u8]) -> Result<Vec<u8>, HuffmanError<'static>> {
+    ///         Ok(data.iter().rev().copied().collect())
+    ///     }
+    ///
+    ///     fn inverse(&self, data: &[u8]) -> Result<Vec<u8>, HuffmanError<'static>> {
+    ///         self.forward(data)
+    ///     }
+    /// }
+    ///
+    /// let mut pipeline = TransformPipeline::new();
+    /// pipeline.register(Box::new(Reverse)).unwrap();
+    ///
+    /// let data: Vec<u8> = Vec::from("this is a test string!");
+    /// let encoded_data = pipeline.encode(&data).unwrap();
+    /// assert_eq!(pipeline.decode(&encoded_data).unwrap(), data);
+    /// ```
+    pub fn encode(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut transformed = data.to_vec();
+        for transform in &self.transforms {
+            transformed = transform.forward(&transformed)?;
+        }
+        let mut huffman_data = HuffmanData::new(&transformed)?;
+        if !self.transforms.is_empty() {
+            huffman_data
+                .metadata
+                .insert(Metadata::TRANSFORMS, self.names().join(","));
+        }
+        huffman_data.to_bytes()
+    }
+
+    /// Decodes an encoding written by `encode` and undoes the transforms it records, last first
+    ///
+    /// # Arguments
+    ///
+    /// * `encoded_data` - The self-contained encoding
+    pub fn decode(&self, encoded_data: &[u8]) -> Result<Vec<u8>> {
+        let huffman_data = HuffmanData::from_bytes(encoded_data)?;
+        let mut data = huffman_data.decode_checked()?;
+        let names = huffman_data.metadata().get(Metadata::TRANSFORMS);
+        for name in names.into_iter().flat_map(|names| names.split(',')).rev() {
+            let transform = self.get(name).ok_or(HuffmanError::TransformError(
+                "Encoding went through a transform that is not registered",
+            ))?;
+            data = transform.inverse(&data)?;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Delta;
+
+    impl Transform for Delta {
+        fn name(&self) -> &str {
+            "delta"
+        }
+
+        fn forward(&self, data: &[u8]) -> Result<Vec<u8>> {
+            let mut previous = 0u8;
+            Ok(data
+                .iter()
+                .map(|&byte| {
+                    let delta = byte.wrapping_sub(previous);
+                    previous = byte;
+                    delta
+                })
+                .collect())
+        }
+
+        fn inverse(&self, data: &[u8]) -> Result<Vec<u8>> {
+            let mut previous = 0u8;
+            Ok(data
+                .iter()
+                .map(|&delta| {
+                    previous = previous.wrapping_add(delta);
+                    previous
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_transform_pipeline() {
+        let input_data: Vec<u8> = (0..=255).chain(0..=255).collect();
+        let mut pipeline = TransformPipeline::new();
+        pipeline.register(Box::new(Delta)).unwrap();
+
+        let test_output = pipeline.encode(&input_data).unwrap();
+
+        // The deltas are nearly all 1, which a plain encoding of the ramp cannot take advantage of
+        assert!(test_output.len() < crate::container::encode(&input_data).unwrap().len());
+        assert_eq!(input_data, pipeline.decode(&test_output).unwrap());
+        assert!(pipeline.register(Box::new(Delta)).is_err());
+        assert!(TransformPipeline::new().decode(&test_output).is_err());
+    }
+}
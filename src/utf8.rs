@@ -0,0 +1,357 @@
+use crate::container::{decode, encode};
+use crate::crc32::crc32;
+use crate::error::{HuffmanError, Result};
+use crate::huffman::check_checksum;
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::io::Read;
+
+/// Identifies an encoding whose symbols are UTF-8 scalar values, ahead of everything else
+const MAGIC: [u8; 4] = *b"HTTU";
+const VERSION: u8 = 1;
+/// Symbol number of the escape, just past every scalar value. It is followed by the 8 raw bits of one u8 of a
+/// sequence that is not valid UTF-8
+const ESCAPE_SYMBOL: u32 = char::MAX as u32 + 1;
+/// Longest code the decoder accepts, far beyond what any data that fits in memory can produce
+const MAX_CODE_LEN: u8 = 64;
+
+/// Huffman encodes `data` with its UTF-8 scalar values as symbols instead of its u8s, so non-ASCII text whose
+/// multi-byte sequences repeat as units gets one code per character. u8s of invalid sequences are escaped one by
+/// one, so any data round trips.
+///
+/// The mode is chosen automatically: when the self-contained encoding of `encode` comes out smaller, as it does for
+/// ASCII and binary data, that is returned instead. `decode_utf8` reads either.
+///
+/// The scalar value layout is:
+/// * `[u8; 4]` magic `HTTU`
+/// * `u8` version, 1
+/// * `u64` little endian number of u8s the data decodes to
+/// * `u32` little endian CRC-32 of the data
+/// * `u32` little endian number of symbols, then per symbol in ascending order a `u32` little endian scalar value,
+///   or 0x110000 for the escape, and a `u8` code length. Codes are canonical, assigned in order of length then symbol
+/// * the codes of the symbols, most significant bit first, padded with 0 bits to a whole u8
+///
+/// # Arguments
+///
+/// * `data` - The data to encode, usually text
+///
+/// # Examples
+///
+/// ```
+/// extern crate huff_tree_tap;
+/// use  huff_tree_tap::*;
+///
+/// let data = "Привет, мир! Как дела? ".repeat(20).into_bytes();
+/// let encoded_data = encode_utf8(&data).unwrap();
+/// assert!(encoded_data.len() < encode(&data).unwrap().len());
+/// assert_eq!(decode_utf8(&encoded_data).unwrap(), data);
+/// ```
+pub fn encode_utf8(data: &[u8]) -> Result<Vec<u8>> {
+    let symbols = symbolize(data);
+    let mut counts: BTreeMap<u32, u64> = BTreeMap::new();
+    for &(symbol, _) in &symbols {
+        *counts.entry(symbol).or_insert(0) += 1;
+    }
+    let lengths = code_lengths(&counts)?;
+    let codes = canonical_codes(&lengths);
+
+    let mut encoded_data = MAGIC.to_vec();
+    encoded_data.push(VERSION);
+    encoded_data.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    encoded_data.extend_from_slice(&crc32(data).to_le_bytes());
+    encoded_data.extend_from_slice(&(lengths.len() as u32).to_le_bytes());
+    for (&symbol, &len) in &lengths {
+        encoded_data.extend_from_slice(&symbol.to_le_bytes());
+        encoded_data.push(len);
+    }
+    let mut bits = BitWriter::default();
+    for &(symbol, raw) in &symbols {
+        let (code, len) = codes[&symbol];
+        bits.write(code, len);
+        if symbol == ESCAPE_SYMBOL {
+            bits.write(raw as u64, 8);
+        }
+    }
+    encoded_data.extend(bits.finish());
+
+    // Falls back to u8 symbols when they do better, or when scalar values are all the data can be encoded as
+    match encode(data) {
+        Ok(bytes) if bytes.len() < encoded_data.len() => Ok(bytes),
+        _ => Ok(encoded_data),
+    }
+}
+
+/// Decodes bytes written by `encode_utf8`, whichever mode it chose
+///
+/// # Arguments
+///
+/// * `encoded_data` - The bytes written by `encode_utf8`
+pub fn decode_utf8(encoded_data: &[u8]) -> Result<Vec<u8>> {
+    let Some(mut reader) = encoded_data.strip_prefix(&MAGIC) else {
+        return decode(encoded_data);
+    };
+    if read_array::<1>(&mut reader)?[0] != VERSION {
+        return Err(HuffmanError::DecodeError(
+            "Unsupported UTF-8 encoding version",
+        ));
+    }
+    let data_len = u64::from_le_bytes(read_array(&mut reader)?);
+    let checksum = u32::from_le_bytes(read_array(&mut reader)?);
+    let symbol_count = u32::from_le_bytes(read_array(&mut reader)?);
+    let mut lengths = BTreeMap::new();
+    for _ in 0..symbol_count {
+        let symbol = u32::from_le_bytes(read_array(&mut reader)?);
+        let len = read_array::<1>(&mut reader)?[0];
+        if (symbol != ESCAPE_SYMBOL && char::from_u32(symbol).is_none())
+            || len == 0
+            || len > MAX_CODE_LEN
+        {
+            return Err(HuffmanError::DecodeError(
+                "UTF-8 encoding has an invalid symbol or code length",
+            ));
+        }
+        if lengths.insert(symbol, len).is_some() {
+            return Err(HuffmanError::DecodeError("UTF-8 encoding repeats a symbol"));
+        }
+    }
+
+    let decoder = CanonicalDecoder::new(&lengths);
+    let mut bits = BitReader {
+        bytes: reader,
+        position: 0,
+    };
+    // Every symbol takes at least one bit, so a length the payload cannot hold is not allocated up front
+    let mut data = Vec::with_capacity(data_len.min(reader.len() as u64 * 8) as usize);
+    while (data.len() as u64) < data_len {
+        match decoder.next_symbol(&mut bits)? {
+            ESCAPE_SYMBOL => data.push(bits.read(8)? as u8),
+            symbol => {
+                // Symbols were checked to be scalar values when read
+                let mut buffer = [0u8; 4];
+                let character = char::from_u32(symbol).unwrap_or_default();
+                data.extend_from_slice(character.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+    if data.len() as u64 != data_len {
+        return Err(HuffmanError::DecodeError(
+            "UTF-8 encoding overruns its data length",
+        ));
+    }
+    check_checksum(checksum, crc32(&data))?;
+    Ok(data)
+}
+
+/// Splits `data` into scalar values, and an escape carrying the u8 for every u8 of an invalid sequence
+fn symbolize(data: &[u8]) -> Vec<(u32, u8)> {
+    let mut symbols = Vec::with_capacity(data.len());
+    for chunk in data.utf8_chunks() {
+        symbols.extend(chunk.valid().chars().map(|character| (character as u32, 0)));
+        symbols.extend(chunk.invalid().iter().map(|&byte| (ESCAPE_SYMBOL, byte)));
+    }
+    symbols
+}
+
+/// Code length of every symbol of a Huffman tree built on `counts`, ties broken by symbol so they are deterministic.
+/// A lone symbol gets a 1 bit code
+fn code_lengths(counts: &BTreeMap<u32, u64>) -> Result<BTreeMap<u32, u8>> {
+    // Nodes are numbered leaves first, each branch recording its parent once merged
+    let mut parents: Vec<usize> = vec![usize::MAX; counts.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = counts
+        .values()
+        .enumerate()
+        .map(|(node, &count)| Reverse((count, node)))
+        .collect();
+    while heap.len() > 1 {
+        let (Some(Reverse((left_count, left))), Some(Reverse((right_count, right)))) =
+            (heap.pop(), heap.pop())
+        else {
+            break;
+        };
+        let branch = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = branch;
+        parents[right] = branch;
+        heap.push(Reverse((left_count.saturating_add(right_count), branch)));
+    }
+
+    let mut lengths = BTreeMap::new();
+    for (leaf, &symbol) in counts.keys().enumerate() {
+        let mut len: u8 = 0;
+        let mut node = leaf;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            len = len.saturating_add(1);
+        }
+        if len > MAX_CODE_LEN {
+            return Err(HuffmanError::TreeError("Code length exceeds 64 bits"));
+        }
+        lengths.insert(symbol, len.max(1));
+    }
+    Ok(lengths)
+}
+
+/// Symbols in the order canonical codes are assigned to them, by code length then symbol
+fn canonical_order(lengths: &BTreeMap<u32, u8>) -> Vec<(u8, u32)> {
+    let mut order: Vec<(u8, u32)> = lengths
+        .iter()
+        .map(|(&symbol, &len)| (len, symbol))
+        .collect();
+    order.sort();
+    order
+}
+
+/// Canonical code and length of every symbol
+fn canonical_codes(lengths: &BTreeMap<u32, u8>) -> HashMap<u32, (u64, u8)> {
+    let mut codes = HashMap::with_capacity(lengths.len());
+    let mut code: u64 = 0;
+    let mut previous_len = 0;
+    for (len, symbol) in canonical_order(lengths) {
+        code <<= len - previous_len;
+        codes.insert(symbol, (code, len));
+        code += 1;
+        previous_len = len;
+    }
+    codes
+}
+
+/// Decodes canonical codes a bit at a time from the number of codes of each length
+struct CanonicalDecoder {
+    /// Number of codes of every length, indexed by length
+    counts: Vec<u64>,
+    /// Symbols in canonical order
+    symbols: Vec<u32>,
+}
+
+impl CanonicalDecoder {
+    fn new(lengths: &BTreeMap<u32, u8>) -> CanonicalDecoder {
+        let mut counts = vec![0; MAX_CODE_LEN as usize + 1];
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for (len, symbol) in canonical_order(lengths) {
+            counts[len as usize] += 1;
+            symbols.push(symbol);
+        }
+        CanonicalDecoder { counts, symbols }
+    }
+
+    fn next_symbol(&self, bits: &mut BitReader) -> Result<u32> {
+        // `first` is the first code of the current length and `index` the canonical index of its symbol
+        let (mut code, mut first, mut index): (u64, u64, u64) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)?;
+            if code.wrapping_sub(first) < count {
+                return self
+                    .symbols
+                    .get((index + code - first) as usize)
+                    .copied()
+                    .ok_or(HuffmanError::DecodeError("Code is not in the code table"));
+            }
+            index += count;
+            first = first.wrapping_add(count) << 1;
+            code <<= 1;
+        }
+        Err(HuffmanError::DecodeError("Code is not in the code table"))
+    }
+}
+
+/// Packs codes most significant bit first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits of the last u8 in use, 0 when it is full or there is none
+    used: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, len: u8) {
+        for shift in (0..len).rev() {
+            if self.used == 0 {
+                self.bytes.push(0);
+            }
+            let bit = (value >> shift) as u8 & 1;
+            if let Some(last) = self.bytes.last_mut() {
+                *last |= bit << (7 - self.used);
+            }
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Number of bits read so far
+    position: usize,
+}
+
+impl BitReader<'_> {
+    /// Reads the next `len` bits, most significant first
+    fn read(&mut self, len: u8) -> Result<u64> {
+        let mut value = 0;
+        for _ in 0..len {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or(HuffmanError::DecodeError("Encoded data is truncated"))?;
+            value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1) as u64;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_utf8() {
+        let mut input_data = "日本語のテキストです。".repeat(40).into_bytes();
+        // A truncated sequence and stray continuation u8s, which are escaped
+        input_data.extend_from_slice(&[0xe6, 0x97, b'!', 0x80, 0xff]);
+
+        let test_output = encode_utf8(&input_data).unwrap();
+
+        assert_eq!(&MAGIC, &test_output[..4]);
+        assert!(test_output.len() < encode(&input_data).unwrap().len());
+        assert_eq!(input_data, decode_utf8(&test_output).unwrap());
+    }
+
+    #[test]
+    fn test_encode_utf8_fallback() {
+        for input_data in [
+            b"this is a test string!".repeat(8),
+            (0..=255).collect::<Vec<u8>>(),
+            Vec::new(),
+        ] {
+            let test_output = encode_utf8(&input_data).unwrap();
+
+            assert!(test_output.len() <= encode(&input_data).unwrap().len());
+            assert_eq!(input_data, decode_utf8(&test_output).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_decode_utf8_corrupt() {
+        let input_data = "Ελληνικά κείμενα ".repeat(30).into_bytes();
+        let test_output = encode_utf8(&input_data).unwrap();
+
+        assert!(decode_utf8(&test_output[..test_output.len() - 1]).is_err());
+        let mut corrupt = test_output.clone();
+        corrupt[13] ^= 1;
+        assert!(matches!(
+            decode_utf8(&corrupt),
+            Err(HuffmanError::ChecksumMismatch(_, _))
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::block_type::BlockType;
+use crate::error::Result;
+use crate::huffman::HuffmanData;
+use crate::options::HuffmanOptions;
+use crate::packing::Packing;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// A named input and the options it is encoded with
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    /// Name used for the files written for this vector
+    pub name: String,
+    /// The unencoded input
+    pub input: Vec<u8>,
+    /// The options the input is encoded with
+    pub options: HuffmanOptions,
+}
+
+/// Returns the set of golden inputs covering every block type and stream count
+pub fn test_vectors() -> Vec<TestVector> {
+    // The sentences are repeated so their codes pay for the map the header carries, as they would be stored otherwise
+    let inputs: [(&str, Vec<u8>); 5] = [
+        ("text", b"this is a test string!".repeat(8)),
+        ("sentence", b"My super test string".repeat(8)),
+        ("single_symbol", vec![b'z'; 16]),
+        ("runs", [vec![b'a'; 300], vec![b'b'; 20]].concat()),
+        ("all_bytes", (0..=255).collect()),
+    ];
+
+    let mut vectors = Vec::new();
+    for (name, input) in inputs {
+        for streams in [1, 2, 4] {
+            vectors.push(TestVector {
+                name: format!("{}_streams_{}", name, streams),
+                input: input.clone(),
+                options: HuffmanOptions {
+                    streams,
+                    ..Default::default()
+                },
+            });
+        }
+    }
+    vectors
+}
+
+/// Writes every test vector into `dir` and returns their names.
+///
+/// Each vector produces three files:
+/// * `<name>.input` - the unencoded bytes
+/// * `<name>.encoded` - the `encoded_data` bytes
+/// * `<name>.meta` - `key=value` lines holding the block type, packing, stream sizes, payload bits and one `code.<hex symbol>` line per code
+///
+/// # Arguments
+///
+/// * `dir` - The directory to write the vectors into, created if missing
+pub fn gen_vectors(dir: &Path) -> Result<Vec<String>> {
+    fs::create_dir_all(dir)?;
+
+    let mut names = Vec::new();
+    for vector in test_vectors() {
+        let huffman_data = HuffmanData::with_options(&vector.input, &vector.options)?;
+        fs::write(dir.join(format!("{}.input", vector.name)), &vector.input)?;
+        fs::write(
+            dir.join(format!("{}.encoded", vector.name)),
+            &huffman_data.encoded_data,
+        )?;
+        fs::write(
+            dir.join(format!("{}.meta", vector.name)),
+            describe(&huffman_data),
+        )?;
+        names.push(vector.name);
+    }
+    Ok(names)
+}
+
+/// Describes everything besides `encoded_data` a decoder needs, as `key=value` lines
+fn describe(huffman_data: &HuffmanData) -> String {
+    let block_type = match huffman_data.block_type {
+        BlockType::Stored => "stored",
+        BlockType::Huffman => "huffman",
+        BlockType::Rle => "rle",
+    };
+    let packing = match huffman_data.packing {
+        Packing::Padded => "padded",
+        Packing::Dense => "dense",
+    };
+    let stream_sizes: Vec<String> = huffman_data
+        .stream_sizes
+        .iter()
+        .map(usize::to_string)
+        .collect();
+
+    let mut meta = String::new();
+    let _ = writeln!(meta, "block_type={}", block_type);
+    let _ = writeln!(meta, "packing={}", packing);
+    let _ = writeln!(meta, "stream_sizes={}", stream_sizes.join(","));
+    let _ = writeln!(meta, "payload_bits={}", huffman_data.stats.payload_bits);
+
+    let mut codes: Vec<_> = huffman_data.encoding_map.as_string_map().iter().collect();
+    codes.sort();
+    for (symbol, code) in codes {
+        let _ = writeln!(meta, "code.{:02x}={}", symbol, code);
+    }
+    meta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gen_vectors() {
+        let dir =
+            std::env::temp_dir().join(format!("huff-tree-tap-vectors-{}", std::process::id()));
+
+        let test_output = gen_vectors(&dir).unwrap();
+
+        assert_eq!(test_vectors().len(), test_output.len());
+        for vector in test_vectors() {
+            let input = fs::read(dir.join(format!("{}.input", vector.name))).unwrap();
+            let meta = fs::read_to_string(dir.join(format!("{}.meta", vector.name))).unwrap();
+            assert_eq!(vector.input, input);
+            assert!(meta.starts_with("block_type="));
+        }
+        let meta = fs::read_to_string(dir.join("sentence_streams_1.meta")).unwrap();
+        assert!(
+            meta.contains("block_type=huffman\npacking=dense\nstream_sizes=\npayload_bits=552\n")
+        );
+        assert!(meta.contains("code.20=111\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::encoding_map::EncodingMap;
+use crate::packing::Packing;
+
+/// Selects the 7 payload bits of every u8 in a padded word
+const PAYLOAD_MASK: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+/// The marker bit of every u8 in a padded word
+const MARKER_BITS: u64 = 0x8080_8080_8080_8080;
+
+/// Longest code (escape code plus its 8 literal bits included) the word packer accepts
+const MAX_PACKED_CODE_LEN: usize = 64;
+
+/// Huffman encodes `data` straight into packed u8s, a u64 word (56 payload bits when padded, 64 when dense) at a
+/// time, instead of pushing one bit per element through `UnPaddedBits`. The output matches `Packing::pack` bit for bit.
+/// Returns the packed u8s and the payload bit count, or `None` when a code is too long for a u64 so the caller
+/// falls back to the bit by bit encoder.
+pub(crate) fn pack(
+    data: &[u8],
+    encoding_map: &EncodingMap,
+    packing: Packing,
+) -> Option<(Vec<u8>, u64)> {
+    let codes = code_table(encoding_map)?;
+    let spread = spread_fn();
+    let word_payload_bits = packing.bits_per_u8() * 8;
+    let u8_payload_bits = packing.bits_per_u8();
+
+    let mut encoded_data = Vec::with_capacity(data.len() / 2 + 8);
+    let mut bits: u128 = 0;
+    let mut bit_count: u32 = 0;
+    let mut payload_bits: u64 = 0;
+    for &byte in data {
+        let (code, code_len) = codes[byte as usize];
+        bits = (bits << code_len) | code as u128;
+        bit_count += code_len;
+        payload_bits += code_len as u64;
+        while bit_count >= word_payload_bits {
+            bit_count -= word_payload_bits;
+            let payload = (bits >> bit_count) as u64;
+            let word = match packing {
+                Packing::Padded => spread(payload & ((1 << word_payload_bits) - 1)) | MARKER_BITS,
+                Packing::Dense => payload,
+            };
+            encoded_data.extend_from_slice(&word.to_be_bytes());
+        }
+        bits &= (1 << bit_count) - 1;
+    }
+
+    // Whatever is left fills full u8s, then a last u8 with only as many bits as remain
+    while bit_count >= u8_payload_bits {
+        bit_count -= u8_payload_bits;
+        let byte = (bits >> bit_count) as u8;
+        encoded_data.push(match packing {
+            Packing::Padded => 0x80 | byte & 0x7f,
+            Packing::Dense => byte,
+        });
+    }
+    let rest = bits as u8 & ((1 << bit_count) - 1);
+    match packing {
+        Packing::Padded if bit_count > 0 || payload_bits == 0 => {
+            encoded_data.push((1 << bit_count) | rest)
+        }
+        Packing::Padded => {}
+        Packing::Dense => encoded_data.push(((rest << 1) | 1) << (7 - bit_count)),
+    }
+    Some((encoded_data, payload_bits))
+}
+
+/// Every u8's code right-aligned in a u64 alongside its length. U8s without a code get the escape code followed by
+/// their 8 literal bits, or an empty code when there is no escape.
+fn code_table(encoding_map: &EncodingMap) -> Option<[(u64, u32); 256]> {
+    let mut codes = [(0u64, 0u32); 256];
+    for (byte, entry) in codes.iter_mut().enumerate() {
+        let byte = byte as u8;
+        let (code, literal) = match (encoding_map.get(&byte), encoding_map.get_escape()) {
+            (Some(code), _) => (code, None),
+            (None, Some(escape)) => (escape, Some(byte)),
+            (None, None) => continue,
+        };
+        let code_len = code.len() + literal.map_or(0, |_| 8);
+        if code_len > MAX_PACKED_CODE_LEN {
+            return None;
+        }
+        let mut bits = code
+            .iter()
+            .fold(0u64, |bits, &bit| (bits << 1) | bit as u64);
+        if let Some(literal) = literal {
+            bits = (bits << 8) | literal as u64;
+        }
+        *entry = (bits, code_len as u32);
+    }
+    Some(codes)
+}
+
+/// Picks how 56 payload bits are spread over 8 u8s, BMI2 `pdep` when the CPU has it
+fn spread_fn() -> fn(u64) -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("bmi2") {
+        return |payload| unsafe { spread_bmi2(payload) };
+    }
+    spread_scalar
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2")]
+unsafe fn spread_bmi2(payload: u64) -> u64 {
+    std::arch::x86_64::_pdep_u64(payload, PAYLOAD_MASK)
+}
+
+fn spread_scalar(payload: u64) -> u64 {
+    (0..8).fold(0, |word, group| {
+        word | ((payload >> (7 * group)) & 0x7f) << (8 * group)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::UnPaddedBits;
+    use crate::frequency_map::{FrequencyMap, FrequencyMapping};
+    use crate::huffman_tree;
+
+    #[test]
+    fn test_pack() {
+        let input_data: Vec<u8> =
+            Vec::from("this is a test string! this is a longer test string!!");
+        let frequency_map = FrequencyMap::build(&input_data);
+        let encoding_map = EncodingMap::new(&huffman_tree::build(&frequency_map).unwrap()).unwrap();
+
+        // Every length so the tail handling is hit with each possible number of leftover bits
+        for len in 0..=input_data.len() {
+            let expected_bits: UnPaddedBits = input_data[..len]
+                .iter()
+                .flat_map(|byte| encoding_map.get(byte).unwrap().clone())
+                .collect();
+
+            for packing in [Packing::Padded, Packing::Dense] {
+                let test_output = pack(&input_data[..len], &encoding_map, packing).unwrap();
+
+                assert_eq!(packing.pack(expected_bits.clone()).unwrap(), test_output.0);
+                assert_eq!(expected_bits.len() as u64, test_output.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_spread() {
+        let input_data = 0x00ab_cdef_0123_4567;
+
+        assert_eq!(0x0100, spread_scalar(0x80));
+        assert_eq!(0, spread_scalar(input_data) & !PAYLOAD_MASK);
+        assert_eq!(spread_scalar(input_data), spread_fn()(input_data));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::encoder::HuffmanEncoder;
+use crate::error::Result;
+use crate::options::HuffmanOptions;
+
+use std::io::{self, IoSlice, Write};
+
+/// Compresses everything written to it into a self-contained encoding, for code that produces through `impl Write`.
+///
+/// The encoding map depends on every u8 of the data and the header holding it comes first, so written u8s are kept
+/// (and counted) until `finish` builds the map and writes the header and payload to the underlying writer.
+/// Nothing reaches the underlying writer before `finish`, dropping the writer without calling it loses the data.
+pub struct HuffmanWriter<W: Write> {
+    writer: W,
+    encoder: HuffmanEncoder,
+}
+
+impl<W: Write> HuffmanWriter<W> {
+    /// Creates a `HuffmanWriter` encoding into `writer`
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Where the encoding is written on `finish`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate huff_tree_tap;
+    /// use  huff_tree_tap::*;
+    /// use std::io::Write;
+    ///
+    /// let mut writer = HuffmanWriter::new(Vec::new());
+    /// writer.write_all(b"this is a ").unwrap();
+    /// writer.write_all(b"test string!").unwrap();
+    /// let encoded_data = writer.finish().unwrap();
+    /// assert_eq!(decode(&encoded_data).unwrap(), b"this is a test string!");
+    /// ```
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, HuffmanOptions::default())
+    }
+
+    /// Creates a `HuffmanWriter` that encodes with the given `HuffmanOptions`
+    pub fn with_options(writer: W, options: HuffmanOptions) -> Self {
+        HuffmanWriter {
+            writer,
+            encoder: HuffmanEncoder::with_options(options),
+        }
+    }
+
+    /// Encodes everything written so far, writes the encoding to the underlying writer and returns it flushed
+    pub fn finish(mut self) -> Result<W> {
+        let huffman_data = self.encoder.finish()?;
+        self.writer.write_all(&huffman_data.to_bytes()?)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for HuffmanWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.update(buf);
+        Ok(buf.len())
+    }
+
+    /// Takes every buffer whole, so scattered buffers never have to be coalesced first
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        self.encoder.update_vectored(bufs);
+        Ok(len)
+    }
+
+    /// Does nothing, the encoding can only be written once all the data is known
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::decode;
+    use crate::huffman::HuffmanData;
+    use crate::reader::HuffmanReader;
+    use std::io::Read;
+
+    #[test]
+    fn test_huffman_writer() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let options = HuffmanOptions {
+            streams: 4,
+            ..Default::default()
+        };
+        let expected_data = HuffmanData::with_options(&input_data, &options)
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+
+        let mut writer = HuffmanWriter::with_options(Vec::new(), options);
+        for chunk in input_data.chunks(1000) {
+            writer.write_all(chunk).unwrap();
+        }
+        let test_output = writer.finish().unwrap();
+
+        assert_eq!(expected_data, test_output);
+        assert_eq!(input_data, decode(&test_output).unwrap());
+
+        let mut decoded_data = Vec::new();
+        HuffmanReader::new(test_output.as_slice())
+            .read_to_end(&mut decoded_data)
+            .unwrap();
+        assert_eq!(input_data, decoded_data);
+    }
+
+    #[test]
+    fn test_huffman_writer_vectored() {
+        let input_data: Vec<u8> = Vec::from("this is a test string!").repeat(5000);
+        let expected_data = HuffmanData::new(&input_data).unwrap().to_bytes().unwrap();
+
+        let mut writer = HuffmanWriter::new(Vec::new());
+        for chunks in input_data.chunks(1000).collect::<Vec<_>>().chunks(3) {
+            let bufs: Vec<IoSlice> = chunks.iter().map(|chunk| IoSlice::new(chunk)).collect();
+            let written = writer.write_vectored(&bufs).unwrap();
+            assert_eq!(chunks.concat().len(), written);
+        }
+        let test_output = writer.finish().unwrap();
+
+        assert_eq!(expected_data, test_output);
+    }
+}
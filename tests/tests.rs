@@ -23,12 +23,19 @@ fn test_huffmandata_decode() {
     .collect();
     let input_data = HuffmanData {
         encoded_data: input_encoded_data,
-        encoding_map: input_encoding_map,
+        encoding_map: EncodingMap::from(input_encoding_map).unwrap(),
         stats: EncodingStats {
             data_size: 1.0,
             encoded_size: 1.0,
             ratio: 1.0,
+            ..Default::default()
         },
+        stream_sizes: Vec::new(),
+        block_type: BlockType::Huffman,
+        data_len: 20,
+        packing: Packing::Padded,
+        checksum: None,
+        metadata: Metadata::new(),
     };
 
     let expected_data = "My super test string".to_string().into_bytes();
@@ -42,7 +49,8 @@ fn test_huffmandata_decode() {
 fn test_huffmandata_encode() {
     let input_data = "My super test string".to_string().into_bytes();
 
-    let expected_encoded_data = vec![182, 188, 239, 160, 190, 196, 223, 148, 209, 87];
+    let expected_encoded_data = vec![108, 243, 122, 7, 209, 47, 148, 162, 188];
+    let expected_padded_data = vec![182, 188, 239, 160, 190, 196, 223, 148, 209, 87];
     let expected_data_encoding_map: HashMap<u8, String> = [
         (b'M', "0110".to_string()),
         (b'g', "0111".to_string()),
@@ -61,10 +69,19 @@ fn test_huffmandata_encode() {
     .cloned()
     .collect();
 
-    let test_output = HuffmanData::new(&input_data).unwrap();
+    let test_output = HuffmanData::with_options(&input_data, &HuffmanOptions::default()).unwrap();
+    let padded_options = HuffmanOptions {
+        packing: Packing::Padded,
+        ..Default::default()
+    };
+    let padded_output = HuffmanData::with_options(&input_data, &padded_options).unwrap();
 
     assert_eq!(expected_encoded_data, test_output.encoded_data);
-    assert_eq!(expected_data_encoding_map, test_output.encoding_map);
+    assert_eq!(expected_padded_data, padded_output.encoded_data);
+    assert_eq!(
+        &expected_data_encoding_map,
+        test_output.encoding_map.as_string_map()
+    );
 }
 
 #[test]
@@ -72,14 +89,111 @@ fn test_huffmandata_stats() {
     let input_data = "My super test string".to_string().into_bytes();
     let expected_stats = EncodingStats {
         data_size: 160.0,
-        encoded_size: 80.0,
-        ratio: 50.0,
+        encoded_size: 72.0,
+        ratio: 55.0,
+        payload_bits: 69,
+        data_len: 20,
+        encoded_len: 9,
     };
-    let test_output = HuffmanData::new(&input_data).unwrap();
+    let test_output = HuffmanData::with_options(&input_data, &HuffmanOptions::default()).unwrap();
 
     assert_eq!(expected_stats.data_size, test_output.stats.data_size);
     assert_eq!(expected_stats.encoded_size, test_output.stats.encoded_size);
     assert_eq!(expected_stats.ratio, test_output.stats.ratio);
+    assert_eq!(expected_stats.payload_bits, test_output.stats.payload_bits);
+    assert_eq!(expected_stats.data_len, test_output.stats.data_len);
+    assert_eq!(expected_stats.encoded_len, test_output.stats.encoded_len);
+}
+
+#[test]
+fn test_huffmandata_interleaved_streams() {
+    let input_data = "My super test string".to_string().into_bytes();
+
+    for streams in 1..=MAX_STREAMS {
+        let options = HuffmanOptions {
+            streams,
+            ..Default::default()
+        };
+        let test_output = HuffmanData::with_options(&input_data, &options).unwrap();
+
+        assert_eq!(streams - 1, test_output.stream_sizes.len());
+        assert_eq!(input_data, test_output.decode().unwrap());
+    }
+
+    assert!(HuffmanData::with_options(
+        &input_data,
+        &HuffmanOptions {
+            streams: 5,
+            ..Default::default()
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn test_huffmandata_block_type_selection() {
+    let text = "My super test string".to_string().into_bytes();
+    let runs = vec![b'a'; 1000];
+    let noise: Vec<u8> = (0..=255).collect();
+
+    for (input_data, expected_block_type) in [
+        (text.repeat(8), BlockType::Huffman),
+        // The map alone would take up more than the text saves
+        (text, BlockType::Stored),
+        (runs, BlockType::Rle),
+        (noise, BlockType::Stored),
+    ] {
+        let encoded_data = encode_with(&input_data, &HuffmanOptions::default()).unwrap();
+        let test_output = HuffmanData::from_bytes(&encoded_data).unwrap();
+
+        assert_eq!(expected_block_type, test_output.block_type);
+        assert_eq!(
+            BlockType::Huffman,
+            HuffmanData::new(&input_data).unwrap().block_type
+        );
+        assert_eq!(input_data, test_output.decode().unwrap());
+    }
+}
+
+#[test]
+fn test_huffmandata_decode_with() {
+    let input_data = "My super test string".to_string().into_bytes();
+    let mut encoded_data = HuffmanData::new(&input_data).unwrap();
+
+    let strict = DecodeOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(input_data, encoded_data.decode_with(&strict).unwrap());
+
+    let limited = DecodeOptions {
+        max_output: Some(input_data.len() as u64 - 1),
+        ..Default::default()
+    };
+    assert!(encoded_data.decode_with(&limited).is_err());
+
+    // A long run encodes to a few u8s as a run-length block, far more than 100 times smaller
+    let run = encode_with(&[0u8; 100_000], &HuffmanOptions::default()).unwrap();
+    let run = HuffmanData::from_bytes(&run).unwrap();
+    let bounded = DecodeOptions {
+        max_growth: Some(100),
+        ..Default::default()
+    };
+    assert!(run.decode_with(&bounded).is_err());
+    assert!(encoded_data.decode_with(&bounded).is_ok());
+
+    // Drop the final code so the payload ends part way through a symbol
+    encoded_data.encoded_data = vec![108, 243, 122, 7, 209, 47, 148, 162, 160];
+    let unchecked = DecodeOptions {
+        verify_checksum: false,
+        ..Default::default()
+    };
+    assert!(encoded_data.decode_with(&unchecked).is_ok());
+    assert!(matches!(
+        encoded_data.decode(),
+        Err(HuffmanError::ChecksumMismatch(..))
+    ));
+    assert!(encoded_data.decode_with(&strict).is_err());
 }
 
 #[test]
@@ -94,8 +208,11 @@ fn test_bench() {
         encoded_data.stats,
         EncodingStats {
             data_size: 64000.0,
-            encoded_size: 27432.0,
-            ratio: 57.1375,
+            encoded_size: 24008.0,
+            ratio: 62.4875,
+            payload_bits: 24000,
+            data_len: 8000,
+            encoded_len: 3001,
         }
     );
 
@@ -115,10 +232,114 @@ fn test_large_payload_10_000_000() {
         encoded_data.stats,
         EncodingStats {
             data_size: 640000000.0,
-            encoded_size: 274285730.0,
-            ratio: 57.142853,
+            encoded_size: 240000000.0,
+            ratio: 62.5,
+            payload_bits: 240_000_000,
+            data_len: 80_000_000,
+            encoded_len: 30_000_001,
         }
     );
 
     assert_eq!(decoded_data, unencoded_data);
 }
+
+/// Synthetic u8 at `position`: a 1 every 16 u8s and 0s otherwise
+fn synthetic_byte(position: u64) -> u8 {
+    position.is_multiple_of(16) as u8
+}
+
+/// `len` synthetic u8s read and sought like a file, without ever being held in memory
+struct SyntheticSource {
+    len: u64,
+    position: u64,
+}
+
+impl std::io::Read for SyntheticSource {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = buf.len().min((self.len - self.position) as usize);
+        for (i, byte) in buf[..read].iter_mut().enumerate() {
+            *byte = synthetic_byte(self.position + i as u64);
+        }
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl std::io::Seek for SyntheticSource {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.position = match pos {
+            std::io::SeekFrom::Start(offset) => offset,
+            std::io::SeekFrom::Current(offset) => self.position.saturating_add_signed(offset),
+            std::io::SeekFrom::End(offset) => self.len.saturating_add_signed(offset),
+        }
+        .min(self.len);
+        Ok(self.position)
+    }
+}
+
+/// Checks decoded u8s against the synthetic u8s as they are written, counting them
+struct SyntheticCheck {
+    position: u64,
+}
+
+impl std::io::Write for SyntheticCheck {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            if byte != synthetic_byte(self.position) {
+                return Err(std::io::Error::other("Decoded u8 differs from the source"));
+            }
+            self.position += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams `len` synthetic u8s through `encode_to_writer` into a temporary file and back through
+/// `decode_from_buf_read`, returning the header version and the number of u8s decoded and checked
+fn synthetic_round_trip(len: u64) -> (u8, u64) {
+    let path = std::env::temp_dir().join(format!(
+        "huff-tree-tap-synthetic-{}-{}",
+        len,
+        std::process::id()
+    ));
+    let mut encoded = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+    let stats = encode_to_writer(&mut SyntheticSource { len, position: 0 }, &mut encoded).unwrap();
+    encoded.into_inner().unwrap();
+    assert_eq!(len, stats.data_len);
+
+    let mut file = std::fs::File::open(&path).unwrap();
+    let mut version = [0u8; 5];
+    std::io::Read::read_exact(&mut file, &mut version).unwrap();
+    let mut check = SyntheticCheck { position: 0 };
+    let decoded = decode_from_buf_read(
+        &mut std::io::BufReader::new(std::fs::File::open(&path).unwrap()),
+        &mut check,
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(len, decoded.unwrap());
+    (version[4], check.position)
+}
+
+#[test]
+fn test_synthetic_round_trip() {
+    let input_data = 1 << 20;
+
+    let test_output = synthetic_round_trip(input_data);
+
+    assert_eq!(input_data, test_output.1);
+}
+
+#[test]
+#[ignore = "streams more than 4 GiB through the encoder and decoder, run with --release -- --ignored"]
+fn test_synthetic_round_trip_past_4_gib() {
+    // Just past the largest size a u32 holds, which needs the 64-bit sizes of a version 6 header
+    let input_data = MAX_PAYLOAD_SIZE + 1 + 4096;
+
+    let test_output = synthetic_round_trip(input_data);
+
+    assert_eq!((6, input_data), test_output);
+}